@@ -0,0 +1,42 @@
+/* C interface to the ssbfile library; build it with
+ *   cargo rustc --lib --release --features cdylib --crate-type cdylib
+ * and link against the resulting libssbfile.so / ssbfile.dll / libssbfile.dylib
+ */
+#ifndef SSBFILE_H
+#define SSBFILE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* an opened rom */
+typedef struct SsbRomHandle SsbRomHandle;
+
+/* Read the rom at `path` (in any byte order). Returns NULL on an error. */
+SsbRomHandle *ssb_open_rom(const char *path);
+
+/* Free a rom opened with ssb_open_rom */
+void ssb_close_rom(SsbRomHandle *rom);
+
+/* The number of files in the resource table */
+size_t ssb_file_count(const SsbRomHandle *rom);
+
+/* The data of file `id`, decompressed if necessary. The length is written to `len`,
+ * and the buffer has to be freed with ssb_free_buffer. Returns NULL on an error. */
+uint8_t *ssb_extract_file(const SsbRomHandle *rom, size_t id, size_t *len);
+
+/* Free a buffer returned by ssb_extract_file */
+void ssb_free_buffer(uint8_t *buf, size_t len);
+
+/* The message of the last error on this thread, or NULL. The string is
+ * valid until the next failing call on this thread. */
+const char *ssb_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SSBFILE_H */
@@ -0,0 +1,52 @@
+use crate::{cli::AddrOpt, timings::Timings};
+use anyhow::Result;
+use ssbfile::TableEntry;
+
+const NO_RELOCS: u16 = 0xFFFF;
+
+pub(crate) fn lookup(opt: AddrOpt, timings: &mut Timings) -> Result<()> {
+    let (source, version) = opt.rom.source(timings)?;
+    let rom = opt.rom.table(&source, version, timings)?;
+    let version = rom.version();
+
+    let addr = opt.addr as usize;
+    if (version.table_start..version.table_end).contains(&addr) {
+        let index = (addr - version.table_start) / TableEntry::SIZE;
+        let field = (addr - version.table_start) % TableEntry::SIZE;
+        if index < rom.file_count() {
+            println!(
+                "{:#X} is byte {:#X} of the table entry for file {}",
+                addr, field, index
+            );
+        } else {
+            println!(
+                "{:#X} is byte {:#X} of the terminal table entry",
+                addr, field
+            );
+        }
+        return Ok(());
+    }
+
+    let Some((id, within)) = rom.file_at_rom_offset(addr) else {
+        println!("{:#X} is outside of the resource files", addr);
+        return Ok(());
+    };
+    let entry = &rom.entries()[id];
+    let size = entry.size as usize * 4;
+
+    let place = if within < size && entry.compressed {
+        "the compressed data"
+    } else if within < size {
+        "the data"
+    } else if entry.exreloc != NO_RELOCS {
+        "the external file list"
+    } else {
+        "the padding"
+    };
+    println!(
+        "{:#X} is offset {:#X} of file {}, in {}",
+        addr, within, id, place
+    );
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::{
+    cli::{AnimOpt, ImportAnimOpt},
+    inject,
+    timings::Timings,
+};
+use anyhow::{Context, Result};
+use ssbfile::animation;
+use std::fs;
+
+pub(crate) fn disassemble(opt: AnimOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let streams = animation::animation(&data, opt.offset as usize, &relocs)
+        .with_context(|| format!("reading the animation of file <{}>", opt.id))?;
+    println!("# animation of file {} at 0x{:04X}", opt.id, opt.offset);
+    print!("{}", animation::disassemble(&streams));
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportAnimOpt, timings: &mut Timings) -> Result<()> {
+    let text = fs::read_to_string(&opt.anim)
+        .with_context(|| format!("issue opening <{}>", opt.anim.display()))?;
+    let streams = animation::assemble(&text)
+        .with_context(|| format!("assembling <{}>", opt.anim.display()))?;
+
+    let (mut rom, version) = opt.rom.read(timings)?;
+    inject::edit_file(
+        &mut rom,
+        &version,
+        opt.id,
+        &opt.compress,
+        timings,
+        |data, relocs| {
+            for stream in &streams {
+                animation::patch(data, stream, relocs).with_context(|| {
+                    format!(
+                        "replacing the stream of joint {} at {:#X}",
+                        stream.joint, stream.offset
+                    )
+                })?;
+            }
+
+            Ok(())
+        },
+    )?;
+    eprintln!("replaced {} streams of file {}", streams.len(), opt.id);
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
@@ -0,0 +1,372 @@
+//! Animations: for each joint of a skeleton, a stream of events that set the values of its
+//! rotation, translation, and scale tracks, and wait for frames between them.
+//!
+//! An animation starts with a pointer (or null) for each joint, to its stream. Each event
+//! is a word: a 7 bit opcode, 10 bits of the tracks it sets, and 15 bits of the frames it
+//! waits for; then a float (or two, for a value and its rate) for each track it sets.
+//! A `jump` is followed by a pointer to where the stream goes on, and `end` ends it.
+//! The opcodes follow the decompilation's `AObjEvent32` kinds.
+use crate::{moveset::number, Error, Relocation};
+use alloc::{format, string::String, vec, vec::Vec};
+use anyhow::Result;
+use core::fmt;
+
+/// the most events that are read from a stream
+const MAX_EVENTS: usize = 0x4000;
+
+/// The tracks of a joint, in the order of their bits in an event
+pub const TRACKS: [&str; 10] = [
+    "rotate_x",
+    "rotate_y",
+    "rotate_z",
+    "translate_x",
+    "translate_y",
+    "translate_z",
+    "scale_x",
+    "scale_y",
+    "scale_z",
+    "extra",
+];
+
+/// The name of each opcode, and the number of floats it has for each of its tracks
+const OPCODES: [(&str, usize); 12] = [
+    ("end", 0),
+    ("jump", 0),
+    ("wait", 0),
+    ("set_block", 1),
+    ("set", 1),
+    ("set_rate_block", 2),
+    ("set_rate", 2),
+    ("target_rate", 1),
+    ("set_zero_rate_block", 1),
+    ("set_zero_rate", 1),
+    ("set_after_block", 1),
+    ("set_after", 1),
+];
+const END: u8 = 0;
+const JUMP: u8 = 1;
+
+/// An event of a joint's stream
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    /// offset of the event in the file
+    pub offset: usize,
+    pub opcode: u8,
+    /// the tracks that the event sets, as bits in the order of [`TRACKS`]
+    pub tracks: u16,
+    pub frames: u16,
+    /// the floats for each track that it sets, in the order of the tracks
+    pub values: Vec<f32>,
+    /// where the stream goes on, for a `jump`
+    pub target: Option<u32>,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        OPCODES[self.opcode as usize].0
+    }
+
+    /// The size of the event in the file
+    pub fn size(&self) -> usize {
+        4 + self.values.len() * 4 + if self.target.is_some() { 4 } else { 0 }
+    }
+
+    fn words(&self) -> Vec<u32> {
+        let mut words =
+            vec![(self.opcode as u32) << 25 | (self.tracks as u32) << 15 | self.frames as u32];
+        words.extend(self.values.iter().map(|v| v.to_bits()));
+        words.extend(self.target);
+        words
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if self.frames != 0 {
+            write!(f, " {}", self.frames)?;
+        }
+        let per_track = OPCODES[self.opcode as usize].1;
+        let mut values = self.values.iter();
+        for (bit, track) in TRACKS.iter().enumerate() {
+            if self.tracks & (1 << bit) != 0 {
+                write!(f, " {}=", track)?;
+                for i in 0..per_track {
+                    let comma = if i == 0 { "" } else { "," };
+                    write!(
+                        f,
+                        "{}{:?}",
+                        comma,
+                        values.next().copied().unwrap_or_default()
+                    )?;
+                }
+            }
+        }
+        if let Some(target) = self.target {
+            write!(f, " to=0x{:04X}", target)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The stream of a joint
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stream {
+    pub joint: usize,
+    /// offset of the stream in the file
+    pub offset: usize,
+    pub events: Vec<Event>,
+}
+
+impl Stream {
+    /// The size of the stream in the file
+    pub fn size(&self) -> usize {
+        self.events.iter().map(Event::size).sum()
+    }
+}
+
+/// Read the animation whose joint pointers are at `offset` of `file`: a stream for each
+/// joint that has one. The pointers run up to the first word that isn't null or a pointer.
+pub fn animation(file: &[u8], offset: usize, relocs: &[Relocation]) -> Result<Vec<Stream>> {
+    let mut streams = Vec::new();
+    for (joint, at) in (offset..file.len().saturating_sub(3))
+        .step_by(4)
+        .enumerate()
+    {
+        let pointer = relocs
+            .iter()
+            .find(|r| r.offset == at && r.external_file.is_none());
+        match pointer {
+            Some(r) => streams.push(Stream {
+                joint,
+                offset: r.target as usize,
+                events: stream(file, r.target as usize, relocs)?,
+            }),
+            None if file[at..at + 4] == [0; 4] => (),
+            None => break,
+        }
+    }
+    if streams.is_empty() {
+        return Err(Error::BadAnimation { offset }.into());
+    }
+
+    Ok(streams)
+}
+
+/// Read the events of the stream at `offset` of `file`, up to its `end` or `jump`
+pub fn stream(file: &[u8], offset: usize, relocs: &[Relocation]) -> Result<Vec<Event>> {
+    let bad = || Error::BadAnimation { offset };
+    let word = |at: usize| -> Result<u32> {
+        let w = file.get(at..at + 4).ok_or_else(bad)?;
+        Ok(u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+    };
+
+    let mut events = Vec::new();
+    let mut at = offset;
+    for _ in 0..MAX_EVENTS {
+        let head = word(at)?;
+        let opcode = (head >> 25) as u8;
+        let &(_, per_track) = OPCODES.get(opcode as usize).ok_or_else(bad)?;
+        let tracks = ((head >> 15) & 0x3FF) as u16;
+        let count = tracks.count_ones() as usize * per_track;
+        let values = (0..count)
+            .map(|i| word(at + 4 + i * 4).map(f32::from_bits))
+            .collect::<Result<Vec<_>>>()?;
+        let target = if opcode == JUMP {
+            let at = at + 4 + count * 4;
+            word(at)?;
+            let r = relocs
+                .iter()
+                .find(|r| r.offset == at && r.external_file.is_none())
+                .ok_or_else(bad)?;
+            Some(r.target)
+        } else {
+            None
+        };
+
+        let event = Event {
+            offset: at,
+            opcode,
+            tracks,
+            frames: (head & 0x7FFF) as u16,
+            values,
+            target,
+        };
+        at += event.size();
+        events.push(event);
+        if opcode == END || opcode == JUMP {
+            return Ok(events);
+        }
+    }
+
+    Err(bad().into())
+}
+
+/// Write `streams` as text: a `.joint JOINT OFFSET` line before the events of each stream
+pub fn disassemble(streams: &[Stream]) -> String {
+    let mut text = String::new();
+    for stream in streams {
+        text += &format!("\n.joint {} 0x{:04X}\n", stream.joint, stream.offset);
+        for event in &stream.events {
+            text += &format!("0x{:04X}: {}\n", event.offset, event);
+        }
+    }
+
+    text
+}
+
+/// Assemble streams in the text format of [`disassemble`]: an event per line, as its name,
+/// the frames it waits for (if any), a `track=value` (or `track=value,rate`) for each track
+/// it sets, and `to=OFFSET` for a `jump`. `#` starts a comment, and an `0xOFFSET:` before
+/// an event is ignored.
+pub fn assemble(text: &str) -> Result<Vec<Stream>> {
+    let mut streams: Vec<Stream> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let syntax = |reason: String| Error::ScriptSyntax {
+            line: i + 1,
+            reason,
+        };
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let line = match line.split_once(':') {
+            Some((offset, event)) if offset.starts_with("0x") => event.trim(),
+            _ => line,
+        };
+        let mut tokens = line.split_whitespace().peekable();
+        let Some(name) = tokens.next() else {
+            continue;
+        };
+
+        if name == ".joint" {
+            let mut arg = || {
+                tokens
+                    .next()
+                    .and_then(number)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or_else(|| syntax(".joint needs the joint and offset of its stream".into()))
+            };
+            streams.push(Stream {
+                joint: arg()?,
+                offset: arg()?,
+                events: Vec::new(),
+            });
+            continue;
+        }
+        let stream = streams
+            .last_mut()
+            .ok_or_else(|| syntax("events need a `.joint JOINT OFFSET` line before them".into()))?;
+        let opcode = OPCODES
+            .iter()
+            .position(|(n, _)| *n == name)
+            .ok_or_else(|| syntax(format!("unknown event <{}>", name)))?;
+        let per_track = OPCODES[opcode].1;
+
+        let mut event = Event {
+            offset: stream.offset + stream.size(),
+            opcode: opcode as u8,
+            tracks: 0,
+            frames: 0,
+            values: Vec::new(),
+            target: None,
+        };
+        if let Some(frames) = tokens.next_if(|t| !t.contains('=')) {
+            event.frames = number(frames)
+                .and_then(|n| u16::try_from(n).ok())
+                .filter(|&n| n < 0x8000)
+                .ok_or_else(|| syntax(format!("bad frame count <{}>", frames)))?;
+        }
+        // the values of each track, to be put in the order of the tracks
+        let mut values = vec![Vec::new(); TRACKS.len()];
+        for token in tokens {
+            let (key, value) = token
+                .split_once('=')
+                .ok_or_else(|| syntax(format!("expected track=value, not <{}>", token)))?;
+            if key == "to" && opcode == JUMP as usize {
+                let target = number(value)
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| syntax(format!("bad offset <{}>", value)))?;
+                event.target = Some(target);
+                continue;
+            }
+            let track = TRACKS
+                .iter()
+                .position(|t| *t == key)
+                .filter(|_| per_track > 0)
+                .ok_or_else(|| syntax(format!("{} has no track <{}>", name, key)))?;
+            let floats = value
+                .split(',')
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| syntax(format!("bad value <{}>", value)))?;
+            if floats.len() != per_track {
+                return Err(syntax(format!(
+                    "{} needs {} values for each track",
+                    name, per_track
+                ))
+                .into());
+            }
+            event.tracks |= 1 << track;
+            values[track] = floats;
+        }
+        if opcode == JUMP as usize && event.target.is_none() {
+            return Err(syntax("jump needs the offset to jump to, as to=OFFSET".into()).into());
+        }
+        event.values = values.concat();
+        stream.events.push(event);
+    }
+
+    Ok(streams)
+}
+
+/// Replace the stream at `stream.offset` of `file` with `stream`, which has to fit in the
+/// place of the stream that is there, and update the pointers of its jumps in `relocs`
+pub fn patch(file: &mut [u8], stream: &Stream, relocs: &mut Vec<Relocation>) -> Result<()> {
+    let start = stream.offset;
+    let capacity = self::stream(file, start, relocs)?
+        .iter()
+        .map(Event::size)
+        .sum::<usize>();
+    if stream.size() > capacity {
+        return Err(Error::ScriptTooLong {
+            start,
+            len: stream.size(),
+            capacity,
+        }
+        .into());
+    }
+    for event in &stream.events {
+        match event.target {
+            Some(target) if target as usize >= file.len() || target % 4 != 0 => {
+                return Err(Error::ScriptPointer {
+                    offset: start,
+                    target,
+                }
+                .into())
+            }
+            _ => (),
+        }
+    }
+
+    relocs.retain(|r| !(start..start + capacity).contains(&r.offset));
+    let mut at = start;
+    for event in &stream.events {
+        for word in event.words() {
+            file[at..at + 4].copy_from_slice(&word.to_be_bytes());
+            at += 4;
+        }
+        if let Some(target) = event.target {
+            relocs.push(Relocation {
+                offset: at - 4,
+                target,
+                external_file: None,
+            });
+        }
+    }
+    // the rest of the old stream's place is `end`s
+    file[at..start + capacity].fill(0);
+    relocs.sort_by_key(|r| r.offset);
+
+    Ok(())
+}
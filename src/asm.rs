@@ -0,0 +1,96 @@
+use crate::{target_symbol, FileReloc};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::fmt::Write;
+
+/// How many plain words are written on one `.word` line
+const WORDS_PER_LINE: usize = 4;
+
+/// Write a file that was relocated with a base address of 0 as GNU assembler source.
+///
+/// The file data goes into `.data`, after a global `fileNNNN_start` label. Each
+/// internal pointer target gets a global `fileNNNN_off_XXXX` label, and each pointer
+/// becomes a `.word` of the symbol it points to: internal pointers use the target's
+/// label, and external pointers are relative to the start symbol of their file
+/// (e.g., `file0003_start + 0x40`). Any bytes after the last whole word are `.byte`s,
+/// and targets at or past the end of the file are `.set` relative to the start symbol.
+pub fn assembly(id: usize, file: &[u8], relocs: &FileReloc) -> String {
+    let labels = relocs
+        .internal
+        .iter()
+        .flatten()
+        .map(|&(_, _, ptr)| ptr as usize)
+        .collect::<BTreeSet<_>>();
+    let internal = relocs
+        .internal
+        .iter()
+        .flatten()
+        .map(|&(_, offset, ptr)| (offset, target_symbol(id, ptr)));
+    let external = relocs
+        .external
+        .iter()
+        .flatten()
+        .map(|&(fid, offset, ptr)| (offset, format!("file{:04}_start + 0x{:X}", fid, ptr)));
+    let pointers = internal.chain(external).collect::<BTreeMap<_, _>>();
+
+    let mut s = format!("/* file {}, {:#X} bytes */\n\n", id, file.len());
+    s += ".section .data\n.balign 16\n\n";
+    s += &format!(".global file{:04}_start\nfile{:04}_start:\n", id, id);
+
+    let words = file.chunks_exact(4);
+    let tail = words.remainder();
+    let mut line = 0;
+    for (i, word) in words.enumerate() {
+        let offset = i * 4;
+        let label = labels.contains(&offset);
+        let pointer = pointers.get(&offset);
+        // a label or pointer starts a new line
+        if line > 0 && (label || pointer.is_some() || line == WORDS_PER_LINE) {
+            s.push('\n');
+            line = 0;
+        }
+        if label {
+            let symbol = target_symbol(id, offset as u32);
+            writeln!(s, ".global {}\n{}:", symbol, symbol).expect("write to string");
+        }
+        if let Some(symbol) = pointer {
+            writeln!(s, "    .word {}", symbol).expect("write to string");
+            continue;
+        }
+
+        let value = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        if line == 0 {
+            write!(s, "    .word 0x{:08X}", value).expect("write to string");
+        } else {
+            write!(s, ", 0x{:08X}", value).expect("write to string");
+        }
+        line += 1;
+    }
+    if line > 0 {
+        s.push('\n');
+    }
+    if !tail.is_empty() {
+        let bytes = tail
+            .iter()
+            .map(|b| format!("0x{:02X}", b))
+            .collect::<Vec<_>>();
+        writeln!(s, "    .byte {}", bytes.join(", ")).expect("write to string");
+    }
+    // pointers to the end of the file (or past the last whole word) can't have a label
+    let end = file.len() - tail.len();
+    for &target in labels.range(end..) {
+        let symbol = target_symbol(id, target as u32);
+        writeln!(
+            s,
+            ".global {}\n.set {}, file{:04}_start + 0x{:X}",
+            symbol, symbol, id, target
+        )
+        .expect("write to string");
+    }
+
+    s
+}
@@ -0,0 +1,739 @@
+//! The game's music and sounds: libultra's sequence bank (`ALSeqFile`) of compressed MIDI
+//! sequences (`ALCSeq`), and their conversion to standard MIDI files; and its instrument
+//! banks (`ALBankFile`), whose sounds are samples in a wave table, decoded to PCM.
+//!
+//! The audio isn't in the resource table, so a bank is found by its header: the `S1`
+//! revision, a count, and the offset and length of each sequence (from the bank's start).
+//! A sequence starts with the offsets of its 16 tracks and its ticks per quarter note.
+//! Its tracks are MIDI with running status, except that:
+//! * a note on has a variable length duration after its velocity, and no note off;
+//! * tempo and end of track meta events have no length byte;
+//! * loops are the meta events `FF 2E nn FF` (start) and `FF 2D count current offset[4]`
+//!   (end);
+//! * `FE hi lo len` repeats the `len` bytes `hi lo` back from the `FE`, and `FE FE` is
+//!   an `FE` byte.
+//!
+//! A bank file (the `.ctl`) starts with the `B1` revision, a count, and the offsets of its
+//! banks; all of its pointers are offsets from its start. Its wave tables point into the
+//! samples (the `.tbl`), which are 16 bit PCM or VADPCM: frames of 9 bytes, a scale and
+//! predictor, then 16 four bit residuals that the predictor's coefficients turn into
+//! 16 samples.
+use crate::Error;
+use alloc::{vec, vec::Vec};
+use anyhow::Result;
+
+/// the revision of a sequence bank: 'S1'
+const SEQ_BANK_REVISION: u16 = 0x5331;
+/// the size of a sequence header: the 16 track offsets and the division
+const SEQUENCE_HEADER: usize = 0x44;
+/// the compressed MIDI code for a repeat of earlier bytes
+const BLOCK_CODE: u8 = 0xFE;
+/// the revision of a bank file: 'B1'
+const BANK_FILE_REVISION: u16 = 0x4231;
+/// the size of a VADPCM frame, and the number of samples in it
+const ADPCM_FRAME: usize = 9;
+const ADPCM_SAMPLES: usize = 16;
+
+/// A sequence bank in the rom
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeqBank {
+    /// rom offset of the bank
+    pub offset: usize,
+    /// the offset (from the start of the bank) and length of each sequence
+    pub sequences: Vec<(usize, usize)>,
+}
+
+impl SeqBank {
+    /// Read the sequence bank at `offset` of `rom`
+    pub fn read(rom: &[u8], offset: usize) -> Result<Self> {
+        Self::parse(rom, offset).ok_or_else(|| Error::BadSeqBank { offset }.into())
+    }
+
+    fn parse(rom: &[u8], offset: usize) -> Option<Self> {
+        let bank = rom.get(offset..)?;
+        if be16(bank, 0)? != SEQ_BANK_REVISION {
+            return None;
+        }
+        let count = be16(bank, 2)? as usize;
+        if count == 0 || count > 0x400 {
+            return None;
+        }
+        let mut sequences = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = be32(bank, 4 + i * 8)? as usize;
+            let len = be32(bank, 8 + i * 8)? as usize;
+            let data = bank.get(start..start.checked_add(len)?)?;
+            if start < 4 + count * 8 || !header_fits(data) {
+                return None;
+            }
+            sequences.push((start, len));
+        }
+
+        Some(Self { offset, sequences })
+    }
+
+    /// The data of sequence `index`
+    pub fn sequence<'a>(&self, rom: &'a [u8], index: usize) -> Option<&'a [u8]> {
+        let &(start, len) = self.sequences.get(index)?;
+        rom.get(self.offset + start..self.offset + start + len)
+    }
+}
+
+/// Whether `data` starts with a sequence header: a division, and track offsets that are
+/// all inside of it
+fn header_fits(data: &[u8]) -> bool {
+    let Some(division) = be32(data, 0x40) else {
+        return false;
+    };
+    division != 0
+        && division <= 0x7FFF
+        && (0..16).all(|t| {
+            be32(data, t * 4)
+                .is_some_and(|o| o == 0 || (SEQUENCE_HEADER..data.len()).contains(&(o as usize)))
+        })
+}
+
+/// The rom offsets of the sequence banks of `rom`, found by their headers
+pub fn find_seq_banks(rom: &[u8]) -> Vec<usize> {
+    (0..rom.len().saturating_sub(4))
+        .step_by(4)
+        .filter(|&at| rom[at..at + 2] == SEQ_BANK_REVISION.to_be_bytes())
+        .filter(|&at| SeqBank::parse(rom, at).is_some())
+        .collect()
+}
+
+/// An event of a MIDI track
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    /// the tick of the event, from the start of the sequence
+    pub tick: u32,
+    /// the event as it is in a standard MIDI file: a status byte and its data,
+    /// or `FF type len data` for meta events
+    pub bytes: Vec<u8>,
+}
+
+/// A track of a sequence, with note offs for the durations of its notes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    /// the track's index (0 to 15) in the sequence header
+    pub index: usize,
+    /// the events of the track, in the order of their ticks
+    pub events: Vec<Event>,
+}
+
+/// A compressed MIDI sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sequence {
+    /// ticks per quarter note
+    pub division: u16,
+    pub tracks: Vec<Track>,
+}
+
+/// The bytes of a compressed MIDI track, with its repeats of earlier bytes undone
+struct TrackReader<'a> {
+    data: &'a [u8],
+    /// the start of the track
+    start: usize,
+    at: usize,
+    /// the position and count of the bytes left of a repeat
+    repeat: (usize, usize),
+}
+
+impl TrackReader<'_> {
+    fn raw(&mut self) -> Result<u8> {
+        let byte = *self.data.get(self.at).ok_or(Error::BadSequence {
+            offset: self.at,
+            reason: "a track runs past the end of the sequence",
+        })?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let (at, left) = self.repeat;
+        if left > 0 {
+            self.repeat = (at + 1, left - 1);
+            return Ok(self.data[at]);
+        }
+
+        let start = self.at;
+        let byte = self.raw()?;
+        if byte != BLOCK_CODE {
+            return Ok(byte);
+        }
+        let next = self.raw()?;
+        if next == BLOCK_CODE {
+            return Ok(BLOCK_CODE);
+        }
+        let back = u16::from_be_bytes([next, self.raw()?]) as usize;
+        let len = self.raw()? as usize;
+        let from = start
+            .checked_sub(back)
+            .filter(|&from| len > 0 && from >= self.start && from + len <= start)
+            .ok_or(Error::BadSequence {
+                offset: start,
+                reason: "a repeat of bytes outside of its track",
+            })?;
+        self.repeat = (from + 1, len - 1);
+
+        Ok(self.data[from])
+    }
+
+    fn var_len(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::BadSequence {
+            offset: self.at,
+            reason: "a variable length number of more than four bytes",
+        }
+        .into())
+    }
+}
+
+impl Sequence {
+    /// Read the compressed MIDI sequence `data`
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if !header_fits(data) {
+            return Err(Error::BadSequence {
+                offset: 0,
+                reason: "no sequence header",
+            }
+            .into());
+        }
+        let division = be32(data, 0x40).unwrap_or_default() as u16;
+        let mut tracks = Vec::new();
+        for index in 0..16 {
+            let start = be32(data, index * 4).unwrap_or_default() as usize;
+            if start != 0 {
+                tracks.push(Track {
+                    index,
+                    events: track(data, start)?,
+                });
+            }
+        }
+
+        Ok(Self { division, tracks })
+    }
+
+    /// The sequence as a standard MIDI file, of format 1 with a track for each of its tracks
+    pub fn midi(&self) -> Vec<u8> {
+        let mut midi = b"MThd".to_vec();
+        midi.extend_from_slice(&6u32.to_be_bytes());
+        midi.extend_from_slice(&1u16.to_be_bytes());
+        midi.extend_from_slice(&(self.tracks.len() as u16).to_be_bytes());
+        midi.extend_from_slice(&self.division.to_be_bytes());
+
+        for track in &self.tracks {
+            let mut chunk = Vec::new();
+            let mut tick = 0;
+            for event in &track.events {
+                write_var_len(&mut chunk, event.tick - tick);
+                chunk.extend_from_slice(&event.bytes);
+                tick = event.tick;
+            }
+            chunk.extend_from_slice(&[0, 0xFF, 0x2F, 0]);
+            midi.extend_from_slice(b"MTrk");
+            midi.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            midi.extend_from_slice(&chunk);
+        }
+
+        midi
+    }
+}
+
+/// Read the events of the track at `start` of the sequence `data`, up to its end of track
+fn track(data: &[u8], start: usize) -> Result<Vec<Event>> {
+    let mut r = TrackReader {
+        data,
+        start,
+        at: start,
+        repeat: (0, 0),
+    };
+    let mut events = Vec::new();
+    let mut tick = r.var_len()?;
+    let mut status = None;
+    loop {
+        let at = r.at;
+        let bad = |reason| Error::BadSequence { offset: at, reason };
+        let first = r.byte()?;
+        let mut event = |bytes: Vec<u8>| events.push(Event { tick, bytes });
+        if first == 0xFF {
+            status = None;
+            match r.byte()? {
+                // tempo
+                0x51 => event(vec![0xFF, 0x51, 3, r.byte()?, r.byte()?, r.byte()?]),
+                // end of track
+                0x2F => break,
+                0x2E => {
+                    r.byte()?;
+                    r.byte()?;
+                    event(marker(b"loopStart"));
+                }
+                0x2D => {
+                    for _ in 0..6 {
+                        r.byte()?;
+                    }
+                    event(marker(b"loopEnd"));
+                }
+                _ => return Err(bad("an unknown meta event").into()),
+            }
+        } else {
+            let (kind, data1) = match first {
+                0x80..=0xEF => {
+                    status = Some(first);
+                    (first, r.byte()?)
+                }
+                0x00..=0x7F => (status.ok_or(bad("running status without a status"))?, first),
+                _ => return Err(bad("a system exclusive event").into()),
+            };
+            match kind & 0xF0 {
+                0xC0 | 0xD0 => event(vec![kind, data1]),
+                0x90 => {
+                    let velocity = r.byte()?;
+                    let duration = r.var_len()?;
+                    event(vec![kind, data1, velocity]);
+                    events.push(Event {
+                        tick: tick + duration,
+                        bytes: vec![0x80 | (kind & 0x0F), data1, 0],
+                    });
+                }
+                _ => event(vec![kind, data1, r.byte()?]),
+            }
+        }
+        tick += r.var_len()?;
+    }
+    // a stable sort, so that events at the same tick stay in the order they were read
+    events.sort_by_key(|e| e.tick);
+
+    Ok(events)
+}
+
+/// A bank file of instruments, and the sounds they play
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BankFile {
+    /// rom offset of the bank file
+    pub offset: usize,
+    /// the end of the structures of the file, from its start
+    pub len: usize,
+    pub banks: Vec<Bank>,
+}
+
+/// A bank of instruments
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bank {
+    /// the rate that the samples of the bank play at, in Hz
+    pub sample_rate: u32,
+    pub percussion: Option<Instrument>,
+    /// the instruments of the bank; the unused ones are `None`
+    pub instruments: Vec<Option<Instrument>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instrument {
+    /// offset of the instrument in the bank file
+    pub offset: usize,
+    pub volume: u8,
+    pub pan: u8,
+    pub sounds: Vec<Sound>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sound {
+    /// offset of the sound in the bank file
+    pub offset: usize,
+    /// the MIDI key that plays the sample at its own pitch
+    pub key_base: u8,
+    /// fine tuning of the sample, in cents
+    pub detune: i8,
+    pub wave: Wave,
+}
+
+/// A sample of the wave table
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wave {
+    /// offset of the wave table in the bank file
+    pub offset: usize,
+    /// offset of the sample in the wave table (`.tbl`)
+    pub base: usize,
+    pub len: usize,
+    pub kind: WaveKind,
+    pub looped: Option<Loop>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaveKind {
+    Adpcm(Book),
+    Raw16,
+}
+
+/// The predictors of a VADPCM sample
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Book {
+    pub order: usize,
+    /// `order * 8` coefficients for each predictor
+    pub predictors: Vec<Vec<i16>>,
+}
+
+/// A loop of a sample, in samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Loop {
+    pub start: u32,
+    pub end: u32,
+    /// the number of times it loops, or -1 to loop forever
+    pub count: i32,
+}
+
+/// A reader of the bank file at the start of `ctl`, that keeps the end of what it has read
+struct BankReader<'a> {
+    ctl: &'a [u8],
+    offset: usize,
+    end: core::cell::Cell<usize>,
+}
+
+impl BankReader<'_> {
+    fn bytes(&self, at: usize, len: usize) -> Result<&[u8]> {
+        let bytes = self.ctl.get(at..at + len).ok_or(Error::BadBankFile {
+            offset: self.offset,
+            at,
+        })?;
+        self.end.set(self.end.get().max(at + len));
+        Ok(bytes)
+    }
+
+    fn u8(&self, at: usize) -> Result<u8> {
+        self.bytes(at, 1).map(|b| b[0])
+    }
+
+    fn u16(&self, at: usize) -> Result<u16> {
+        self.bytes(at, 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, at: usize) -> Result<u32> {
+        self.bytes(at, 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// The offset that the pointer at `at` holds, or `None` if it's null
+    fn pointer(&self, at: usize) -> Result<Option<usize>> {
+        self.u32(at).map(|p| (p != 0).then_some(p as usize))
+    }
+
+    fn instrument(&self, at: usize) -> Result<Instrument> {
+        let count = self.u16(at + 0x0E)? as usize;
+        let sounds = (0..count)
+            .map(|i| {
+                let sound = self.pointer(at + 0x10 + i * 4)?.ok_or(Error::BadBankFile {
+                    offset: self.offset,
+                    at: at + 0x10 + i * 4,
+                })?;
+                self.sound(sound)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Instrument {
+            offset: at,
+            volume: self.u8(at)?,
+            pan: self.u8(at + 1)?,
+            sounds,
+        })
+    }
+
+    fn sound(&self, at: usize) -> Result<Sound> {
+        let bad = |at| Error::BadBankFile {
+            offset: self.offset,
+            at,
+        };
+        let (key_base, detune) = match self.pointer(at + 4)? {
+            Some(key_map) => (self.u8(key_map + 4)?, self.u8(key_map + 5)? as i8),
+            None => (60, 0),
+        };
+        let wave = self.pointer(at + 8)?.ok_or_else(|| bad(at + 8))?;
+        self.bytes(at, 0x10)?;
+
+        Ok(Sound {
+            offset: at,
+            key_base,
+            detune,
+            wave: self.wave(wave)?,
+        })
+    }
+
+    fn wave(&self, at: usize) -> Result<Wave> {
+        let looped = match self.pointer(at + 0x0C)? {
+            Some(l) => Some(Loop {
+                start: self.u32(l)?,
+                end: self.u32(l + 4)?,
+                count: self.u32(l + 8)? as i32,
+            }),
+            None => None,
+        };
+        let kind = match self.u8(at + 8)? {
+            0 => {
+                let book = self.pointer(at + 0x10)?.ok_or(Error::BadBankFile {
+                    offset: self.offset,
+                    at: at + 0x10,
+                })?;
+                let order = self.u32(book)? as usize;
+                let count = self.u32(book + 4)? as usize;
+                if order == 0 || order > 8 || count == 0 || count > 16 {
+                    return Err(Error::BadBankFile {
+                        offset: self.offset,
+                        at: book,
+                    }
+                    .into());
+                }
+                let coefficients = self.bytes(book + 8, order * count * 8 * 2)?;
+                let predictors = coefficients
+                    .chunks(order * 8 * 2)
+                    .map(|p| {
+                        p.chunks(2)
+                            .map(|c| i16::from_be_bytes([c[0], c[1]]))
+                            .collect()
+                    })
+                    .collect();
+                WaveKind::Adpcm(Book { order, predictors })
+            }
+            1 => WaveKind::Raw16,
+            _ => {
+                return Err(Error::BadBankFile {
+                    offset: self.offset,
+                    at: at + 8,
+                }
+                .into())
+            }
+        };
+
+        Ok(Wave {
+            offset: at,
+            base: self.u32(at)? as usize,
+            len: self.u32(at + 4)? as usize,
+            kind,
+            looped,
+        })
+    }
+}
+
+impl BankFile {
+    /// Read the bank file at `offset` of `rom`
+    pub fn read(rom: &[u8], offset: usize) -> Result<Self> {
+        let bad = |at| Error::BadBankFile { offset, at };
+        let r = BankReader {
+            ctl: rom.get(offset..).unwrap_or_default(),
+            offset,
+            end: core::cell::Cell::new(0),
+        };
+        if r.u16(0)? != BANK_FILE_REVISION {
+            return Err(bad(0).into());
+        }
+        let count = r.u16(2)? as usize;
+        let mut banks = Vec::with_capacity(count);
+        for i in 0..count {
+            let bank = r.pointer(4 + i * 4)?.ok_or_else(|| bad(4 + i * 4))?;
+            let instruments = r.u16(bank)? as usize;
+            let percussion = r.pointer(bank + 8)?.map(|p| r.instrument(p)).transpose()?;
+            let instruments = (0..instruments)
+                .map(|k| {
+                    r.pointer(bank + 0x0C + k * 4)?
+                        .map(|p| r.instrument(p))
+                        .transpose()
+                })
+                .collect::<Result<_>>()?;
+            banks.push(Bank {
+                sample_rate: r.u32(bank + 4)?,
+                percussion,
+                instruments,
+            });
+        }
+
+        Ok(Self {
+            offset,
+            len: r.end.get(),
+            banks,
+        })
+    }
+
+    /// The waves of the sounds of every instrument, once each, in the order of their
+    /// offsets in the bank file
+    pub fn waves(&self) -> Vec<&Wave> {
+        let mut waves = self
+            .banks
+            .iter()
+            .flat_map(|b| b.percussion.iter().chain(b.instruments.iter().flatten()))
+            .flat_map(|i| i.sounds.iter().map(|s| &s.wave))
+            .collect::<Vec<_>>();
+        waves.sort_by_key(|w| w.offset);
+        waves.dedup_by_key(|w| w.offset);
+        waves
+    }
+}
+
+/// The rom offsets of the bank files of `rom`, found by their headers
+pub fn find_bank_files(rom: &[u8]) -> Vec<usize> {
+    (0..rom.len().saturating_sub(4))
+        .step_by(4)
+        .filter(|&at| rom[at..at + 2] == BANK_FILE_REVISION.to_be_bytes())
+        .filter(|&at| {
+            let count = be16(rom, at + 2).unwrap_or_default();
+            (1..0x100).contains(&count) && BankFile::read(rom, at).is_ok()
+        })
+        .collect()
+}
+
+impl Wave {
+    /// Decode the sample of the wave table `tbl` to 16 bit PCM
+    pub fn decode(&self, tbl: &[u8]) -> Result<Vec<i16>> {
+        let data = tbl
+            .get(self.base..self.base + self.len)
+            .ok_or(Error::OutsideFile {
+                start: self.base,
+                end: self.base + self.len,
+                len: tbl.len(),
+            })?;
+        Ok(match &self.kind {
+            WaveKind::Raw16 => data
+                .chunks_exact(2)
+                .map(|s| i16::from_be_bytes([s[0], s[1]]))
+                .collect(),
+            WaveKind::Adpcm(book) => decode_adpcm(data, book),
+        })
+    }
+}
+
+/// Decode the VADPCM frames of `data` with the predictors of `book`
+pub fn decode_adpcm(data: &[u8], book: &Book) -> Vec<i16> {
+    let order = book.order;
+    let mut samples = Vec::with_capacity(data.len() / ADPCM_FRAME * ADPCM_SAMPLES);
+    // the last samples of the frame before, which start the prediction of the next
+    let mut last = [0i32; 8];
+    for frame in data.chunks_exact(ADPCM_FRAME) {
+        let scale = 1i32 << (frame[0] >> 4);
+        let coefficients = book
+            .predictors
+            .get((frame[0] & 0xF) as usize)
+            .map_or(&[][..], |p| p.as_slice());
+        let residuals = frame[1..]
+            .iter()
+            .flat_map(|b| [b >> 4, b & 0xF])
+            .map(|n| ((n as i8) << 4 >> 4) as i32 * scale)
+            .collect::<Vec<_>>();
+
+        let mut out = [0i32; ADPCM_SAMPLES];
+        for half in 0..2 {
+            let residuals = &residuals[half * 8..half * 8 + 8];
+            // the samples before this half
+            let previous = if half == 0 {
+                &last[8 - order..]
+            } else {
+                &out[8 - order..8]
+            };
+            let previous = previous.to_vec();
+            // the coefficient of `previous[k]` for the i-th sample
+            let c = |k: usize, i: usize| coefficients.get(k * 8 + i).copied().unwrap_or(0) as i32;
+            for i in 0..8 {
+                let mut total = 0;
+                for (k, &p) in previous.iter().enumerate() {
+                    total += c(k, i) * p;
+                }
+                // each residual adds to the samples after it, through the last coefficients
+                total += residuals[i] << 11;
+                for (j, &r) in residuals[..i].iter().enumerate() {
+                    total += c(order - 1, i - j - 1) * r;
+                }
+                out[half * 8 + i] = total.div_euclid(1 << 11);
+            }
+        }
+        last.copy_from_slice(&out[8..]);
+        samples.extend(
+            out.iter()
+                .map(|&s| s.clamp(i16::MIN as i32, i16::MAX as i32) as i16),
+        );
+    }
+
+    samples
+}
+
+/// Write mono 16 bit `samples` as a WAV file, with a `smpl` chunk for a loop
+pub fn wav(samples: &[i16], sample_rate: u32, looped: Option<Loop>) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let smpl_len = if looped.is_some() { 8 + 36 + 24 } else { 0 };
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(4 + 8 + 16 + 8 + data_len + smpl_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel, the rate, bytes per second, block align, and bits per sample
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        wav.extend_from_slice(&s.to_le_bytes());
+    }
+
+    if let Some(l) = looped {
+        wav.extend_from_slice(b"smpl");
+        wav.extend_from_slice(&(smpl_len - 8).to_le_bytes());
+        // manufacturer, product, sample period, unity note, pitch fraction, smpte format
+        // and offset, one loop, and no sampler data
+        let period = 1_000_000_000 / sample_rate.max(1);
+        for field in [0, 0, period, 60, 0, 0, 0, 1, 0] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        // the loop's id, type (forward), start, end (inclusive), fraction, and play count
+        let count = if l.count < 0 { 0 } else { l.count as u32 };
+        for field in [0, 0, l.start, l.end.saturating_sub(1), 0, count] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+
+    wav
+}
+
+/// A marker meta event with `text`
+fn marker(text: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0x06, text.len() as u8];
+    bytes.extend_from_slice(text);
+    bytes
+}
+
+fn write_var_len(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        bytes.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+    let b = data.get(at..at + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    let b = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
@@ -0,0 +1,87 @@
+use crate::{cli::SkeletonOpt, inject, timings::Timings};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use ssbfile::skeleton::{self, Bone};
+
+pub(crate) fn dump(opt: SkeletonOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let offsets = match opt.offset {
+        Some(offset) => vec![offset as usize],
+        None => skeleton::find(&data, &relocs),
+    };
+    if offsets.is_empty() {
+        bail!("found no skeleton in file <{}>; give its --offset", opt.id);
+    }
+    let mut skeletons = Vec::new();
+    for &offset in &offsets {
+        let bones = skeleton::skeleton(&data, offset, &relocs)
+            .with_context(|| format!("reading the skeleton at {:#X}", offset))?;
+        skeletons.push((offset, bones));
+    }
+
+    if opt.json {
+        let json = Json {
+            file: opt.id,
+            skeletons: skeletons
+                .iter()
+                .map(|(offset, bones)| SkeletonJson {
+                    offset: *offset,
+                    bones: bones.iter().map(BoneJson::from).collect(),
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string(&json)?);
+        return Ok(());
+    }
+    for (i, (offset, bones)) in skeletons.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "skeleton of file {} at 0x{:04X}: {} bones",
+            opt.id,
+            offset,
+            bones.len()
+        );
+        print!("{}", skeleton::tree(bones));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Json {
+    file: usize,
+    skeletons: Vec<SkeletonJson>,
+}
+
+#[derive(Serialize)]
+struct SkeletonJson {
+    offset: usize,
+    bones: Vec<BoneJson>,
+}
+
+#[derive(Serialize)]
+struct BoneJson {
+    offset: usize,
+    parent: Option<usize>,
+    display_list: Option<usize>,
+    translate: [f32; 3],
+    rotate: [f32; 3],
+    scale: [f32; 3],
+}
+
+impl From<&Bone> for BoneJson {
+    fn from(bone: &Bone) -> Self {
+        Self {
+            offset: bone.offset,
+            parent: bone.parent,
+            display_list: bone.display_list,
+            translate: bone.translate,
+            rotate: bone.rotate,
+            scale: bone.scale,
+        }
+    }
+}
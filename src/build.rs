@@ -0,0 +1,271 @@
+use crate::{
+    cli::{BuildOpt, CompressOpt, Mode},
+    diff::fnv1a,
+    inject,
+    manifest::{Manifest, ManifestFile},
+    timings::{Stage, Timings},
+};
+use anyhow::{ensure, Context, Result};
+use ssbfile::{versions::SSBInfo, DataRegion, TableEntry, TableFile};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+const NO_RELOCS: u16 = 0xFFFF;
+
+pub(crate) fn rom(opt: BuildOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let original = opt.patch.any().then(|| rom.clone());
+    let manifest = Manifest::read(&opt.manifest)?;
+    let root = opt.manifest.parent().unwrap_or_else(|| Path::new("."));
+    check_manifest(&manifest, &version)?;
+    opt.grow.check(&manifest, &version)?;
+
+    let version = apply_manifest(
+        &mut rom,
+        &version,
+        &manifest,
+        |path| read_file(&root.join(path)),
+        &opt.compress,
+        &mut EncodedFiles::new(),
+        timings,
+    )?;
+    inject::remap_externs(&mut rom, &version, &opt.remap_externs)?;
+    if opt.find_duplicates {
+        report_duplicates(&rom, &version, timings)?;
+    }
+    if opt.share_duplicates {
+        let mut region = DataRegion::read(&rom, &version)?;
+        let saved = region.share_duplicates();
+        timings.time(Stage::Layout, || region.write(&mut rom, &version))?;
+        eprintln!(
+            "sharing the data of identical files saved {:#X} bytes",
+            saved
+        );
+    }
+    // a rom with a changed header gets its crcs recalculated along with it
+    if opt.header.apply(&mut rom)? || opt.out.fix_crc {
+        ssbfile::rom_info::fix_crc(&mut rom)?;
+    }
+    if let Some(original) = &original {
+        opt.patch.write(original, &rom, timings)?;
+    }
+
+    opt.out.backup.write(&opt.output, &rom, timings)
+}
+
+/// Print the groups of files of `rom` that are the same once decompressed, with the same
+/// relocations and external files, and the bytes that storing each group once would save
+fn report_duplicates(rom: &[u8], version: &SSBInfo, timings: &mut Timings) -> Result<()> {
+    type Key = (u64, usize, Option<usize>, Option<usize>, Vec<u16>);
+    let mut groups: BTreeMap<Key, Vec<(usize, usize)>> = BTreeMap::new();
+    for id in 0..version.total_entries() {
+        let file = timings
+            .time(Stage::TableParse, || TableFile::get(id, rom, version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let data = timings
+            .time(Stage::Decompress, || file.decompressed())
+            .with_context(|| format!("decompressing file <{}>", id))?;
+        let externs = file.includes().unwrap_or_default().to_vec();
+        // the stored data and the list of external files, padded as it is laid out
+        let stored = (file.raw.len() + externs.len() * 2).next_multiple_of(4);
+        let key = (
+            fnv1a(&data),
+            data.len(),
+            file.inreloc.as_ref().map(|r| r.get_starting_offset()),
+            file.exreloc.as_ref().map(|r| r.get_starting_offset()),
+            externs,
+        );
+        groups.entry(key).or_default().push((id, stored));
+    }
+
+    let mut saved = 0;
+    let mut duplicates = groups
+        .into_values()
+        .filter(|ids| ids.len() > 1 && ids.iter().any(|&(_, stored)| stored > 0))
+        .collect::<Vec<_>>();
+    duplicates.sort();
+    for ids in &duplicates {
+        // the smallest copy would be the one that is kept
+        let kept = ids.iter().map(|&(_, stored)| stored).min().unwrap_or(0);
+        let bytes = ids.iter().map(|&(_, stored)| stored).sum::<usize>() - kept;
+        saved += bytes;
+        let list = ids
+            .iter()
+            .map(|(id, _)| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("* files {}: {:#X} bytes", list, bytes);
+    }
+    println!(
+        "{} groups of identical files; sharing their data would save {:#X} bytes",
+        duplicates.len(),
+        saved
+    );
+
+    Ok(())
+}
+
+/// Check that `manifest` can be built into a rom of `version`
+pub(crate) fn check_manifest(manifest: &Manifest, version: &SSBInfo) -> Result<()> {
+    if let Some(mode) = &manifest.mode {
+        ensure!(
+            mode == Mode::Decompressed.name(),
+            "the files of the manifest were extracted with `--mode {}`; only decompressed files can be built",
+            mode
+        );
+    }
+    match &manifest.version {
+        Some(v) if *v != version.version => eprintln!(
+            "the manifest was extracted from a {} rom, and is being built into a {} rom",
+            v, version.version
+        ),
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// The table entry and packed data of files that were already encoded,
+/// by id, with the hash of the file data they were encoded from
+pub(crate) type EncodedFiles = HashMap<usize, (u64, TableEntry, Vec<u8>)>;
+
+/// Read the file at `path`
+pub(crate) fn read_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("issue opening <{}>", path.display()))
+}
+
+/// Build the files of `manifest` into `rom`, reading their data with `read` from the
+/// paths in the manifest. Files that were
+/// encoded before from the same data are taken from `encoded`, and new ones are added to it.
+/// Returns the version info of the built rom, whose table may have grown.
+pub(crate) fn apply_manifest(
+    rom: &mut Vec<u8>,
+    version: &SSBInfo,
+    manifest: &Manifest,
+    read: impl Fn(&Path) -> Result<Vec<u8>>,
+    compress: &CompressOpt,
+    encoded: &mut EncodedFiles,
+    timings: &mut Timings,
+) -> Result<SSBInfo> {
+    let mut region = timings
+        .time(Stage::TableParse, || DataRegion::read(rom, version))
+        .context("reading the resource table")?;
+
+    // files past the end of the table are added to it, in order of id
+    let mut files = manifest.files.iter().collect::<Vec<_>>();
+    files.sort_by_key(|file| file.id);
+    let mut unchanged = 0;
+    for file in files {
+        let data = read(&file.path)?;
+        // files that haven't been edited since they were extracted keep their data from the rom
+        let original = TableFile::get(file.id, rom, version).ok();
+        if original.is_some_and(|entry| file.unchanged(&entry, &data)) {
+            unchanged += 1;
+            continue;
+        }
+
+        let hash = fnv1a(&data);
+        let (entry, packed) = match encoded.get(&file.id) {
+            Some((h, entry, packed)) if *h == hash => (*entry, packed.clone()),
+            _ => {
+                let (entry, packed) = encode(file, &data, &region, compress, timings)?;
+                encoded.insert(file.id, (hash, entry, packed.clone()));
+                (entry, packed)
+            }
+        };
+
+        let count = region.entries().len();
+        if file.id < count {
+            region.replace(file.id, entry, packed).with_context(|| {
+                format!(
+                    "replacing file <{}> with <{}>",
+                    file.id,
+                    file.path.display()
+                )
+            })?;
+        } else {
+            ensure!(
+                file.id == count,
+                "new file <{}> has to follow the last file of the table (<{}>)",
+                file.id,
+                count - 1
+            );
+            region.push(entry, packed);
+        }
+    }
+    if unchanged > 0 {
+        eprintln!("kept the rom's data for {} unchanged files", unchanged);
+    }
+
+    timings
+        .time(Stage::Layout, || region.write(rom, version))
+        .context("laying out the resource table")?;
+    let grown = region.table_info(version);
+    if grown.table_end != version.table_end {
+        eprintln!(
+            "the resource table grew to {} files, and is now at {:#X}..{:#X}. \
+             This moved the data of every file by {:#X} bytes, and the game's code still \
+             reads {} files, so it can't load any of them until it is patched; \
+             read the new rom with --table-start {:#X} --table-end {:#X}",
+            grown.total_entries(),
+            grown.table_start,
+            grown.table_end,
+            grown.table_end - version.table_end,
+            version.total_entries(),
+            grown.table_start,
+            grown.table_end,
+        );
+    }
+
+    Ok(grown)
+}
+
+/// The table entry and packed data (with the list of external files) for `file`
+fn encode(
+    file: &ManifestFile,
+    data: &[u8],
+    region: &DataRegion,
+    compress: &CompressOpt,
+    timings: &mut Timings,
+) -> Result<(TableEntry, Vec<u8>)> {
+    let stored = if file.compressed {
+        let original = region
+            .entries()
+            .get(file.id)
+            .filter(|e| e.compressed)
+            .and_then(|_| region.file(file.id));
+        let settings = compress.settings(original)?;
+        timings.time(Stage::Compress, || {
+            ssbfile::compress_with(data, file.id, &settings)
+        })?
+    } else {
+        data.to_vec()
+    };
+
+    let words = |len: usize| {
+        let words = len.div_ceil(4);
+        ensure!(
+            words <= u16::MAX as usize,
+            "file <{}> of {:#X} bytes is too large for the table entry",
+            file.id,
+            len
+        );
+        Ok(words as u16)
+    };
+    let reloc = |offset: Option<u32>| offset.map_or(NO_RELOCS, |o| (o / 4) as u16);
+    let entry = TableEntry {
+        offset: 0,
+        compressed: file.compressed,
+        inreloc: reloc(file.inreloc),
+        size: words(stored.len())?,
+        exreloc: reloc(file.exreloc),
+        decompressed_size: words(data.len())?,
+    };
+
+    let packed = ssbfile::pack_file(&stored, &file.externs);
+
+    Ok((entry, packed))
+}
@@ -0,0 +1,54 @@
+use alloc::{format, string::String};
+use core::fmt::Write;
+
+/// How many bytes are written on one line of the array
+const BYTES_PER_LINE: usize = 16;
+
+/// The default symbol for the array of file `id`, like `file_0012`
+pub fn default_symbol(id: usize) -> String {
+    format!("file_{:04}", id)
+}
+
+/// Whether `symbol` can be used as a C identifier
+pub fn is_identifier(symbol: &str) -> bool {
+    let mut chars = symbol.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// C source that defines `data` as `unsigned char symbol[] = {...};`,
+/// and includes the `header` that declares it
+pub fn source(symbol: &str, header: &str, data: &[u8]) -> String {
+    let mut s = format!("#include \"{}\"\n\n", header);
+    writeln!(s, "unsigned char {}[{}] = {{", symbol, size_macro(symbol)).expect("write to string");
+    for line in data.chunks(BYTES_PER_LINE) {
+        s += "   ";
+        for byte in line {
+            write!(s, " 0x{:02X},", byte).expect("write to string");
+        }
+        s.push('\n');
+    }
+    s += "};\n";
+
+    s
+}
+
+/// A C header with the length of the array `symbol`, and an extern declaration of it
+pub fn header(symbol: &str, len: usize) -> String {
+    let guard = format!("{}_H", symbol.to_ascii_uppercase());
+    let size = size_macro(symbol);
+
+    format!(
+        "#ifndef {guard}\n#define {guard}\n\n\
+         #define {size} {len:#X}\n\n\
+         extern unsigned char {symbol}[{size}];\n\n\
+         #endif\n",
+    )
+}
+
+/// The macro for the length of the array `symbol`, like `FILE_0012_SIZE`
+fn size_macro(symbol: &str) -> String {
+    format!("{}_SIZE", symbol.to_ascii_uppercase())
+}
@@ -0,0 +1,60 @@
+//! The structure of a fighter's main file: its attribute block, the bone trees of its
+//! models and the display lists they draw, and the other files it points into.
+//!
+//! Each part is found by the finder of its own module, from the file's relocations:
+//! the layout of the main file's header isn't mapped, so nothing here depends on a
+//! fixed offset into it.
+use crate::{display_list, fighter::Attributes, skeleton, Relocation};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// A bone tree of a fighter's models
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Model {
+    /// offset of its `DObjDesc` array
+    pub offset: usize,
+    pub bones: usize,
+    /// the bones that draw a display list
+    pub parts: usize,
+}
+
+/// The parts of a fighter's main file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Character {
+    /// offset of the attributes, if a block of them is found by [`Attributes::find`]
+    pub attributes: Option<usize>,
+    pub models: Vec<Model>,
+    /// offsets of the display lists of the file
+    pub display_lists: Vec<usize>,
+    /// the files that the main file points into (its animations and special files among
+    /// them), with the number of pointers to each
+    pub linked_files: Vec<(usize, usize)>,
+}
+
+/// Find the parts of the fighter main `file`
+pub fn character(file: &[u8], relocs: &[Relocation]) -> Character {
+    let models = skeleton::find(file, relocs)
+        .into_iter()
+        .filter_map(|offset| {
+            let bones = skeleton::skeleton(file, offset, relocs).ok()?;
+            Some(Model {
+                offset,
+                bones: bones.len(),
+                parts: bones.iter().filter(|b| b.display_list.is_some()).count(),
+            })
+        })
+        .collect();
+
+    let mut linked_files = BTreeMap::new();
+    for file in relocs.iter().filter_map(|r| r.external_file) {
+        *linked_files.entry(file as usize).or_insert(0) += 1;
+    }
+
+    Character {
+        attributes: Attributes::find(file),
+        models,
+        display_lists: display_list::find(file, relocs),
+        linked_files: linked_files.into_iter().collect(),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{cli::CharacterOpt, fighters, inject, timings::Timings};
+use anyhow::Result;
+use ssbfile::character;
+
+pub(crate) fn print(opt: CharacterOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (id, _) = fighters::location(&opt.rom, &version, &opt.fighter, opt.file, None)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, id, timings)?;
+
+    let character = character::character(&data, &relocs);
+    if opt.json {
+        println!("{}", serde_json::to_string(&character)?);
+        return Ok(());
+    }
+
+    println!("{} (file {})", opt.fighter.to_lowercase(), id);
+    match character.attributes {
+        Some(offset) => println!("attributes at 0x{:04X}", offset),
+        None => println!("no attributes found"),
+    }
+    println!("{} models", character.models.len());
+    for model in &character.models {
+        println!(
+            "  0x{:04X}: {} bones, {} with display lists",
+            model.offset, model.bones, model.parts
+        );
+    }
+    println!("{} display lists", character.display_lists.len());
+    println!("{} linked files", character.linked_files.len());
+    for (file, pointers) in &character.linked_files {
+        println!("  file {}: {} pointers", file, pointers);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::Relocation;
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::fmt;
+
+/// A guess at what a file holds, from the shape of its decompressed data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileKind {
+    /// no data, or only zeros
+    Empty,
+    /// has F3DEX2 display lists, so it is (or has) a model
+    Geometry,
+    /// has libultra `Sprite` structs: sprites and their bitmaps
+    Sprites,
+    /// mostly pointers, like a table of other structs
+    Pointers,
+    /// mostly 32-bit floats, like animation or collision data
+    Floats,
+    /// anything else
+    Data,
+}
+
+impl FileKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::Geometry => "geometry",
+            Self::Sprites => "sprites",
+            Self::Pointers => "pointers",
+            Self::Floats => "floats",
+            Self::Data => "data",
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// F3DEX2 opcodes that are common in the display lists of models
+const GFX_OPCODES: &[u8] = &[
+    0x01, // G_VTX
+    0x05, // G_TRI1
+    0x06, // G_TRI2
+    0x07, // G_QUAD
+    0xD7, // G_TEXTURE
+    0xD9, // G_GEOMETRYMODE
+    0xDA, // G_MTX
+    0xDB, // G_MOVEWORD
+    0xDC, // G_MOVEMEM
+    0xDE, // G_DL
+    0xE2, // G_SETOTHERMODE_L
+    0xE3, // G_SETOTHERMODE_H
+    0xE6, // G_RDPLOADSYNC
+    0xE7, // G_RDPPIPESYNC
+    0xE8, // G_RDPTILESYNC
+    0xF0, // G_LOADTLUT
+    0xF2, // G_SETTILESIZE
+    0xF3, // G_LOADBLOCK
+    0xF5, // G_SETTILE
+    0xFA, // G_SETPRIMCOLOR
+    0xFB, // G_SETENVCOLOR
+    0xFC, // G_SETCOMBINE
+    0xFD, // G_SETTIMG
+];
+const G_ENDDL: u64 = 0xDF00_0000_0000_0000;
+/// how many commands have to come before a `G_ENDDL` to count as a display list
+const MIN_DL_COMMANDS: usize = 3;
+
+/// the offsets in a libultra `Sprite` struct
+const SPRITE_SIZE: usize = 0x44;
+const SPRITE_SCALE: usize = 0x08;
+const SPRITE_BMFMT: usize = 0x30;
+const SPRITE_BITMAP: usize = 0x34;
+
+/// Guess what `file` holds from its decompressed data and its pointers (see
+/// [`relocations`](crate::relocations)). The checks go from the most to the least
+/// specific: display lists, sprite structs, the share of pointers, and then floats.
+pub fn classify(file: &[u8], relocs: &[Relocation]) -> FileKind {
+    let words = file
+        .chunks_exact(4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+        .collect::<Vec<_>>();
+    let nonzero = words.iter().filter(|&&w| w != 0).count();
+    let pointers = relocs.iter().map(|r| r.offset).collect::<BTreeSet<_>>();
+
+    if nonzero == 0 {
+        FileKind::Empty
+    } else if has_display_list(file) {
+        FileKind::Geometry
+    } else if has_sprite(file, relocs, &pointers) {
+        FileKind::Sprites
+    } else if pointers.len() * 2 >= nonzero {
+        FileKind::Pointers
+    } else if words.iter().filter(|&&w| is_float(w)).count() * 2 >= nonzero {
+        FileKind::Floats
+    } else {
+        FileKind::Data
+    }
+}
+
+/// Whether `file` has a run of display list commands that ends in a `G_ENDDL`
+fn has_display_list(file: &[u8]) -> bool {
+    let mut run = 0;
+    for cmd in file.chunks_exact(8) {
+        let cmd = u64::from_be_bytes(cmd.try_into().expect("8 byte chunk"));
+        if cmd == G_ENDDL && run >= MIN_DL_COMMANDS {
+            return true;
+        }
+        run = if GFX_OPCODES.contains(&((cmd >> 56) as u8)) {
+            run + 1
+        } else {
+            0
+        };
+    }
+
+    false
+}
+
+/// Whether a pointer of `file` points to something shaped like a libultra `Sprite`:
+/// a scale of 1.0, a valid bitmap format and size, and a pointer to its bitmaps
+fn has_sprite(file: &[u8], relocs: &[Relocation], pointers: &BTreeSet<usize>) -> bool {
+    let word = |at: usize| {
+        file.get(at..at + 4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+    };
+    relocs
+        .iter()
+        .filter(|r| r.external_file.is_none())
+        .map(|r| r.target as usize)
+        .filter(|&s| s + SPRITE_SIZE <= file.len())
+        .any(|s| {
+            word(s + SPRITE_SCALE) == Some(0x3F80_0000)
+                && word(s + SPRITE_SCALE + 4) == Some(0x3F80_0000)
+                && file[s + SPRITE_BMFMT] <= 4
+                && file[s + SPRITE_BMFMT + 1] <= 3
+                && pointers.contains(&(s + SPRITE_BITMAP))
+        })
+}
+
+/// Whether `word` is a 32-bit float with a magnitude of about 1/1024 to 2^20,
+/// which is where most of the floats of a model or animation are
+fn is_float(word: u32) -> bool {
+    let exponent = (word >> 23) & 0xFF;
+    (117..=147).contains(&exponent)
+}
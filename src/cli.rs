@@ -0,0 +1,1839 @@
+use crate::{
+    manifest::Manifest,
+    template::NameTemplate,
+    timings::{Stage, Timings},
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use ssbfile::{
+    image::TexelFormat,
+    mips::Arg,
+    names::NameDb,
+    versions::{SSBInfo, VersionDb},
+    CompressSettings, PartialRom, RomSource, SsbRom, VpkMethod,
+};
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+/// A quick utility to export the relocatable data from SSB64
+#[derive(Debug, StructOpt)]
+pub(crate) struct Opt {
+    /// print the time spent in each stage of the command
+    #[structopt(long)]
+    pub(crate) timings: bool,
+    #[structopt(subcommand)]
+    pub(crate) cmd: Command,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) enum Command {
+    /// export a file from the resource table
+    Extract(ExtractOpt),
+    /// list every file in the resource table
+    List(ListOpt),
+    /// print the table entry of a file, without exporting it
+    Info(InfoOpt),
+    /// replace a file in the resource table, and write out a new rom
+    Inject(InjectOpt),
+    /// rebuild the resource table of a rom from a manifest of files
+    Build(BuildOpt),
+    /// compare the resource tables of two roms
+    Diff(DiffOpt),
+    /// build a rom whose files each come from one of several roms (e.g., the audio of one
+    /// region with the text of another), or extract the files of the merged table
+    Merge(MergeOpt),
+    /// search the decompressed data of every file for a byte pattern
+    Search(SearchOpt),
+    /// find the file that holds a rom address
+    Addr(AddrOpt),
+    /// summarize the sizes, compression, and relocations of the resource table
+    Stats(StatsOpt),
+    /// check that a rom dump is good: its crcs, version, and resource table
+    Verify(VerifyOpt),
+    /// print GameShark codes that make an in-place edit of a file in RAM
+    Gameshark(GameSharkOpt),
+    /// check that files survive being extracted and encoded again: relocated and
+    /// re-chained, and decompressed and recompressed
+    Roundtrip(RoundtripOpt),
+    /// build a rom from a manifest, and build it again whenever its files change
+    Watch(WatchOpt),
+    /// start a modding project: extract every file with a manifest, and write
+    /// a config and a .gitignore for the rom
+    Init(InitOpt),
+    /// print the resource table and its files as segments for splat or n64split
+    Segments(SegmentsOpt),
+    /// edit table entries in place, without touching the data of the files
+    Entry(EntryCmd),
+    /// bundle the files of a manifest that differ from a clean rom into a .ssbmod archive
+    PackMod(PackModOpt),
+    /// apply a .ssbmod archive to a clean rom
+    ApplyMod(ApplyModOpt),
+    /// restore a rom from the backup that was made when it was last overwritten
+    Undo(UndoOpt),
+    /// decode the textures of a file and write them as PNGs
+    Texture(TextureOpt),
+    /// encode a PNG into a texture of a file, and write out a new rom
+    ImportTexture(ImportTextureOpt),
+    /// write the palettes of color indexed textures as JASC .pal files and PNG swatches
+    Palette(PaletteOpt),
+    /// replace a palette of a file with a .pal or swatch, recoloring its textures without
+    /// touching their texels, and write out a new rom
+    ImportPalette(ImportPaletteOpt),
+    /// print the F3DEX2 display lists of a file as gbi.h macros
+    Gfx(GfxOpt),
+    /// print the parts of a fighter's main file: its attributes, the bone trees of its
+    /// models, its display lists, and the files it points into
+    Character(CharacterOpt),
+    /// export the meshes that the display lists of a file draw as OBJ and MTL (with their
+    /// textures as PNGs), or as glTF with their skeleton
+    Model(ModelOpt),
+    /// disassemble the subaction scripts of a fighter file, and the scripts they call
+    Script(ScriptOpt),
+    /// assemble edited subaction scripts, in the format that `script` prints, back into
+    /// their places in a fighter file
+    ImportScript(ImportScriptOpt),
+    /// print the hitboxes that subaction scripts make, with the frames they are made on, as json
+    Hitboxes(HitboxesOpt),
+    /// print the collision lines, spawn points, and bounds of a stage file, or draw them as an SVG
+    Stage(StageOpt),
+    /// decode the glyphs of a font into a PNG atlas, with a json map of the characters
+    Font(FontOpt),
+    /// find the strings in the data of files, and write them as a PO template or CSV
+    /// keyed by message id, for translation
+    Text(TextOpt),
+    /// write the translations of a PO or CSV file from `text` back into the strings of
+    /// their files, moving strings that outgrow their places to the end of the file
+    ImportText(ImportTextOpt),
+    /// list the sequences of the music bank, or convert them to MIDI files
+    Music(MusicOpt),
+    /// decode the samples of the instrument banks to WAV files, with a json manifest of
+    /// the banks, their instruments, and the sounds that play each sample
+    Samples(SamplesOpt),
+    /// print the attributes and spawn weights of items as json, or change them with --set
+    Item(ItemOpt),
+    /// print the event streams of the joints of an animation
+    Anim(AnimOpt),
+    /// assemble edited animation streams, in the format that `anim` prints, back into
+    /// their places in a file
+    ImportAnim(ImportAnimOpt),
+    /// print the bone trees of a file, with the display list and default transform of
+    /// each bone
+    Skeleton(SkeletonOpt),
+    /// print where the sprites of a menu file are drawn (position, depth, color, and
+    /// flags) as toml
+    Layout(LayoutOpt),
+    /// write an edited layout, in the toml that `layout` prints, back into the sprites
+    /// of a menu file
+    ImportLayout(ImportLayoutOpt),
+    /// disassemble the particle scripts of an effect bank, with the names of their commands
+    Effect(EffectOpt),
+    /// print the targets and platforms of a bonus stage as json, or move them with --set
+    Bonus(BonusOpt),
+    /// print the attributes of a fighter (speeds, jump heights, weight, ...) as toml or json
+    Fighter(FighterOpt),
+    /// write edited fighter attributes, in the toml that `fighter` prints, back into the
+    /// fighter's main file
+    ImportFighter(ImportFighterOpt),
+    /// run the game's own file loader over files in a MIPS interpreter, and check that it
+    /// writes the same pointers as this crate
+    CheckLoader(CheckLoaderOpt),
+}
+
+/// The rom that a command reads from
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) struct RomOpt {
+    /// path to SSB64 rom
+    #[structopt(name = "rom", short = "r", long = "rom", parse(from_os_str))]
+    pub(crate) path: PathBuf,
+    /// toml file of additional versions (name, crc, table_start, and table_end) to recognize
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) versions: Option<PathBuf>,
+    /// yaml file that maps file ids to names, used in listings and the names of exported files
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) names: Option<PathBuf>,
+    /// rom offset of the resource table, for versions that aren't known (e.g., other
+    /// regions, prototypes, or hacks) when it isn't found by its layout; requires --table-end
+    #[structopt(long, requires = "table-end", parse(try_from_str = parse_addr))]
+    pub(crate) table_start: Option<u32>,
+    /// rom offset of the end of the resource table (after its terminal entry)
+    #[structopt(long, requires = "table-start", parse(try_from_str = parse_addr))]
+    pub(crate) table_end: Option<u32>,
+}
+
+impl RomOpt {
+    /// Read the rom into memory (converting it to big-endian, if needed) to be modified,
+    /// and determine its version
+    pub(crate) fn read(&self, timings: &mut Timings) -> Result<(Vec<u8>, SSBInfo)> {
+        let (rom, version) = self.source(timings)?;
+
+        Ok((rom.into_vec(), version))
+    }
+
+    /// Open the rom (converting it to big-endian, if needed) to be read from,
+    /// and determine its version
+    pub(crate) fn source(&self, timings: &mut Timings) -> Result<(RomSource, SSBInfo)> {
+        let table = self.table_start.zip(self.table_end);
+        read_rom(&self.path, &self.version_db()?, table, timings)
+    }
+
+    /// Open the rom (converting it to big-endian, if needed) to be read from, and determine
+    /// its version if it can be, for commands that report an unknown version
+    pub(crate) fn open(&self, timings: &mut Timings) -> Result<(RomSource, Option<SSBInfo>)> {
+        let rom = timings
+            .time(Stage::RomRead, || RomSource::open(&self.path))
+            .with_context(|| format!("issue opening <{}>", self.path.display()))?;
+        let table = self.table_start.zip(self.table_end);
+        let version = known_version(&self.path, &self.version_db()?, table, &rom)?;
+
+        Ok((rom, version))
+    }
+
+    /// Open the rom to read only its header, its resource table, and the files `ids`,
+    /// and determine its version. A rom whose version has to be found from its resource
+    /// table (an unknown version, or one with --table-start) is read whole.
+    pub(crate) fn source_files(
+        &self,
+        ids: &[usize],
+        timings: &mut Timings,
+    ) -> Result<(PartialRom, SSBInfo)> {
+        let mut rom = timings
+            .time(Stage::RomRead, || PartialRom::open(&self.path))
+            .with_context(|| format!("issue opening <{}>", self.path.display()))?;
+        let table = self.table_start.zip(self.table_end);
+        let db = self.version_db()?;
+        let version = match (table, db.find(rom.header())) {
+            (None, Some(version)) => version.clone(),
+            _ => {
+                let whole = timings
+                    .time(Stage::RomRead, || rom.read_all())
+                    .with_context(|| format!("issue reading <{}>", self.path.display()))?;
+                find_version(&self.path, &db, table, whole)?
+            }
+        };
+        timings
+            .time(Stage::RomRead, || rom.read_files(&version, ids))
+            .with_context(|| format!("issue reading <{}>", self.path.display()))?;
+
+        Ok((rom, version))
+    }
+
+    /// Parse the resource table of `rom`
+    pub(crate) fn table<'r>(
+        &self,
+        rom: &'r [u8],
+        version: SSBInfo,
+        timings: &mut Timings,
+    ) -> Result<SsbRom<'r>> {
+        timings
+            .time(Stage::TableParse, || SsbRom::with_info(rom.into(), version))
+            .with_context(|| format!("reading resource table of <{}>", self.path.display()))
+    }
+
+    /// Parse the resource table of `rom`, or the secondary table after it
+    pub(crate) fn file_table<'r>(
+        &self,
+        rom: &'r [u8],
+        version: SSBInfo,
+        table: FileTable,
+        timings: &mut Timings,
+    ) -> Result<SsbRom<'r>> {
+        let resource = self.table(rom, version, timings)?;
+        match table {
+            FileTable::Resource => Ok(resource),
+            FileTable::Secondary => timings
+                .time(Stage::TableParse, || {
+                    let info = resource.secondary_table()?.version().clone();
+                    SsbRom::with_info(rom.into(), info)
+                })
+                .with_context(|| format!("reading secondary table of <{}>", self.path.display())),
+        }
+    }
+
+    /// The built-in versions, and those from --versions
+    pub(crate) fn version_db(&self) -> Result<VersionDb> {
+        let mut db = VersionDb::builtin();
+        if let Some(path) = &self.versions {
+            db.load_file(path)?;
+        }
+
+        Ok(db)
+    }
+
+    /// The file names from --names
+    pub(crate) fn name_db(&self) -> Result<NameDb> {
+        let mut db = NameDb::default();
+        if let Some(path) = &self.names {
+            db.load_file(path)?;
+        }
+
+        Ok(db)
+    }
+}
+
+/// Open the rom at `path` (converting it to big-endian, if needed) and find its version in `db`.
+/// If the bounds of the resource `table` are given, they are used instead of the known versions.
+pub(crate) fn read_rom(
+    path: &Path,
+    db: &VersionDb,
+    table: Option<(u32, u32)>,
+    timings: &mut Timings,
+) -> Result<(RomSource, SSBInfo)> {
+    let rom = timings
+        .time(Stage::RomRead, || RomSource::open(path))
+        .with_context(|| format!("issue opening <{}>", path.display()))?;
+    let version = find_version(path, db, table, &rom)?;
+
+    Ok((rom, version))
+}
+
+/// The version of `rom` (at `path`) in `db`, or with the bounds of its resource `table`
+fn find_version(
+    path: &Path,
+    db: &VersionDb,
+    table: Option<(u32, u32)>,
+    rom: &[u8],
+) -> Result<SSBInfo> {
+    known_version(path, db, table, rom)?.ok_or_else(|| {
+        anyhow!(
+            "could not determine version for <{}>; \
+             give the bounds of its resource table with --table-start and --table-end",
+            path.display()
+        )
+    })
+}
+
+/// The version of `rom` (at `path`) in `db`, or with the bounds of its resource `table`,
+/// or with the table found by its layout, if any
+fn known_version(
+    path: &Path,
+    db: &VersionDb,
+    table: Option<(u32, u32)>,
+    rom: &[u8],
+) -> Result<Option<SSBInfo>> {
+    match table {
+        Some((start, end)) => SSBInfo::custom(rom, start as usize, end as usize)
+            .map(Some)
+            .with_context(|| format!("using the resource table of <{}>", path.display())),
+        None => Ok(db.find(rom).cloned().or_else(|| detect(rom))),
+    }
+}
+
+/// Find the resource table of a rom whose version isn't known, and say where it was found
+fn detect(rom: &[u8]) -> Option<SSBInfo> {
+    let info = SSBInfo::detect(rom)?;
+    eprintln!(
+        "unknown version {}: using the resource table found at {:#X}..{:#X}",
+        info.version, info.table_start, info.table_end
+    );
+
+    Some(info)
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ExtractOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// output for exported file, or file-id if not present; `-` writes to stdout.
+    /// When exporting more than one file, the directory to export the files into
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    /// export every file in the resource table, and write a manifest.toml next to them.
+    /// With `--mode decompress`, the manifest can be given to `build` and `inject`
+    #[structopt(short, long)]
+    pub(crate) all: bool,
+    /// also export the external files that the requested files point into, and their
+    /// external files, and so on
+    #[structopt(long)]
+    pub(crate) with_deps: bool,
+    /// emit the location and values of the internal and external relocations
+    #[structopt(short, long)]
+    pub(crate) emit_relocs: bool,
+    /// format of the emitted relocations: text, csv, or ld (a linker script fragment)
+    #[structopt(long, default_value = "text", parse(try_from_str))]
+    pub(crate) reloc_format: RelocFormat,
+    /// five ways to export a file: raw, decompress, reloc, elf, or asm
+    ///
+    /// raw          export the raw data
+    ///
+    /// decompress   decompress the data, if necessary
+    ///
+    /// reloc        calculate the relocations (based on --base-addr)
+    ///
+    /// elf          a MIPS ELF object with a relocation record for each pointer
+    ///
+    /// asm          GNU assembler source, with a symbol for each pointer
+    #[structopt(default_value = "reloc", short, long, parse(try_from_str))]
+    pub(crate) mode: Mode,
+    /// how the exported data is written: bin, or c (a C byte array, and a header
+    /// with its length and an extern declaration)
+    #[structopt(long, default_value = "bin", parse(try_from_str))]
+    pub(crate) format: DataFormat,
+    /// name of the C array, instead of file_NNNN, with `--format c`
+    #[structopt(long)]
+    pub(crate) symbol: Option<String>,
+    /// the address the file is loaded at, for calculating internal pointers in reloc mode
+    #[structopt(short, long, default_value = "0", parse(try_from_str = parse_addr))]
+    pub(crate) base_addr: u32,
+    /// file ids to export, either single ids or inclusive ranges (e.g., `10 12 20-35`)
+    #[structopt(required_unless = "all")]
+    pub(crate) ids: Vec<FileIds>,
+    /// the table to export files from: `resource` (or `1`), or `secondary` (or `2`, the
+    /// image table after the resource table). The files of the secondary table get no
+    /// manifest, since they can't be built back into the resource table
+    #[structopt(long, default_value = "resource", parse(try_from_str))]
+    pub(crate) table: FileTable,
+    /// do everything except write files, and print what would be written
+    #[structopt(short = "n", long)]
+    pub(crate) dry_run: bool,
+    /// write the exported files, and a manifest.toml describing them, into a tar archive.
+    /// With `--mode decompress`, the manifest can be given to `build`
+    #[structopt(long, parse(from_os_str), conflicts_with = "output")]
+    pub(crate) archive: Option<PathBuf>,
+    /// template for the names of exported files, with the placeholders {id} (or {id:04}),
+    /// {kind}, {mode}, {compression}, {ext}, and {name}
+    #[structopt(long, default_value = NameTemplate::DEFAULT, parse(try_from_str))]
+    pub(crate) name_template: NameTemplate,
+    /// when exporting more than one file, the number of threads that decompress files
+    /// while they are written; defaults to the number of cpus
+    #[structopt(short, long)]
+    pub(crate) jobs: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ListOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// decompress every file to guess what it holds (geometry, sprites, pointers,
+    /// floats, or data), and show it in a kind column
+    #[structopt(short, long)]
+    pub(crate) classify: bool,
+    /// the table to list: `resource` (or `1`, the resource table of files), `secondary`
+    /// (or `2`, the image table after the resource table), or `audio` (the sequences,
+    /// instrument banks, and samples that are found by their headers)
+    #[structopt(long, alias = "tables", default_value = "resource")]
+    pub(crate) table: ListTable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListTable {
+    Files(FileTable),
+    Audio,
+}
+
+impl FromStr for ListTable {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "audio" => Ok(Self::Audio),
+            _ => s.parse().map(Self::Files),
+        }
+    }
+}
+
+/// A table of files: the resource table, or the secondary table after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileTable {
+    Resource,
+    Secondary,
+}
+
+impl FromStr for FileTable {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "resource" | "1" => Ok(Self::Resource),
+            "secondary" | "2" => Ok(Self::Secondary),
+            _ => Err(anyhow::anyhow!("Unknown table <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct InfoOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// print the entry as json
+    #[structopt(short, long, conflicts_with = "header")]
+    pub(crate) json: bool,
+    /// print the rom header (boot address, libultra version, cic, ...)
+    #[structopt(long)]
+    pub(crate) header: bool,
+    /// decompress the file to guess what it holds, like `list --classify`
+    #[structopt(short, long)]
+    pub(crate) classify: bool,
+    /// file id to print
+    #[structopt(required_unless = "header")]
+    pub(crate) id: Option<usize>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct InjectOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id to replace
+    pub(crate) id: usize,
+    /// the new (decompressed) file data; it is compressed if the original file was.
+    /// Without any vpk0 options, other settings are tried if it doesn't fit the
+    /// original's slot, and it is stored uncompressed if that is smaller
+    #[structopt(parse(from_os_str), required_unless = "manifest")]
+    pub(crate) file: Option<PathBuf>,
+    /// a manifest from `extract --all`; without a file, the data is read from
+    /// the path that the manifest gives for the file id
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) manifest: Option<PathBuf>,
+    /// csv of the pointers in a file whose pointers are addresses (like a file exported in
+    /// reloc mode), as written by `extract --emit-relocs --reloc-format csv`. The relocation
+    /// chains of the file, and the table entry and list of external files, are rebuilt from it
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) relocs: Option<PathBuf>,
+    /// the address that the internal pointers of a --relocs file are relative to
+    #[structopt(short, long, default_value = "0", parse(try_from_str = parse_addr))]
+    pub(crate) base_addr: u32,
+    /// do everything except writing the rom, and print the new table entry
+    #[structopt(short = "n", long)]
+    pub(crate) dry_run: bool,
+    /// rewrite the ids of external files that the files of the rom point into, after files
+    /// were moved, as `OLD=NEW` (e.g., `--remap-extern 12=2140`); can be given more than once
+    #[structopt(long = "remap-extern", number_of_values = 1, parse(try_from_str = parse_remap))]
+    pub(crate) remap_externs: Vec<(u16, u16)>,
+    #[structopt(flatten)]
+    pub(crate) header: HeaderOpt,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) patch: PatchOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct BuildOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// manifest (toml) of the files to build into the rom;
+    /// file ids not in the manifest, or whose files are unchanged since they were extracted,
+    /// keep the data from the rom, and ids that follow the last file of the table are
+    /// added to the end of it with --grow-table
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) manifest: PathBuf,
+    /// path for the rebuilt rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// rewrite the ids of external files that the files of the rom point into, after files
+    /// were moved, as `OLD=NEW` (e.g., `--remap-extern 12=2140`); can be given more than once
+    #[structopt(long = "remap-extern", number_of_values = 1, parse(try_from_str = parse_remap))]
+    pub(crate) remap_externs: Vec<(u16, u16)>,
+    /// report the files of the new rom whose decompressed data and relocations are the
+    /// same, and how many bytes sharing their data would save
+    #[structopt(long)]
+    pub(crate) find_duplicates: bool,
+    /// store the data of files whose table entries and stored data are the same once, and
+    /// point the entries of the copies at it. A file only shares data when neither it nor
+    /// the file before it has external files, which the game finds from the next entry
+    #[structopt(long)]
+    pub(crate) share_duplicates: bool,
+    #[structopt(flatten)]
+    pub(crate) grow: GrowTableOpt,
+    #[structopt(flatten)]
+    pub(crate) header: HeaderOpt,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) patch: PatchOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct PackModOpt {
+    /// the clean rom that the mod is made for; only files that differ from it are bundled
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// manifest (toml) of the mod's files, as given to `build`
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) manifest: PathBuf,
+    /// path for the .ssbmod archive
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// name of the mod
+    #[structopt(long)]
+    pub(crate) name: Option<String>,
+    /// vpk0 settings for the mod's compressed files, stored in the archive
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ApplyModOpt {
+    /// the clean rom to apply the mod to
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the .ssbmod archive
+    #[structopt(parse(from_os_str))]
+    pub(crate) bundle: PathBuf,
+    /// path for the modded rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    #[structopt(flatten)]
+    pub(crate) grow: GrowTableOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct TextureOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the textures
+    pub(crate) id: usize,
+    /// the PNG to write the texture at --offset to; without --offset, the directory
+    /// to write a PNG of each sprite that is found in the file (or their sheet) to
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// offset of a texture in the decompressed file. Without it, the file is searched for
+    /// libultra sprites, which give the format, size, and palette of their textures
+    #[structopt(long, parse(try_from_str = parse_addr), requires_all = &["format", "width", "height"])]
+    pub(crate) offset: Option<u32>,
+    /// texel format of the texture at --offset: rgba16, rgba32, ci4, ci8, ia4, ia8, ia16, i4, or i8
+    #[structopt(short, long)]
+    pub(crate) format: Option<TexelFormat>,
+    /// width of the texture at --offset, in texels
+    #[structopt(long)]
+    pub(crate) width: Option<usize>,
+    /// height of the texture at --offset, in texels
+    #[structopt(long)]
+    pub(crate) height: Option<usize>,
+    /// offset of the RGBA16 palette of a ci4 or ci8 texture at --offset
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) palette: Option<u32>,
+    /// pack the sprites into one PNG, with a json atlas of where each one is, instead
+    /// of writing a PNG of each
+    #[structopt(long, conflicts_with = "offset")]
+    pub(crate) sheet: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct FontOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the font
+    #[structopt(required_unless = "list")]
+    pub(crate) id: Option<usize>,
+    /// search every file for fonts (runs of at least ten sprites of one height and format,
+    /// none over 32x32), and list the ones that are found instead of writing an atlas
+    #[structopt(short, long, conflicts_with = "id")]
+    pub(crate) list: bool,
+    /// the directory to write the atlas and its json to
+    #[structopt(short, long, parse(from_os_str), required_unless = "list")]
+    pub(crate) output: Option<PathBuf>,
+    /// the characters of the glyphs, in order (e.g., "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    /// without it, the glyphs are only numbered
+    #[structopt(long)]
+    pub(crate) chars: Option<String>,
+    /// offset of the first of --count glyphs that follow each other in the decompressed
+    /// file, all --width by --height texels of --format. Without it, each sprite of the
+    /// file is a glyph
+    #[structopt(
+        long,
+        parse(try_from_str = parse_addr),
+        requires_all = &["format", "width", "height", "count"]
+    )]
+    pub(crate) offset: Option<u32>,
+    /// texel format of the glyphs at --offset: rgba16, rgba32, ci4, ci8, ia4, ia8, ia16, i4, or i8
+    #[structopt(short, long)]
+    pub(crate) format: Option<TexelFormat>,
+    /// width of each glyph at --offset, in texels
+    #[structopt(long)]
+    pub(crate) width: Option<usize>,
+    /// height of each glyph at --offset, in texels
+    #[structopt(long)]
+    pub(crate) height: Option<usize>,
+    /// the number of glyphs at --offset
+    #[structopt(long)]
+    pub(crate) count: Option<usize>,
+    /// offset of the RGBA16 palette of ci4 or ci8 glyphs at --offset
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) palette: Option<u32>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct TextOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file ids to search for strings; without any, every file is searched
+    pub(crate) ids: Vec<usize>,
+    /// format of the messages: po or csv
+    #[structopt(short, long, default_value = "po")]
+    pub(crate) format: TextFormat,
+    /// path to write the messages to, or stdout if not present
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    /// the fewest characters that a string can have
+    #[structopt(long, default_value = "4")]
+    pub(crate) min_len: usize,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportTextOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// the PO or CSV file, as written by `text`, with the translations filled in;
+    /// messages without a translation are left as they are
+    #[structopt(parse(from_os_str))]
+    pub(crate) messages: PathBuf,
+    /// format of the messages: po or csv
+    #[structopt(short, long, default_value = "po")]
+    pub(crate) format: TextFormat,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextFormat {
+    Po,
+    Csv,
+}
+
+impl FromStr for TextFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "po" => Ok(Self::Po),
+            "csv" => Ok(Self::Csv),
+            _ => Err(anyhow::anyhow!("Unknown text format <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct MusicOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// rom offset of the sequence bank; without it, the bank is found by its header
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) bank: Option<u32>,
+    /// the directory to write the sequences to as MIDI files; without it, the sequences
+    /// are only listed
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    /// index of a sequence to convert; can be given more than once. Without it, every
+    /// sequence of the bank is converted
+    #[structopt(long = "seq", number_of_values = 1)]
+    pub(crate) sequences: Vec<usize>,
+    /// also write each sequence as it is in the rom (compressed MIDI), as `.cseq`
+    #[structopt(long)]
+    pub(crate) raw: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct SamplesOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the directory to write the samples and manifest to
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// rom offset of the bank file (`.ctl`); without it, the bank file is found by its header
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) ctl: Option<u32>,
+    /// rom offset of the samples (`.tbl`); without it, the samples are taken to start
+    /// after the bank file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) tbl: Option<u32>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ItemOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the item data
+    pub(crate) id: usize,
+    /// offset of the attributes of an item in the decompressed file
+    #[structopt(long, required_unless = "weights", parse(try_from_str = parse_addr))]
+    pub(crate) attributes: Option<u32>,
+    /// offset of the table of item spawn weights in the decompressed file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) weights: Option<u32>,
+    /// change a field of the attributes, or a spawn weight as `weight.ITEM` (e.g.,
+    /// `--set damage=20` or `--set weight.bob_omb=0`); can be given more than once
+    #[structopt(long, number_of_values = 1, requires = "output", parse(try_from_str = parse_set))]
+    pub(crate) set: Vec<(String, i64)>,
+    /// path for the modified rom, with --set
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportTextureOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id with the texture
+    pub(crate) id: usize,
+    /// the PNG to encode; it has to be the size of the texture
+    #[structopt(parse(from_os_str))]
+    pub(crate) png: PathBuf,
+    /// offset of a libultra sprite in the decompressed file, as listed by `texture`;
+    /// the PNG is encoded in the format of the sprite, and split into its bitmaps
+    #[structopt(long, parse(try_from_str = parse_addr), required_unless = "offset")]
+    pub(crate) sprite: Option<u32>,
+    /// offset of a texture in the decompressed file, instead of a sprite
+    #[structopt(long, parse(try_from_str = parse_addr), requires = "format")]
+    pub(crate) offset: Option<u32>,
+    /// texel format of the texture at --offset: rgba16, rgba32, ci4, ci8, ia4, ia8, ia16, i4, or i8
+    #[structopt(short, long)]
+    pub(crate) format: Option<TexelFormat>,
+    /// offset of the RGBA16 palette of a ci4 or ci8 texture at --offset
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) palette: Option<u32>,
+    /// match the colors of the PNG to the current palette, instead of writing a new
+    /// palette with the colors of the PNG; for palettes that other textures share
+    #[structopt(long)]
+    pub(crate) keep_palette: bool,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct PaletteOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the palettes
+    pub(crate) id: usize,
+    /// the directory to write `file-NNNN-pal-XXXX.pal` and `.png` of each palette to
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// offset of a palette in the decompressed file. Without it, the palettes of the
+    /// file's sprites and display lists are written.
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    /// the number of colors of the palette at --offset, if the file doesn't load it
+    #[structopt(long, requires = "offset")]
+    pub(crate) colors: Option<usize>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportPaletteOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id with the palette
+    pub(crate) id: usize,
+    /// the new colors: a JASC .pal file, which keeps the alpha of the current colors, or a
+    /// PNG swatch like `palette` writes
+    #[structopt(parse(from_os_str))]
+    pub(crate) palette: PathBuf,
+    /// offset of the palette in the decompressed file, as listed by `palette`
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) offset: u32,
+    /// the number of colors of the palette, if the file doesn't load it
+    #[structopt(long)]
+    pub(crate) colors: Option<usize>,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct GfxOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the display lists
+    pub(crate) id: usize,
+    /// offset of a display list in the decompressed file; without it, every display
+    /// list that a pointer of the file points to is printed
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    /// also print the vertices that each gsSPVertex in the same file loads
+    #[structopt(long)]
+    pub(crate) vertices: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct CharacterOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the fighter: mario, fox, donkey, samus, luigi, link, yoshi, captain, kirby,
+    /// pikachu, purin, or ness
+    pub(crate) fighter: String,
+    /// file id of the fighter's main file, for a version that doesn't know where it is
+    /// (see --versions)
+    #[structopt(long)]
+    pub(crate) file: Option<usize>,
+    /// print json instead of text
+    #[structopt(long)]
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ModelOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the model
+    pub(crate) id: usize,
+    /// the directory to write the model and its textures to
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// offset of a display list in the decompressed file to export; without it, every
+    /// display list that a pointer of the file points to is exported
+    #[structopt(long, parse(try_from_str = parse_addr), conflicts_with = "skeleton")]
+    pub(crate) offset: Option<u32>,
+    /// the format to write: `obj` (with an MTL and PNGs), or glTF 2.0 as `gltf` (one
+    /// json file) or `glb` (binary), which include the skeleton
+    #[structopt(short, long, default_value = "obj")]
+    pub(crate) format: ModelFormat,
+    /// offset of the bone hierarchy (`DObjDesc` array) in the decompressed file to pose and
+    /// bind a glTF model to; without it, the first one that a pointer points to is used
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) skeleton: Option<u32>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ScriptOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the scripts
+    pub(crate) id: usize,
+    /// offset of a script in the decompressed file; can be given more than once
+    #[structopt(long, required = true, number_of_values = 1, parse(try_from_str = parse_addr))]
+    pub(crate) offset: Vec<u32>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct AnimOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the animation
+    pub(crate) id: usize,
+    /// offset of the joint pointers of the animation in the decompressed file
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_addr))]
+    pub(crate) offset: u32,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct EffectOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the effect bank
+    pub(crate) id: usize,
+    /// offset of the bank (its count of scripts) in the decompressed file
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_addr))]
+    pub(crate) bank: u32,
+    /// offset of the header of a script, to decode instead of the whole bank; can be
+    /// given more than once
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_addr))]
+    pub(crate) script: Vec<u32>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct SkeletonOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the skeleton
+    pub(crate) id: usize,
+    /// offset of the `DObjDesc` array of a skeleton in the decompressed file; without it,
+    /// every skeleton that a pointer of the file points to is printed
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    /// print the bones as json
+    #[structopt(short, long)]
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportAnimOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id with the animation
+    pub(crate) id: usize,
+    /// the streams to assemble; each starts with a `.joint JOINT OFFSET` line, and has to
+    /// fit in the place of the stream at that offset
+    #[structopt(parse(from_os_str))]
+    pub(crate) anim: PathBuf,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct LayoutOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id of the menu
+    pub(crate) id: usize,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportLayoutOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id of the menu
+    pub(crate) id: usize,
+    /// the layout to write; each `[[sprite]]` is matched to the sprite at its offset, and
+    /// its width, height, and format are ignored
+    #[structopt(parse(from_os_str))]
+    pub(crate) layout: PathBuf,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportScriptOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id with the scripts
+    pub(crate) id: usize,
+    /// the scripts to assemble; each starts with a `.script OFFSET` line, and has to fit
+    /// in the place of the script at that offset
+    #[structopt(parse(from_os_str))]
+    pub(crate) script: PathBuf,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct HitboxesOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id with the scripts
+    pub(crate) id: usize,
+    /// offset of the script of a move in the decompressed file, optionally named as
+    /// `NAME=OFFSET` (e.g., `--move jab=0x120`); can be given more than once
+    #[structopt(
+        long = "move",
+        required = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_move)
+    )]
+    pub(crate) moves: Vec<(Option<String>, u32)>,
+    /// write the json to a file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct StageOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id of the stage
+    pub(crate) id: usize,
+    /// offset of the collision geometry (`MPGeometryData`) in the decompressed file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) geometry: u32,
+    /// offset of the camera bounds, followed by the blast zones, in the decompressed file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) bounds: Option<u32>,
+    /// draw the stage from the side as an SVG at this path, instead of printing it
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) svg: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct FighterOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the fighter: mario, fox, donkey, samus, luigi, link, yoshi, captain, kirby,
+    /// pikachu, purin, or ness
+    pub(crate) fighter: String,
+    /// file id with the attributes, for a version that doesn't know where they are
+    /// (see --versions); without --offset, the attributes are found by their values
+    #[structopt(long)]
+    pub(crate) file: Option<usize>,
+    /// offset of the attributes in the decompressed --file
+    #[structopt(long, requires = "file", parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    /// print json instead of toml
+    #[structopt(long)]
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ImportFighterOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// the attributes to write, in the toml that `fighter` prints; attributes that it
+    /// leaves out are kept
+    #[structopt(parse(from_os_str))]
+    pub(crate) attributes: PathBuf,
+    /// file id with the attributes, for a version that doesn't know where they are
+    /// (see --versions); without --offset, the attributes are found by their values
+    #[structopt(long)]
+    pub(crate) file: Option<usize>,
+    /// offset of the attributes in the decompressed --file
+    #[structopt(long, requires = "file", parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct CheckLoaderOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file ids to check, either single ids or inclusive ranges (e.g., `10 12 20-35`)
+    #[structopt(long = "id", required_unless = "all")]
+    pub(crate) ids: Vec<FileIds>,
+    /// check every file in the resource table
+    #[structopt(short, long)]
+    pub(crate) all: bool,
+    /// address of the game's function that relocates a loaded file; by default, it is found
+    /// in the code by calling each function that could be it over a small file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) loader: Option<u32>,
+    /// the arguments of the loader, in a0 to a3: `file` (the address of the file),
+    /// `inreloc` (the word index of its first internal relocation), `size` (its
+    /// decompressed size), or a number
+    #[structopt(long, use_delimiter = true, default_value = "file,inreloc")]
+    pub(crate) args: Vec<LoaderArg>,
+    /// rom offset of the code with the loader
+    #[structopt(long, default_value = "0x1000", parse(try_from_str = parse_addr))]
+    pub(crate) code_start: u32,
+    /// size of the code with the loader; by default, the 1 MiB that the bootcode loads
+    #[structopt(long, default_value = "0x100000", parse(try_from_str = parse_addr))]
+    pub(crate) code_size: u32,
+    /// address that the code is loaded at; by default, the boot address of the rom header
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) code_addr: Option<u32>,
+    /// address that the file is loaded at
+    #[structopt(long, default_value = "0x80400000", parse(try_from_str = parse_addr))]
+    pub(crate) file_addr: u32,
+    /// instructions that the loader may run for each file before it is stopped
+    #[structopt(long, default_value = "50000000")]
+    pub(crate) max_steps: usize,
+}
+
+/// An argument of the loader of `check-loader`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoaderArg(pub(crate) Arg);
+
+impl FromStr for LoaderArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let arg = match s {
+            "file" => Arg::File,
+            "inreloc" => Arg::InReloc,
+            "size" => Arg::Size,
+            _ => Arg::Value(parse_addr(s)?),
+        };
+
+        Ok(Self(arg))
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct BonusOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file id of the bonus stage; each fighter's Break the Targets and Board the
+    /// Platforms stages are files of their own
+    pub(crate) id: usize,
+    /// offset of the collision geometry (`MPGeometryData`) in the decompressed file
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) geometry: u32,
+    /// move a target or platform, by its index in the json, as `target.N.x` or
+    /// `platform.N.y` (e.g., `--set target.0.y=1200`); can be given more than once
+    #[structopt(long, number_of_values = 1, requires = "output", parse(try_from_str = parse_set))]
+    pub(crate) set: Vec<(String, i64)>,
+    /// path for the modified rom, with --set
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ModelFormat {
+    Obj,
+    Gltf,
+    Glb,
+}
+
+impl FromStr for ModelFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "obj" => Ok(Self::Obj),
+            "gltf" => Ok(Self::Gltf),
+            "glb" => Ok(Self::Glb),
+            _ => Err(anyhow::anyhow!("Unknown model format <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct UndoOpt {
+    /// the rom to restore from its newest backup, leaving the older ones to undo next
+    #[structopt(parse(from_os_str))]
+    pub(crate) rom: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) enum EntryCmd {
+    /// change fields of the 12-byte table entry of a file, e.g. `--in-reloc 0xFFFF`
+    /// to disable its internal relocations. The fields are given as they are stored:
+    /// the relocations and sizes in words, and the offset in bytes
+    Set(EntrySetOpt),
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct EntrySetOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path for the modified rom
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// file id of the entry to change
+    #[structopt(long)]
+    pub(crate) id: usize,
+    /// whether the file is vpk0 compressed: true or false
+    #[structopt(long)]
+    pub(crate) compressed: Option<bool>,
+    /// offset of the file's data from the end of the table, in bytes
+    #[structopt(long, parse(try_from_str = parse_addr))]
+    pub(crate) offset: Option<u32>,
+    /// offset of the first internal relocation in words, or 0xFFFF for none
+    #[structopt(long, parse(try_from_str = parse_half))]
+    pub(crate) in_reloc: Option<u16>,
+    /// size of the stored data, in words
+    #[structopt(long, parse(try_from_str = parse_half))]
+    pub(crate) size: Option<u16>,
+    /// offset of the first external relocation in words, or 0xFFFF for none
+    #[structopt(long, parse(try_from_str = parse_half))]
+    pub(crate) ex_reloc: Option<u16>,
+    /// size of the decompressed data, in words
+    #[structopt(long, parse(try_from_str = parse_half))]
+    pub(crate) decompressed_size: Option<u16>,
+    /// write the entry even if it doesn't agree with the data around it
+    #[structopt(long)]
+    pub(crate) force: bool,
+    /// do everything except writing the rom, and print the new table entry
+    #[structopt(short = "n", long)]
+    pub(crate) dry_run: bool,
+    #[structopt(flatten)]
+    pub(crate) backup: BackupOpt,
+}
+
+/// Patches of a modified rom, to share a mod without the rom
+#[derive(Debug, StructOpt)]
+pub(crate) struct PatchOpt {
+    /// also write an IPS patch from the (big-endian) input rom to the output rom
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) emit_ips: Option<PathBuf>,
+    /// also write a BPS patch from the (big-endian) input rom to the output rom;
+    /// unlike IPS, it copies moved data, and checks the roms' crc32s
+    #[structopt(long, parse(from_os_str))]
+    pub(crate) emit_bps: Option<PathBuf>,
+}
+
+impl PatchOpt {
+    /// Is any patch requested, so the original rom has to be kept
+    pub(crate) fn any(&self) -> bool {
+        self.emit_ips.is_some() || self.emit_bps.is_some()
+    }
+
+    /// Write the requested patches from `original` to `modified`
+    pub(crate) fn write(
+        &self,
+        original: &[u8],
+        modified: &[u8],
+        timings: &mut Timings,
+    ) -> Result<()> {
+        if let Some(path) = &self.emit_ips {
+            let patch = ssbfile::patch::ips(original, modified).context("creating IPS patch")?;
+            timings
+                .time(Stage::Write, || std::fs::write(path, patch))
+                .with_context(|| format!("writing IPS patch to <{}>", path.display()))?;
+        }
+        if let Some(path) = &self.emit_bps {
+            let patch = ssbfile::patch::bps(original, modified);
+            timings
+                .time(Stage::Write, || std::fs::write(path, patch))
+                .with_context(|| format!("writing BPS patch to <{}>", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writing the modified rom over an existing file
+#[derive(Debug, StructOpt)]
+pub(crate) struct BackupOpt {
+    /// don't keep the previous contents of an existing output file as a backup, which
+    /// `undo` restores: `<output>.bak`, or `<output>.bak.1` (and so on) beside older ones
+    #[structopt(long)]
+    pub(crate) no_backup: bool,
+}
+
+impl BackupOpt {
+    /// Write `rom` to `output`, after moving any file already there to its backup path
+    pub(crate) fn write(&self, output: &Path, rom: &[u8], timings: &mut Timings) -> Result<()> {
+        if !self.no_backup && output.is_file() {
+            let backup = crate::undo::backup(output)?;
+            eprintln!("backed up <{}> to <{}>", output.display(), backup.display());
+        }
+
+        timings
+            .time(Stage::Write, || std::fs::write(output, rom))
+            .with_context(|| format!("writing rom to <{}>", output.display()))
+    }
+}
+
+/// Writing out a modified rom
+#[derive(Debug, StructOpt)]
+pub(crate) struct OutputOpt {
+    /// recalculate the header crcs of the new rom, so that the bootcode accepts it.
+    /// The version of a rom is found by its crcs, so a rom with changed crcs may need
+    /// --table-start and --table-end to be read again
+    #[structopt(long)]
+    pub(crate) fix_crc: bool,
+    #[structopt(flatten)]
+    pub(crate) backup: BackupOpt,
+}
+
+impl OutputOpt {
+    /// Recalculate the crcs of `rom` if asked to, and write it to `output`
+    pub(crate) fn write(&self, output: &Path, rom: &mut [u8], timings: &mut Timings) -> Result<()> {
+        if self.fix_crc {
+            ssbfile::rom_info::fix_crc(rom)?;
+        }
+
+        self.backup.write(output, rom, timings)
+    }
+}
+
+/// Adding files to the end of the resource table
+#[derive(Debug, StructOpt)]
+pub(crate) struct GrowTableOpt {
+    /// add the files that follow the last file of the table to the end of it. The data of
+    /// the files starts after the table, and the game's code has the size of the table built
+    /// in, so growing the table moves the data of every file away from where the game looks
+    /// for it: none of them load until the code is patched to match
+    #[structopt(long)]
+    pub(crate) grow_table: bool,
+}
+
+impl GrowTableOpt {
+    /// Check that the files of `manifest` only grow the table of `version` if it is allowed
+    pub(crate) fn check(&self, manifest: &Manifest, version: &SSBInfo) -> Result<()> {
+        let count = version.total_entries();
+        if let Some(file) = manifest.files.iter().find(|file| file.id >= count) {
+            ensure!(
+                self.grow_table,
+                "file <{}> would grow the resource table of {} files, which moves the data of \
+                 every file so that the game can't load any of them without patching its code; \
+                 pass --grow-table to build it anyway",
+                file.id,
+                count
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes to the header of a modified rom, so that it can be told apart from the original
+#[derive(Debug, StructOpt)]
+pub(crate) struct HeaderOpt {
+    /// write a new title (up to 20 bytes of UTF-8) into the header of the output rom
+    /// and recalculate its crcs
+    #[structopt(long)]
+    pub(crate) title: Option<String>,
+    /// write a new four letter game code (e.g., NALE) into the header of the output rom
+    /// and recalculate its crcs
+    #[structopt(long)]
+    pub(crate) game_code: Option<String>,
+}
+
+impl HeaderOpt {
+    /// Write the requested header fields into `rom`, and return whether any were changed
+    pub(crate) fn apply(&self, rom: &mut [u8]) -> Result<bool> {
+        if let Some(title) = &self.title {
+            ssbfile::rom_info::set_title(rom, title)?;
+        }
+        if let Some(code) = &self.game_code {
+            ssbfile::rom_info::set_game_code(rom, code)?;
+        }
+
+        Ok(self.title.is_some() || self.game_code.is_some())
+    }
+}
+
+/// The vpk0 settings for compressing files
+#[derive(Debug, StructOpt)]
+pub(crate) struct CompressOpt {
+    /// vpk0 sample method, one or two; defaults to the method of the file being replaced
+    #[structopt(long, parse(try_from_str = parse_method))]
+    pub(crate) vpk_method: Option<VpkMethod>,
+    /// vpk0 window size, in bits
+    #[structopt(long)]
+    pub(crate) window_bits: Option<usize>,
+    /// vpk0 match length, in bits
+    #[structopt(long)]
+    pub(crate) length_bits: Option<usize>,
+}
+
+impl CompressOpt {
+    /// Were any of the settings given
+    pub(crate) fn is_set(&self) -> bool {
+        self.vpk_method.is_some() || self.window_bits.is_some() || self.length_bits.is_some()
+    }
+
+    /// The settings for compressing a file that replaces `original` (the compressed data)
+    pub(crate) fn settings(&self, original: Option<&[u8]>) -> Result<CompressSettings> {
+        let mut settings = match original {
+            Some(data) => CompressSettings::matching(data)?,
+            None => CompressSettings::default(),
+        };
+        if self.is_set() {
+            // the trees of the original only fit the settings that it was compressed with
+            settings.trees = None;
+        }
+        if let Some(method) = self.vpk_method {
+            settings.method = method;
+        }
+        if let Some(bits) = self.window_bits {
+            settings.window_bits = bits;
+        }
+        if let Some(bits) = self.length_bits {
+            settings.length_bits = bits;
+        }
+
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct DiffOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// path to the SSB64 rom to compare against
+    #[structopt(parse(from_os_str))]
+    pub(crate) other: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct MergeOpt {
+    /// the base rom, which the files that aren't taken from another rom come from
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// another rom to take files from; the roms are numbered from 1, in the order given
+    #[structopt(long = "from", number_of_values = 1, parse(from_os_str))]
+    pub(crate) from: Vec<PathBuf>,
+    /// take files from a --from rom, as `IDS=ROM` (e.g., `--take 1000-1100=1`); can be
+    /// given more than once
+    #[structopt(long = "take", number_of_values = 1, parse(try_from_str = parse_take))]
+    pub(crate) take: Vec<(FileIds, usize)>,
+    /// path for the merged rom, or the directory for its files with --extract
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// write the decompressed data of every file of the merged table into the output
+    /// directory, instead of building a rom
+    #[structopt(long)]
+    pub(crate) extract: bool,
+    #[structopt(flatten)]
+    pub(crate) out: OutputOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct SearchOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// treat the pattern as hex bytes (e.g., `8004 0A00` or `80040a00`) instead of an ascii string
+    #[structopt(short = "x", long)]
+    pub(crate) hex: bool,
+    /// the string (or hex bytes, with --hex) to search for
+    pub(crate) pattern: String,
+}
+
+impl SearchOpt {
+    /// The bytes to search for
+    pub(crate) fn bytes(&self) -> Result<Vec<u8>> {
+        if !self.hex {
+            return Ok(self.pattern.as_bytes().to_vec());
+        }
+
+        let digits = self
+            .pattern
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '_')
+            .collect::<Vec<_>>();
+        if digits.len() % 2 != 0 {
+            return Err(anyhow!("Odd number of hex digits in <{}>", self.pattern));
+        }
+        digits
+            .chunks(2)
+            .map(|byte| {
+                let byte = byte.iter().collect::<String>();
+                u8::from_str_radix(&byte, 16)
+                    .with_context(|| format!("Bad hex byte <{}> in <{}>", byte, self.pattern))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct AddrOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the rom address to look up, in hex (with a `0x` prefix) or decimal
+    #[structopt(parse(try_from_str = parse_addr))]
+    pub(crate) addr: u32,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct StatsOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct VerifyOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct GameSharkOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// the address that the game loads the file to
+    #[structopt(short, long, parse(try_from_str = parse_addr))]
+    pub(crate) base_addr: u32,
+    /// file id that was edited
+    pub(crate) id: usize,
+    /// the edited file, as extracted in reloc mode with the same --base-addr
+    #[structopt(parse(from_os_str))]
+    pub(crate) file: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct RoundtripOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// file ids to check, either single ids or inclusive ranges (e.g., `10 12 20-35`)
+    #[structopt(long = "id", required_unless = "all")]
+    pub(crate) ids: Vec<FileIds>,
+    /// check every file in the resource table
+    #[structopt(short, long)]
+    pub(crate) all: bool,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct WatchOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// manifest (toml) of the files to build into the rom, as for `build`
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) manifest: PathBuf,
+    /// path for the rebuilt rom; its header crcs are always recalculated
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: PathBuf,
+    /// emulator to (re)start with the rebuilt rom after every build, e.g. `mupen64plus`.
+    /// The path of the rom is added after the command's arguments
+    #[structopt(long)]
+    pub(crate) emulator: Option<String>,
+    /// how often to check the files for changes, in milliseconds
+    #[structopt(long, default_value = "500")]
+    pub(crate) interval: u64,
+    #[structopt(flatten)]
+    pub(crate) grow: GrowTableOpt,
+    #[structopt(flatten)]
+    pub(crate) compress: CompressOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct InitOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// directory for the new project; it has to be empty, if it exists
+    #[structopt(parse(from_os_str))]
+    pub(crate) dir: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct SegmentsOpt {
+    #[structopt(flatten)]
+    pub(crate) rom: RomOpt,
+    /// splat or n64split
+    #[structopt(short, long, default_value = "splat", parse(try_from_str))]
+    pub(crate) format: SegmentFormat,
+    /// segment type for vpk0 compressed files, e.g. the name of a splat extension;
+    /// by default, they are `bin` segments like the other files
+    #[structopt(long, default_value = "bin")]
+    pub(crate) vpk0_type: String,
+    /// write the segments to a file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub(crate) output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SegmentFormat {
+    Splat,
+    N64Split,
+}
+
+impl FromStr for SegmentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "splat" => Ok(Self::Splat),
+            "n64split" => Ok(Self::N64Split),
+            _ => Err(anyhow::anyhow!("Unknown segment format <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    RawBytes,
+    Decompressed,
+    Relocated,
+    Elf,
+    Asm,
+}
+
+impl Mode {
+    /// The name of the mode, as given to `--mode`
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::RawBytes => "raw",
+            Self::Decompressed => "decompress",
+            Self::Relocated => "reloc",
+            Self::Elf => "elf",
+            Self::Asm => "asm",
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" | "bytes" => Ok(Self::RawBytes),
+            "decompress" => Ok(Self::Decompressed),
+            "reloc" | "full" => Ok(Self::Relocated),
+            "elf" | "obj" => Ok(Self::Elf),
+            "asm" | "s" => Ok(Self::Asm),
+            _ => Err(anyhow::anyhow!("Unknown mode <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataFormat {
+    Binary,
+    C,
+}
+
+impl FromStr for DataFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bin" | "binary" => Ok(Self::Binary),
+            "c" => Ok(Self::C),
+            _ => Err(anyhow::anyhow!("Unknown data format <{}>", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RelocFormat {
+    Text,
+    Csv,
+    LinkerScript,
+}
+
+impl RelocFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Csv => "csv",
+            Self::LinkerScript => "ld",
+        }
+    }
+}
+
+impl FromStr for RelocFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Self::Text),
+            "csv" => Ok(Self::Csv),
+            "ld" => Ok(Self::LinkerScript),
+            _ => Err(anyhow::anyhow!("Unknown relocation format <{}>", s)),
+        }
+    }
+}
+
+/// A single file id, or an inclusive range of file ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileIds {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl FileIds {
+    pub(crate) fn ids(&self) -> std::ops::RangeInclusive<usize> {
+        self.start..=self.end
+    }
+}
+
+impl FromStr for FileIds {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |id: &str| {
+            id.trim()
+                .parse::<usize>()
+                .with_context(|| format!("Bad file id <{}>", id))
+        };
+
+        match s.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(anyhow!("Empty file id range <{}>", s));
+                }
+                Ok(Self { start, end })
+            }
+            None => parse(s).map(|id| Self { start: id, end: id }),
+        }
+    }
+}
+
+pub(crate) fn parse_method(s: &str) -> Result<VpkMethod> {
+    match s {
+        "one" | "1" => Ok(VpkMethod::OneSample),
+        "two" | "2" => Ok(VpkMethod::TwoSample),
+        _ => Err(anyhow!("Unknown vpk0 method <{}>", s)),
+    }
+}
+
+/// Parse an `OLD=NEW` pair of file ids
+fn parse_remap(s: &str) -> Result<(u16, u16)> {
+    let (old, new) = s
+        .split_once('=')
+        .with_context(|| format!("Bad external file remapping <{}>; expected OLD=NEW", s))?;
+    let id = |id: &str| {
+        id.trim()
+            .parse()
+            .with_context(|| format!("Bad file id <{}> in remapping <{}>", id, s))
+    };
+
+    Ok((id(old)?, id(new)?))
+}
+
+/// Parse an `IDS=ROM` choice of the rom that files come from
+fn parse_take(s: &str) -> Result<(FileIds, usize)> {
+    let (ids, rom) = s
+        .split_once('=')
+        .with_context(|| format!("expected IDS=ROM, not <{}>", s))?;
+    let rom = rom
+        .trim()
+        .parse()
+        .with_context(|| format!("Bad rom number <{}> in <{}>", rom, s))?;
+
+    Ok((ids.parse()?, rom))
+}
+
+fn parse_move(s: &str) -> Result<(Option<String>, u32)> {
+    match s.split_once('=') {
+        Some((name, offset)) => Ok((Some(name.trim().into()), parse_addr(offset.trim())?)),
+        None => Ok((None, parse_addr(s)?)),
+    }
+}
+
+/// Parse a `FIELD=VALUE` change, with a decimal or hex (`0x`) value that can be negative
+fn parse_set(s: &str) -> Result<(String, i64)> {
+    let (name, value) = s
+        .split_once('=')
+        .with_context(|| format!("expected FIELD=VALUE, not <{}>", s))?;
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let magnitude = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .with_context(|| format!("Bad value <{}>", value))?;
+
+    Ok((
+        name.trim().into(),
+        if negative { -magnitude } else { magnitude },
+    ))
+}
+
+/// Parse a 16-bit table entry field, like an address
+fn parse_half(s: &str) -> Result<u16> {
+    parse_addr(s)?
+        .try_into()
+        .with_context(|| format!("<{}> doesn't fit in 16 bits", s))
+}
+
+/// Parse an address as either hex (with a `0x` prefix) or decimal
+pub(crate) fn parse_addr(s: &str) -> Result<u32> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16),
+        None => s.replace('_', "").parse(),
+    };
+
+    parsed.with_context(|| format!("Bad address <{}>", s))
+}
@@ -0,0 +1,380 @@
+use crate::Error;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    io::{Cursor, Read, Write},
+};
+use vpk0::format::VpkMethod;
+
+pub fn decompress(data: &[u8], id: usize) -> Result<Vec<u8>> {
+    decompress_from(Cursor::new(data), id)
+}
+
+pub fn compress(data: &[u8], id: usize) -> Result<Vec<u8>> {
+    compress_with(data, id, &CompressSettings::default())
+}
+
+/// Decompress the vpk0 data read from `rdr`
+pub fn decompress_from<R: Read>(rdr: R, id: usize) -> Result<Vec<u8>> {
+    vpk0::decode(rdr).with_context(|| format!("decompressing file <{}>", id))
+}
+
+/// vpk0 compress the data read from `rdr`, and write the compressed data to `wtr`
+pub fn compress_to<R: Read, W: Write>(
+    mut rdr: R,
+    mut wtr: W,
+    id: usize,
+    settings: &CompressSettings,
+) -> Result<()> {
+    let mut data = Vec::new();
+    rdr.read_to_end(&mut data)
+        .with_context(|| format!("reading file <{}>", id))?;
+    let compressed = compress_with(&data, id, settings)?;
+
+    wtr.write_all(&compressed)
+        .with_context(|| format!("writing compressed file <{}>", id))
+}
+
+/// vpk0 compress `data` with the given `settings`. Without the trees of the settings, they
+/// are built from the values that the encoder finds. The encoder would build its own in an
+/// order that changes from run to run; these are built in an order that doesn't, so that
+/// the same data always compresses to the same bytes.
+pub fn compress_with(data: &[u8], id: usize, settings: &CompressSettings) -> Result<Vec<u8>> {
+    let compress = || -> Result<Vec<u8>> {
+        settings.check()?;
+        let encode = |offsets: &str, lengths: &str| {
+            vpk0::Encoder::for_bytes(data)
+                .method(settings.method)
+                .with_lzss_settings(settings.lzss())
+                .with_offsets(offsets)
+                .with_lengths(lengths)
+                .encode_to_vec()
+        };
+
+        // vpk0 panics when it builds trees without any values, or is given trees without a
+        // leaf for a value, so the values are found first with trees of one leaf that is as
+        // wide as any value of the settings
+        let any = encode(
+            &settings.window_bits.to_string(),
+            &settings.length_bits.to_string(),
+        )?;
+        let (offsets, lengths) = value_widths(&any).context("reading the encoded values")?;
+        let trees = match &settings.trees {
+            Some(trees) => {
+                trees.check(&offsets, &lengths)?;
+                trees.clone()
+            }
+            None => {
+                ensure!(!lengths.is_empty(), Error::NoRepeats);
+                Trees {
+                    offsets: huffman(&offsets),
+                    lengths: huffman(&lengths),
+                }
+            }
+        };
+
+        Ok(encode(&trees.offsets, &trees.lengths)?)
+    };
+
+    compress().with_context(|| format!("compressing file <{}>", id))
+}
+
+/// The encoding settings for vpk0 compressing a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressSettings {
+    /// one or two sample encoding of the offsets
+    pub method: VpkMethod,
+    /// number of bits for an offset back into the window (the window size)
+    pub window_bits: usize,
+    /// number of bits for the length of a match
+    pub length_bits: usize,
+    /// the longest run of bytes that is stored without encoding a match
+    pub max_uncoded: usize,
+    /// the huffman trees of the offsets and lengths; without them, the trees are built
+    /// from the data
+    pub trees: Option<Trees>,
+}
+
+/// The huffman trees of a vpk0 file, written out as nested pairs of the bit widths of
+/// the values at their leaves (e.g., `((1, 4), (8, 16))`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trees {
+    pub offsets: String,
+    pub lengths: String,
+}
+
+impl Trees {
+    /// The widest value at a leaf of `tree`
+    fn widest(tree: &str) -> Option<usize> {
+        tree.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|bits| bits.parse().ok())
+            .max()
+    }
+
+    /// Check that the trees have a leaf for the values of each width of `offsets` and
+    /// `lengths`, and that their codes fit in the encoder's 32 bits
+    fn check(&self, offsets: &Widths, lengths: &Widths) -> Result<()> {
+        for (tree, string, widths) in [
+            ("offset", &self.offsets, offsets),
+            ("length", &self.lengths, lengths),
+        ] {
+            let widest = Self::widest(string).unwrap_or(0);
+            if let Some(&bits) = widths.keys().next_back() {
+                ensure!(
+                    bits as usize <= widest,
+                    Error::UnencodableValue {
+                        tree,
+                        bits: bits as usize,
+                        widest
+                    }
+                );
+            }
+            // a leaf's code is a bit for each pair that it is nested in
+            let depth = string
+                .chars()
+                .scan(0usize, |depth, c| {
+                    match c {
+                        '(' => *depth += 1,
+                        ')' => *depth = depth.saturating_sub(1),
+                        _ => (),
+                    }
+                    Some(*depth)
+                })
+                .max()
+                .unwrap_or(0);
+            ensure!(depth < 32, Error::TreeTooDeep { tree, depth });
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of values of each bit width
+type Widths = BTreeMap<u32, usize>;
+
+/// The widths of the offsets and of the lengths that the vpk0 data `compressed` encodes
+fn value_widths(compressed: &[u8]) -> Result<(Widths, Widths)> {
+    let Some(&[a, b, c, d, method]) = compressed.get(4..9) else {
+        bail!("the vpk0 header is cut off");
+    };
+    // the trees start after the 9 byte header
+    let mut bits = Bits {
+        data: compressed,
+        at: 9 * 8,
+    };
+    let size = u32::from_be_bytes([a, b, c, d]) as usize;
+    let offset_tree = bits.tree()?;
+    let length_tree = bits.tree()?;
+
+    let (mut offsets, mut lengths) = (BTreeMap::new(), BTreeMap::new());
+    let count = |widths: &mut Widths, value: u32| {
+        *widths.entry(u32::BITS - value.leading_zeros()).or_insert(0) += 1;
+        value
+    };
+    let cut_off = || anyhow!("the vpk0 data is cut off");
+    let mut decompressed = 0;
+    while decompressed < size {
+        if !bits.bit().ok_or_else(cut_off)? {
+            bits.take(8).ok_or_else(cut_off)?;
+            decompressed += 1;
+            continue;
+        }
+        let first = count(&mut offsets, bits.value(&offset_tree).ok_or_else(cut_off)?);
+        // a two sample offset below 3 is the remainder, with the quotient after it
+        if method == VpkMethod::TwoSample as u8 && first < 3 {
+            count(&mut offsets, bits.value(&offset_tree).ok_or_else(cut_off)?);
+        }
+        decompressed += count(&mut lengths, bits.value(&length_tree).ok_or_else(cut_off)?) as usize;
+    }
+
+    Ok((offsets, lengths))
+}
+
+/// A huffman tree of `widths` and their counts, written out as nested pairs. Nodes of the
+/// same count are joined in the order that they were made, starting from the narrowest
+/// widths.
+fn huffman(widths: &Widths) -> String {
+    // (count, order made, tree)
+    let mut nodes = widths
+        .iter()
+        .enumerate()
+        .map(|(order, (width, &count))| Reverse((count, order, width.to_string())))
+        .collect::<BinaryHeap<_>>();
+    let mut order = nodes.len();
+    while nodes.len() > 1 {
+        let (Some(Reverse(left)), Some(Reverse(right))) = (nodes.pop(), nodes.pop()) else {
+            unreachable!();
+        };
+        nodes.push(Reverse((
+            left.0 + right.0,
+            order,
+            format!("({}, {})", left.2, right.2),
+        )));
+        order += 1;
+    }
+
+    // data without any matches still needs a tree
+    nodes
+        .pop()
+        .map_or_else(|| "0".to_string(), |Reverse((_, _, tree))| tree)
+}
+
+/// A huffman tree read from vpk0 data, with the nodes after the leaves they join
+enum TreeEntry {
+    Leaf(u32),
+    Node(usize, usize),
+}
+
+/// Reads the bits of vpk0 data, from the highest bit of each byte
+struct Bits<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> Option<bool> {
+        let byte = self.data.get(self.at / 8)?;
+        let bit = byte >> (7 - self.at % 8) & 1 == 1;
+        self.at += 1;
+        Some(bit)
+    }
+
+    fn take(&mut self, count: u32) -> Option<u32> {
+        if count > u32::BITS {
+            return None;
+        }
+        (0..count).try_fold(0, |value, _| Some(value << 1 | self.bit()? as u32))
+    }
+
+    /// A tree, as a leaf (0 and an 8-bit width) or a node of the last two entries (1)
+    /// for each entry, ended by a 1 without two entries to join
+    fn tree(&mut self) -> Result<Vec<TreeEntry>> {
+        let cut_off = || anyhow!("the vpk0 trees are cut off");
+        let (mut entries, mut unjoined) = (Vec::new(), Vec::new());
+        loop {
+            if !self.bit().ok_or_else(cut_off)? {
+                entries.push(TreeEntry::Leaf(self.take(8).ok_or_else(cut_off)?));
+            } else if let [.., left, right] = unjoined[..] {
+                unjoined.truncate(unjoined.len() - 2);
+                entries.push(TreeEntry::Node(left, right));
+            } else {
+                return Ok(entries);
+            }
+            unjoined.push(entries.len() - 1);
+        }
+    }
+
+    /// The next value, with the width at the leaf of `tree` that the bits lead to
+    fn value(&mut self, tree: &[TreeEntry]) -> Option<u32> {
+        let mut entry = tree.len().checked_sub(1)?;
+        loop {
+            match tree[entry] {
+                TreeEntry::Node(left, right) => {
+                    entry = if self.bit()? { right } else { left };
+                }
+                TreeEntry::Leaf(width) => return self.take(width),
+            }
+        }
+    }
+}
+
+/// The widest window that the encoder is given: it keeps the whole window in memory
+const MAX_WINDOW_BITS: usize = 24;
+/// The widest match length that the encoder is given: it looks that far ahead
+const MAX_LENGTH_BITS: usize = 16;
+
+impl CompressSettings {
+    fn lzss(&self) -> vpk0::LzssSettings {
+        vpk0::LzssSettings::new(self.window_bits, self.length_bits, self.max_uncoded)
+    }
+
+    /// Check that the window and match length can be given to the encoder
+    fn check(&self) -> Result<()> {
+        for (what, bits, max) in [
+            ("windows", self.window_bits, MAX_WINDOW_BITS),
+            ("match lengths", self.length_bits, MAX_LENGTH_BITS),
+        ] {
+            ensure!(
+                (1..=max).contains(&bits),
+                Error::CompressBits { what, bits, max }
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The settings of an already compressed file: its sample method and huffman trees,
+    /// and the window and match length that its trees can encode. With the trees given,
+    /// recompressing the same data gives the same bytes every time.
+    pub fn matching(compressed: &[u8]) -> Result<Self> {
+        let (header, trees) =
+            vpk0::vpk_info(Cursor::new(compressed)).context("reading vpk0 header")?;
+        let default = Self::default();
+        // the two sample method stores offsets in about a quarter of the value
+        let window_bits = Trees::widest(&trees.offsets).map(|bits| match header.method {
+            VpkMethod::OneSample => bits,
+            VpkMethod::TwoSample => bits + 1,
+        });
+
+        Ok(Self {
+            method: header.method,
+            window_bits: window_bits.unwrap_or(default.window_bits),
+            length_bits: Trees::widest(&trees.lengths).unwrap_or(default.length_bits),
+            trees: Some(Trees {
+                offsets: trees.offsets,
+                lengths: trees.lengths,
+            }),
+            ..default
+        })
+    }
+}
+
+impl CompressSettings {
+    /// Settings to try when the default compression of a file is too large:
+    /// both sample methods, with a few window sizes
+    pub fn candidates() -> impl Iterator<Item = Self> {
+        let methods = [VpkMethod::OneSample, VpkMethod::TwoSample];
+        methods.into_iter().flat_map(|method| {
+            [16, 14, 12].into_iter().map(move |window_bits| Self {
+                method,
+                window_bits,
+                ..Self::default()
+            })
+        })
+    }
+}
+
+/// vpk0 compress `data` with each of `candidates`, and keep the smallest result
+pub fn compress_smallest(
+    data: &[u8],
+    id: usize,
+    candidates: impl IntoIterator<Item = CompressSettings>,
+) -> Result<(CompressSettings, Vec<u8>)> {
+    let mut best: Option<(CompressSettings, Vec<u8>)> = None;
+    for settings in candidates {
+        let compressed = compress_with(data, id, &settings)?;
+        if best
+            .as_ref()
+            .is_none_or(|(_, b)| compressed.len() < b.len())
+        {
+            best = Some((settings, compressed));
+        }
+    }
+
+    best.with_context(|| format!("no compression settings to try for file <{}>", id))
+}
+
+impl Default for CompressSettings {
+    fn default() -> Self {
+        let lzss = vpk0::LzssSettings::default();
+
+        Self {
+            method: VpkMethod::OneSample,
+            window_bits: lzss.offset_bits,
+            length_bits: lzss.length_bits,
+            max_uncoded: lzss.max_uncoded,
+            trees: None,
+        }
+    }
+}
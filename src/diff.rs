@@ -0,0 +1,82 @@
+use crate::{
+    cli::DiffOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{read_table, TableEntry};
+
+pub(crate) fn tables(opt: DiffOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (other, other_version) =
+        crate::cli::read_rom(&opt.other, &opt.rom.version_db()?, None, timings)?;
+
+    let (entries, other_entries) = timings.time(Stage::TableParse, || {
+        let entries = read_table(&rom, &version)
+            .with_context(|| format!("reading resource table of <{}>", opt.rom.path.display()))?;
+        let other_entries = read_table(&other, &other_version)
+            .with_context(|| format!("reading resource table of <{}>", opt.other.display()))?;
+
+        Ok::<_, anyhow::Error>((entries.0, other_entries.0))
+    })?;
+
+    if entries.len() != other_entries.len() {
+        println!(
+            "table sizes differ: {} files -> {} files",
+            entries.len(),
+            other_entries.len()
+        );
+    }
+
+    let data = |rom: &[u8], table_end: usize, e: &TableEntry| {
+        let start = table_end + e.offset as usize;
+        rom.get(start..start + e.size as usize * 4)
+            .map(fnv1a)
+            .unwrap_or_default()
+    };
+
+    let mut count = 0;
+    for (id, (a, b)) in entries.iter().zip(&other_entries).enumerate() {
+        let mut changes = Vec::new();
+        if a.offset != b.offset {
+            changes.push(format!("offset {:#X} -> {:#X}", a.offset, b.offset));
+        }
+        if a.size != b.size {
+            changes.push(format!(
+                "size {:#X} -> {:#X}",
+                a.size as usize * 4,
+                b.size as usize * 4
+            ));
+        }
+        if a.decompressed_size != b.decompressed_size {
+            changes.push(format!(
+                "decompressed size {:#X} -> {:#X}",
+                a.decompressed_size as usize * 4,
+                b.decompressed_size as usize * 4
+            ));
+        }
+        if a.compressed != b.compressed {
+            changes.push(format!("compressed {} -> {}", a.compressed, b.compressed));
+        }
+        if a.inreloc != b.inreloc || a.exreloc != b.exreloc {
+            changes.push("relocations".to_string());
+        }
+        if data(&rom, version.table_end, a) != data(&other, other_version.table_end, b) {
+            changes.push("data".to_string());
+        }
+
+        if !changes.is_empty() {
+            count += 1;
+            println!("{:4}  {}", id, changes.join(", "));
+        }
+    }
+    println!("{} files differ", count);
+
+    Ok(())
+}
+
+/// 64-bit FNV-1a hash, to compare file contents
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,659 @@
+//! F3DEX2 display lists: the graphics commands that draw the models of the game.
+//! Commands are decoded from the file's data, and the pointers in them are resolved
+//! with the file's relocations, so that they name the vertices and textures they load.
+use crate::{Error, Relocation};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use anyhow::{ensure, Result};
+use core::{fmt, str::FromStr};
+
+/// how many commands have to come before a `G_ENDDL` to count as a display list
+const MIN_COMMANDS: usize = 2;
+const VERTEX_SIZE: usize = 16;
+
+/// Where the pointer of a command points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Address {
+    /// an offset in the same file
+    Internal(u32),
+    /// an offset in another file
+    External { file: u16, offset: u32 },
+    /// a segmented or physical address that isn't relocated, like a segment
+    /// that the game sets up before drawing the model
+    Raw(u32),
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Internal(offset) => write!(f, "file+0x{:04X}", offset),
+            Self::External { file, offset } => write!(f, "file{:04}+0x{:04X}", file, offset),
+            Self::Raw(addr) => write!(f, "0x{:08X}", addr),
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    /// Parse an address as it is displayed
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || Error::BadAddress { text: s.into() };
+        let hex = |h: &str| {
+            h.strip_prefix("0x")
+                .or_else(|| h.strip_prefix("0X"))
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .ok_or_else(bad)
+        };
+        match s.split_once('+') {
+            Some(("file", offset)) => Ok(Self::Internal(hex(offset)?)),
+            Some((file, offset)) => {
+                let file = file
+                    .strip_prefix("file")
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(bad)?;
+                Ok(Self::External {
+                    file,
+                    offset: hex(offset)?,
+                })
+            }
+            None => Ok(Self::Raw(hex(s)?)),
+        }
+    }
+}
+
+/// A decoded F3DEX2 command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    NoOp,
+    /// load `count` vertices into the vertex buffer, starting at index `first`
+    Vertex {
+        addr: Address,
+        count: u8,
+        first: u8,
+    },
+    Triangle([u8; 3]),
+    Triangles([u8; 3], [u8; 3]),
+    Quad([u8; 4]),
+    Texture {
+        s: u16,
+        t: u16,
+        level: u8,
+        tile: u8,
+        on: bool,
+    },
+    GeometryMode {
+        clear: u32,
+        set: u32,
+    },
+    Matrix {
+        addr: Address,
+        params: u8,
+    },
+    PopMatrix {
+        count: u32,
+    },
+    MoveWord {
+        index: u8,
+        offset: u16,
+        data: u32,
+    },
+    MoveMem {
+        addr: Address,
+        size: u16,
+        index: u8,
+        offset: u16,
+    },
+    /// call another display list, or jump to it if `branch`
+    DisplayList {
+        addr: Address,
+        branch: bool,
+    },
+    EndDisplayList,
+    SetOtherMode {
+        high: bool,
+        shift: u8,
+        len: u8,
+        data: u32,
+    },
+    LoadSync,
+    PipeSync,
+    TileSync,
+    FullSync,
+    LoadTlut {
+        tile: u8,
+        count: u16,
+    },
+    SetTileSize {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        lrt: u16,
+    },
+    LoadBlock {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        dxt: u16,
+    },
+    LoadTile {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        lrt: u16,
+    },
+    SetTile {
+        format: u8,
+        size: u8,
+        line: u16,
+        tmem: u16,
+        tile: u8,
+        palette: u8,
+        cmt: u8,
+        maskt: u8,
+        shiftt: u8,
+        cms: u8,
+        masks: u8,
+        shifts: u8,
+    },
+    FillColor(u32),
+    FogColor([u8; 4]),
+    BlendColor([u8; 4]),
+    PrimColor {
+        min_level: u8,
+        lod_frac: u8,
+        color: [u8; 4],
+    },
+    EnvColor([u8; 4]),
+    SetCombine(u64),
+    /// the texture that the next load commands read from
+    SetTextureImage {
+        format: u8,
+        size: u8,
+        width: u16,
+        addr: Address,
+    },
+    Unknown(u64),
+}
+
+impl Command {
+    /// Decode the command `cmd`, whose pointer (if it has one) is `addr`
+    pub fn decode(cmd: u64, addr: Address) -> Self {
+        let (w0, w1) = ((cmd >> 32) as u32, cmd as u32);
+        let bits = |word: u32, shift: u32, len: u32| (word >> shift) & ((1 << len) - 1);
+        let vtx = |word: u32, shift: u32| (bits(word, shift, 8) / 2) as u8;
+        let color = w1.to_be_bytes();
+
+        match w0 >> 24 {
+            0x00 => Self::NoOp,
+            0x01 => {
+                let count = bits(w0, 12, 8);
+                Self::Vertex {
+                    addr,
+                    count: count as u8,
+                    first: bits(w0, 1, 7).wrapping_sub(count) as u8,
+                }
+            }
+            0x05 => Self::Triangle([vtx(w0, 16), vtx(w0, 8), vtx(w0, 0)]),
+            0x06 => Self::Triangles(
+                [vtx(w0, 16), vtx(w0, 8), vtx(w0, 0)],
+                [vtx(w1, 16), vtx(w1, 8), vtx(w1, 0)],
+            ),
+            0x07 => Self::Quad([vtx(w0, 16), vtx(w0, 8), vtx(w0, 0), vtx(w1, 0)]),
+            0xD7 => Self::Texture {
+                s: (w1 >> 16) as u16,
+                t: w1 as u16,
+                level: bits(w0, 11, 3) as u8,
+                tile: bits(w0, 8, 3) as u8,
+                on: bits(w0, 1, 7) != 0,
+            },
+            0xD8 => Self::PopMatrix { count: w1 / 64 },
+            0xD9 => Self::GeometryMode {
+                clear: !w0 & 0x00FF_FFFF,
+                set: w1,
+            },
+            // G_MTX_PUSH is stored inverted
+            0xDA => Self::Matrix {
+                addr,
+                params: (w0 as u8) ^ 1,
+            },
+            0xDB => Self::MoveWord {
+                index: bits(w0, 16, 8) as u8,
+                offset: w0 as u16,
+                data: w1,
+            },
+            0xDC => Self::MoveMem {
+                addr,
+                size: ((bits(w0, 19, 5) + 1) * 8) as u16,
+                index: w0 as u8,
+                offset: (bits(w0, 8, 8) * 8) as u16,
+            },
+            0xDE => Self::DisplayList {
+                addr,
+                branch: bits(w0, 16, 8) != 0,
+            },
+            0xDF => Self::EndDisplayList,
+            op @ (0xE2 | 0xE3) => {
+                let len = bits(w0, 0, 8) + 1;
+                Self::SetOtherMode {
+                    high: op == 0xE3,
+                    shift: 32u32.saturating_sub(bits(w0, 8, 8) + len) as u8,
+                    len: len as u8,
+                    data: w1,
+                }
+            }
+            0xE6 => Self::LoadSync,
+            0xE7 => Self::PipeSync,
+            0xE8 => Self::TileSync,
+            0xE9 => Self::FullSync,
+            0xF0 => Self::LoadTlut {
+                tile: bits(w1, 24, 3) as u8,
+                count: bits(w1, 14, 10) as u16,
+            },
+            0xF2 => Self::SetTileSize {
+                tile: bits(w1, 24, 3) as u8,
+                uls: bits(w0, 12, 12) as u16,
+                ult: bits(w0, 0, 12) as u16,
+                lrs: bits(w1, 12, 12) as u16,
+                lrt: bits(w1, 0, 12) as u16,
+            },
+            0xF3 => Self::LoadBlock {
+                tile: bits(w1, 24, 3) as u8,
+                uls: bits(w0, 12, 12) as u16,
+                ult: bits(w0, 0, 12) as u16,
+                lrs: bits(w1, 12, 12) as u16,
+                dxt: bits(w1, 0, 12) as u16,
+            },
+            0xF4 => Self::LoadTile {
+                tile: bits(w1, 24, 3) as u8,
+                uls: bits(w0, 12, 12) as u16,
+                ult: bits(w0, 0, 12) as u16,
+                lrs: bits(w1, 12, 12) as u16,
+                lrt: bits(w1, 0, 12) as u16,
+            },
+            0xF5 => Self::SetTile {
+                format: bits(w0, 21, 3) as u8,
+                size: bits(w0, 19, 2) as u8,
+                line: bits(w0, 9, 9) as u16,
+                tmem: bits(w0, 0, 9) as u16,
+                tile: bits(w1, 24, 3) as u8,
+                palette: bits(w1, 20, 4) as u8,
+                cmt: bits(w1, 18, 2) as u8,
+                maskt: bits(w1, 14, 4) as u8,
+                shiftt: bits(w1, 10, 4) as u8,
+                cms: bits(w1, 8, 2) as u8,
+                masks: bits(w1, 4, 4) as u8,
+                shifts: bits(w1, 0, 4) as u8,
+            },
+            0xF7 => Self::FillColor(w1),
+            0xF8 => Self::FogColor(color),
+            0xF9 => Self::BlendColor(color),
+            0xFA => Self::PrimColor {
+                min_level: bits(w0, 8, 8) as u8,
+                lod_frac: w0 as u8,
+                color,
+            },
+            0xFB => Self::EnvColor(color),
+            0xFC => Self::SetCombine(cmd & 0x00FF_FFFF_FFFF_FFFF),
+            0xFD => Self::SetTextureImage {
+                format: bits(w0, 21, 3) as u8,
+                size: bits(w0, 19, 2) as u8,
+                width: (bits(w0, 0, 12) + 1) as u16,
+                addr,
+            },
+            _ => Self::Unknown(cmd),
+        }
+    }
+
+    /// The pointer of the command, if it has one
+    pub fn address(&self) -> Option<Address> {
+        match *self {
+            Self::Vertex { addr, .. }
+            | Self::Matrix { addr, .. }
+            | Self::MoveMem { addr, .. }
+            | Self::DisplayList { addr, .. }
+            | Self::SetTextureImage { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+}
+
+/// The names of the `G_IM_FMT` and `G_IM_SIZ` of a texture
+fn texel_names(format: u8, size: u8) -> (&'static str, &'static str) {
+    let format = match format {
+        0 => "G_IM_FMT_RGBA",
+        1 => "G_IM_FMT_YUV",
+        2 => "G_IM_FMT_CI",
+        3 => "G_IM_FMT_IA",
+        4 => "G_IM_FMT_I",
+        _ => "?",
+    };
+    let size = ["G_IM_SIZ_4b", "G_IM_SIZ_8b", "G_IM_SIZ_16b", "G_IM_SIZ_32b"][size as usize & 3];
+    (format, size)
+}
+
+/// The command as the gbi.h macro that makes it
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rgba = |c: &[u8; 4]| {
+            let [r, g, b, a] = *c;
+            (r, g, b, a)
+        };
+        match self {
+            Self::NoOp => write!(f, "gsDPNoOp()"),
+            Self::Vertex { addr, count, first } => {
+                write!(f, "gsSPVertex({}, {}, {})", addr, count, first)
+            }
+            Self::Triangle([a, b, c]) => write!(f, "gsSP1Triangle({}, {}, {}, 0)", a, b, c),
+            Self::Triangles([a, b, c], [d, e, g]) => write!(
+                f,
+                "gsSP2Triangles({}, {}, {}, 0, {}, {}, {}, 0)",
+                a, b, c, d, e, g
+            ),
+            Self::Quad([a, b, c, d]) => {
+                write!(f, "gsSP1Quadrangle({}, {}, {}, {}, 0)", a, b, c, d)
+            }
+            Self::Texture {
+                s,
+                t,
+                level,
+                tile,
+                on,
+            } => write!(
+                f,
+                "gsSPTexture(0x{:04X}, 0x{:04X}, {}, {}, {})",
+                s,
+                t,
+                level,
+                tile,
+                if *on { "G_ON" } else { "G_OFF" }
+            ),
+            Self::GeometryMode { clear, set } => {
+                write!(f, "gsSPGeometryMode(0x{:06X}, 0x{:06X})", clear, set)
+            }
+            Self::Matrix { addr, params } => write!(f, "gsSPMatrix({}, 0x{:02X})", addr, params),
+            Self::PopMatrix { count } => {
+                write!(f, "gsSPPopMatrixN(G_MTX_MODELVIEW, {})", count)
+            }
+            Self::MoveWord {
+                index,
+                offset,
+                data,
+            } => write!(
+                f,
+                "gsMoveWd(0x{:02X}, 0x{:04X}, 0x{:08X})",
+                index, offset, data
+            ),
+            Self::MoveMem {
+                addr,
+                size,
+                index,
+                offset,
+            } => write!(
+                f,
+                "gsDma2p(G_MOVEMEM, {}, 0x{:X}, 0x{:02X}, 0x{:X})",
+                addr, size, index, offset
+            ),
+            Self::DisplayList {
+                addr,
+                branch: false,
+            } => write!(f, "gsSPDisplayList({})", addr),
+            Self::DisplayList { addr, branch: true } => write!(f, "gsSPBranchList({})", addr),
+            Self::EndDisplayList => write!(f, "gsSPEndDisplayList()"),
+            Self::SetOtherMode {
+                high,
+                shift,
+                len,
+                data,
+            } => write!(
+                f,
+                "gsSPSetOtherMode({}, {}, {}, 0x{:08X})",
+                if *high {
+                    "G_SETOTHERMODE_H"
+                } else {
+                    "G_SETOTHERMODE_L"
+                },
+                shift,
+                len,
+                data
+            ),
+            Self::LoadSync => write!(f, "gsDPLoadSync()"),
+            Self::PipeSync => write!(f, "gsDPPipeSync()"),
+            Self::TileSync => write!(f, "gsDPTileSync()"),
+            Self::FullSync => write!(f, "gsDPFullSync()"),
+            Self::LoadTlut { tile, count } => {
+                write!(f, "gsDPLoadTLUTCmd({}, {})", tile, count)
+            }
+            Self::SetTileSize {
+                tile,
+                uls,
+                ult,
+                lrs,
+                lrt,
+            } => write!(
+                f,
+                "gsDPSetTileSize({}, 0x{:03X}, 0x{:03X}, 0x{:03X}, 0x{:03X})",
+                tile, uls, ult, lrs, lrt
+            ),
+            Self::LoadBlock {
+                tile,
+                uls,
+                ult,
+                lrs,
+                dxt,
+            } => write!(
+                f,
+                "gsDPLoadBlock({}, {}, {}, {}, 0x{:03X})",
+                tile, uls, ult, lrs, dxt
+            ),
+            Self::LoadTile {
+                tile,
+                uls,
+                ult,
+                lrs,
+                lrt,
+            } => write!(
+                f,
+                "gsDPLoadTile({}, 0x{:03X}, 0x{:03X}, 0x{:03X}, 0x{:03X})",
+                tile, uls, ult, lrs, lrt
+            ),
+            Self::SetTile {
+                format,
+                size,
+                line,
+                tmem,
+                tile,
+                palette,
+                cmt,
+                maskt,
+                shiftt,
+                cms,
+                masks,
+                shifts,
+            } => {
+                let (format, size) = texel_names(*format, *size);
+                write!(
+                    f,
+                    "gsDPSetTile({}, {}, {}, 0x{:03X}, {}, {}, {}, {}, {}, {}, {}, {})",
+                    format, size, line, tmem, tile, palette, cmt, maskt, shiftt, cms, masks, shifts
+                )
+            }
+            Self::FillColor(color) => write!(f, "gsDPSetFillColor(0x{:08X})", color),
+            Self::FogColor(c) => write!(f, "gsDPSetFogColor{:?}", rgba(c)),
+            Self::BlendColor(c) => write!(f, "gsDPSetBlendColor{:?}", rgba(c)),
+            Self::PrimColor {
+                min_level,
+                lod_frac,
+                color: [r, g, b, a],
+            } => write!(
+                f,
+                "gsDPSetPrimColor({}, {}, {}, {}, {}, {})",
+                min_level, lod_frac, r, g, b, a
+            ),
+            Self::EnvColor(c) => write!(f, "gsDPSetEnvColor{:?}", rgba(c)),
+            Self::SetCombine(combine) => write!(
+                f,
+                "{{0x{:08X}, 0x{:08X}}} /* G_SETCOMBINE */",
+                (combine >> 32) as u32 | 0xFC00_0000,
+                *combine as u32
+            ),
+            Self::SetTextureImage {
+                format,
+                size,
+                width,
+                addr,
+            } => {
+                let (format, size) = texel_names(*format, *size);
+                write!(
+                    f,
+                    "gsDPSetTextureImage({}, {}, {}, {})",
+                    format, size, width, addr
+                )
+            }
+            Self::Unknown(cmd) => {
+                write!(f, "{{0x{:08X}, 0x{:08X}}}", (cmd >> 32) as u32, *cmd as u32)
+            }
+        }
+    }
+}
+
+/// A `Vtx` of a vertex buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vertex {
+    pub position: [i16; 3],
+    pub flag: u16,
+    /// texture coordinates, in 10.5 fixed point
+    pub st: [i16; 2],
+    /// the color, or the normal and alpha of a lit vertex
+    pub color: [u8; 4],
+}
+
+/// Read `count` vertices from `file` at `offset`
+pub fn vertices(file: &[u8], offset: usize, count: usize) -> Result<Vec<Vertex>> {
+    let end = offset + count * VERTEX_SIZE;
+    let data = file.get(offset..end).ok_or(Error::OutsideFile {
+        start: offset,
+        end,
+        len: file.len(),
+    })?;
+    let half = |v: &[u8], at: usize| i16::from_be_bytes([v[at], v[at + 1]]);
+
+    Ok(data
+        .chunks_exact(VERTEX_SIZE)
+        .map(|v| Vertex {
+            position: [half(v, 0), half(v, 2), half(v, 4)],
+            flag: half(v, 6) as u16,
+            st: [half(v, 8), half(v, 10)],
+            color: [v[12], v[13], v[14], v[15]],
+        })
+        .collect())
+}
+
+/// The internal and external pointers of a file by their offset
+pub(crate) fn pointers(relocs: &[Relocation]) -> BTreeMap<usize, Address> {
+    relocs
+        .iter()
+        .map(|r| {
+            let addr = match r.external_file {
+                Some(file) => Address::External {
+                    file,
+                    offset: r.target,
+                },
+                None => Address::Internal(r.target),
+            };
+            (r.offset, addr)
+        })
+        .collect()
+}
+
+/// Decode the display list of `file` that starts at `start`, up to and including its
+/// `G_ENDDL` (or a `G_DL` branch, which doesn't return), with the offset of each command.
+/// The pointers of the commands are found in `relocs` (see [`relocations`](crate::relocations)).
+pub fn parse(file: &[u8], start: usize, relocs: &[Relocation]) -> Result<Vec<(usize, Command)>> {
+    parse_with(file, start, &pointers(relocs))
+}
+
+fn parse_with(
+    file: &[u8],
+    start: usize,
+    pointers: &BTreeMap<usize, Address>,
+) -> Result<Vec<(usize, Command)>> {
+    ensure!(
+        start.is_multiple_of(8),
+        Error::UnterminatedDisplayList { start }
+    );
+    let mut commands = Vec::new();
+    for (i, cmd) in file
+        .get(start..)
+        .unwrap_or_default()
+        .chunks_exact(8)
+        .enumerate()
+    {
+        let offset = start + i * 8;
+        let cmd = u64::from_be_bytes(cmd.try_into().expect("8 byte chunk"));
+        let addr = pointers
+            .get(&(offset + 4))
+            .copied()
+            .unwrap_or(Address::Raw(cmd as u32));
+        let command = Command::decode(cmd, addr);
+        commands.push((offset, command));
+        if matches!(
+            command,
+            Command::EndDisplayList | Command::DisplayList { branch: true, .. }
+        ) {
+            return Ok(commands);
+        }
+    }
+
+    Err(Error::UnterminatedDisplayList { start }.into())
+}
+
+/// Find the display lists of `file`: the internal pointer targets (and the lists that
+/// those call) that hold at least a few known commands, followed by a `G_ENDDL`
+pub fn find(file: &[u8], relocs: &[Relocation]) -> Vec<usize> {
+    let pointers = pointers(relocs);
+    let mut queue = pointers
+        .values()
+        .filter_map(|addr| match addr {
+            Address::Internal(offset) => Some(*offset as usize),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let mut found = BTreeSet::new();
+    let mut checked = BTreeSet::new();
+    while let Some(start) = queue.pop_first() {
+        if !checked.insert(start) {
+            continue;
+        }
+        let Ok(commands) = parse_with(file, start, &pointers) else {
+            continue;
+        };
+        let known = commands
+            .iter()
+            .all(|(_, c)| !matches!(c, Command::Unknown(_)));
+        if !known || commands.len() <= MIN_COMMANDS {
+            continue;
+        }
+        found.insert(start);
+        // the lists that this one calls are display lists too
+        for (_, command) in &commands {
+            if let Command::DisplayList {
+                addr: Address::Internal(target),
+                ..
+            } = command
+            {
+                queue.insert(*target as usize);
+            }
+        }
+    }
+
+    found.into_iter().collect()
+}
@@ -0,0 +1,337 @@
+//! Particle effect scripts: the byte code that drives the particles of an effect,
+//! moving them, fading their color and size, and spawning other effects.
+//!
+//! A bank of effects starts with a word of how many scripts it has, then a word for each
+//! script with its offset from the start of the bank; the `effect` of a subaction
+//! script's `effect` command indexes this table. Each script is a header (the
+//! decompilation's `LBScript`: the kind and texture of the particles, how long they and
+//! their generator live, and the forces on them) followed by its commands, which run
+//! until `end`. The names and layouts of the commands follow the decompilation's
+//! particle script as far as they have been mapped; a command without a known layout
+//! stops the decoding, since its length isn't known.
+use crate::Error;
+use alloc::{format, string::String, vec::Vec};
+use anyhow::Result;
+use core::fmt;
+
+/// the most scripts that a bank is read with
+const MAX_SCRIPTS: usize = 0x400;
+/// the most commands that are read from a script
+const MAX_COMMANDS: usize = 0x1000;
+/// the size of the header of a script
+pub const HEADER_SIZE: usize = 0x30;
+
+/// `0x00..=0x7F`: wait for the frames in the low 6 bits, or with bit 6 set, for those
+/// bits and the next byte
+const WAIT_END: u8 = 0x7F;
+/// `0x80..=0x9F`: set the position to the floats that bits 0 to 2 (x, y, z) choose;
+/// bit 3 adds them to the position instead
+const POSITION_END: u8 = 0x9F;
+/// `0xA0..=0xBF`: the same for the velocity
+const VELOCITY_END: u8 = 0xBF;
+/// the command that ends a script
+const END: u8 = 0xFF;
+
+/// A kind of argument of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    U8,
+    U16,
+    F32,
+}
+
+impl Kind {
+    fn size(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::F32 => 4,
+        }
+    }
+}
+
+/// The name and kind of each argument of a command
+type Args = &'static [(&'static str, Kind)];
+
+/// The commands past the position and velocity ones, with their names and arguments
+const COMMANDS: &[(u8, &str, Args)] = &[
+    (0xC0, "size", &[("frames", Kind::U16), ("size", Kind::F32)]),
+    (
+        0xC1,
+        "color",
+        &[
+            ("frames", Kind::U16),
+            ("r", Kind::U8),
+            ("g", Kind::U8),
+            ("b", Kind::U8),
+            ("a", Kind::U8),
+        ],
+    ),
+    (
+        0xC2,
+        "env_color",
+        &[
+            ("frames", Kind::U16),
+            ("r", Kind::U8),
+            ("g", Kind::U8),
+            ("b", Kind::U8),
+            ("a", Kind::U8),
+        ],
+    ),
+    (0xC3, "gravity", &[("gravity", Kind::F32)]),
+    (0xC4, "friction", &[("friction", Kind::F32)]),
+    (0xC5, "texture_frame", &[("frame", Kind::U8)]),
+    (0xC6, "spawn", &[("effect", Kind::U16)]),
+    (0xC7, "scatter", &[("speed", Kind::F32)]),
+    (0xFA, "loop", &[("count", Kind::U8)]),
+    (0xFB, "loop_end", &[]),
+    (0xFC, "set_flags", &[("flags", Kind::U8)]),
+    (0xFD, "clear_flags", &[("flags", Kind::U8)]),
+    (0xFE, "vanish", &[]),
+    (END, "end", &[]),
+];
+
+/// The name of each axis bit of a position or velocity command
+const AXES: [&str; 3] = ["x", "y", "z"];
+/// the bit of a position or velocity command that adds to the current value
+const RELATIVE: u8 = 0x08;
+
+/// The value of an argument of a command
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Int(u32),
+    Float(f32),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Float(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+/// A command of an effect script
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Command {
+    /// offset of the command in the file
+    pub offset: usize,
+    pub opcode: u8,
+    /// the name of the command, if its layout is known
+    pub name: Option<&'static str>,
+    pub args: Vec<(&'static str, Value)>,
+    /// the size of the command in bytes
+    pub size: usize,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(name) = self.name else {
+            return write!(f, ".byte 0x{:02X}", self.opcode);
+        };
+        f.write_str(name)?;
+        for (arg, value) in &self.args {
+            write!(f, " {}={}", arg, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The header of an effect script
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub kind: u16,
+    pub texture: u16,
+    /// the frames that the generator makes particles for
+    pub generator_lifetime: u16,
+    /// the frames that each particle lives for
+    pub particle_lifetime: u16,
+    pub flags: u32,
+    pub gravity: f32,
+    pub friction: f32,
+    pub velocity: [f32; 3],
+    pub size: f32,
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [x, y, z] = self.velocity;
+        write!(
+            f,
+            "kind={} texture={} generator_lifetime={} particle_lifetime={} flags=0x{:08X} \
+             gravity={:?} friction={:?} velocity=({:?}, {:?}, {:?}) size={:?}",
+            self.kind,
+            self.texture,
+            self.generator_lifetime,
+            self.particle_lifetime,
+            self.flags,
+            self.gravity,
+            self.friction,
+            x,
+            y,
+            z,
+            self.size
+        )
+    }
+}
+
+/// An effect script of a bank
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Script {
+    /// the index of the script in its bank, which is its effect id
+    pub index: usize,
+    /// offset of the script's header in the file
+    pub offset: usize,
+    pub header: Header,
+    pub commands: Vec<Command>,
+}
+
+/// Read the bank of effect scripts at `offset` of `file`
+pub fn bank(file: &[u8], offset: usize) -> Result<Vec<Script>> {
+    let bad = || Error::BadEffectBank { offset };
+    let count = word(file, offset).ok_or_else(bad)? as usize;
+    if count == 0 || count > MAX_SCRIPTS {
+        return Err(bad().into());
+    }
+
+    (0..count)
+        .map(|index| {
+            let start = word(file, offset + 4 + index * 4).ok_or_else(bad)? as usize;
+            script(file, offset + start, index)
+        })
+        .collect()
+}
+
+/// Read the effect script whose header is at `offset` of `file`; `index` is its effect id
+pub fn script(file: &[u8], offset: usize, index: usize) -> Result<Script> {
+    let outside = |end: usize| Error::OutsideFile {
+        start: offset,
+        end,
+        len: file.len(),
+    };
+    let h = file
+        .get(offset..offset + HEADER_SIZE)
+        .ok_or_else(|| outside(offset + HEADER_SIZE))?;
+    let half = |at: usize| u16::from_be_bytes([h[at], h[at + 1]]);
+    let float = |at: usize| f32::from_be_bytes([h[at], h[at + 1], h[at + 2], h[at + 3]]);
+    let header = Header {
+        kind: half(0x00),
+        texture: half(0x02),
+        generator_lifetime: half(0x04),
+        particle_lifetime: half(0x06),
+        flags: u32::from_be_bytes([h[0x08], h[0x09], h[0x0A], h[0x0B]]),
+        gravity: float(0x0C),
+        friction: float(0x10),
+        velocity: [float(0x14), float(0x18), float(0x1C)],
+        size: float(0x2C),
+    };
+
+    let mut commands = Vec::new();
+    let mut at = offset + HEADER_SIZE;
+    while commands.len() < MAX_COMMANDS {
+        let command = command(file, at).ok_or_else(|| outside(at + 1))?;
+        at += command.size;
+        let stops = command.name.is_none() || command.opcode == END;
+        commands.push(command);
+        if stops {
+            break;
+        }
+    }
+
+    Ok(Script {
+        index,
+        offset,
+        header,
+        commands,
+    })
+}
+
+/// Decode the command at `at` of `file`, or `None` if it runs past the end of the file
+fn command(file: &[u8], at: usize) -> Option<Command> {
+    let opcode = *file.get(at)?;
+    let mut size = 1;
+    let mut read = |kind: Kind| {
+        let bytes = file.get(at + size..at + size + kind.size())?;
+        size += kind.size();
+        Some(match kind {
+            Kind::U8 => Value::Int(bytes[0] as u32),
+            Kind::U16 => Value::Int(u16::from_be_bytes([bytes[0], bytes[1]]) as u32),
+            Kind::F32 => Value::Float(f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        })
+    };
+
+    let mut args = Vec::new();
+    let name = match opcode {
+        0..=WAIT_END => {
+            let mut frames = (opcode & 0x3F) as u32;
+            if opcode & 0x40 != 0 {
+                frames = (frames << 8) | *file.get(at + 1)? as u32;
+                size += 1;
+            }
+            args.push(("frames", Value::Int(frames)));
+            Some("wait")
+        }
+        0x80..=VELOCITY_END => {
+            for (bit, axis) in AXES.iter().enumerate() {
+                if opcode & (1 << bit) != 0 {
+                    args.push((*axis, read(Kind::F32)?));
+                }
+            }
+            let relative = opcode & RELATIVE != 0;
+            Some(match (opcode <= POSITION_END, relative) {
+                (true, false) => "position",
+                (true, true) => "add_position",
+                (false, false) => "velocity",
+                (false, true) => "add_velocity",
+            })
+        }
+        _ => match COMMANDS.iter().find(|(op, ..)| *op == opcode) {
+            Some(&(_, name, kinds)) => {
+                for &(arg, kind) in kinds {
+                    args.push((arg, read(kind)?));
+                }
+                Some(name)
+            }
+            None => None,
+        },
+    };
+
+    Some(Command {
+        offset: at,
+        opcode,
+        name,
+        args,
+        size,
+    })
+}
+
+/// Write `scripts` as text: a `.effect INDEX OFFSET` line with the header of each
+/// script, then its commands
+pub fn disassemble(scripts: &[Script]) -> String {
+    let mut text = String::new();
+    for script in scripts {
+        text += &format!(
+            "\n.effect {} 0x{:04X}\n# {}\n",
+            script.index, script.offset, script.header
+        );
+        for command in &script.commands {
+            text += &format!("0x{:04X}: {}\n", command.offset, command);
+            if command.name.is_none() {
+                text += "# unknown command; the rest of the script isn't decoded\n";
+            }
+        }
+    }
+
+    text
+}
+
+fn word(file: &[u8], at: usize) -> Option<u32> {
+    file.get(at..at + 4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+}
@@ -0,0 +1,286 @@
+use crate::{target_symbol, FileReloc};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format, vec,
+    vec::Vec,
+};
+
+const R_MIPS_32: u32 = 2;
+const EF_MIPS_ARCH_3: u32 = 0x20000000;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_REL: u32 = 9;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_SECTION: u8 = 3;
+
+/// section header indices
+const DATA: u16 = 1;
+const SYMTAB: u32 = 3;
+const STRTAB: u32 = 4;
+const SHSTRTAB: u16 = 5;
+
+/// Build a big-endian MIPS ELF32 relocatable object out of a file that was relocated
+/// with a base address of 0.
+///
+/// The file data is put into `.data`, and a global `fileNNNN_start` symbol marks its start.
+/// Every relocation becomes an `R_MIPS_32` entry in `.rel.data`: internal pointers
+/// are relocated against this file's start symbol, and external pointers against the
+/// (undefined) start symbol of their file. The pointer offsets are stored in `.data`
+/// as the implicit addends. Each internal pointer target also gets a
+/// `fileNNNN_off_XXXX` symbol.
+pub fn relocatable_object(id: usize, file: &[u8], relocs: &FileReloc) -> Vec<u8> {
+    let mut strtab = StrTab::default();
+    let mut symbols = vec![
+        Symbol::default(),
+        Symbol {
+            info: (STB_LOCAL << 4) | STT_SECTION,
+            shndx: DATA,
+            ..Default::default()
+        },
+    ];
+    // the globals follow the local symbols, starting with the file's start symbol
+    let start = symbols.len() as u32;
+    symbols.push(Symbol {
+        name: strtab.add(&format!("file{:04}_start", id)),
+        value: 0,
+        size: file.len() as u32,
+        info: (STB_GLOBAL << 4) | STT_OBJECT,
+        shndx: DATA,
+    });
+
+    let targets = relocs
+        .internal
+        .iter()
+        .flatten()
+        .map(|&(_, _, ptr)| ptr)
+        .collect::<BTreeSet<_>>();
+    for target in targets {
+        symbols.push(Symbol {
+            name: strtab.add(&target_symbol(id, target)),
+            value: target,
+            size: 0,
+            info: (STB_GLOBAL << 4) | STT_OBJECT,
+            shndx: DATA,
+        });
+    }
+
+    let externs = relocs
+        .external
+        .iter()
+        .flatten()
+        .map(|&(fid, _, _)| fid)
+        .collect::<BTreeSet<_>>();
+    let mut extern_symbols = BTreeMap::new();
+    for fid in externs {
+        extern_symbols.insert(fid, symbols.len() as u32);
+        symbols.push(Symbol {
+            name: strtab.add(&format!("file{:04}_start", fid)),
+            info: (STB_GLOBAL << 4) | STT_OBJECT,
+            ..Default::default()
+        });
+    }
+
+    let internal = relocs
+        .internal
+        .iter()
+        .flatten()
+        .map(|&(_, offset, _)| (offset as u32, start));
+    let external = relocs
+        .external
+        .iter()
+        .flatten()
+        .map(|&(fid, offset, _)| (offset as u32, extern_symbols[&fid]));
+    let mut rels = internal.chain(external).collect::<Vec<_>>();
+    rels.sort_unstable();
+
+    let mut shstrtab = StrTab::default();
+    let names = [
+        0,
+        shstrtab.add(".data"),
+        shstrtab.add(".rel.data"),
+        shstrtab.add(".symtab"),
+        shstrtab.add(".strtab"),
+        shstrtab.add(".shstrtab"),
+    ];
+
+    // lay out the sections after the elf header
+    let mut out = vec![0; 52];
+    let data_off = align(&mut out, 16);
+    out.extend_from_slice(file);
+
+    let rel_off = align(&mut out, 4);
+    for (offset, sym) in &rels {
+        out.extend_from_slice(&offset.to_be_bytes());
+        out.extend_from_slice(&((sym << 8) | R_MIPS_32).to_be_bytes());
+    }
+
+    let sym_off = align(&mut out, 4);
+    for sym in &symbols {
+        sym.write(&mut out);
+    }
+
+    let str_off = out.len();
+    out.extend_from_slice(&strtab.0);
+    let shstr_off = out.len();
+    out.extend_from_slice(&shstrtab.0);
+
+    let sh_off = align(&mut out, 4);
+    let sections = [
+        SectionHeader::default(),
+        SectionHeader {
+            name: names[1],
+            kind: SHT_PROGBITS,
+            flags: 0x3, // SHF_WRITE | SHF_ALLOC
+            offset: data_off,
+            size: file.len(),
+            align: 16,
+            ..Default::default()
+        },
+        SectionHeader {
+            name: names[2],
+            kind: SHT_REL,
+            offset: rel_off,
+            size: rels.len() * 8,
+            link: SYMTAB,
+            info: DATA as u32,
+            align: 4,
+            entsize: 8,
+            ..Default::default()
+        },
+        SectionHeader {
+            name: names[3],
+            kind: SHT_SYMTAB,
+            offset: sym_off,
+            size: symbols.len() * 16,
+            link: STRTAB,
+            info: start,
+            align: 4,
+            entsize: 16,
+            ..Default::default()
+        },
+        SectionHeader {
+            name: names[4],
+            kind: SHT_STRTAB,
+            offset: str_off,
+            size: strtab.0.len(),
+            align: 1,
+            ..Default::default()
+        },
+        SectionHeader {
+            name: names[5],
+            kind: SHT_STRTAB,
+            offset: shstr_off,
+            size: shstrtab.0.len(),
+            align: 1,
+            ..Default::default()
+        },
+    ];
+    for section in &sections {
+        section.write(&mut out);
+    }
+
+    let header = elf_header(sh_off as u32, sections.len() as u16);
+    out[..header.len()].copy_from_slice(&header);
+
+    out
+}
+
+fn elf_header(shoff: u32, shnum: u16) -> Vec<u8> {
+    let mut h = vec![0x7F, b'E', b'L', b'F', 1, 2, 1, 0];
+    h.resize(16, 0);
+    h.extend_from_slice(&1u16.to_be_bytes()); // ET_REL
+    h.extend_from_slice(&8u16.to_be_bytes()); // EM_MIPS
+    h.extend_from_slice(&1u32.to_be_bytes()); // EV_CURRENT
+    h.extend_from_slice(&0u32.to_be_bytes()); // entry
+    h.extend_from_slice(&0u32.to_be_bytes()); // phoff
+    h.extend_from_slice(&shoff.to_be_bytes());
+    h.extend_from_slice(&EF_MIPS_ARCH_3.to_be_bytes());
+    h.extend_from_slice(&52u16.to_be_bytes()); // ehsize
+    h.extend_from_slice(&0u16.to_be_bytes()); // phentsize
+    h.extend_from_slice(&0u16.to_be_bytes()); // phnum
+    h.extend_from_slice(&40u16.to_be_bytes()); // shentsize
+    h.extend_from_slice(&shnum.to_be_bytes());
+    h.extend_from_slice(&SHSTRTAB.to_be_bytes());
+    h
+}
+
+/// pad `out` to `alignment`, and return the new length
+fn align(out: &mut Vec<u8>, alignment: usize) -> usize {
+    out.resize(out.len().next_multiple_of(alignment), 0);
+    out.len()
+}
+
+/// A string table, starting with the empty string
+struct StrTab(Vec<u8>);
+
+impl Default for StrTab {
+    fn default() -> Self {
+        Self(vec![0])
+    }
+}
+
+impl StrTab {
+    fn add(&mut self, s: &str) -> u32 {
+        let idx = self.0.len() as u32;
+        self.0.extend_from_slice(s.as_bytes());
+        self.0.push(0);
+        idx
+    }
+}
+
+#[derive(Default)]
+struct Symbol {
+    name: u32,
+    value: u32,
+    size: u32,
+    info: u8,
+    shndx: u16,
+}
+
+impl Symbol {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.name.to_be_bytes());
+        out.extend_from_slice(&self.value.to_be_bytes());
+        out.extend_from_slice(&self.size.to_be_bytes());
+        out.push(self.info);
+        out.push(0);
+        out.extend_from_slice(&self.shndx.to_be_bytes());
+    }
+}
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    offset: usize,
+    size: usize,
+    link: u32,
+    info: u32,
+    align: u32,
+    entsize: u32,
+}
+
+impl SectionHeader {
+    fn write(&self, out: &mut Vec<u8>) {
+        for field in [
+            self.name,
+            self.kind,
+            self.flags,
+            0, // addr
+            self.offset as u32,
+            self.size as u32,
+            self.link,
+            self.info,
+            self.align,
+            self.entsize,
+        ] {
+            out.extend_from_slice(&field.to_be_bytes());
+        }
+    }
+}
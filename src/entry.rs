@@ -0,0 +1,52 @@
+use crate::{
+    cli::EntrySetOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{read_table, TableEntry};
+
+pub(crate) fn set(opt: EntrySetOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let (entries, _) = timings
+        .time(Stage::TableParse, || read_table(&rom, &version))
+        .context("reading the resource table")?;
+    let old = *entries
+        .get(opt.id)
+        .with_context(|| format!("file <{}> isn't in the table", opt.id))?;
+
+    let entry = TableEntry {
+        offset: opt.offset.unwrap_or(old.offset),
+        compressed: opt.compressed.unwrap_or(old.compressed),
+        inreloc: opt.in_reloc.unwrap_or(old.inreloc),
+        size: opt.size.unwrap_or(old.size),
+        exreloc: opt.ex_reloc.unwrap_or(old.exreloc),
+        decompressed_size: opt.decompressed_size.unwrap_or(old.decompressed_size),
+    };
+    match ssbfile::check_entry(&rom, &version, opt.id, &entry) {
+        Err(e) if opt.force => eprintln!("{:#}; writing it anyway", e),
+        checked => checked?,
+    }
+    eprintln!("file {}:", opt.id);
+    eprintln!("  old {}", Fields(&old));
+    eprintln!("  new {}", Fields(&entry));
+    if opt.dry_run {
+        return Ok(());
+    }
+
+    ssbfile::write_entry(&mut rom, &version, opt.id, &entry)?;
+    opt.backup.write(&opt.output, &rom, timings)
+}
+
+/// The fields of a table entry, as they are stored
+struct Fields<'a>(&'a TableEntry);
+
+impl std::fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let e = self.0;
+        write!(
+            f,
+            "offset {:#X}, compressed {}, inreloc {:#06X}, size {:#06X}, exreloc {:#06X}, decompressed {:#06X}",
+            e.offset, e.compressed, e.inreloc, e.size, e.exreloc, e.decompressed_size
+        )
+    }
+}
@@ -0,0 +1,192 @@
+use crate::rom_info::RomFormat;
+use thiserror::Error;
+
+/// The errors raised by the library. Functions return an [`anyhow::Error`] so that
+/// context can be added along the way; use [`anyhow::Error::downcast_ref`] to get
+/// the underlying `Error`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("requested file <{id}> but the table only has {count} entries")]
+    FileIdOutOfRange { id: usize, count: usize },
+    #[error("range {start:#X}..{end:#X} is outside of the rom ({len:#X} bytes)")]
+    OutsideRom {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[error("range {start:#X}..{end:#X} of the rom wasn't read")]
+    NotRead { start: usize, end: usize },
+    #[error("rom is too small to have a header ({len} bytes)")]
+    NoHeader { len: usize },
+    #[error("unknown rom format (first word {magic:08X})")]
+    UnknownFormat { magic: u32 },
+    #[error("rom is {0:?}, and needs to be converted to big-endian")]
+    NotBigEndian(RomFormat),
+    #[error("could not determine the version of the rom (crc {crc1:08X} {crc2:08X})")]
+    UnknownVersion { crc1: u32, crc2: u32 },
+    #[error("resource table {start:#X}..{end:#X} does not hold a whole number of entries")]
+    BadTableBounds { start: usize, end: usize },
+    #[error("found no table of files at the secondary table offset {offset:#X}")]
+    NoSecondaryTable { offset: usize },
+    #[error("expected {expected} table entries, but found {found}")]
+    EntryCount { expected: usize, found: usize },
+    #[error("relocation at {offset:#X} is outside of the file ({len:#X} bytes)")]
+    RelocationOutsideFile { offset: usize, len: usize },
+    #[error("relocation chain starting at {start:#X} loops")]
+    RelocationLoop { start: usize },
+    #[error("no address was given for external file <{id}>")]
+    MissingExternal { id: u16 },
+    #[error("line {line} of the relocation list isn't `kind,offset[,value[,file id]]`")]
+    MalformedRelocationList { line: usize },
+    #[error("{what} {value:#X} can't be stored in a relocation")]
+    UnencodableRelocation { what: &'static str, value: usize },
+    #[error("two different pointers are listed at offset {offset:#X}")]
+    DuplicateRelocation { offset: usize },
+    #[error("file <{id}> of {size:#X} bytes is too large for the table entry")]
+    FileTooLarge { id: usize, size: usize },
+    #[error("file <{id}> needs {needed:#X} bytes, but only {available:#X} bytes are available in its slot")]
+    DoesNotFit {
+        id: usize,
+        needed: usize,
+        available: usize,
+    },
+    #[error("the new table entry of file <{id}> is inconsistent: {reason}")]
+    InconsistentEntry { id: usize, reason: &'static str },
+    #[error("a title of {len} bytes doesn't fit in the 20 bytes of the rom header")]
+    TitleTooLong { len: usize },
+    #[error("game code <{code}> isn't four ASCII letters or digits")]
+    BadGameCode { code: alloc::string::String },
+    #[error("an IPS patch can't change the rom at {offset:#X}, past 16 MiB")]
+    IpsOffset { offset: usize },
+    #[error("malformed patch: {reason}")]
+    MalformedPatch { reason: &'static str },
+    #[error("the rom isn't the one that the patch was made for")]
+    WrongPatchSource,
+    #[error("unknown texel format <{name}>")]
+    UnknownTexelFormat { name: alloc::string::String },
+    #[error(
+        "a texture of {size:#X} bytes is larger than the {len:#X} bytes of data it is read from"
+    )]
+    TextureOutsideData { size: usize, len: usize },
+    #[error("color indexed textures need a palette")]
+    MissingPalette,
+    #[error(
+        "the image is {width}x{height}, but the texture is {expected_width}x{expected_height}"
+    )]
+    TextureSize {
+        width: usize,
+        height: usize,
+        expected_width: usize,
+        expected_height: usize,
+    },
+    #[error("range {start:#X}..{end:#X} is outside of the file ({len:#X} bytes)")]
+    OutsideFile {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    #[error("the display list at {start:#X} doesn't end")]
+    UnterminatedDisplayList { start: usize },
+    #[error("no bone hierarchy (DObjDesc array) at {offset:#X}")]
+    BadSkeleton { offset: usize },
+    #[error("bad address <{text}>; expected file+0xOFFSET, file0012+0xOFFSET, or 0xADDRESS")]
+    BadAddress { text: alloc::string::String },
+    #[error("line {line} of the script: {reason}")]
+    ScriptSyntax {
+        line: usize,
+        reason: alloc::string::String,
+    },
+    #[error(
+        "the script at {start:#X} is {len:#X} bytes, but only {capacity:#X} bytes fit in its place"
+    )]
+    ScriptTooLong {
+        start: usize,
+        len: usize,
+        capacity: usize,
+    },
+    #[error("the pointer at {offset:#X} to {target:#X} isn't to a word in the file")]
+    ScriptPointer { offset: usize, target: u32 },
+    #[error("no stage collision geometry at {offset:#X}")]
+    BadGeometry { offset: usize },
+    #[error("GameShark codes can't write to {addr:#010X}, outside of 0x80000000..0x81000000")]
+    GameSharkAddress { addr: u32 },
+    #[error("bad escape in <{text}>; expected \\\", \\\\, \\n, \\t, or \\xNN")]
+    BadEscape { text: alloc::string::String },
+    #[error(
+        "<{c}> isn't in Shift-JIS, the encoding of the game's strings; write its bytes as \\xNN"
+    )]
+    TextChar { c: char },
+    #[error("line {line} of the messages: {reason}")]
+    TextSyntax {
+        line: usize,
+        reason: alloc::string::String,
+    },
+    #[error("no string at {offset:#X}")]
+    NoText { offset: usize },
+    #[error(
+        "the string at {offset:#X} is {len} bytes with its NUL, but only {capacity} fit in its place, and nothing points to it to move it"
+    )]
+    TextTooLong {
+        offset: usize,
+        len: usize,
+        capacity: usize,
+    },
+    #[error("no sequence bank at {offset:#X}")]
+    BadSeqBank { offset: usize },
+    #[error("bad sequence at {offset:#X}: {reason}")]
+    BadSequence { offset: usize, reason: &'static str },
+    #[error("bad bank file at {offset:#X}: it doesn't hold a structure at {at:#X}")]
+    BadBankFile { offset: usize, at: usize },
+    #[error("unknown item field <{name}>")]
+    UnknownItemField { name: alloc::string::String },
+    #[error("{value} doesn't fit in the item field <{name}>")]
+    ItemValue { name: &'static str, value: i64 },
+    #[error("no animation stream at {offset:#X}")]
+    BadAnimation { offset: usize },
+    #[error("line {line} of the palette: {reason}")]
+    PaletteSyntax { line: usize, reason: &'static str },
+    #[error("the palette has {colors} colors, but the one it replaces has {expected}")]
+    PaletteSize { colors: usize, expected: usize },
+    #[error("unknown sprite attribute <{name}>")]
+    UnknownSpriteAttribute { name: alloc::string::String },
+    #[error("no bank of effect scripts at {offset:#X}")]
+    BadEffectBank { offset: usize },
+    #[error("unknown fighter attribute <{name}>")]
+    UnknownFighterField { name: alloc::string::String },
+    #[error("the fighter attribute <{name}> has to be a whole number")]
+    FighterValue { name: &'static str },
+    #[error("unknown instruction {word:08X} at {pc:#010X}")]
+    UnknownInstruction { pc: u32, word: u32 },
+    #[error(
+        "the instruction at {pc:#010X} accessed {addr:#010X}, which isn't aligned or in RDRAM"
+    )]
+    BadMemoryAccess { pc: u32, addr: u32 },
+    #[error("the call didn't return within {steps} instructions")]
+    StepLimit { steps: usize },
+    #[error("vpk0 can't encode data without any repeats")]
+    NoRepeats,
+    #[error("vpk0 {what} of {bits} bits are outside of 1..={max}")]
+    CompressBits {
+        what: &'static str,
+        bits: usize,
+        max: usize,
+    },
+    #[error("the {tree} tree has no leaf for values of {bits} bits (its widest is {widest})")]
+    UnencodableValue {
+        tree: &'static str,
+        bits: usize,
+        widest: usize,
+    },
+    #[error("the {tree} tree is {depth} levels deep, but vpk0 codes are at most 31 bits")]
+    TreeTooDeep { tree: &'static str, depth: usize },
+}
+
+// without `std`, anyhow can only convert errors that it knows of, so
+// keep the error as a message that can still be downcast
+#[cfg(not(feature = "std"))]
+impl From<Error> for anyhow::Error {
+    fn from(error: Error) -> Self {
+        anyhow::Error::msg(error)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{relocate, relocations, versions::SSBInfo, TableFile};
+use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+/// Loads relocated files out of a rom on demand, and keeps them around
+/// so that following many references into the same file only extracts it once.
+pub struct FileLoader<'r> {
+    rom: &'r [u8],
+    info: &'r SSBInfo,
+    cache: RefCell<HashMap<usize, Rc<LoadedFile>>>,
+}
+
+struct LoadedFile {
+    data: Rc<[u8]>,
+    /// (file, &ptr, ptr)
+    externals: Vec<(u16, usize, u32)>,
+}
+
+impl<'r> FileLoader<'r> {
+    pub fn new(rom: &'r [u8], info: &'r SSBInfo) -> Self {
+        Self {
+            rom,
+            info,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Get the decompressed and relocated (based on a base address of 0) data for file `id`
+    pub fn load(&self, id: usize) -> Result<Rc<[u8]>> {
+        self.get(id).map(|f| Rc::clone(&f.data))
+    }
+
+    /// Get the pointers in file `id` that point into other files
+    pub fn external_refs(&self, id: usize) -> Result<Vec<ExternalRef<'_, 'r>>> {
+        let file = self.get(id)?;
+
+        Ok(file
+            .externals
+            .iter()
+            .map(|&(file, site, target)| ExternalRef {
+                file,
+                site,
+                target,
+                loader: self,
+            })
+            .collect())
+    }
+
+    fn get(&self, id: usize) -> Result<Rc<LoadedFile>> {
+        if let Some(file) = self.cache.borrow().get(&id) {
+            return Ok(Rc::clone(file));
+        }
+
+        let entry = TableFile::get(id, self.rom, self.info)
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let (data, relocs) = relocate(entry.data()?, &entry, 0)
+            .with_context(|| format!("relocating pointers in file <{}>", id))?;
+        let file = Rc::new(LoadedFile {
+            data: data.into(),
+            externals: relocs.external.unwrap_or_default(),
+        });
+
+        self.cache.borrow_mut().insert(id, Rc::clone(&file));
+
+        Ok(file)
+    }
+}
+
+/// A pointer in one file that points into another file in the resource table.
+/// The referenced file is only extracted when [`ExternalRef::load`] is called.
+#[derive(Clone, Copy)]
+pub struct ExternalRef<'l, 'r> {
+    file: u16,
+    site: usize,
+    target: u32,
+    loader: &'l FileLoader<'r>,
+}
+
+impl ExternalRef<'_, '_> {
+    /// The id of the file that is pointed to
+    pub fn file_id(&self) -> u16 {
+        self.file
+    }
+
+    /// The offset of the pointer in the referencing file
+    pub fn site(&self) -> usize {
+        self.site
+    }
+
+    /// The offset into the external file that the pointer points to
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+
+    /// Extract (or get the already extracted) relocated data of the external file
+    pub fn load(&self) -> Result<Rc<[u8]>> {
+        self.loader.load(self.file as usize)
+    }
+}
+
+impl std::fmt::Debug for ExternalRef<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ExternalRef")
+            .field("file", &self.file)
+            .field("site", &self.site)
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+/// Rewrite the ids in the lists of external files that follow the files of `rom` with `map`
+/// (from old to new id), so that pointers into files that were moved point to their new ids.
+/// Only the ids that are used by a file's external relocations are rewritten, and not the
+/// padding after them. Returns how many ids were rewritten.
+pub fn remap_externs(rom: &mut [u8], info: &SSBInfo, map: &BTreeMap<u16, u16>) -> Result<usize> {
+    let mut rewrites = Vec::new();
+    for id in 0..info.total_entries() {
+        let entry = TableFile::get(id, rom, info)?;
+        let Some(externs) = entry.includes() else {
+            continue;
+        };
+        if !externs.iter().any(|ext| map.contains_key(ext)) {
+            continue;
+        }
+
+        let data = entry
+            .decompressed()
+            .with_context(|| format!("decompressing file <{}>", id))?;
+        let used = relocations(&data, &entry)
+            .with_context(|| format!("following the relocations of file <{}>", id))?
+            .iter()
+            .filter(|r| r.external_file.is_some())
+            .count();
+        let list = info.table_end + entry.offset + entry.raw.len();
+        for (i, ext) in externs.iter().take(used).enumerate() {
+            if let Some(&new) = map.get(ext) {
+                rewrites.push((list + i * 2, new));
+            }
+        }
+    }
+
+    for &(at, id) in &rewrites {
+        rom[at..at + 2].copy_from_slice(&id.to_be_bytes());
+    }
+
+    Ok(rewrites.len())
+}
@@ -1,282 +1,468 @@
-use crate::{versions::SSBInfo, Mode};
-use anyhow::{anyhow, bail, Context, Result};
+use crate::{
+    cli::{DataFormat, ExtractOpt, FileTable, Mode, RelocFormat},
+    manifest::{Manifest, ManifestFile},
+    timings::{Stage, Timings},
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use ssbfile::{
+    names::NameDb, relocate, versions::SSBInfo, FileLoader, PartialRom, RomSource, TableFile,
+};
 use std::{
     borrow::Cow,
-    fmt, fs,
-    io::Cursor,
-    path::{Path, PathBuf},
+    collections::BTreeSet,
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    sync::mpsc,
+    thread,
 };
 
-pub(crate) fn data(opt: crate::Opt) -> Result<()> {
-    let rom =
-        fs::read(&opt.rom).with_context(|| format!("issue opening <{}>", opt.rom.display()))?;
-    let version = crate::versions::find_version(&rom)
-        .ok_or_else(|| anyhow!("could not determine version for <{}>", opt.rom.display()))?;
-
-    let entry = TableFile::get(opt.id, &rom, version)
-        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
-
-    let output = generate_filename(&opt, &entry);
-    match opt.mode {
-        Mode::RawBytes => fs::write(&*output, entry.raw)
-            .with_context(|| format!("writing raw data to <{}>", output.display()))?,
-        Mode::Decompressed => {
-            let data = if entry.compressed {
-                Cow::from(decompress(entry.raw, entry.id)?)
-            } else {
-                Cow::from(entry.raw)
-            };
-
-            fs::write(&*output, &*data)
-                .with_context(|| format!("writing data to <{}>", output.display()))?
+const STDOUT: &str = "-";
+const MANIFEST: &str = "manifest.toml";
+/// the most loaded files that each worker holds while they wait to be written
+const BUFFERED: usize = 4;
+
+pub(crate) fn data(opt: ExtractOpt, timings: &mut Timings) -> Result<()> {
+    let requested = opt.ids.iter().flat_map(|r| r.ids()).collect::<Vec<_>>();
+    // without --all or --with-deps, only the requested files have to be read from the rom
+    let (rom, version) = match opt.table {
+        FileTable::Resource if !opt.all && !opt.with_deps => {
+            let (rom, version) = opt.rom.source_files(&requested, timings)?;
+            (Rom::Partial(rom), version)
         }
-        Mode::Relocated => {
-            let file = if entry.compressed {
-                decompress(entry.raw, entry.id)?
-            } else {
-                entry.raw.to_vec()
-            };
-
-            let (file, relocations) = relocate(file, &entry)
-                .with_context(|| format!("relocating pointers in file <{}>", entry.id))?;
+        FileTable::Resource => {
+            let (rom, version) = opt.rom.source(timings)?;
+            (Rom::Whole(rom), version)
+        }
+        // the secondary table is found from the data after it, so the whole rom is read
+        FileTable::Secondary => {
+            let (rom, version) = opt.rom.source(timings)?;
+            let version = opt
+                .rom
+                .file_table(&rom, version, FileTable::Secondary, timings)?
+                .version()
+                .clone();
+            (Rom::Whole(rom), version)
+        }
+    };
 
-            fs::write(&*output, &file)
-                .with_context(|| format!("writing data to <{}>", output.display()))?;
+    let mut ids = if opt.all {
+        (0..version.total_entries()).collect()
+    } else {
+        requested
+    };
+    // --with-deps reads the whole rom, to find the external files of the requested files
+    if let (true, false, Rom::Whole(rom)) = (opt.with_deps, opt.all, &rom) {
+        ids = timings
+            .time(Stage::Relocate, || dependencies(&ids, rom, &version))
+            .context("finding the external files of the requested files")?;
+    }
+    // more than one file is exported into the output directory
+    let batch = opt.all || ids.len() > 1;
+    if opt.output.as_deref() == Some(Path::new(STDOUT)) {
+        ensure!(!batch, "only one file can be written to stdout");
+        ensure!(
+            !opt.emit_relocs,
+            "relocations can't be emitted when writing to stdout"
+        );
+        ensure!(
+            opt.format == DataFormat::Binary,
+            "a C array and its header can't be written to stdout"
+        );
+    }
+    if let Some(symbol) = &opt.symbol {
+        ensure!(!batch, "a --symbol can only be given to one file");
+        ensure!(
+            ssbfile::c_array::is_identifier(symbol),
+            "the symbol <{}> isn't a C identifier",
+            symbol
+        );
+    }
+    if let (true, Some(dir), false) = (batch, &opt.output, opt.dry_run) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("creating output directory <{}>", dir.display()))?;
+    }
 
-            if opt.emit_relocs {
-                let f = generate_reloc_filename(&*output);
-                let relocs = format!("{}", relocations);
+    let mut out = Output::new(&opt, &version)?;
+    let jobs = opt
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, |n| n.get())
+        .min(ids.len());
+    if jobs > 1 {
+        pipeline(&opt, &ids, jobs, &rom, &version, &mut out, timings)?;
+    } else {
+        ids.into_iter().try_for_each(|id| {
+            let loaded = load(&opt, id, &rom, &version, timings)?;
+            file(&opt, loaded, batch, &mut out, timings)
+        })?;
+    }
+    out.finish(&opt, timings)
+}
 
-                fs::write(&f, relocs.as_bytes())
-                    .with_context(|| format!("writing relocations to <{}>", f.display()))?;
+/// Export `ids` with `jobs` worker threads that get and decompress the files while this
+/// thread relocates and writes them, in order. Worker `n` loads every `jobs`th file from
+/// the `n`th into its own bounded channel, so the workers only run ahead of the writing
+/// by [`BUFFERED`] files each.
+fn pipeline(
+    opt: &ExtractOpt,
+    ids: &[usize],
+    jobs: usize,
+    rom: &Rom,
+    version: &SSBInfo,
+    out: &mut Output,
+    timings: &mut Timings,
+) -> Result<()> {
+    thread::scope(|s| {
+        let (receivers, workers): (Vec<_>, Vec<_>) = (0..jobs)
+            .map(|n| {
+                let (tx, rx) = mpsc::sync_channel(BUFFERED);
+                let worker = s.spawn(move || {
+                    let mut timings = Timings::default();
+                    for &id in ids.iter().skip(n).step_by(jobs) {
+                        let loaded = load(opt, id, rom, version, &mut timings);
+                        // the writing stopped at an error, so the rest aren't needed
+                        if tx.send(loaded).is_err() {
+                            break;
+                        }
+                    }
+                    timings
+                });
+                (rx, worker)
+            })
+            .unzip();
+
+        let written = ids.iter().enumerate().try_for_each(|(i, id)| {
+            let loaded = receivers[i % jobs]
+                .recv()
+                .with_context(|| format!("loading file <{}>", id))??;
+            file(opt, loaded, true, out, timings)
+        });
+        // let any workers that are waiting to send see that nothing more is received
+        drop(receivers);
+        let mut joined = Ok(());
+        for worker in workers {
+            match worker.join() {
+                Ok(worker) => timings.merge(&worker),
+                Err(_) => joined = Err(anyhow!("an extract worker panicked")),
             }
         }
-    }
 
-    Ok(())
+        written.and(joined)
+    })
 }
 
-/// The start of the runtime relocation list in a file.
-/// If the relocations are for pointers into external files,
-/// there is the processed list of external file ids.
-#[derive(Debug, Clone)]
-enum RelocInfo {
-    Internal(usize),
-    External(usize, Vec<u16>),
+/// The rom that files are exported from: all of it, or only the parts with the files
+enum Rom {
+    Whole(RomSource),
+    Partial(PartialRom),
 }
 
-impl RelocInfo {
-    fn get_starting_offset(&self) -> usize {
+impl Rom {
+    /// Get file `id` from the table of `version`
+    fn file(&self, id: usize, version: &SSBInfo) -> Result<TableFile<'_>> {
         match self {
-            Self::Internal(o) => *o,
-            Self::External(o, _) => *o,
-        }
-    }
-
-    fn get_external_files(&self) -> Option<&[u16]> {
-        match self {
-            Self::Internal(..) => None,
-            Self::External(_, ex) => Some(ex.as_slice()),
+            Self::Whole(rom) => TableFile::get(id, rom, version),
+            Self::Partial(rom) => rom.file(id, version),
         }
     }
 }
 
-struct TableFile<'r> {
-    id: usize,
-    /// offset from the end of the table
-    offset: usize,
-    compressed: bool,
-    raw: &'r [u8],
-    inreloc: Option<RelocInfo>,
-    exreloc: Option<RelocInfo>,
+/// A file's table entry, and its data: decompressed, unless it is exported raw
+struct Loaded<'r> {
+    entry: TableFile<'r>,
+    data: Cow<'r, [u8]>,
 }
 
-impl<'r> TableFile<'r> {
-    const ENTRY_SIZE: usize = 12;
-    const COMPRESS_BIT: u32 = 0x80000000;
-
-    fn get(id: usize, rom: &'r [u8], info: &SSBInfo) -> Result<Self> {
-        fn read_checked_u16(raw: &[u8]) -> Result<Option<u16>> {
-            raw.try_into()
-                .map(u16::from_be_bytes)
-                .map(|val| if val == 0xFFFF { None } else { Some(val) })
-                .map_err(Into::into)
-        }
-
-        if id >= info.total_entries() {
-            bail!(
-                "Requested file <{}> but table only has {} entries (file id 0 to {})",
-                id,
-                info.total_entries(),
-                info.total_entries() - 1
-            );
-        }
+/// Get the table entry of file `id`, and decompress its data if the mode needs it
+fn load<'r>(
+    opt: &ExtractOpt,
+    id: usize,
+    rom: &'r Rom,
+    version: &SSBInfo,
+    timings: &mut Timings,
+) -> Result<Loaded<'r>> {
+    let entry = timings
+        .time(Stage::TableParse, || rom.file(id, version))
+        .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+    let data = match opt.mode {
+        Mode::RawBytes => Cow::from(entry.raw),
+        _ => timings.time(Stage::Decompress, || entry.decompressed())?,
+    };
 
-        let start = info.table_start + id as usize * Self::ENTRY_SIZE;
-        let end = start + Self::ENTRY_SIZE;
+    Ok(Loaded { entry, data })
+}
 
-        let entry = &rom[start..end];
-        let offset = u32::from_be_bytes(entry[0..4].try_into()?);
-        let compressed = offset & Self::COMPRESS_BIT > 0;
-        let offset = (offset & !Self::COMPRESS_BIT) as usize;
-        let size = u16::from_be_bytes(entry[6..8].try_into()?) as usize * 4;
+/// Where the exported files are written: loose files (or stdout), or a tar archive.
+/// The manifest is written into the archive, or next to the files of `--all`.
+struct Output {
+    archive: Option<tar::Builder<fs::File>>,
+    manifest: Manifest,
+    /// the known names of files, for their filenames
+    names: NameDb,
+}
 
-        let raw = {
-            let fstart = info.table_end + offset;
-            let fend = fstart + size as usize;
-            &rom[fstart..fend]
+impl Output {
+    fn new(opt: &ExtractOpt, version: &SSBInfo) -> Result<Self> {
+        let archive = match (&opt.archive, opt.dry_run) {
+            (Some(path), false) => {
+                let f = fs::File::create(path)
+                    .with_context(|| format!("creating archive <{}>", path.display()))?;
+                Some(tar::Builder::new(f))
+            }
+            _ => None,
         };
-        let inreloc = read_checked_u16(&entry[4..6])?
-            .map(|x| x as usize * 4)
-            .map(RelocInfo::Internal);
-        let exreloc = read_checked_u16(&entry[8..10])?
-            .map(|x| x as usize * 4)
-            .map(|start| {
-                Self::get_next_entry_offset(id, rom, info)
-                    .and_then(|next_start| {
-                        let exoffstart = offset + size;
-                        let exoffsize = next_start - exoffstart;
-                        let exstart = exoffstart + info.table_end;
-                        let exend = exstart + exoffsize;
-
-                        Self::parse_externs(&rom[exstart..exend])
-                    })
-                    .map(|externs| RelocInfo::External(start, externs))
-            })
-            .transpose()?;
 
         Ok(Self {
-            id,
-            offset,
-            compressed,
-            raw,
-            inreloc,
-            exreloc,
+            archive,
+            manifest: Manifest {
+                version: Some(version.version.to_string()),
+                mode: Some(opt.mode.name().to_string()),
+                files: Vec::new(),
+            },
+            // the names are of the files in the resource table
+            names: match opt.table {
+                FileTable::Resource => opt.rom.name_db()?,
+                FileTable::Secondary => NameDb::default(),
+            },
         })
     }
 
-    fn parse_externs(raw: &[u8]) -> Result<Vec<u16>> {
-        if raw.len() % 2 != 0 {
-            bail!("expected list of BE u16, got slice of size {}", raw.len());
+    /// Write `data` to the file at `path`, to stdout if `path` is `-`, or into the archive.
+    /// On a dry run, only report what would be written.
+    fn write(&mut self, opt: &ExtractOpt, path: &Path, data: &[u8]) -> io::Result<()> {
+        if opt.dry_run {
+            eprintln!(
+                "would write {:#X} bytes to <{}>",
+                data.len(),
+                path.display()
+            );
+            Ok(())
+        } else if let Some(archive) = &mut self.archive {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            archive.append_data(&mut header, archive_path(path)?, data)
+        } else if path == Path::new(STDOUT) {
+            io::stdout().lock().write_all(data)
+        } else {
+            fs::write(path, data)
         }
-
-        Ok(raw
-            .chunks(2)
-            .map(|e| u16::from_be_bytes(e.try_into().unwrap()))
-            .collect())
     }
 
-    fn get_next_entry_offset(id: usize, rom: &'r [u8], info: &SSBInfo) -> Result<usize> {
-        let next = id + 1;
-        if next >= info.total_entries() {
-            let start = info.table_start + (next * Self::ENTRY_SIZE);
-            let table_data_end = u32::from_be_bytes(rom[start..start + 4].try_into()?);
+    /// Describe a file whose `data` was written to `path` in the manifest
+    fn record(&mut self, entry: &TableFile, path: &Path, data: &[u8]) {
+        // in an archive, the manifest is at its root; the path was checked when it was written
+        let path = match self.archive {
+            Some(_) => archive_path(path).unwrap_or_else(|_| path.to_path_buf()),
+            None => path.to_path_buf(),
+        };
+        self.manifest
+            .files
+            .push(ManifestFile::for_entry(entry, path, data));
+    }
 
-            Ok(table_data_end as usize)
-        } else {
-            Self::get(next, rom, info).map(|e| e.offset)
+    /// Write the manifest, and finish writing the archive
+    fn finish(mut self, opt: &ExtractOpt, timings: &mut Timings) -> Result<()> {
+        // the files of the secondary table can't be built into the resource table
+        if opt.table == FileTable::Secondary {
+            return self
+                .archive
+                .take()
+                .map_or(Ok(()), |a| a.into_inner().map(drop))
+                .context("finishing the archive");
         }
+        let manifest_path = if opt.archive.is_some() {
+            PathBuf::from(MANIFEST)
+        } else if opt.all {
+            opt.output
+                .as_deref()
+                .unwrap_or(Path::new("."))
+                .join(MANIFEST)
+        } else {
+            return Ok(());
+        };
+        let path = opt.archive.as_ref().unwrap_or(&manifest_path);
+
+        let manifest = self.manifest.to_toml()?;
+        timings
+            .time(Stage::Write, || {
+                // C arrays can't be built into a rom, so they have no manifest
+                if opt.format == DataFormat::Binary {
+                    self.write(opt, &manifest_path, manifest.as_bytes())?;
+                }
+                self.archive
+                    .take()
+                    .map_or(Ok(()), |a| a.into_inner().map(drop))
+            })
+            .with_context(|| format!("writing manifest to <{}>", path.display()))
     }
 }
 
-fn generate_filename<'a>(opt: &'a crate::Opt, entry: &TableFile) -> Cow<'a, Path> {
-    opt.output.as_deref().map(Cow::from).unwrap_or_else(|| {
-        let s = match opt.mode {
-            Mode::RawBytes => format!(
-                "raw-{:04}.{}",
-                opt.id,
-                if entry.compressed { "vpk" } else { "bin" }
-            ),
-            Mode::Decompressed | Mode::Relocated => format!("file-{:04}.bin", opt.id),
-        };
-
-        Cow::from(PathBuf::from(s))
-    })
+/// The path of the entry for `path` in an archive: relative to the root of the archive,
+/// without a leading `/` or `./`
+fn archive_path(path: &Path) -> io::Result<PathBuf> {
+    path.components()
+        .filter(|c| {
+            !matches!(
+                c,
+                Component::RootDir | Component::Prefix(_) | Component::CurDir
+            )
+        })
+        .map(|c| match c {
+            Component::Normal(name) => Ok(name),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("<{}> is outside of the archive", path.display()),
+            )),
+        })
+        .collect()
 }
 
-fn generate_reloc_filename(datafile: &Path) -> PathBuf {
-    let name = format!(
-        "{}-relocs.txt",
-        datafile
-            .file_stem()
-            .expect("named bin output file")
-            .to_string_lossy()
-    );
+/// Find `ids` and all of the files that they (transitively) point into
+fn dependencies(ids: &[usize], rom: &[u8], version: &SSBInfo) -> Result<Vec<usize>> {
+    let loader = FileLoader::new(rom, version);
+    let mut found = ids.iter().copied().collect::<BTreeSet<_>>();
+    let mut queue = ids.to_vec();
+
+    while let Some(id) = queue.pop() {
+        for ext in loader.external_refs(id)? {
+            let ext = ext.file_id() as usize;
+            if found.insert(ext) {
+                queue.push(ext);
+            }
+        }
+    }
 
-    datafile.with_file_name(name)
+    Ok(found.into_iter().collect())
 }
 
-fn decompress(data: &[u8], id: usize) -> Result<Vec<u8>> {
-    vpk0::decode(Cursor::new(data)).with_context(|| format!("decompressing file <{}>", id))
-}
+fn file(
+    opt: &ExtractOpt,
+    loaded: Loaded,
+    batch: bool,
+    out: &mut Output,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Loaded { entry, data } = loaded;
+    let output = generate_filename(opt, &entry, batch, &out.names);
+    let data: Cow<[u8]> = match opt.mode {
+        Mode::RawBytes | Mode::Decompressed => data,
+        Mode::Relocated => {
+            let data = data.into_owned();
+            let (file, relocations) = timings
+                .time(Stage::Relocate, || relocate(data, &entry, opt.base_addr))
+                .with_context(|| format!("relocating pointers in file <{}>", entry.id))?;
 
-fn relocate(mut file: Vec<u8>, entry: &TableFile) -> Result<(Vec<u8>, FileReloc)> {
-    let mut relocs = FileReloc {
-        internal: None,
-        external: None,
+            if opt.emit_relocs {
+                let f = generate_reloc_filename(&output, opt.reloc_format);
+                let relocs = match opt.reloc_format {
+                    RelocFormat::Text => relocations.to_string(),
+                    RelocFormat::Csv => relocations.csv(),
+                    RelocFormat::LinkerScript => relocations.linker_script(entry.id, opt.base_addr),
+                };
+
+                timings
+                    .time(Stage::Write, || out.write(opt, &f, relocs.as_bytes()))
+                    .with_context(|| format!("writing relocations to <{}>", f.display()))?;
+            }
+            Cow::from(file)
+        }
+        Mode::Elf => {
+            let data = data.into_owned();
+            // the pointers are the addends of the relocations, so relocate with a base of 0
+            let (file, relocations) = timings
+                .time(Stage::Relocate, || relocate(data, &entry, 0))
+                .with_context(|| format!("relocating pointers in file <{}>", entry.id))?;
+            Cow::from(ssbfile::elf::relocatable_object(
+                entry.id,
+                &file,
+                &relocations,
+            ))
+        }
+        Mode::Asm => {
+            let data = data.into_owned();
+            let (file, relocations) = timings
+                .time(Stage::Relocate, || relocate(data, &entry, 0))
+                .with_context(|| format!("relocating pointers in file <{}>", entry.id))?;
+            Cow::from(ssbfile::asm::assembly(entry.id, &file, &relocations).into_bytes())
+        }
     };
-    // relocation data stored as BE {u16 next; u16 ptrOffset}
-    // next * 4 is the location of the next relocation
-    // ptrOffset * 4 + baseAddr is the value of the pointer
-    if let Some(reloc) = &entry.inreloc {
-        relocs.internal = Some(write_relocations(&mut file, reloc)?);
-    }
 
-    if let Some(exreloc) = &entry.exreloc {
-        relocs.external = Some(write_relocations(&mut file, exreloc)?);
+    if opt.format == DataFormat::C {
+        return c_array(opt, &entry, &output, &data, out, timings);
     }
 
-    Ok((file, relocs))
-}
-
-fn write_relocations(file: &mut [u8], reloc: &RelocInfo) -> Result<Relocations> {
-    const END: usize = 0xFFFF * 4;
-    let mut relocations = Relocations::with_capacity(64);
+    timings
+        .time(Stage::Write, || out.write(opt, &output, &data))
+        .with_context(|| format!("writing data to <{}>", output.display()))?;
+    // paths in the manifest are relative to it
+    let relative = match &opt.output {
+        Some(dir) if batch => output.strip_prefix(dir).unwrap_or(&output),
+        _ => &output,
+    };
+    out.record(&entry, relative, &data);
 
-    let mut ex = reloc.get_external_files().map(|x| x.into_iter());
-    let mut next = reloc.get_starting_offset();
-    while next != END {
-        let reloc = &mut file[next..next + 4];
-        let raw_next = u16::from_be_bytes(reloc[0..2].try_into()?);
-        let raw_ptr = u16::from_be_bytes(reloc[2..4].try_into()?);
+    Ok(())
+}
 
-        let ptr = raw_ptr as u32 * 4;
-        reloc.copy_from_slice(&ptr.to_be_bytes());
-        // lazy, but whatever; if external use the file id; else just put in 0
-        let fid = ex.as_mut().and_then(|x| x.next()).copied().unwrap_or(0);
-        relocations.push((fid, next, ptr));
+/// Write `data` as a C array to `output`, and a header that declares it next to it
+fn c_array(
+    opt: &ExtractOpt,
+    entry: &TableFile,
+    output: &Path,
+    data: &[u8],
+    out: &mut Output,
+    timings: &mut Timings,
+) -> Result<()> {
+    use ssbfile::c_array;
+
+    let symbol = opt
+        .symbol
+        .clone()
+        .unwrap_or_else(|| c_array::default_symbol(entry.id));
+    let header = output.with_extension("h");
+    let header_name = header
+        .file_name()
+        .expect("named output file")
+        .to_string_lossy();
+
+    let source = c_array::source(&symbol, &header_name, data);
+    timings
+        .time(Stage::Write, || out.write(opt, output, source.as_bytes()))
+        .with_context(|| format!("writing C array to <{}>", output.display()))?;
+    let declaration = c_array::header(&symbol, data.len());
+    timings
+        .time(Stage::Write, || {
+            out.write(opt, &header, declaration.as_bytes())
+        })
+        .with_context(|| format!("writing C header to <{}>", header.display()))
+}
 
-        next = raw_next as usize * 4;
+fn generate_filename<'a>(
+    opt: &'a ExtractOpt,
+    entry: &TableFile,
+    batch: bool,
+    names: &NameDb,
+) -> Cow<'a, Path> {
+    let name = || PathBuf::from(opt.name_template.render(entry, opt.mode, opt.format, names));
+
+    match opt.output.as_deref() {
+        Some(dir) if batch => Cow::from(dir.join(name())),
+        Some(output) => Cow::from(output),
+        None => Cow::from(name()),
     }
-
-    Ok(relocations)
 }
 
-/// (file, &ptr, ptr)
-type Relocations = Vec<(u16, usize, u32)>;
-
-#[derive(Debug)]
-struct FileReloc {
-    internal: Option<Relocations>,
-    external: Option<Relocations>,
-}
+fn generate_reloc_filename(datafile: &Path, format: RelocFormat) -> PathBuf {
+    let name = format!(
+        "{}-relocs.{}",
+        datafile
+            .file_stem()
+            .expect("named bin output file")
+            .to_string_lossy(),
+        format.extension()
+    );
 
-impl fmt::Display for FileReloc {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "# Relocations")?;
-        if let Some(internal) = &self.internal {
-            writeln!(f, "## Internal Relocations")?;
-            for &(_, offset, ptr) in internal {
-                writeln!(f, "* {:06X} -> {:08X}", offset, ptr)?;
-            }
-        }
-        writeln!(f, "")?;
-        if let Some(external) = &self.external {
-            writeln!(f, "## External Relocations")?;
-            for &(fid, offset, ptr) in external {
-                writeln!(f, "* {:06X} -> {:08X} from {}", offset, ptr, fid)?;
-            }
-        }
-        Ok(())
-    }
+    datafile.with_file_name(name)
 }
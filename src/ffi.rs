@@ -0,0 +1,124 @@
+//! A C interface to open a rom and extract its files; see `include/ssbfile.h`.
+//!
+//! Every function that can fail returns `NULL` (or 0) on an error, and the error
+//! message can then be read with [`ssb_last_error`].
+
+use crate::SsbRom;
+use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An opened rom, behind an opaque pointer
+pub struct SsbRomHandle(SsbRom<'static>);
+
+fn set_error(err: anyhow::Error) {
+    let msg = format!("{:#}", err).replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(msg).ok());
+}
+
+/// Read the rom at the nul-terminated `path`, in any byte order.
+/// Returns `NULL` if the rom can't be read or its version isn't known.
+///
+/// # Safety
+/// `path` has to be a valid nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ssb_open_rom(path: *const c_char) -> *mut SsbRomHandle {
+    let open = || -> Result<SsbRom<'static>> {
+        anyhow::ensure!(!path.is_null(), "rom path is NULL");
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .context("rom path isn't utf-8")?;
+        let rom = std::fs::read(path).with_context(|| format!("issue opening rom <{}>", path))?;
+
+        SsbRom::from_vec(rom)
+    };
+
+    match open() {
+        Ok(rom) => Box::into_raw(Box::new(SsbRomHandle(rom))),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a rom opened with [`ssb_open_rom`]
+///
+/// # Safety
+/// `rom` has to come from [`ssb_open_rom`] (or be `NULL`), and can't be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn ssb_close_rom(rom: *mut SsbRomHandle) {
+    if !rom.is_null() {
+        drop(Box::from_raw(rom));
+    }
+}
+
+/// The number of files in the resource table of `rom`
+///
+/// # Safety
+/// `rom` has to come from [`ssb_open_rom`] (or be `NULL`, which has no files)
+#[no_mangle]
+pub unsafe extern "C" fn ssb_file_count(rom: *const SsbRomHandle) -> usize {
+    rom.as_ref().map_or(0, |rom| rom.0.file_count())
+}
+
+/// The data of file `id`, decompressed if necessary. The length of the data is
+/// written to `len`, and the buffer has to be freed with [`ssb_free_buffer`].
+/// Returns `NULL` if the file can't be extracted.
+///
+/// # Safety
+/// `rom` has to come from [`ssb_open_rom`], and `len` has to be valid to write to
+#[no_mangle]
+pub unsafe extern "C" fn ssb_extract_file(
+    rom: *const SsbRomHandle,
+    id: usize,
+    len: *mut usize,
+) -> *mut u8 {
+    let extract = || -> Result<Box<[u8]>> {
+        let rom = rom.as_ref().context("rom is NULL")?;
+        let data = rom
+            .0
+            .data(id)
+            .with_context(|| format!("extracting file <{}>", id))?;
+
+        Ok(data.into_owned().into_boxed_slice())
+    };
+
+    match extract() {
+        Ok(data) => {
+            if let Some(len) = len.as_mut() {
+                *len = data.len();
+            }
+            Box::into_raw(data).cast()
+        }
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a buffer returned by [`ssb_extract_file`]
+///
+/// # Safety
+/// `buf` and `len` have to be exactly as returned by [`ssb_extract_file`] (or `buf` is `NULL`)
+#[no_mangle]
+pub unsafe extern "C" fn ssb_free_buffer(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// The message of the last error on this thread, or `NULL` if there hasn't been one.
+/// The string is valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn ssb_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |msg| msg.as_ptr()))
+}
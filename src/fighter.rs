@@ -0,0 +1,227 @@
+//! Fighter attributes: the block of a fighter's main file with its speeds, jump heights,
+//! weight, and the other values that set how it moves.
+//!
+//! The block is the decompilation's `FTAttributes`, as far as its fields have been
+//! mapped: a word for each, a float unless it is a count or a flag, laid out in
+//! [`ATTRIBUTES`]. Where the block is in a version of the game is loaded into the
+//! [`VersionDb`](crate::versions::VersionDb) from a version file, and a block can also
+//! be found in a main file by its values, with [`Attributes::find`].
+use crate::Error;
+use alloc::{borrow::Cow, vec::Vec};
+use anyhow::Result;
+
+/// The fighters, in the order of their ids
+pub const FIGHTERS: [&str; 12] = [
+    "mario", "fox", "donkey", "samus", "luigi", "link", "yoshi", "captain", "kirby", "pikachu",
+    "purin", "ness",
+];
+
+/// Where the attributes of a fighter are in a version of the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FighterLocation {
+    /// the name of the version, as in [`SSBInfo`](crate::versions::SSBInfo)
+    pub version: Cow<'static, str>,
+    /// the fighter, as named in [`FIGHTERS`]
+    pub fighter: Cow<'static, str>,
+    /// the id of the fighter's main file
+    pub file: usize,
+    /// offset of the attributes in the decompressed file
+    pub offset: usize,
+}
+
+/// The kind of value of an attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kind {
+    Float,
+    Int,
+}
+
+/// An attribute, and where it is in the block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute {
+    pub name: &'static str,
+    /// offset of the word from the start of the block
+    pub offset: usize,
+    pub kind: Kind,
+}
+
+const fn float(name: &'static str, offset: usize) -> Attribute {
+    Attribute {
+        name,
+        offset,
+        kind: Kind::Float,
+    }
+}
+
+const fn int(name: &'static str, offset: usize) -> Attribute {
+    Attribute {
+        name,
+        offset,
+        kind: Kind::Int,
+    }
+}
+
+/// The attributes of a fighter
+pub const ATTRIBUTES: &[Attribute] = &[
+    float("size_mul", 0x00),
+    float("walkslow_anim_speed", 0x04),
+    float("walkmiddle_anim_speed", 0x08),
+    float("walkfast_anim_speed", 0x0C),
+    float("throw_walkslow_anim_speed", 0x10),
+    float("throw_walkmiddle_anim_speed", 0x14),
+    float("throw_walkfast_anim_speed", 0x18),
+    float("rebound_anim_length", 0x1C),
+    float("walk_speed_mul", 0x20),
+    float("traction", 0x24),
+    float("dash_speed", 0x28),
+    float("dash_decelerate", 0x2C),
+    float("run_speed", 0x30),
+    float("kneebend_anim_length", 0x34),
+    float("jump_vel_x", 0x38),
+    float("jump_height_mul", 0x3C),
+    float("jump_height_base", 0x40),
+    float("aerial_jump_vel_x", 0x44),
+    float("aerial_jump_height", 0x48),
+    float("air_accel", 0x4C),
+    float("air_speed_max_x", 0x50),
+    float("air_friction", 0x54),
+    float("gravity", 0x58),
+    float("fall_speed_max", 0x5C),
+    float("fast_fall_speed", 0x60),
+    int("jumps_max", 0x64),
+    float("weight", 0x68),
+    float("attack1_followup_frames", 0x6C),
+    float("dash_to_run", 0x70),
+    float("shield_size", 0x74),
+    float("shield_break_vel_y", 0x78),
+    float("shadow_size", 0x7C),
+    float("jostle_width", 0x80),
+    float("jostle_x", 0x84),
+    int("is_metallic", 0x88),
+    float("cam_offset_y", 0x8C),
+    float("vs_pause_zoom", 0x90),
+    float("cam_zoom", 0x94),
+    float("cam_zoom_default", 0x98),
+];
+
+/// The size of the mapped part of the block, in bytes
+pub const ATTRIBUTES_SIZE: usize = 0x9C;
+
+/// The value of an attribute
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Float(f32),
+    Int(i32),
+}
+
+/// The attributes of a fighter
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attributes {
+    /// offset of the block in the decompressed file
+    pub offset: usize,
+    pub words: Vec<u32>,
+}
+
+impl Attributes {
+    /// Read the attributes at `offset` of `file`
+    pub fn read(file: &[u8], offset: usize) -> Result<Self> {
+        let words = file
+            .get(offset..offset + ATTRIBUTES_SIZE)
+            .ok_or(Error::OutsideFile {
+                start: offset,
+                end: offset + ATTRIBUTES_SIZE,
+                len: file.len(),
+            })?
+            .chunks_exact(4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+
+        Ok(Self { offset, words })
+    }
+
+    /// The offset of the first block in `file` whose values look like attributes: its
+    /// floats are zero or of a sensible magnitude, its size, weight, gravity, and fall
+    /// speed are positive, it has between 1 and 10 jumps, and its metal flag is 0 or 1
+    pub fn find(file: &[u8]) -> Option<usize> {
+        let word =
+            |at: usize| u32::from_be_bytes([file[at], file[at + 1], file[at + 2], file[at + 3]]);
+        let positive = ["size_mul", "weight", "gravity", "fall_speed_max"];
+
+        (0..=file.len().checked_sub(ATTRIBUTES_SIZE)?)
+            .step_by(4)
+            .find(|&start| {
+                ATTRIBUTES.iter().all(|a| {
+                    let word = word(start + a.offset);
+                    match (a.kind, a.name) {
+                        (Kind::Int, "jumps_max") => (1..=10).contains(&word),
+                        (Kind::Int, _) => word <= 1,
+                        (Kind::Float, name) => {
+                            let value = f32::from_bits(word);
+                            let magnitude = value.abs();
+                            let sensible = magnitude == 0.0 || (1e-4..=1e5).contains(&magnitude);
+                            sensible && (value > 0.0 || !positive.contains(&name))
+                        }
+                    }
+                })
+            })
+    }
+
+    /// The value of each attribute, in the order of [`ATTRIBUTES`]
+    pub fn values(&self) -> Vec<(&'static str, Value)> {
+        ATTRIBUTES
+            .iter()
+            .map(|a| {
+                let word = self.words[a.offset / 4];
+                let value = match a.kind {
+                    Kind::Float => Value::Float(f32::from_bits(word)),
+                    Kind::Int => Value::Int(word as i32),
+                };
+                (a.name, value)
+            })
+            .collect()
+    }
+
+    /// Set the attribute `name` to `value`; an int attribute has to be set to a whole
+    /// number that fits in its word
+    pub fn set(&mut self, name: &str, value: f64) -> Result<()> {
+        let attribute = ATTRIBUTES
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| Error::UnknownFighterField { name: name.into() })?;
+        let word = match attribute.kind {
+            Kind::Float => (value as f32).to_bits(),
+            // the cast saturates, so a value that doesn't fit doesn't come back the same
+            Kind::Int if value as i32 as f64 == value => value as i32 as u32,
+            Kind::Int => {
+                return Err(Error::FighterValue {
+                    name: attribute.name,
+                }
+                .into())
+            }
+        };
+        self.words[attribute.offset / 4] = word;
+
+        Ok(())
+    }
+
+    /// Write the attributes back into `file`
+    pub fn write(&self, file: &mut [u8]) -> Result<()> {
+        let end = self.offset + self.words.len() * 4;
+        let len = file.len();
+        let block = file.get_mut(self.offset..end).ok_or(Error::OutsideFile {
+            start: self.offset,
+            end,
+            len,
+        })?;
+        for (at, word) in block.chunks_exact_mut(4).zip(&self.words) {
+            at.copy_from_slice(&word.to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Fighter attributes as toml, like
+//! ```toml
+//! fighter = "mario"
+//!
+//! [attributes]
+//! size_mul = 1.0
+//! walk_speed_mul = 0.5
+//! jumps_max = 2
+//! ```
+use crate::{
+    cli::{FighterOpt, ImportFighterOpt, RomOpt},
+    info::Ordered,
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use ssbfile::{
+    fighter::{Attributes, Value, FIGHTERS},
+    versions::SSBInfo,
+    TableFile,
+};
+use std::{collections::BTreeMap, fmt::Write, fs};
+
+#[derive(Debug, Deserialize)]
+struct FighterFile {
+    fighter: String,
+    #[serde(default)]
+    attributes: BTreeMap<String, toml::Value>,
+}
+
+pub(crate) fn print(opt: FighterOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (id, offset) = location(&opt.rom, &version, &opt.fighter, opt.file, opt.offset)?;
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", id))?;
+
+    let offset = find(&data, id, offset)?;
+    let attributes = Attributes::read(&data, offset)
+        .with_context(|| format!("reading the attributes of <{}>", opt.fighter))?;
+    let fighter = opt.fighter.to_lowercase();
+    if opt.json {
+        let json = Json {
+            fighter: &fighter,
+            file: id,
+            offset,
+            attributes: Ordered(
+                attributes
+                    .values()
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::Int(i) => JsonValue::Int(i),
+                            Value::Float(f) => JsonValue::Float(f),
+                        };
+                        (name, value)
+                    })
+                    .collect(),
+            ),
+        };
+        println!("{}", serde_json::to_string(&json)?);
+    } else {
+        let mut toml = format!(
+            "# file {}, offset 0x{:04X}\nfighter = \"{}\"\n\n[attributes]\n",
+            id, offset, fighter
+        );
+        for (name, value) in attributes.values() {
+            writeln!(toml, "{} = {}", name, toml_value(value))?;
+        }
+        print!("{}", toml);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportFighterOpt, timings: &mut Timings) -> Result<()> {
+    let text = fs::read_to_string(&opt.attributes)
+        .with_context(|| format!("issue opening <{}>", opt.attributes.display()))?;
+    let edits: FighterFile =
+        toml::from_str(&text).with_context(|| format!("parsing <{}>", opt.attributes.display()))?;
+
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let (id, offset) = location(&opt.rom, &version, &edits.fighter, opt.file, opt.offset)?;
+    inject::edit_file(&mut rom, &version, id, &opt.compress, timings, |data, _| {
+        let offset = find(data, id, offset)?;
+        let mut attributes = Attributes::read(data, offset)
+            .with_context(|| format!("reading the attributes of <{}>", edits.fighter))?;
+        for (name, value) in &edits.attributes {
+            let value = match value {
+                toml::Value::Float(f) => *f,
+                toml::Value::Integer(i) => *i as f64,
+                _ => bail!("the attribute <{}> has to be a number", name),
+            };
+            attributes.set(name, value)?;
+        }
+        attributes.write(data)
+    })?;
+    eprintln!(
+        "changed {} attributes of {}",
+        edits.attributes.len(),
+        edits.fighter
+    );
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
+
+/// The file id and offset of the attributes of `fighter`: `file` and `offset`, if they
+/// are given, or else where the versions of `rom` say they are in `version`. Given only
+/// the `file`, the offset is found in its data with [`find`].
+pub(crate) fn location(
+    rom: &RomOpt,
+    version: &SSBInfo,
+    fighter: &str,
+    file: Option<usize>,
+    offset: Option<u32>,
+) -> Result<(usize, Option<usize>)> {
+    if !FIGHTERS.iter().any(|f| f.eq_ignore_ascii_case(fighter)) {
+        bail!(
+            "unknown fighter <{}>; expected one of {}",
+            fighter,
+            FIGHTERS.join(", ")
+        );
+    }
+    if let Some(file) = file {
+        return Ok((file, offset.map(|o| o as usize)));
+    }
+
+    rom.version_db()?
+        .fighter(&version.version, fighter)
+        .map(|l| (l.file, Some(l.offset)))
+        .with_context(|| {
+            format!(
+                "where the attributes of <{}> are in version {} isn't known; \
+                 give the --file with them, or add them to --versions",
+                fighter, version.version
+            )
+        })
+}
+
+/// `offset`, or the offset of the attributes that are found in `data` of file `id`
+fn find(data: &[u8], id: usize, offset: Option<usize>) -> Result<usize> {
+    offset
+        .or_else(|| Attributes::find(data))
+        .with_context(|| format!("found no attributes in file <{}>; give their --offset", id))
+}
+
+fn toml_value(value: Value) -> String {
+    match value {
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) if f.is_nan() => "nan".into(),
+        Value::Float(f) => format!("{:?}", f),
+    }
+}
+
+#[derive(Serialize)]
+struct Json<'a> {
+    fighter: &'a str,
+    file: usize,
+    offset: usize,
+    attributes: Ordered<JsonValue>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum JsonValue {
+    Int(i32),
+    Float(f32),
+}
@@ -0,0 +1,31 @@
+use crate::{
+    cli::GameSharkOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{relocate, TableFile};
+use std::fs;
+
+pub(crate) fn codes(opt: GameSharkOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let modified =
+        fs::read(&opt.file).with_context(|| format!("issue opening <{}>", opt.file.display()))?;
+
+    let entry = timings
+        .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+    // the file as it is in RAM, with its pointers resolved
+    let data = timings.time(Stage::Decompress, || entry.data())?;
+    let (original, _) = timings
+        .time(Stage::Relocate, || relocate(data, &entry, opt.base_addr))
+        .with_context(|| format!("relocating pointers in file <{}>", opt.id))?;
+
+    let codes = ssbfile::patch::gameshark(&original, &modified, opt.base_addr)
+        .with_context(|| format!("comparing <{}> to file <{}>", opt.file.display(), opt.id))?;
+    for code in &codes {
+        println!("{}", code);
+    }
+    eprintln!("{} codes for file {}", codes.len(), opt.id);
+
+    Ok(())
+}
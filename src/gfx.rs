@@ -0,0 +1,72 @@
+use crate::{cli::GfxOpt, inject, timings::Timings};
+use anyhow::{bail, Context, Result};
+use ssbfile::display_list::{self, Address, Command};
+
+pub(crate) fn display_lists(opt: GfxOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let starts = match opt.offset {
+        Some(offset) => vec![offset as usize],
+        None => display_list::find(&data, &relocs),
+    };
+    if starts.is_empty() {
+        bail!(
+            "found no display lists in file <{}>; give the --offset of one",
+            opt.id
+        );
+    }
+
+    for (i, &start) in starts.iter().enumerate() {
+        let commands = display_list::parse(&data, start, &relocs).with_context(|| {
+            format!(
+                "reading the display list at {:#X} of file <{}>",
+                start, opt.id
+            )
+        })?;
+        if i > 0 {
+            println!();
+        }
+        println!("# display list at 0x{:04X} of file {}", start, opt.id);
+        for (offset, command) in &commands {
+            println!("0x{:04X}: {}", offset, command);
+            if let Command::Vertex {
+                addr: Address::Internal(at),
+                count,
+                first,
+            } = command
+            {
+                if opt.vertices {
+                    print_vertices(&data, *at as usize, *count as usize, *first as usize)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_vertices(data: &[u8], at: usize, count: usize, first: usize) -> Result<()> {
+    let vertices = display_list::vertices(data, at, count)
+        .with_context(|| format!("reading {} vertices at {:#X}", count, at))?;
+    for (i, v) in vertices.iter().enumerate() {
+        let [x, y, z] = v.position;
+        let [s, t] = v.st;
+        let [r, g, b, a] = v.color;
+        println!(
+            "        v{:<3} ({}, {}, {}) st ({}, {}) color ({}, {}, {}, {})",
+            first + i,
+            x,
+            y,
+            z,
+            s,
+            t,
+            r,
+            g,
+            b,
+            a
+        );
+    }
+
+    Ok(())
+}
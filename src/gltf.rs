@@ -0,0 +1,593 @@
+//! Write meshes as glTF 2.0, with the bones of their skeleton as joints that each
+//! vertex is rigidly bound to, like the game draws each part with the matrix of its bone
+use crate::{
+    mesh::{Mesh, Texture},
+    skeleton::{self, Bone},
+};
+use anyhow::Result;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+
+/// The bones that the display lists of a mesh were drawn with
+#[derive(Debug, Clone, Copy)]
+pub struct Skin<'a> {
+    pub bones: &'a [Bone],
+    /// the index in `bones` of each display list given to [`mesh()`](crate::mesh::mesh)
+    pub joints: &'a [usize],
+}
+
+/// A glTF asset: its json, without the buffer, and its one binary buffer
+#[derive(Debug, Clone)]
+pub struct Gltf {
+    json: json::Root,
+    buffer: Vec<u8>,
+}
+
+impl Gltf {
+    /// Build the asset of `mesh`, posed and bound to the bones of `skin`, with the PNG
+    /// of each texture from `texture` (or an untextured material, if it gives none)
+    pub fn new(
+        mesh: &Mesh,
+        skin: Option<Skin>,
+        texture: impl Fn(&Texture) -> Option<Vec<u8>>,
+    ) -> Self {
+        let mut b = Builder::default();
+        let world = skin.map(|s| skeleton::world_matrices(s.bones));
+        let bone_of = |list: usize| skin.and_then(|s| s.joints.get(list).copied());
+        let pose = |list: usize| {
+            world
+                .as_ref()
+                .zip(bone_of(list))
+                .map(|(world, bone)| world[bone])
+        };
+
+        // vertex attributes, posed like the game draws them
+        let positions = mesh
+            .vertices
+            .iter()
+            .map(|v| {
+                let p = v.position.map(f32::from);
+                pose(v.list).map_or(p, |m| skeleton::transform(&m, p))
+            })
+            .collect::<Vec<_>>();
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in &positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let mut attributes = vec![(
+            "POSITION",
+            b.accessor(
+                &f32s(positions.iter().flatten()),
+                ARRAY_BUFFER,
+                FLOAT,
+                "VEC3",
+                positions.len(),
+                Some((&min[..], &max[..])),
+            ),
+        )];
+
+        if mesh.vertices.iter().any(|v| v.normal.is_some()) {
+            let normals = mesh
+                .vertices
+                .iter()
+                .flat_map(|v| {
+                    let n = v.normal.map_or([0.0, 0.0, 1.0], |n| n.map(f32::from));
+                    let n = match pose(v.list) {
+                        Some(mut m) => {
+                            m[12..15].fill(0.0);
+                            skeleton::transform(&m, n)
+                        }
+                        None => n,
+                    };
+                    let len = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+                    if len > 0.0 {
+                        n.map(|c| c / len)
+                    } else {
+                        [0.0, 0.0, 1.0]
+                    }
+                })
+                .collect::<Vec<_>>();
+            let n = b.accessor(
+                &f32s(&normals),
+                ARRAY_BUFFER,
+                FLOAT,
+                "VEC3",
+                mesh.vertices.len(),
+                None,
+            );
+            attributes.push(("NORMAL", n));
+        }
+
+        // glTF texture coordinates have v down
+        let uvs = mesh
+            .vertices
+            .iter()
+            .flat_map(|v| [v.uv[0], 1.0 - v.uv[1]])
+            .collect::<Vec<_>>();
+        let uv = b.accessor(
+            &f32s(&uvs),
+            ARRAY_BUFFER,
+            FLOAT,
+            "VEC2",
+            mesh.vertices.len(),
+            None,
+        );
+        attributes.push(("TEXCOORD_0", uv));
+
+        // lit vertices have normals instead of colors
+        let colors = mesh
+            .vertices
+            .iter()
+            .flat_map(|v| match v.normal {
+                Some(_) => [0xFF; 4],
+                None => v.color,
+            })
+            .collect::<Vec<_>>();
+        let color = b.normalized_accessor(&colors, UNSIGNED_BYTE, "VEC4", mesh.vertices.len());
+        attributes.push(("COLOR_0", color));
+
+        if let Some(skin) = skin {
+            let joints = mesh
+                .vertices
+                .iter()
+                .flat_map(|v| {
+                    let joint = bone_of(v.list).unwrap_or(0) as u16;
+                    [joint, 0, 0, 0].into_iter().flat_map(u16::to_le_bytes)
+                })
+                .collect::<Vec<_>>();
+            let joints = b.accessor(
+                &joints,
+                ARRAY_BUFFER,
+                UNSIGNED_SHORT,
+                "VEC4",
+                mesh.vertices.len(),
+                None,
+            );
+            let weights = mesh
+                .vertices
+                .iter()
+                .flat_map(|_| [1.0, 0.0, 0.0, 0.0])
+                .collect::<Vec<f32>>();
+            let weights = b.accessor(
+                &f32s(&weights),
+                ARRAY_BUFFER,
+                FLOAT,
+                "VEC4",
+                mesh.vertices.len(),
+                None,
+            );
+            attributes.push(("JOINTS_0", joints));
+            attributes.push(("WEIGHTS_0", weights));
+
+            let inverse = world
+                .iter()
+                .flatten()
+                .flat_map(skeleton::invert)
+                .collect::<Vec<_>>();
+            let inverse = b.accessor(&f32s(&inverse), 0, FLOAT, "MAT4", skin.bones.len(), None);
+            b.skin = Some((inverse, skin.bones.to_vec()));
+        }
+
+        // a primitive of the triangles of each material
+        for (i, material) in mesh.materials.iter().enumerate() {
+            let indices = mesh
+                .triangles
+                .iter()
+                .filter(|t| t.material == i)
+                .flat_map(|t| t.vertices.map(|v| v as u32))
+                .collect::<Vec<_>>();
+            if indices.is_empty() {
+                continue;
+            }
+            let bytes = indices
+                .iter()
+                .flat_map(|i| i.to_le_bytes())
+                .collect::<Vec<_>>();
+            let accessor = b.accessor(
+                &bytes,
+                ELEMENT_ARRAY_BUFFER,
+                UNSIGNED_INT,
+                "SCALAR",
+                indices.len(),
+                None,
+            );
+            let image = material
+                .texture
+                .as_ref()
+                .and_then(&texture)
+                .map(|png| b.view(&png, 0));
+            b.materials.push(image);
+            b.primitives.push((accessor, b.materials.len() - 1));
+        }
+
+        let json = b.json(&attributes);
+        Self {
+            json,
+            buffer: b.buffer,
+        }
+    }
+
+    /// The asset as a `.gltf` file, with the buffer embedded as a data URI
+    pub fn embedded(&self) -> Result<String> {
+        let mut uri = String::from("data:application/octet-stream;base64,");
+        base64(&self.buffer, &mut uri);
+        let mut json = serde_json::to_string(&self.with_buffer(Some(uri)))?;
+        json.push('\n');
+
+        Ok(json)
+    }
+
+    /// The asset as a binary `.glb` file
+    pub fn glb(&self) -> Result<Vec<u8>> {
+        let mut json = serde_json::to_vec(&self.with_buffer(None))?;
+        // chunks are padded to 4 bytes: the json with spaces, the buffer with zeros
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut buffer = self.buffer.clone();
+        buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+        let total = 12 + 8 + json.len() + 8 + buffer.len();
+        let mut glb = Vec::with_capacity(total);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total as u32).to_le_bytes());
+        for (chunk, kind) in [(&json, b"JSON"), (&buffer, b"BIN\0")] {
+            glb.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            glb.extend_from_slice(kind);
+            glb.extend_from_slice(chunk);
+        }
+
+        Ok(glb)
+    }
+
+    /// The json with the buffer, at `uri` or in the binary chunk of a `.glb`
+    fn with_buffer(&self, uri: Option<String>) -> json::Root {
+        json::Root {
+            buffers: vec![json::Buffer {
+                byte_length: self.buffer.len(),
+                uri,
+            }],
+            ..self.json.clone()
+        }
+    }
+}
+
+/// The buffer, views, and accessors as they are added, and the rest of the asset
+#[derive(Debug, Default)]
+struct Builder {
+    buffer: Vec<u8>,
+    views: Vec<json::View>,
+    accessors: Vec<json::Accessor>,
+    /// the accessor of the indices and the material of each primitive
+    primitives: Vec<(usize, usize)>,
+    /// the buffer view of the PNG of each material
+    materials: Vec<Option<usize>>,
+    /// the accessor of the inverse bind matrices, and the bones
+    skin: Option<(usize, Vec<Bone>)>,
+}
+
+impl Builder {
+    /// Add `data` to the buffer in a new view, aligned to 4 bytes
+    fn view(&mut self, data: &[u8], target: u32) -> usize {
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+        self.views.push(json::View {
+            buffer: 0,
+            byte_offset: self.buffer.len(),
+            byte_length: data.len(),
+            target: (target != 0).then_some(target),
+        });
+        self.buffer.extend_from_slice(data);
+        self.views.len() - 1
+    }
+
+    fn accessor(
+        &mut self,
+        data: &[u8],
+        target: u32,
+        component: u32,
+        kind: &'static str,
+        count: usize,
+        bounds: Option<(&[f32], &[f32])>,
+    ) -> usize {
+        let view = self.view(data, target);
+        self.accessors.push(json::Accessor {
+            buffer_view: view,
+            component_type: component,
+            count,
+            kind,
+            min: bounds.map(|(min, _)| finite(min)),
+            max: bounds.map(|(_, max)| finite(max)),
+            normalized: None,
+        });
+        self.accessors.len() - 1
+    }
+
+    fn normalized_accessor(
+        &mut self,
+        data: &[u8],
+        component: u32,
+        kind: &'static str,
+        count: usize,
+    ) -> usize {
+        let i = self.accessor(data, ARRAY_BUFFER, component, kind, count, None);
+        self.accessors[i].normalized = Some(true);
+        i
+    }
+
+    /// The json of the asset, without its buffers
+    fn json(&self, attributes: &[(&'static str, usize)]) -> json::Root {
+        let primitives = self
+            .primitives
+            .iter()
+            .map(|&(indices, material)| json::Primitive {
+                attributes: attributes.iter().copied().collect(),
+                indices,
+                material,
+            })
+            .collect();
+
+        // the bones come first, then the node of the mesh
+        let bones = self.skin.as_ref().map_or(&[][..], |(_, bones)| bones);
+        let mut nodes = bones
+            .iter()
+            .enumerate()
+            .map(|(i, bone)| json::Node {
+                name: format!("bone_{}", i),
+                translation: Some(finite(&bone.translate)),
+                rotation: Some(finite(&bone.rotation())),
+                scale: Some(finite(&bone.scale)),
+                children: bones
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| b.parent == Some(i))
+                    .map(|(c, _)| c)
+                    .collect(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut roots = (0..bones.len())
+            .filter(|&i| bones[i].parent.is_none())
+            .collect::<Vec<_>>();
+        roots.push(nodes.len());
+        nodes.push(json::Node {
+            name: "model".into(),
+            mesh: Some(0),
+            skin: self.skin.as_ref().map(|_| 0),
+            ..Default::default()
+        });
+        let skins = self
+            .skin
+            .iter()
+            .map(|(inverse, bones)| json::Skin {
+                inverse_bind_matrices: *inverse,
+                joints: (0..bones.len()).collect(),
+            })
+            .collect();
+
+        // materials, and the textures and images of those that have them
+        let mut materials = Vec::new();
+        let mut images = Vec::new();
+        for (i, view) in self.materials.iter().enumerate() {
+            let texture = view.map(|view| {
+                images.push(json::Image {
+                    buffer_view: view,
+                    mime_type: "image/png",
+                });
+                json::TextureRef {
+                    index: images.len() - 1,
+                }
+            });
+            materials.push(json::Material {
+                name: crate::mesh::material_name(i),
+                pbr_metallic_roughness: json::Pbr {
+                    metallic_factor: 0,
+                    base_color_texture: texture,
+                },
+                alpha_mode: "MASK",
+            });
+        }
+        let textures = (0..images.len())
+            .map(|source| json::Texture { source })
+            .collect();
+
+        json::Root {
+            asset: json::Asset {
+                version: "2.0",
+                generator: "ssbfile",
+            },
+            scene: 0,
+            meshes: vec![json::Mesh { primitives }],
+            scenes: vec![json::Scene { nodes: roots }],
+            nodes,
+            skins,
+            materials,
+            textures,
+            images,
+            buffer_views: self.views.clone(),
+            accessors: self.accessors.clone(),
+            buffers: Vec::new(),
+        }
+    }
+}
+
+/// The types of the json of an asset, with only the properties that are written
+mod json {
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Root {
+        pub(super) asset: Asset,
+        pub(super) scene: usize,
+        pub(super) meshes: Vec<Mesh>,
+        pub(super) scenes: Vec<Scene>,
+        pub(super) nodes: Vec<Node>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub(super) skins: Vec<Skin>,
+        pub(super) materials: Vec<Material>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub(super) textures: Vec<Texture>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub(super) images: Vec<Image>,
+        pub(super) buffer_views: Vec<View>,
+        pub(super) accessors: Vec<Accessor>,
+        pub(super) buffers: Vec<Buffer>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct Asset {
+        pub(super) version: &'static str,
+        pub(super) generator: &'static str,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct Mesh {
+        pub(super) primitives: Vec<Primitive>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct Primitive {
+        pub(super) attributes: BTreeMap<&'static str, usize>,
+        pub(super) indices: usize,
+        pub(super) material: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct Scene {
+        pub(super) nodes: Vec<usize>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub(super) struct Node {
+        pub(super) name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) translation: Option<Vec<f32>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) rotation: Option<Vec<f32>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) scale: Option<Vec<f32>>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub(super) children: Vec<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) mesh: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) skin: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Skin {
+        pub(super) inverse_bind_matrices: usize,
+        pub(super) joints: Vec<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Material {
+        pub(super) name: String,
+        pub(super) pbr_metallic_roughness: Pbr,
+        pub(super) alpha_mode: &'static str,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Pbr {
+        pub(super) metallic_factor: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) base_color_texture: Option<TextureRef>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct TextureRef {
+        pub(super) index: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(super) struct Texture {
+        pub(super) source: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Image {
+        pub(super) buffer_view: usize,
+        pub(super) mime_type: &'static str,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct View {
+        pub(super) buffer: usize,
+        pub(super) byte_offset: usize,
+        pub(super) byte_length: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) target: Option<u32>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Accessor {
+        pub(super) buffer_view: usize,
+        pub(super) component_type: u32,
+        pub(super) count: usize,
+        #[serde(rename = "type")]
+        pub(super) kind: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) min: Option<Vec<f32>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) max: Option<Vec<f32>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) normalized: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Buffer {
+        pub(super) byte_length: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) uri: Option<String>,
+    }
+}
+
+fn f32s<'a>(floats: impl IntoIterator<Item = &'a f32>) -> Vec<u8> {
+    floats.into_iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// `floats` as json numbers, which have no NaN or infinity
+fn finite(floats: &[f32]) -> Vec<f32> {
+    floats
+        .iter()
+        .map(|&n| if n.is_finite() { n } else { 0.0 })
+        .collect()
+}
+
+/// Append `data` in standard base64, with padding, to `out`
+fn base64(data: &[u8], out: &mut String) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    out.reserve(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
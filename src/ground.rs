@@ -0,0 +1,146 @@
+use crate::{
+    cli::{BonusOpt, StageOpt},
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use ssbfile::stage::{self, Geometry};
+use std::fs;
+
+pub(crate) fn report(opt: StageOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let geometry = stage::geometry(&data, opt.geometry as usize, &relocs)
+        .with_context(|| format!("reading the collision of file <{}>", opt.id))?;
+    let bounds = opt
+        .bounds
+        .map(|at| stage::Bounds::read(&data, at as usize))
+        .transpose()
+        .with_context(|| format!("reading the bounds of file <{}>", opt.id))?;
+    let stage = stage::Stage { geometry, bounds };
+    match &opt.svg {
+        Some(path) => timings
+            .time(Stage::Write, || fs::write(path, stage.svg()))
+            .with_context(|| format!("writing <{}>", path.display())),
+        None => {
+            print!("{}", stage);
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn bonus(opt: BonusOpt, timings: &mut Timings) -> Result<()> {
+    if opt.set.is_empty() {
+        print_bonus(&opt, timings)
+    } else {
+        edit_bonus(&opt, timings)
+    }
+}
+
+fn print_bonus(opt: &BonusOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+    let geometry = stage::geometry(&data, opt.geometry as usize, &relocs)
+        .with_context(|| format!("reading the collision of file <{}>", opt.id))?;
+
+    let objects = |bonus| {
+        bonus_objects(&geometry, bonus)
+            .enumerate()
+            .map(|(index, i)| {
+                let object = geometry.objects[i];
+                ObjectJson {
+                    index,
+                    x: object.x,
+                    y: object.y,
+                }
+            })
+            .collect()
+    };
+    let json = BonusJson {
+        file: opt.id,
+        targets: objects("target"),
+        platforms: objects("platform"),
+    };
+    println!("{}", serde_json::to_string(&json)?);
+
+    Ok(())
+}
+
+fn edit_bonus(opt: &BonusOpt, timings: &mut Timings) -> Result<()> {
+    let output = opt.output.as_ref().expect("output is required with set");
+    let (mut rom, version) = opt.rom.read(timings)?;
+    inject::edit_file(
+        &mut rom,
+        &version,
+        opt.id,
+        &opt.compress,
+        timings,
+        |data, relocs| {
+            let mut geometry = stage::geometry(data, opt.geometry as usize, relocs)
+                .with_context(|| format!("reading the collision of file <{}>", opt.id))?;
+            move_bonus_objects(opt, &mut geometry)?;
+            geometry.write_objects(data)
+        },
+    )?;
+    eprintln!("moved {} bonus objects of file {}", opt.set.len(), opt.id);
+
+    opt.out.write(output, &mut rom, timings)
+}
+
+/// Move the targets and platforms of `geometry` to the `--set` positions of `opt`
+fn move_bonus_objects(opt: &BonusOpt, geometry: &mut Geometry) -> Result<()> {
+    for (name, value) in &opt.set {
+        let mut parts = name.split('.');
+        let (Some(bonus @ ("target" | "platform")), Some(n), Some(axis @ ("x" | "y")), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            bail!(
+                "expected target.N.x, target.N.y, platform.N.x, or platform.N.y, not <{}>",
+                name
+            );
+        };
+        let Some(i) = n
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| bonus_objects(geometry, bonus).nth(n))
+        else {
+            bail!("file <{}> has no {} {}", opt.id, bonus, n);
+        };
+        let value = i16::try_from(*value)
+            .with_context(|| format!("{} doesn't fit in <{}>", value, name))?;
+        let object = &mut geometry.objects[i];
+        match axis {
+            "x" => object.x = value,
+            _ => object.y = value,
+        }
+    }
+
+    Ok(())
+}
+
+/// The targets and platforms of a bonus stage file, as json
+#[derive(Serialize)]
+struct BonusJson {
+    file: usize,
+    targets: Vec<ObjectJson>,
+    platforms: Vec<ObjectJson>,
+}
+
+#[derive(Serialize)]
+struct ObjectJson {
+    index: usize,
+    x: i16,
+    y: i16,
+}
+
+/// The indices of the map objects of `geometry` that are a `bonus` (a target or platform)
+fn bonus_objects<'a>(geometry: &'a Geometry, bonus: &'a str) -> impl Iterator<Item = usize> + 'a {
+    geometry
+        .objects
+        .iter()
+        .enumerate()
+        .filter(move |(_, o)| o.bonus() == Some(bonus))
+        .map(|(i, _)| i)
+}
@@ -0,0 +1,881 @@
+//! N64 textures: decoding the texel formats of the RDP into RGBA and encoding them
+//! again, finding the libultra `Sprite` structs in a file that describe the
+//! textures of its sprites, and the palettes of color indexed textures
+use crate::{
+    display_list::{self, Address, Command},
+    Error, Relocation,
+};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{ensure, Result};
+use core::{fmt, str::FromStr};
+
+/// A texel format of the RDP: the `G_IM_FMT` and `G_IM_SIZ` of a texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TexelFormat {
+    Rgba16,
+    Rgba32,
+    Ci4,
+    Ci8,
+    Ia4,
+    Ia8,
+    Ia16,
+    I4,
+    I8,
+}
+
+impl TexelFormat {
+    pub const ALL: [Self; 9] = [
+        Self::Rgba16,
+        Self::Rgba32,
+        Self::Ci4,
+        Self::Ci8,
+        Self::Ia4,
+        Self::Ia8,
+        Self::Ia16,
+        Self::I4,
+        Self::I8,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rgba16 => "rgba16",
+            Self::Rgba32 => "rgba32",
+            Self::Ci4 => "ci4",
+            Self::Ci8 => "ci8",
+            Self::Ia4 => "ia4",
+            Self::Ia8 => "ia8",
+            Self::Ia16 => "ia16",
+            Self::I4 => "i4",
+            Self::I8 => "i8",
+        }
+    }
+
+    /// The format from the `G_IM_FMT` (0 RGBA, 2 CI, 3 IA, 4 I) and `G_IM_SIZ`
+    /// (0 4b, 1 8b, 2 16b, 3 32b) of a texture; YUV and the other sizes aren't supported
+    pub fn from_fmt_siz(fmt: u8, siz: u8) -> Option<Self> {
+        match (fmt, siz) {
+            (0, 2) => Some(Self::Rgba16),
+            (0, 3) => Some(Self::Rgba32),
+            (2, 0) => Some(Self::Ci4),
+            (2, 1) => Some(Self::Ci8),
+            (3, 0) => Some(Self::Ia4),
+            (3, 1) => Some(Self::Ia8),
+            (3, 2) => Some(Self::Ia16),
+            (4, 0) => Some(Self::I4),
+            (4, 1) => Some(Self::I8),
+            _ => None,
+        }
+    }
+
+    pub fn bits(self) -> usize {
+        match self {
+            Self::Ci4 | Self::Ia4 | Self::I4 => 4,
+            Self::Ci8 | Self::Ia8 | Self::I8 => 8,
+            Self::Rgba16 | Self::Ia16 => 16,
+            Self::Rgba32 => 32,
+        }
+    }
+
+    /// Whether the texels are indices into a palette
+    pub fn has_palette(self) -> bool {
+        matches!(self, Self::Ci4 | Self::Ci8)
+    }
+
+    /// The bytes of a `width` by `height` texture
+    pub fn size(self, width: usize, height: usize) -> usize {
+        (width * height * self.bits()).div_ceil(8)
+    }
+
+    /// The number of colors in the palette of a color indexed format
+    pub fn palette_len(self) -> usize {
+        1 << self.bits()
+    }
+}
+
+impl fmt::Display for TexelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TexelFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownTexelFormat {
+                name: s.to_string(),
+            })
+    }
+}
+
+/// A decoded texture, as 8-bit RGBA pixels in rows from the top left
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    fn blank(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    /// The `width` by `height` part of this image from `x`, `y`; anything
+    /// outside of this image is transparent black
+    fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        let mut cropped = Image::blank(width, height);
+        let columns = width.min(self.width.saturating_sub(x));
+        for row in 0..height.min(self.height.saturating_sub(y)) {
+            let src = ((y + row) * self.width + x) * 4;
+            let dst = row * width * 4;
+            cropped.pixels[dst..dst + columns * 4]
+                .copy_from_slice(&self.pixels[src..src + columns * 4]);
+        }
+
+        cropped
+    }
+
+    /// Copy `other` into this image with its top left corner at `x`, `y`,
+    /// cutting off anything outside of this image
+    fn blit(&mut self, other: &Image, x: usize, y: usize) {
+        let width = other.width.min(self.width.saturating_sub(x));
+        for row in 0..other.height.min(self.height.saturating_sub(y)) {
+            let src = row * other.width * 4;
+            let dst = ((y + row) * self.width + x) * 4;
+            self.pixels[dst..dst + width * 4].copy_from_slice(&other.pixels[src..src + width * 4]);
+        }
+    }
+}
+
+/// Pack `images` into one sheet, in rows from the tallest image down, with a pixel of
+/// space between them. Returns the sheet and the position of each image in it.
+pub fn pack(images: &[Image]) -> (Image, Vec<(usize, usize)>) {
+    const GAP: usize = 1;
+
+    // rows about as wide as the sheet is tall, but at least as wide as the widest image
+    let area = images
+        .iter()
+        .map(|i| (i.width + GAP) * (i.height + GAP))
+        .sum::<usize>();
+    let widest = images.iter().map(|i| i.width).max().unwrap_or(0);
+    let width = widest.max(area.isqrt());
+
+    let mut order = (0..images.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| core::cmp::Reverse(images[i].height));
+    let mut positions = vec![(0, 0); images.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for i in order {
+        let image = &images[i];
+        if x > 0 && x + image.width > width {
+            (x, y) = (0, y + row_height + GAP);
+            row_height = 0;
+        }
+        positions[i] = (x, y);
+        x += image.width + GAP;
+        row_height = row_height.max(image.height);
+    }
+
+    let height = images
+        .iter()
+        .zip(&positions)
+        .map(|(i, (_, y))| y + i.height)
+        .max()
+        .unwrap_or(0);
+    let mut sheet = Image::blank(width, height);
+    for (image, &(x, y)) in images.iter().zip(&positions) {
+        sheet.blit(image, x, y);
+    }
+
+    (sheet, positions)
+}
+
+/// Decode a `width` by `height` texture of `format` at the start of `data`.
+/// Color indexed textures need a `palette` of RGBA16 colors (the usual TLUT format).
+pub fn decode(
+    data: &[u8],
+    format: TexelFormat,
+    width: usize,
+    height: usize,
+    palette: Option<&[u8]>,
+) -> Result<Image> {
+    let size = format.size(width, height);
+    ensure!(
+        size <= data.len(),
+        Error::TextureOutsideData {
+            size,
+            len: data.len()
+        }
+    );
+    let palette = match palette {
+        Some(palette) if format.has_palette() => palette,
+        None if format.has_palette() => return Err(Error::MissingPalette.into()),
+        _ => &[],
+    };
+    let color = |index: usize| {
+        palette
+            .get(index * 2..index * 2 + 2)
+            .map_or([0; 4], |c| rgba16([c[0], c[1]]))
+    };
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for texel in 0..width * height {
+        let byte = data[texel * format.bits() / 8];
+        // 4-bit texels are packed with the first in the high nibble
+        let nibble = if texel % 2 == 0 {
+            byte >> 4
+        } else {
+            byte & 0xF
+        };
+        let at = |n: usize| data[texel * format.bits() / 8 + n];
+        let rgba = match format {
+            TexelFormat::Rgba16 => rgba16([at(0), at(1)]),
+            TexelFormat::Rgba32 => [at(0), at(1), at(2), at(3)],
+            TexelFormat::Ci4 => color(nibble as usize),
+            TexelFormat::Ci8 => color(byte as usize),
+            TexelFormat::Ia4 => {
+                let i = nibble >> 1;
+                let i = (i << 5) | (i << 2) | (i >> 1);
+                [i, i, i, if nibble & 1 != 0 { 0xFF } else { 0 }]
+            }
+            TexelFormat::Ia8 => {
+                let i = (byte >> 4) * 0x11;
+                [i, i, i, (byte & 0xF) * 0x11]
+            }
+            TexelFormat::Ia16 => [at(0), at(0), at(0), at(1)],
+            TexelFormat::I4 => [nibble * 0x11; 4],
+            TexelFormat::I8 => [byte; 4],
+        };
+        pixels.extend_from_slice(&rgba);
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// A 5-5-5-1 color as RGBA
+fn rgba16(raw: [u8; 2]) -> [u8; 4] {
+    let c = u16::from_be_bytes(raw);
+    let expand = |bits: u16| (((bits & 0x1F) << 3) | ((bits & 0x1F) >> 2)) as u8;
+    [
+        expand(c >> 11),
+        expand(c >> 6),
+        expand(c >> 1),
+        if c & 1 != 0 { 0xFF } else { 0 },
+    ]
+}
+
+/// the offsets in a libultra `Sprite` struct
+const SPRITE_SIZE: usize = 0x44;
+const SPRITE_WIDTH: usize = 0x04;
+const SPRITE_HEIGHT: usize = 0x06;
+const SPRITE_NTLUT: usize = 0x1E;
+const SPRITE_LUT: usize = 0x20;
+const SPRITE_NBITMAPS: usize = 0x28;
+const SPRITE_BMHEIGHT: usize = 0x2C;
+const SPRITE_BMFMT: usize = 0x30;
+const SPRITE_BMSIZ: usize = 0x31;
+const SPRITE_BITMAP: usize = 0x34;
+/// the offsets in a libultra `Bitmap` struct
+const BITMAP_SIZE: usize = 0x10;
+const BITMAP_WIDTH: usize = 0x00;
+const BITMAP_WIDTH_IMG: usize = 0x02;
+const BITMAP_BUF: usize = 0x08;
+const BITMAP_HEIGHT: usize = 0x0C;
+
+/// A libultra `Sprite` struct in a file: a texture made of one or more bitmaps
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sprite {
+    /// offset of the struct in the file
+    pub offset: usize,
+    pub width: usize,
+    pub height: usize,
+    pub format: TexelFormat,
+    /// offset of the palette, for color indexed sprites
+    pub palette: Option<usize>,
+    /// the number of colors in the palette
+    pub palette_colors: usize,
+    /// the height of a row of bitmaps
+    pub bitmap_height: usize,
+    pub bitmaps: Vec<Bitmap>,
+}
+
+/// One of the bitmaps of a [`Sprite`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bitmap {
+    /// the width of the bitmap that is drawn
+    pub width: usize,
+    /// the width of the texels of the bitmap, which can be larger than `width`
+    pub width_img: usize,
+    pub height: usize,
+    /// offset of the texels in the file
+    pub texels: usize,
+}
+
+/// Find the libultra `Sprite` structs that the internal pointers of `file` (see
+/// [`relocations`](crate::relocations)) point to, with the bitmaps and palettes that
+/// they point to in turn. Sprites of an unsupported format are skipped.
+pub fn sprites(file: &[u8], relocs: &[Relocation]) -> Vec<Sprite> {
+    let pointers = relocs
+        .iter()
+        .filter(|r| r.external_file.is_none())
+        .map(|r| (r.offset, r.target as usize))
+        .collect::<BTreeMap<_, _>>();
+    let half = |at: usize| {
+        file.get(at..at + 2)
+            .map(|h| u16::from_be_bytes([h[0], h[1]]) as usize)
+    };
+    let float = |at: usize| file.get(at..at + 4).map(|w| [w[0], w[1], w[2], w[3]]);
+
+    let mut targets = pointers.values().copied().collect::<Vec<_>>();
+    targets.sort_unstable();
+    targets.dedup();
+    let mut sprites = Vec::new();
+    for s in targets {
+        if s + SPRITE_SIZE > file.len()
+            || float(s + 0x08) != Some(1f32.to_be_bytes())
+            || float(s + 0x0C) != Some(1f32.to_be_bytes())
+        {
+            continue;
+        }
+        let Some(format) =
+            TexelFormat::from_fmt_siz(file[s + SPRITE_BMFMT], file[s + SPRITE_BMSIZ])
+        else {
+            continue;
+        };
+        let Some(&bitmaps_at) = pointers.get(&(s + SPRITE_BITMAP)) else {
+            continue;
+        };
+        let palette = pointers.get(&(s + SPRITE_LUT)).copied();
+        if format.has_palette() && palette.is_none() {
+            continue;
+        }
+
+        let count = half(s + SPRITE_NBITMAPS).unwrap_or(0);
+        let bitmaps = (0..count)
+            .map(|i| {
+                let b = bitmaps_at + i * BITMAP_SIZE;
+                Some(Bitmap {
+                    width: half(b + BITMAP_WIDTH)?,
+                    width_img: half(b + BITMAP_WIDTH_IMG)?,
+                    height: half(b + BITMAP_HEIGHT)?,
+                    texels: *pointers.get(&(b + BITMAP_BUF))?,
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(bitmaps) = bitmaps.filter(|b| !b.is_empty()) else {
+            continue;
+        };
+
+        sprites.push(Sprite {
+            offset: s,
+            width: half(s + SPRITE_WIDTH).unwrap_or(0),
+            height: half(s + SPRITE_HEIGHT).unwrap_or(0),
+            format,
+            palette,
+            palette_colors: half(s + SPRITE_NTLUT)
+                .filter(|&n| n > 0 && n <= format.palette_len())
+                .unwrap_or(format.palette_len()),
+            bitmap_height: half(s + SPRITE_BMHEIGHT).unwrap_or(0),
+            bitmaps,
+        });
+    }
+
+    sprites
+}
+
+/// The fewest glyphs of a font
+const MIN_GLYPHS: usize = 10;
+/// The widest and tallest glyph of a font, in texels
+const MAX_GLYPH: usize = 32;
+
+/// If `sprites` (as found by [`sprites`]) are the glyphs of a font: at least ten small
+/// sprites, none over 32 texels on a side, that are all the same height and format
+pub fn is_font(sprites: &[Sprite]) -> bool {
+    let Some(first) = sprites.first() else {
+        return false;
+    };
+
+    sprites.len() >= MIN_GLYPHS
+        && sprites.iter().all(|s| {
+            s.height == first.height
+                && s.format == first.format
+                && (1..=MAX_GLYPH).contains(&s.width)
+                && (1..=MAX_GLYPH).contains(&s.height)
+        })
+}
+
+/// Decode `sprite` from `file`, placing its bitmaps left to right, and starting a new row
+/// (of the sprite's bitmap height) when a row is as wide as the sprite
+pub fn decode_sprite(file: &[u8], sprite: &Sprite) -> Result<Image> {
+    // a texture past the end of the file is reported by `decode`
+    let slice = |start: usize| file.get(start..).unwrap_or_default();
+    let palette = sprite.palette.map(slice).map(|palette| {
+        let len = (sprite.palette_colors * 2).min(palette.len());
+        &palette[..len]
+    });
+
+    let mut image = Image::blank(sprite.width, sprite.height);
+    for (bitmap, x, y) in placements(sprite) {
+        let texels = slice(bitmap.texels);
+        let decoded = decode(
+            texels,
+            sprite.format,
+            bitmap.width_img,
+            bitmap.height,
+            palette,
+        )?;
+        let drawn = decoded.crop(0, 0, bitmap.width.min(bitmap.width_img), bitmap.height);
+        image.blit(&drawn, x, y);
+    }
+
+    Ok(image)
+}
+
+/// Encode `image` into the texels (and the palette, for color indexed sprites) of
+/// `sprite` in `file`. The image has to be the size of the sprite. A new palette is
+/// made from the colors of the image, unless `keep_palette` is set, in which case
+/// each color is matched to the nearest color of the sprite's current palette.
+pub fn encode_sprite(
+    file: &mut [u8],
+    sprite: &Sprite,
+    image: &Image,
+    keep_palette: bool,
+) -> Result<()> {
+    ensure!(
+        (image.width, image.height) == (sprite.width, sprite.height),
+        Error::TextureSize {
+            width: image.width,
+            height: image.height,
+            expected_width: sprite.width,
+            expected_height: sprite.height,
+        }
+    );
+    let palette = match sprite.palette {
+        Some(at) if keep_palette => {
+            let palette = file.get(at..).unwrap_or_default();
+            Some(palette[..(sprite.palette_colors * 2).min(palette.len())].to_vec())
+        }
+        Some(at) => {
+            let palette = quantize(image, sprite.palette_colors);
+            write_at(file, at, &palette)?;
+            Some(palette)
+        }
+        None => None,
+    };
+    for (bitmap, x, y) in placements(sprite) {
+        let part = image.crop(x, y, bitmap.width_img, bitmap.height);
+        let encoded = encode(&part, sprite.format, palette.as_deref())?;
+        write_at(file, bitmap.texels, &encoded.texels)?;
+    }
+
+    Ok(())
+}
+
+/// Copy `data` into `file` at `at`
+fn write_at(file: &mut [u8], at: usize, data: &[u8]) -> Result<()> {
+    let len = file.len().saturating_sub(at);
+    let dst = file
+        .get_mut(at..at + data.len())
+        .ok_or(Error::TextureOutsideData {
+            size: data.len(),
+            len,
+        })?;
+    dst.copy_from_slice(data);
+
+    Ok(())
+}
+
+/// The bitmaps of `sprite` with the position of their top left corner in the sprite
+fn placements(sprite: &Sprite) -> impl Iterator<Item = (&Bitmap, usize, usize)> {
+    let (mut x, mut y) = (0, 0);
+    sprite.bitmaps.iter().map(move |bitmap| {
+        let at = (bitmap, x, y);
+        x += bitmap.width;
+        if x >= sprite.width {
+            x = 0;
+            y += sprite.bitmap_height.max(1);
+        }
+        at
+    })
+}
+
+/// The texels of an encoded texture, and the palette that was made for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Encoded {
+    pub texels: Vec<u8>,
+    /// the RGBA16 palette of a color indexed texture
+    pub palette: Option<Vec<u8>>,
+}
+
+/// Encode `image` as texels of `format`. Color indexed textures use the RGBA16 colors of
+/// `palette`, or a new palette with the colors of the image (see [`quantize`]) if there
+/// is none; each pixel becomes the nearest color of the palette.
+pub fn encode(image: &Image, format: TexelFormat, palette: Option<&[u8]>) -> Result<Encoded> {
+    let palette = match palette {
+        _ if !format.has_palette() => None,
+        Some(palette) => Some(palette.to_vec()),
+        None => Some(quantize(image, format.palette_len())),
+    };
+    let colors = palette
+        .iter()
+        .flat_map(|p| p.chunks_exact(2))
+        .map(|c| rgba16([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    ensure!(
+        !format.has_palette() || !colors.is_empty(),
+        Error::MissingPalette
+    );
+    let mut nearest = BTreeMap::new();
+    let mut index = |rgba: [u8; 4]| {
+        *nearest
+            .entry(to_rgba16(rgba))
+            .or_insert_with(|| nearest_color(&colors, rgba)) as u8
+    };
+
+    let mut texels = Vec::with_capacity(format.size(image.width, image.height));
+    let mut nibbles = image.pixels.chunks_exact(4).map(|p| {
+        let rgba = [p[0], p[1], p[2], p[3]];
+        let i = intensity(rgba);
+        match format {
+            TexelFormat::Ci4 => index(rgba),
+            TexelFormat::Ia4 => ((i >> 5) << 1) | (rgba[3] >= 0x80) as u8,
+            _ => i >> 4,
+        }
+    });
+    if format.bits() == 4 {
+        while let Some(high) = nibbles.next() {
+            texels.push((high << 4) | nibbles.next().unwrap_or(0));
+        }
+    } else {
+        for p in image.pixels.chunks_exact(4) {
+            let rgba = [p[0], p[1], p[2], p[3]];
+            match format {
+                TexelFormat::Rgba16 => texels.extend_from_slice(&to_rgba16(rgba).to_be_bytes()),
+                TexelFormat::Rgba32 => texels.extend_from_slice(&rgba),
+                TexelFormat::Ci8 => texels.push(index(rgba)),
+                TexelFormat::Ia8 => texels.push((intensity(rgba) & 0xF0) | (rgba[3] >> 4)),
+                TexelFormat::Ia16 => texels.extend_from_slice(&[intensity(rgba), rgba[3]]),
+                _ => texels.push(intensity(rgba)),
+            }
+        }
+    }
+
+    Ok(Encoded { texels, palette })
+}
+
+/// A palette of at most `colors` RGBA16 colors for `image`: every color of the image if
+/// there are few enough, or else the average colors of a median cut of its colors
+pub fn quantize(image: &Image, colors: usize) -> Vec<u8> {
+    let mut unique = image
+        .pixels
+        .chunks_exact(4)
+        .map(|p| rgba16(to_rgba16([p[0], p[1], p[2], p[3]]).to_be_bytes()))
+        .collect::<Vec<_>>();
+    unique.sort_unstable();
+    unique.dedup();
+
+    let mut boxes = vec![unique];
+    while boxes.len() < colors.max(1) {
+        // split the box with the widest range of a channel at its median
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = (0..4)
+                    .map(|c| {
+                        let (min, max) = b
+                            .iter()
+                            .fold((u8::MAX, 0), |(lo, hi), p| (lo.min(p[c]), hi.max(p[c])));
+                        (c, max - min)
+                    })
+                    .max_by_key(|&(_, range)| range)
+                    .unwrap_or((0, 0));
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range);
+        let Some((i, channel, _)) = widest else {
+            break;
+        };
+        let mut b = boxes.swap_remove(i);
+        b.sort_unstable_by_key(|p| p[channel]);
+        let upper = b.split_off(b.len() / 2);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .filter(|b| !b.is_empty())
+        .flat_map(|b| {
+            let mut sum = [0usize; 4];
+            for p in b {
+                for (s, &c) in sum.iter_mut().zip(p) {
+                    *s += c as usize;
+                }
+            }
+            let average = sum.map(|s| (s / b.len()) as u8);
+            to_rgba16(average).to_be_bytes()
+        })
+        .collect()
+}
+
+/// A color as 5-5-5-1
+fn to_rgba16(rgba: [u8; 4]) -> u16 {
+    let [r, g, b, a] = rgba.map(u16::from);
+    ((r >> 3) << 11) | ((g >> 3) << 6) | ((b >> 3) << 1) | (a >= 0x80) as u16
+}
+
+/// The brightness of a color, for the intensity formats
+fn intensity(rgba: [u8; 4]) -> u8 {
+    let [r, g, b, _] = rgba.map(u32::from);
+    ((r * 299 + g * 587 + b * 114) / 1000) as u8
+}
+
+/// The index of the color of `palette` that is nearest to `rgba`
+fn nearest_color(palette: &[[u8; 4]], rgba: [u8; 4]) -> usize {
+    let distance = |c: &[u8; 4]| {
+        c.iter()
+            .zip(&rgba)
+            .map(|(&a, &b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap_or(0)
+}
+
+/// An RGBA16 palette in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    /// offset of the palette in the file
+    pub offset: usize,
+    /// the number of colors in the palette
+    pub colors: usize,
+    /// offsets of the sprites, and of the display list commands, that load the palette
+    pub users: Vec<usize>,
+}
+
+/// Find the palettes of `file`: those of its color indexed sprites, and those that its
+/// display lists load with `gsDPLoadTLUT` (which are also the costume palettes of a
+/// fighter's model). A palette loaded with different sizes is given its largest size.
+pub fn palettes(file: &[u8], relocs: &[Relocation]) -> Vec<Palette> {
+    let mut found = BTreeMap::<usize, Palette>::new();
+    let mut add = |offset: usize, colors: usize, user: usize| {
+        let palette = found.entry(offset).or_insert(Palette {
+            offset,
+            colors,
+            users: Vec::new(),
+        });
+        palette.colors = palette.colors.max(colors);
+        palette.users.push(user);
+    };
+
+    for sprite in sprites(file, relocs) {
+        if let Some(at) = sprite.palette {
+            add(at, sprite.palette_colors, sprite.offset);
+        }
+    }
+    for start in display_list::find(file, relocs) {
+        let Ok(commands) = display_list::parse(file, start, relocs) else {
+            continue;
+        };
+        let mut image = None;
+        for (offset, command) in commands {
+            match command {
+                Command::SetTextureImage { addr, .. } => image = Some(addr),
+                Command::LoadTlut { count, .. } => {
+                    if let Some(Address::Internal(at)) = image {
+                        add(at as usize, count as usize + 1, offset);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut palettes = found.into_values().collect::<Vec<_>>();
+    for palette in &mut palettes {
+        palette.users.sort_unstable();
+        palette.users.dedup();
+    }
+
+    palettes
+}
+
+/// The colors of the RGBA16 palette `data`, as RGBA
+pub fn palette_colors(data: &[u8]) -> Vec<[u8; 4]> {
+    data.chunks_exact(2).map(|c| rgba16([c[0], c[1]])).collect()
+}
+
+/// `colors` as an RGBA16 palette
+pub fn encode_palette(colors: &[[u8; 4]]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|&c| to_rgba16(c).to_be_bytes())
+        .collect()
+}
+
+/// The width and height of a color in a swatch
+pub const SWATCH_CELL: usize = 8;
+/// The number of colors in a row of a swatch
+pub const SWATCH_COLUMNS: usize = 16;
+
+/// A swatch of `colors`: a square of [`SWATCH_CELL`] pixels for each color, in rows
+/// of [`SWATCH_COLUMNS`]
+pub fn swatch(colors: &[[u8; 4]]) -> Image {
+    let rows = colors.len().div_ceil(SWATCH_COLUMNS);
+    let mut image = Image::blank(SWATCH_COLUMNS * SWATCH_CELL, rows.max(1) * SWATCH_CELL);
+    for (i, color) in colors.iter().enumerate() {
+        let (x, y) = (i % SWATCH_COLUMNS, i / SWATCH_COLUMNS);
+        for row in 0..SWATCH_CELL {
+            let at = ((y * SWATCH_CELL + row) * image.width + x * SWATCH_CELL) * 4;
+            for pixel in image.pixels[at..at + SWATCH_CELL * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(color);
+            }
+        }
+    }
+
+    image
+}
+
+/// The first `colors` colors of a [`swatch`], each read from the center of its square
+pub fn read_swatch(image: &Image, colors: usize) -> Result<Vec<[u8; 4]>> {
+    let expected = swatch(&vec![[0; 4]; colors]);
+    ensure!(
+        (image.width, image.height) == (expected.width, expected.height),
+        Error::TextureSize {
+            width: image.width,
+            height: image.height,
+            expected_width: expected.width,
+            expected_height: expected.height,
+        }
+    );
+
+    Ok((0..colors)
+        .map(|i| {
+            let x = (i % SWATCH_COLUMNS) * SWATCH_CELL + SWATCH_CELL / 2;
+            let y = (i / SWATCH_COLUMNS) * SWATCH_CELL + SWATCH_CELL / 2;
+            let at = (y * image.width + x) * 4;
+            let p = &image.pixels[at..at + 4];
+            [p[0], p[1], p[2], p[3]]
+        })
+        .collect())
+}
+
+/// `colors` as a JASC (Paint Shop Pro) palette, which most image editors can load.
+/// The format has no alpha, so the alpha bits are lost.
+pub fn jasc_pal(colors: &[[u8; 4]]) -> String {
+    let mut pal = format!("JASC-PAL\r\n0100\r\n{}\r\n", colors.len());
+    for [r, g, b, _] in colors {
+        pal += &format!("{} {} {}\r\n", r, g, b);
+    }
+
+    pal
+}
+
+/// The colors of a JASC palette, as RGB
+pub fn read_jasc_pal(text: &str) -> Result<Vec<[u8; 3]>> {
+    let syntax = |line, reason| Error::PaletteSyntax { line, reason };
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .map(|(i, l)| (i + 1, l));
+    let mut next = |reason| lines.next().ok_or(syntax(0, reason));
+
+    let (line, magic) = next("expected the JASC-PAL header")?;
+    ensure!(
+        magic == "JASC-PAL",
+        syntax(line, "expected the JASC-PAL header")
+    );
+    next("expected the version")?;
+    let (line, count) = next("expected the number of colors")?;
+    let count = count
+        .parse::<usize>()
+        .map_err(|_| syntax(line, "expected the number of colors"))?;
+    let mut colors = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (line, color) = next("fewer colors than its count")?;
+        let channels = color
+            .split_whitespace()
+            .map(str::parse::<u8>)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|c| c.len() == 3)
+            .ok_or(syntax(line, "expected a red, green, and blue of 0 to 255"))?;
+        colors.push([channels[0], channels[1], channels[2]]);
+    }
+
+    Ok(colors)
+}
+
+/// Read a PNG as 8-bit RGBA, whatever its color type and depth
+#[cfg(feature = "std")]
+pub fn read_png(data: &[u8]) -> Result<Image> {
+    use anyhow::Context;
+
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(
+        png::Transformations::EXPAND | png::Transformations::ALPHA | png::Transformations::STRIP_16,
+    );
+    let mut reader = decoder.read_info().context("reading PNG header")?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).context("reading PNG data")?;
+    buf.truncate(frame.buffer_size());
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        other => anyhow::bail!("unexpected PNG color type {:?}", other),
+    };
+
+    Ok(Image {
+        width: frame.width as usize,
+        height: frame.height as usize,
+        pixels,
+    })
+}
+
+/// Encode `image` as a PNG
+#[cfg(feature = "std")]
+pub fn png(image: &Image) -> Result<Vec<u8>> {
+    use anyhow::Context;
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().context("writing PNG header")?;
+    writer
+        .write_image_data(&image.pixels)
+        .context("writing PNG data")?;
+    writer.finish().context("writing PNG")?;
+
+    Ok(out)
+}
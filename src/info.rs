@@ -0,0 +1,175 @@
+use crate::{
+    cli::InfoOpt,
+    list,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use serde::{Serialize, Serializer};
+use ssbfile::{classify::FileKind, rom_info::N64Header, RelocInfo, TableFile};
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+pub(crate) fn entry(opt: InfoOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let mut out = io::stdout().lock();
+    if opt.header {
+        write!(out, "{}", Header(&N64Header::parse(&rom)?))?;
+    }
+    let Some(id) = opt.id else {
+        return Ok(());
+    };
+    let entry = timings
+        .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+
+    let names = opt.rom.name_db()?;
+    let info = EntryInfo {
+        rom_offset: version.table_end + entry.offset,
+        entry: &entry,
+        name: names.get(id),
+        kind: match opt.classify {
+            true => Some(list::kind(&entry, timings)?),
+            false => None,
+        },
+    };
+    if opt.json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&Json::new(&info))?)?;
+    } else {
+        write!(out, "{}", info)?;
+    }
+
+    Ok(())
+}
+
+struct EntryInfo<'a> {
+    rom_offset: usize,
+    entry: &'a TableFile<'a>,
+    /// the known name of the file
+    name: Option<&'a str>,
+    /// a guess at what the file holds, if it was classified
+    kind: Option<FileKind>,
+}
+
+impl EntryInfo<'_> {
+    fn external_files(&self) -> String {
+        self.entry
+            .includes()
+            .unwrap_or_default()
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for EntryInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reloc = |r: &Option<RelocInfo>| match r {
+            Some(r) => format!("{:#X}", r.get_starting_offset()),
+            None => "none".to_string(),
+        };
+        let e = self.entry;
+
+        match self.name {
+            Some(name) => writeln!(f, "# File {}: {}", e.id, name)?,
+            None => writeln!(f, "# File {}", e.id)?,
+        }
+        writeln!(f, "* rom offset:        {:#X}", self.rom_offset)?;
+        writeln!(f, "* table offset:      {:#X}", e.offset)?;
+        if let Some(kind) = self.kind {
+            writeln!(f, "* kind:              {}", kind)?;
+        }
+        writeln!(f, "* compressed:        {}", e.compressed)?;
+        writeln!(f, "* size:              {:#X}", e.raw.len())?;
+        writeln!(f, "* decompressed size: {:#X}", e.decompressed_size)?;
+        writeln!(f, "* internal relocs:   {}", reloc(&e.inreloc))?;
+        writeln!(f, "* external relocs:   {}", reloc(&e.exreloc))?;
+        writeln!(f, "* external files:    [{}]", self.external_files())
+    }
+}
+
+struct Header<'a>(&'a N64Header);
+
+impl fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let h = self.0;
+        let pi = h.pi_settings();
+        let (major, minor, revision) = h.libultra_version();
+
+        writeln!(f, "# {} ({})", h.title(), h.game_code())?;
+        writeln!(f, "* rom version:       {}", h.rom_version())?;
+        writeln!(
+            f,
+            "* crc:               {:08X} {:08X}",
+            h.crc().0,
+            h.crc().1
+        )?;
+        writeln!(f, "* boot address:      {:#010X}", h.boot_address())?;
+        writeln!(f, "* clock rate:        {:#X}", h.clock_rate())?;
+        writeln!(
+            f,
+            "* libultra:          {}.{}{}",
+            major,
+            minor,
+            revision.map(String::from).unwrap_or_default()
+        )?;
+        writeln!(
+            f,
+            "* pi timings:        latency {:#X}, pulse width {:#X}, page size {:#X}, release {:#X}",
+            pi.latency, pi.pulse_width, pi.page_size, pi.release
+        )?;
+        match h.cic() {
+            Some(cic) => writeln!(f, "* cic:               {}", cic.name())?,
+            None => writeln!(f, "* cic:               unknown bootcode")?,
+        }
+        writeln!(f)
+    }
+}
+
+/// The json of an entry, with the same fields as its text
+#[derive(Serialize)]
+struct Json<'a> {
+    id: usize,
+    name: Option<&'a str>,
+    rom_offset: usize,
+    table_offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    compressed: bool,
+    size: usize,
+    decompressed_size: usize,
+    internal_relocs: Option<usize>,
+    external_relocs: Option<usize>,
+    external_files: &'a [u16],
+}
+
+impl<'a> Json<'a> {
+    fn new(info: &'a EntryInfo) -> Self {
+        let e = info.entry;
+        Self {
+            id: e.id,
+            name: info.name,
+            rom_offset: info.rom_offset,
+            table_offset: e.offset,
+            kind: info.kind.map(|kind| kind.to_string()),
+            compressed: e.compressed,
+            size: e.raw.len(),
+            decompressed_size: e.decompressed_size,
+            internal_relocs: e.inreloc.as_ref().map(RelocInfo::get_starting_offset),
+            external_relocs: e.exreloc.as_ref().map(RelocInfo::get_starting_offset),
+            external_files: e.includes().unwrap_or_default(),
+        }
+    }
+}
+
+/// Named values that are serialized as a json object in their order,
+/// rather than sorted like a map
+pub(crate) struct Ordered<T>(pub(crate) Vec<(&'static str, T)>);
+
+impl<T: Serialize> Serialize for Ordered<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
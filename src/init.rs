@@ -0,0 +1,99 @@
+use crate::{
+    cli::{DataFormat, ExtractOpt, FileTable, InitOpt, Mode, RelocFormat},
+    extract,
+    template::NameTemplate,
+    timings::Timings,
+};
+use anyhow::{ensure, Context, Result};
+use serde::Serialize;
+use std::{fs, path::PathBuf};
+
+const CONFIG: &str = "ssbfile.toml";
+const MANIFEST: &str = "manifest.toml";
+const FILES: &str = "files";
+const OUTPUT: &str = "build/ssb64.z64";
+const GITIGNORE: &str = "\
+# the rom can't be shared, so keep it and the built roms out of the repository
+*.z64
+*.v64
+*.n64
+/build/
+";
+
+/// Where a project's rom comes from, and where it is built to
+#[derive(Debug, Serialize)]
+struct ProjectConfig {
+    /// the rom that the files were extracted from
+    rom: PathBuf,
+    manifest: PathBuf,
+    output: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table_start: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table_end: Option<u32>,
+}
+
+pub(crate) fn project(opt: InitOpt, timings: &mut Timings) -> Result<()> {
+    let dir = &opt.dir;
+    let empty = match fs::read_dir(dir) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => true,
+    };
+    ensure!(empty, "project directory <{}> isn't empty", dir.display());
+    for sub in [FILES, "build"] {
+        fs::create_dir_all(dir.join(sub))
+            .with_context(|| format!("creating project directory <{}>", dir.display()))?;
+    }
+
+    // the manifest is written into the project directory, with paths into the files directory
+    let extract = ExtractOpt {
+        rom: opt.rom.clone(),
+        output: Some(dir.clone()),
+        all: true,
+        with_deps: false,
+        emit_relocs: false,
+        reloc_format: RelocFormat::Text,
+        mode: Mode::Decompressed,
+        format: DataFormat::Binary,
+        symbol: None,
+        base_addr: 0,
+        ids: Vec::new(),
+        table: FileTable::Resource,
+        dry_run: false,
+        archive: None,
+        name_template: format!("{}/{}", FILES, NameTemplate::DEFAULT).parse()?,
+        jobs: None,
+    };
+    extract::data(extract, timings)?;
+
+    let rom = fs::canonicalize(&opt.rom.path)
+        .with_context(|| format!("finding rom <{}>", opt.rom.path.display()))?;
+    let config = ProjectConfig {
+        rom: rom.clone(),
+        manifest: PathBuf::from(MANIFEST),
+        output: PathBuf::from(OUTPUT),
+        table_start: opt.rom.table_start,
+        table_end: opt.rom.table_end,
+    };
+    let table = match opt.rom.table_start.zip(opt.rom.table_end) {
+        Some((start, end)) => format!(" --table-start {:#X} --table-end {:#X}", start, end),
+        None => String::new(),
+    };
+    let config = format!(
+        "# build the project with\n#   ssbfile build -r {}{} -m {} -o {}\n{}",
+        rom.display(),
+        table,
+        MANIFEST,
+        OUTPUT,
+        toml::to_string(&config).context("serializing project config")?
+    );
+    fs::write(dir.join(CONFIG), config).context("writing project config")?;
+    fs::write(dir.join(".gitignore"), GITIGNORE).context("writing .gitignore")?;
+
+    eprintln!(
+        "created project <{}>; edit the files in {}/, and build them with `ssbfile build` or `ssbfile watch`",
+        dir.display(),
+        FILES
+    );
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use crate::{
+    cli::{CompressOpt, InjectOpt},
+    manifest::Manifest,
+    timings::{Stage, Timings},
+};
+use anyhow::{ensure, Context, Result};
+use ssbfile::{versions::SSBInfo, CompressSettings, RelocHeads, Relocation, TableEntry, TableFile};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub(crate) fn file(opt: InjectOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let original_rom = opt.patch.any().then(|| rom.clone());
+    let original = timings
+        .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+
+    let path = data_path(&opt, &original)?;
+    let mut data =
+        fs::read(&path).with_context(|| format!("issue opening <{}>", path.display()))?;
+    let heads = opt
+        .relocs
+        .as_deref()
+        .map(|relocs| link_relocations(&mut data, relocs, opt.base_addr))
+        .transpose()
+        .with_context(|| format!("rebuilding the relocations of <{}>", path.display()))?;
+    let mut entry = replace(&mut rom, &version, opt.id, &data, &opt.compress, timings)
+        .with_context(|| format!("injecting <{}> as file <{}>", path.display(), opt.id))?;
+    if let Some(heads) = &heads {
+        entry = ssbfile::set_relocations(&mut rom, &version, opt.id, heads)?;
+    }
+    remap_externs(&mut rom, &version, &opt.remap_externs)?;
+
+    if opt.dry_run {
+        eprintln!(
+            "would write <{}> with file {} at {:#X}: {:#X} bytes stored, {:#X} bytes decompressed",
+            opt.output.display(),
+            opt.id,
+            version.table_end + entry.offset as usize,
+            entry.size as usize * 4,
+            entry.decompressed_size as usize * 4,
+        );
+        return Ok(());
+    }
+    // a rom with a changed header gets its crcs recalculated along with it
+    if opt.header.apply(&mut rom)? || opt.out.fix_crc {
+        ssbfile::rom_info::fix_crc(&mut rom)?;
+    }
+    if let Some(original) = &original_rom {
+        opt.patch.write(original, &rom, timings)?;
+    }
+
+    opt.out.backup.write(&opt.output, &rom, timings)
+}
+
+/// The file to inject: the file given, or the path from the manifest
+fn data_path(opt: &InjectOpt, original: &TableFile) -> Result<PathBuf> {
+    let Some(manifest_path) = &opt.manifest else {
+        return Ok(opt.file.clone().expect("file or manifest is required"));
+    };
+    let manifest = Manifest::read(manifest_path)?;
+    let file = manifest.file(opt.id).with_context(|| {
+        format!(
+            "file <{}> isn't in manifest <{}>",
+            opt.id,
+            manifest_path.display()
+        )
+    })?;
+    // only the data of a file can be injected; the table entry stays the same
+    ensure!(
+        file.matches_entry(original),
+        "the compression or relocations of file <{}> differ from the rom; use `build` instead",
+        opt.id
+    );
+
+    let root = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    Ok(opt.file.clone().unwrap_or_else(|| root.join(&file.path)))
+}
+
+/// Replace the data of file `id` with the decompressed `data`, compressing it if the
+/// original was compressed, and return the new table entry
+pub(crate) fn replace(
+    rom: &mut Vec<u8>,
+    version: &SSBInfo,
+    id: usize,
+    data: &[u8],
+    compress: &CompressOpt,
+    timings: &mut Timings,
+) -> Result<TableEntry> {
+    let original = TableFile::get(id, rom, version)?;
+    let (stored, compressed) = if original.compressed {
+        let capacity = ssbfile::slot_capacity(rom, version, id)?;
+        timings.time(Stage::Compress, || {
+            recompress(compress, &original, data, capacity)
+        })?
+    } else {
+        (data.to_vec(), false)
+    };
+
+    let len = rom.len();
+    let mut entry = ssbfile::inject_resized(rom, version, id, &stored, data.len())?;
+    if compressed != entry.compressed {
+        entry = ssbfile::set_compressed(rom, version, id, compressed)?;
+    }
+    if rom.len() > len {
+        eprintln!(
+            "file {} didn't fit in its slot; the rom grew from {:#X} to {:#X} bytes",
+            id,
+            len,
+            rom.len()
+        );
+    }
+
+    Ok(entry)
+}
+
+/// The decompressed data of file `id`, and the pointers of its relocation chains
+pub(crate) fn read_relocated(
+    rom: &[u8],
+    version: &SSBInfo,
+    id: usize,
+    timings: &mut Timings,
+) -> Result<(Vec<u8>, Vec<Relocation>)> {
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(id, rom, version))
+        .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", id))?;
+    let relocs = timings
+        .time(Stage::Relocate, || ssbfile::relocations(&data, &file))
+        .with_context(|| format!("reading the relocations of file <{}>", id))?;
+
+    Ok((data.into_owned(), relocs))
+}
+
+/// Change the decompressed data of file `id` with `edit`, which moves any pointers that it
+/// moves in `relocs`, and put it back: the pointers are linked into relocation chains
+/// again, and the data is compressed like the original. Returns what `edit` returns.
+pub(crate) fn edit_file<T>(
+    rom: &mut Vec<u8>,
+    version: &SSBInfo,
+    id: usize,
+    compress: &CompressOpt,
+    timings: &mut Timings,
+    edit: impl FnOnce(&mut Vec<u8>, &mut Vec<Relocation>) -> Result<T>,
+) -> Result<T> {
+    let (mut data, mut relocs) = read_relocated(rom, version, id, timings)?;
+    let edited = edit(&mut data, &mut relocs)?;
+
+    // the pointers that the edits overlap are written back over them
+    let heads = ssbfile::unrelocate(&mut data, &relocs)
+        .with_context(|| format!("rebuilding the relocations of file <{}>", id))?;
+    replace(rom, version, id, &data, compress, timings)
+        .with_context(|| format!("injecting file <{}>", id))?;
+    ssbfile::set_relocations(rom, version, id, &heads)?;
+
+    Ok(edited)
+}
+
+/// Rewrite the external file ids of the files of `rom` from the `OLD=NEW` pairs of `remap`
+pub(crate) fn remap_externs(rom: &mut [u8], version: &SSBInfo, remap: &[(u16, u16)]) -> Result<()> {
+    if remap.is_empty() {
+        return Ok(());
+    }
+    let map = remap.iter().copied().collect::<BTreeMap<_, _>>();
+    let rewritten =
+        ssbfile::remap_externs(rom, version, &map).context("remapping external file ids")?;
+    eprintln!("remapped {} external file ids", rewritten);
+
+    Ok(())
+}
+
+/// Turn the pointers listed in the csv at `relocs` back into the relocation chains of
+/// `data`, and return the heads of the chains and the list of external files
+fn link_relocations(data: &mut [u8], relocs: &Path, base: u32) -> Result<RelocHeads> {
+    let csv = fs::read_to_string(relocs)
+        .with_context(|| format!("issue opening <{}>", relocs.display()))?;
+    let offsets = ssbfile::pointer_offsets_from_csv(&csv)
+        .with_context(|| format!("reading relocations from <{}>", relocs.display()))?;
+    let resolved = ssbfile::resolved_relocations(data, base, &offsets.internal, &offsets.external)?;
+
+    ssbfile::unrelocate(data, &resolved)
+}
+
+/// Compress `data` to replace the compressed file `original`, and return the data to store
+/// and whether it is compressed. Without any vpk0 options, other settings are tried when
+/// the original's settings don't fit the file's slot, and the data is stored uncompressed
+/// if that is smaller; the game reads uncompressed files just as well.
+fn recompress(
+    compress: &CompressOpt,
+    original: &TableFile,
+    data: &[u8],
+    capacity: usize,
+) -> Result<(Vec<u8>, bool)> {
+    let words = |len: usize| len.next_multiple_of(4);
+    let settings = compress.settings(Some(original.raw))?;
+    let stored = match ssbfile::compress_with(data, original.id, &settings) {
+        Err(e) if !compress.is_set() => {
+            eprintln!("{:#}; storing it without vpk0", e);
+            return Ok((data.to_vec(), false));
+        }
+        stored => stored?,
+    };
+    if compress.is_set() || words(stored.len()) <= capacity {
+        return Ok((stored, true));
+    }
+
+    let candidates = CompressSettings::candidates().filter(|c| *c != settings);
+    let (tuned, smallest) = ssbfile::compress_smallest(data, original.id, candidates)?;
+    let stored = if smallest.len() < stored.len() {
+        eprintln!(
+            "file {} is smaller with vpk0 method {}, and a {} bit window: {:#X} bytes",
+            original.id,
+            tuned.method as u8,
+            tuned.window_bits,
+            smallest.len()
+        );
+        smallest
+    } else {
+        stored
+    };
+
+    if words(data.len()) <= words(stored.len()) {
+        eprintln!(
+            "file {} is smaller uncompressed, and is stored without vpk0",
+            original.id
+        );
+        return Ok((data.to_vec(), false));
+    }
+    Ok((stored, true))
+}
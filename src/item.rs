@@ -0,0 +1,191 @@
+//! Item data: the attributes of an item (the offsets of its model in its file, and its
+//! hitbox), and the table of spawn weights of the common items.
+//!
+//! The attributes are the decompilation's `ITAttributes`, as far as its fields have been
+//! mapped: words of bit fields, laid out in [`ATTRIBUTES`]. The spawn weights are a byte
+//! for each item kind that can spawn, in the order of [`ITEMS`]; an item with a weight of
+//! 0 never spawns.
+use crate::{
+    moveset::{field, signed, Field},
+    Error,
+};
+use alloc::{string::String, vec::Vec};
+use anyhow::Result;
+
+/// The item kinds that spawn, in the order of their ids and spawn weights
+pub const ITEMS: [&str; 20] = [
+    "box",
+    "barrel",
+    "capsule",
+    "egg",
+    "maxim_tomato",
+    "heart",
+    "star",
+    "beam_sword",
+    "home_run_bat",
+    "fan",
+    "star_rod",
+    "ray_gun",
+    "fire_flower",
+    "hammer",
+    "motion_sensor_bomb",
+    "bob_omb",
+    "bumper",
+    "green_shell",
+    "red_shell",
+    "poke_ball",
+];
+
+/// The fields of the attributes of an item
+pub const ATTRIBUTES: &[Field] = &[
+    // offsets of the parts of the item's model in its file
+    field("dobj_setup", 2, 0, 32),
+    field("mobj_sub", 3, 0, 32),
+    field("anim_joint", 4, 0, 32),
+    field("matanim_joint", 5, 0, 32),
+    signed("hit_offset_x", 7, 16, 16),
+    signed("hit_offset_y", 7, 0, 16),
+    signed("hit_offset_z", 8, 16, 16),
+    field("hit_size", 12, 16, 16),
+    field("angle", 13, 22, 10),
+    field("knockback_scale", 13, 12, 10),
+    field("damage", 13, 4, 8),
+    field("element", 13, 0, 4),
+    field("knockback_weight", 14, 22, 10),
+    signed("shield_damage", 14, 14, 8),
+    field("hitbox_count", 14, 12, 2),
+    field("hit_sfx", 14, 0, 10),
+    field("priority", 15, 29, 3),
+    field("knockback_base", 15, 0, 10),
+    field("spin_speed", 16, 16, 16),
+];
+
+/// The size of the attributes, in words
+pub const ATTRIBUTE_WORDS: usize = 17;
+
+/// The attributes of an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attributes {
+    /// offset of the attributes in the decompressed file
+    pub offset: usize,
+    pub words: Vec<u32>,
+}
+
+impl Attributes {
+    /// Read the attributes at `offset` of `file`
+    pub fn read(file: &[u8], offset: usize) -> Result<Self> {
+        let words = bytes(file, offset, ATTRIBUTE_WORDS * 4)?
+            .chunks_exact(4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+
+        Ok(Self { offset, words })
+    }
+
+    /// The value of each field, in the order of [`ATTRIBUTES`]
+    pub fn fields(&self) -> Vec<(&'static str, i64)> {
+        ATTRIBUTES
+            .iter()
+            .map(|f| {
+                let value = f.get(&self.words);
+                // the 32 bit offsets aren't signed
+                let value = if f.bits == 32 {
+                    value as u32 as i64
+                } else {
+                    value as i64
+                };
+                (f.name, value)
+            })
+            .collect()
+    }
+
+    /// Set the field `name` to `value`
+    pub fn set(&mut self, name: &str, value: i64) -> Result<()> {
+        let field = ATTRIBUTES
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| Error::UnknownItemField { name: name.into() })?;
+        if !field.set(&mut self.words, value) {
+            return Err(Error::ItemValue {
+                name: field.name,
+                value,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Write the attributes back into `file`
+    pub fn write(&self, file: &mut [u8]) -> Result<()> {
+        bytes(file, self.offset, self.words.len() * 4)?;
+        for (i, word) in self.words.iter().enumerate() {
+            let at = self.offset + i * 4;
+            file[at..at + 4].copy_from_slice(&word.to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+/// The spawn weights of the items of [`ITEMS`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weights {
+    /// offset of the weights in the decompressed file
+    pub offset: usize,
+    pub values: [u8; ITEMS.len()],
+}
+
+impl Weights {
+    /// Read the weights at `offset` of `file`
+    pub fn read(file: &[u8], offset: usize) -> Result<Self> {
+        let mut values = [0; ITEMS.len()];
+        values.copy_from_slice(bytes(file, offset, ITEMS.len())?);
+
+        Ok(Self { offset, values })
+    }
+
+    /// The weight of each item, in the order of [`ITEMS`]
+    pub fn items(&self) -> impl Iterator<Item = (&'static str, u8)> + '_ {
+        ITEMS.iter().copied().zip(self.values.iter().copied())
+    }
+
+    /// Set the weight of the item `name` to `value`
+    pub fn set(&mut self, name: &str, value: i64) -> Result<()> {
+        let i =
+            ITEMS
+                .iter()
+                .position(|&item| item == name)
+                .ok_or_else(|| Error::UnknownItemField {
+                    name: String::from("weight.") + name,
+                })?;
+        self.values[i] = u8::try_from(value).map_err(|_| Error::ItemValue {
+            name: ITEMS[i],
+            value,
+        })?;
+
+        Ok(())
+    }
+
+    /// Write the weights back into `file`
+    pub fn write(&self, file: &mut [u8]) -> Result<()> {
+        bytes(file, self.offset, self.values.len())?;
+        file[self.offset..self.offset + self.values.len()].copy_from_slice(&self.values);
+
+        Ok(())
+    }
+}
+
+/// The `len` bytes at `offset` of `file`
+fn bytes(file: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    file.get(offset..offset + len).ok_or_else(|| {
+        Error::OutsideFile {
+            start: offset,
+            end: offset + len,
+            len: file.len(),
+        }
+        .into()
+    })
+}
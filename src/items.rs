@@ -0,0 +1,122 @@
+use crate::{
+    cli::ItemOpt,
+    info::Ordered,
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use ssbfile::{
+    item::{Attributes, Weights},
+    TableFile,
+};
+
+pub(crate) fn data(opt: ItemOpt, timings: &mut Timings) -> Result<()> {
+    if opt.set.is_empty() {
+        print(&opt, timings)
+    } else {
+        edit(&opt, timings)
+    }
+}
+
+fn print(opt: &ItemOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", opt.id))?;
+
+    let attributes = opt
+        .attributes
+        .map(|offset| {
+            let attributes = Attributes::read(&data, offset as usize)
+                .with_context(|| format!("reading the item attributes at {:#X}", offset))?;
+            anyhow::Ok(Table {
+                offset,
+                values: Ordered(attributes.fields()),
+            })
+        })
+        .transpose()?;
+    let weights = opt
+        .weights
+        .map(|offset| {
+            let weights = Weights::read(&data, offset as usize)
+                .with_context(|| format!("reading the item weights at {:#X}", offset))?;
+            anyhow::Ok(Table {
+                offset,
+                values: Ordered(weights.items().map(|(name, w)| (name, w as i64)).collect()),
+            })
+        })
+        .transpose()?;
+    let json = Json {
+        file: opt.id,
+        attributes,
+        weights,
+    };
+    println!("{}", serde_json::to_string(&json)?);
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Json {
+    file: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<Table>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weights: Option<Table>,
+}
+
+/// The values of a table of the file, after its offset
+#[derive(Serialize)]
+struct Table {
+    offset: u32,
+    #[serde(flatten)]
+    values: Ordered<i64>,
+}
+
+fn edit(opt: &ItemOpt, timings: &mut Timings) -> Result<()> {
+    let output = opt.output.as_ref().expect("output is required with set");
+    let (mut rom, version) = opt.rom.read(timings)?;
+    inject::edit_file(
+        &mut rom,
+        &version,
+        opt.id,
+        &opt.compress,
+        timings,
+        |data, _| {
+            let mut attributes = opt
+                .attributes
+                .map(|offset| Attributes::read(data, offset as usize))
+                .transpose()
+                .context("reading the item attributes")?;
+            let mut weights = opt
+                .weights
+                .map(|offset| Weights::read(data, offset as usize))
+                .transpose()
+                .context("reading the item weights")?;
+            for (name, value) in &opt.set {
+                match (name.strip_prefix("weight."), &mut weights, &mut attributes) {
+                    (Some(item), Some(weights), _) => weights.set(item, *value)?,
+                    (Some(_), None, _) => bail!("give the --weights offset to set <{}>", name),
+                    (None, _, Some(attributes)) => attributes.set(name, *value)?,
+                    (None, _, None) => bail!("give the --attributes offset to set <{}>", name),
+                }
+            }
+
+            if let Some(attributes) = &attributes {
+                attributes.write(data)?;
+            }
+            if let Some(weights) = &weights {
+                weights.write(data)?;
+            }
+
+            Ok(())
+        },
+    )?;
+    eprintln!("changed {} item values of file {}", opt.set.len(), opt.id);
+
+    opt.out.write(output, &mut rom, timings)
+}
@@ -0,0 +1,122 @@
+//! Menu layouts: where the sprites of a menu file are drawn.
+//!
+//! The menu screens are put together by code, but the libultra `Sprite` structs in
+//! their files carry the placement of each sprite: its screen position, depth, color,
+//! and drawing flags (`attr`). Those are what this module reads and writes; the
+//! textures themselves are handled by [`image`](crate::image). The text on the menus is
+//! drawn glyph by glyph from the font files by code, so the files hold no text ids.
+use crate::{
+    image::{self, TexelFormat},
+    Error, Relocation,
+};
+use alloc::vec::Vec;
+use anyhow::Result;
+
+/// the offsets of the placement in a libultra `Sprite` struct
+const SPRITE_X: usize = 0x00;
+const SPRITE_Y: usize = 0x02;
+const SPRITE_ATTR: usize = 0x14;
+const SPRITE_ZDEPTH: usize = 0x16;
+const SPRITE_COLOR: usize = 0x18;
+
+/// The `SP_*` flags of a sprite's `attr`, by name
+pub const ATTRIBUTES: [(&str, u16); 11] = [
+    ("transparent", 0x0001),
+    ("cutout", 0x0002),
+    ("hidden", 0x0004),
+    ("z", 0x0008),
+    ("scale", 0x0010),
+    ("fastcopy", 0x0020),
+    ("overlap", 0x0040),
+    ("texshift", 0x0080),
+    ("fracpos", 0x0100),
+    ("texshuf", 0x0200),
+    ("extern", 0x0400),
+];
+
+/// Where and how a sprite of a menu file is drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Placement {
+    /// offset of the `Sprite` struct in the file
+    pub offset: usize,
+    pub x: i16,
+    pub y: i16,
+    /// the `SP_*` flags, see [`ATTRIBUTES`]
+    pub attr: u16,
+    pub zdepth: i16,
+    /// the RGBA color that the sprite is shaded with
+    pub color: [u8; 4],
+    pub width: usize,
+    pub height: usize,
+    pub format: TexelFormat,
+}
+
+impl Placement {
+    /// The names of the flags of `attr` that are set
+    pub fn attributes(&self) -> Vec<&'static str> {
+        ATTRIBUTES
+            .iter()
+            .filter(|&&(_, bit)| self.attr & bit != 0)
+            .map(|&(name, _)| name)
+            .collect()
+    }
+
+    /// Set the flags of `attr` to those in `names`, keeping any bits without a name
+    pub fn set_attributes<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let known = ATTRIBUTES.iter().fold(0, |mask, &(_, bit)| mask | bit);
+        let mut attr = self.attr & !known;
+        for name in names {
+            let &(_, bit) = ATTRIBUTES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| Error::UnknownSpriteAttribute { name: name.into() })?;
+            attr |= bit;
+        }
+        self.attr = attr;
+
+        Ok(())
+    }
+
+    /// Write the placement back into the `Sprite` struct in `file`
+    pub fn write(&self, file: &mut [u8]) -> Result<()> {
+        let end = self.offset + SPRITE_COLOR + 4;
+        let len = file.len();
+        let sprite = file.get_mut(self.offset..end).ok_or(Error::OutsideFile {
+            start: self.offset,
+            end,
+            len,
+        })?;
+        sprite[SPRITE_X..SPRITE_X + 2].copy_from_slice(&self.x.to_be_bytes());
+        sprite[SPRITE_Y..SPRITE_Y + 2].copy_from_slice(&self.y.to_be_bytes());
+        sprite[SPRITE_ATTR..SPRITE_ATTR + 2].copy_from_slice(&self.attr.to_be_bytes());
+        sprite[SPRITE_ZDEPTH..SPRITE_ZDEPTH + 2].copy_from_slice(&self.zdepth.to_be_bytes());
+        sprite[SPRITE_COLOR..SPRITE_COLOR + 4].copy_from_slice(&self.color);
+
+        Ok(())
+    }
+}
+
+/// The placements of the sprites of `file` that [`image::sprites`] finds
+pub fn layout(file: &[u8], relocs: &[Relocation]) -> Vec<Placement> {
+    let half = |at: usize| u16::from_be_bytes([file[at], file[at + 1]]);
+
+    image::sprites(file, relocs)
+        .into_iter()
+        .map(|sprite| {
+            let s = sprite.offset;
+            let color = &file[s + SPRITE_COLOR..s + SPRITE_COLOR + 4];
+            Placement {
+                offset: s,
+                x: half(s + SPRITE_X) as i16,
+                y: half(s + SPRITE_Y) as i16,
+                attr: half(s + SPRITE_ATTR),
+                zdepth: half(s + SPRITE_ZDEPTH) as i16,
+                color: [color[0], color[1], color[2], color[3]],
+                width: sprite.width,
+                height: sprite.height,
+                format: sprite.format,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,78 @@
+//! Read the relocatable resource files out of the SSB64 rom
+//!
+//! Without the default `std` feature, the crate is `no_std` (with `alloc`), and only has
+//! the rom and resource table parsing: vpk0 (de)compression and file io need `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod animation;
+pub mod asm;
+pub mod audio;
+pub mod c_array;
+pub mod character;
+pub mod classify;
+#[cfg(feature = "std")]
+mod compression;
+pub mod display_list;
+pub mod effect;
+pub mod elf;
+mod error;
+#[cfg(feature = "std")]
+mod externals;
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+pub mod fighter;
+#[cfg(feature = "std")]
+pub mod gltf;
+pub mod image;
+pub mod item;
+pub mod layout;
+#[cfg(feature = "std")]
+mod merged;
+pub mod mesh;
+pub mod mips;
+pub mod moveset;
+pub mod names;
+pub mod patch;
+mod rebuild;
+mod reloc;
+#[cfg(feature = "std")]
+mod rom;
+pub mod rom_info;
+pub mod skeleton;
+#[cfg(feature = "std")]
+mod source;
+pub mod stage;
+mod table;
+pub mod text;
+mod validate;
+pub mod versions;
+
+#[cfg(feature = "std")]
+pub use compression::{
+    compress, compress_smallest, compress_to, compress_with, decompress, decompress_from,
+    CompressSettings, Trees,
+};
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use externals::{remap_externs, ExternalRef, FileLoader};
+#[cfg(feature = "std")]
+pub use merged::MergedView;
+pub use rebuild::{
+    check_entry, file_ends, inject, inject_resized, pack_file, read_table, set_compressed,
+    set_relocations, shared_files, slot_capacity, table_bytes, write_entry, write_table,
+    DataRegion, TableEntry, TerminalEntry,
+};
+pub use reloc::{
+    apply_relocations, pointer_offsets_from_csv, relocate, relocations, resolved_relocations,
+    target_symbol, unrelocate, FileReloc, PointerOffsets, RelocHeads, Relocation, Relocations,
+};
+#[cfg(feature = "std")]
+pub use rom::SsbRom;
+#[cfg(feature = "std")]
+pub use source::{PartialRom, RomSource};
+pub use table::{RelocInfo, TableFile};
+pub use validate::{validate, Problem, Validation};
+#[cfg(feature = "std")]
+pub use vpk0::format::VpkMethod;
@@ -0,0 +1,102 @@
+use crate::{
+    cli::{FileTable, ListOpt, ListTable},
+    music,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{
+    audio::{self, BankFile, SeqBank},
+    classify::FileKind,
+    names::NameDb,
+    TableEntry, TableFile,
+};
+use std::io::{self, Write};
+
+const NO_RELOCS: u16 = 0xFFFF;
+
+pub(crate) fn table(opt: ListOpt, timings: &mut Timings) -> Result<()> {
+    let (source, version) = opt.rom.source(timings)?;
+    let table = match opt.table {
+        ListTable::Files(table) => table,
+        ListTable::Audio => return audio_tables(&source),
+    };
+    let rom = opt.rom.file_table(&source, version, table, timings)?;
+    // the names are of the files in the resource table
+    let names = match table {
+        FileTable::Resource => opt.rom.name_db()?,
+        FileTable::Secondary => NameDb::default(),
+    };
+
+    let mut out = io::stdout().lock();
+    let kind_column = if opt.classify { "  kind    " } else { "" };
+    writeln!(
+        out,
+        "  id  rom offset  vpk0      size  decompressed  inreloc  exreloc{}  name",
+        kind_column
+    )?;
+    for (id, entry) in rom.entries().iter().enumerate() {
+        let mut line = format_entry(id, entry, rom.version().table_end);
+        if opt.classify {
+            let file = timings
+                .time(Stage::TableParse, || {
+                    TableFile::get(id, &source, rom.version())
+                })
+                .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+            line += &format!("  {:8}", kind(&file, timings)?.name());
+        }
+        match names.get(id) {
+            Some(name) => writeln!(out, "{}  {}", line, name)?,
+            None => writeln!(out, "{}", line.trim_end())?,
+        }
+    }
+
+    Ok(())
+}
+
+/// List the sequence banks and bank files of `rom`, that are found by their headers
+fn audio_tables(rom: &[u8]) -> Result<()> {
+    let seq_banks = audio::find_seq_banks(rom);
+    let bank_files = audio::find_bank_files(rom);
+    if seq_banks.is_empty() && bank_files.is_empty() {
+        println!("found no sequence banks or bank files");
+    }
+    for offset in seq_banks {
+        let bank = SeqBank::read(rom, offset)?;
+        let indices = (0..bank.sequences.len()).collect::<Vec<_>>();
+        music::print_sequences(rom, &bank, &indices)?;
+    }
+    for offset in bank_files {
+        let banks = BankFile::read(rom, offset)?;
+        music::print_banks(&banks, music::tbl_offset(&banks));
+    }
+
+    Ok(())
+}
+
+fn format_entry(id: usize, entry: &TableEntry, table_end: usize) -> String {
+    let yes_no = |b| if b { "yes" } else { "no" };
+
+    format!(
+        "{:4}  {:#10X}  {:>4}  {:#8X}  {:#12X}  {:>7}  {:>7}",
+        id,
+        table_end + entry.offset as usize,
+        yes_no(entry.compressed),
+        entry.size as usize * 4,
+        entry.decompressed_size as usize * 4,
+        yes_no(entry.inreloc != NO_RELOCS),
+        yes_no(entry.exreloc != NO_RELOCS),
+    )
+}
+
+/// Guess what `file` holds from its decompressed data
+pub(crate) fn kind(file: &TableFile, timings: &mut Timings) -> Result<FileKind> {
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", file.id))?;
+    // a file with broken relocations can still be classified by its data
+    let relocs = timings
+        .time(Stage::Relocate, || ssbfile::relocations(&data, file))
+        .unwrap_or_default();
+
+    Ok(ssbfile::classify::classify(&data, &relocs))
+}
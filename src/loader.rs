@@ -0,0 +1,76 @@
+use crate::{
+    cli::CheckLoaderOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use ssbfile::{
+    mips::{check_loader, find_loader, Loader},
+    rom_info::N64Header,
+    TableFile,
+};
+
+/// RDRAM with the expansion pak
+const RAM_SIZE: usize = 0x80_0000;
+
+pub(crate) fn check(opt: CheckLoaderOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let ids = if opt.all {
+        (0..version.total_entries()).collect()
+    } else {
+        opt.ids.iter().flat_map(|r| r.ids()).collect::<Vec<_>>()
+    };
+
+    let start = opt.code_start as usize;
+    let code = rom
+        .get(start..start + opt.code_size as usize)
+        .context("the code of the loader is outside of the rom")?;
+    let code_addr = match opt.code_addr {
+        Some(addr) => addr,
+        None => N64Header::parse(&rom)?.boot_address(),
+    };
+    let mut loader = Loader {
+        code,
+        code_addr,
+        entry: opt.loader.unwrap_or(code_addr),
+        args: opt.args.iter().map(|a| a.0).collect(),
+        file_addr: opt.file_addr,
+        ram_size: RAM_SIZE,
+        max_steps: opt.max_steps,
+    };
+    if opt.loader.is_none() {
+        loader.entry = find_loader(&loader)
+            .context("the loader wasn't found in the code; give its address with --loader")?;
+        println!("found the loader at 0x{:08X}", loader.entry);
+    }
+
+    let mut diverged = 0;
+    for &id in &ids {
+        let entry = timings
+            .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let data = timings
+            .time(Stage::Decompress, || entry.decompressed())
+            .with_context(|| format!("decompressing file <{}>", id))?;
+        let mismatches = timings
+            .time(Stage::Relocate, || check_loader(&loader, &data, &entry))
+            .with_context(|| format!("running the loader over file <{}>", id))?;
+        if mismatches.is_empty() {
+            continue;
+        }
+
+        diverged += 1;
+        println!("{:4}  {} words differ", id, mismatches.len());
+        for m in mismatches.iter().take(8) {
+            println!(
+                "      0x{:06X}: loader {:08X}, relocated {:08X}",
+                m.offset, m.loader, m.relocated
+            );
+        }
+    }
+    println!("{} of {} files diverged", diverged, ids.len());
+
+    if diverged > 0 {
+        bail!("{} files were loaded differently", diverged);
+    }
+    Ok(())
+}
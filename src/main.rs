@@ -1,57 +1,119 @@
 use anyhow::Result;
-use std::{path::PathBuf, str::FromStr};
+use cli::{Command, EntryCmd, Opt};
+use std::io;
 use structopt::StructOpt;
+use timings::Timings;
 
+mod addr;
+mod anim;
+mod bones;
+mod build;
+mod characters;
+mod cli;
+mod diff;
+mod entry;
 mod extract;
-mod versions;
+mod fighters;
+mod gameshark;
+mod gfx;
+mod ground;
+mod info;
+mod init;
+mod inject;
+mod items;
+mod list;
+mod loader;
+mod manifest;
+mod menu;
+mod merge;
+mod messages;
+mod model;
+mod music;
+mod particles;
+mod roundtrip;
+mod script;
+mod search;
+mod segments;
+mod ssbmod;
+mod stats;
+mod template;
+mod texture;
+mod timings;
+mod undo;
+mod verify;
+mod watch;
 
-/// A quick utility to export the relocatable data from SSB64
-#[derive(Debug, StructOpt)]
-struct Opt {
-    /// path to SSB64 rom
-    #[structopt(short, long, parse(from_os_str))]
-    rom: PathBuf,
-    /// output for exported file, or file-id if not present
-    #[structopt(short, long, parse(from_os_str))]
-    output: Option<PathBuf>,
-    /// emit the location and values of the internal and external relocations
-    #[structopt(short, long)]
-    emit_relocs: bool,
-    /// three ways to export a file: raw, decompress, or reloc
-    ///
-    /// raw          export the raw data
-    ///
-    /// decompress   decompress the data, if necessary
-    ///
-    /// reloc        calculate the relocations (based on a base address of 0)
-    #[structopt(default_value = "reloc", short, long, parse(try_from_str))]
-    mode: Mode,
-    /// file id to export
-    id: usize,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Mode {
-    RawBytes,
-    Decompressed,
-    Relocated,
+fn main() -> Result<()> {
+    match run() {
+        // the output was piped into something that stopped reading it, like `head`
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        result => result,
+    }
 }
 
-impl FromStr for Mode {
-    type Err = anyhow::Error;
+fn run() -> Result<()> {
+    let opt = Opt::from_args();
+    let mut timings = Timings::default();
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "raw" | "bytes" => Ok(Self::RawBytes),
-            "decompress" => Ok(Self::Decompressed),
-            "reloc" | "full" => Ok(Self::Relocated),
-            _ => Err(anyhow::anyhow!("Unknown mode <{}>", s)),
-        }
+    match opt.cmd {
+        Command::Extract(extract) => extract::data(extract, &mut timings)?,
+        Command::List(list) => list::table(list, &mut timings)?,
+        Command::Info(info) => info::entry(info, &mut timings)?,
+        Command::Inject(inject) => inject::file(inject, &mut timings)?,
+        Command::Build(build) => build::rom(build, &mut timings)?,
+        Command::Diff(diff) => diff::tables(diff, &mut timings)?,
+        Command::Merge(merge) => merge::roms(merge, &mut timings)?,
+        Command::Search(search) => search::files(search, &mut timings)?,
+        Command::Addr(addr) => addr::lookup(addr, &mut timings)?,
+        Command::Stats(stats) => stats::table(stats, &mut timings)?,
+        Command::Verify(verify) => verify::rom(verify, &mut timings)?,
+        Command::Gameshark(gameshark) => gameshark::codes(gameshark, &mut timings)?,
+        Command::Roundtrip(roundtrip) => roundtrip::files(roundtrip, &mut timings)?,
+        Command::Watch(watch) => watch::project(watch, &mut timings)?,
+        Command::Init(init) => init::project(init, &mut timings)?,
+        Command::Segments(segments) => segments::yaml(segments, &mut timings)?,
+        Command::Entry(EntryCmd::Set(set)) => entry::set(set, &mut timings)?,
+        Command::PackMod(pack) => ssbmod::pack(pack, &mut timings)?,
+        Command::ApplyMod(apply) => ssbmod::apply(apply, &mut timings)?,
+        Command::Undo(undo) => undo::restore(undo)?,
+        Command::Texture(texture) => texture::export(texture, &mut timings)?,
+        Command::ImportTexture(texture) => texture::import(texture, &mut timings)?,
+        Command::Palette(palette) => texture::palettes(palette, &mut timings)?,
+        Command::ImportPalette(palette) => texture::import_palette(palette, &mut timings)?,
+        Command::Gfx(gfx) => gfx::display_lists(gfx, &mut timings)?,
+        Command::Character(character) => characters::print(character, &mut timings)?,
+        Command::Model(model) => model::export(model, &mut timings)?,
+        Command::Script(script) => script::disassemble(script, &mut timings)?,
+        Command::ImportScript(script) => script::import(script, &mut timings)?,
+        Command::Hitboxes(hitboxes) => script::hitboxes(hitboxes, &mut timings)?,
+        Command::Stage(stage) => ground::report(stage, &mut timings)?,
+        Command::Font(font) => texture::font(font, &mut timings)?,
+        Command::Text(text) => messages::extract(text, &mut timings)?,
+        Command::ImportText(text) => messages::import(text, &mut timings)?,
+        Command::Music(music) => music::sequences(music, &mut timings)?,
+        Command::Samples(samples) => music::samples(samples, &mut timings)?,
+        Command::Item(item) => items::data(item, &mut timings)?,
+        Command::Anim(anim) => anim::disassemble(anim, &mut timings)?,
+        Command::ImportAnim(anim) => anim::import(anim, &mut timings)?,
+        Command::Skeleton(skeleton) => bones::dump(skeleton, &mut timings)?,
+        Command::Layout(layout) => menu::print(layout, &mut timings)?,
+        Command::ImportLayout(layout) => menu::import(layout, &mut timings)?,
+        Command::Effect(effect) => particles::disassemble(effect, &mut timings)?,
+        Command::Bonus(bonus) => ground::bonus(bonus, &mut timings)?,
+        Command::Fighter(fighter) => fighters::print(fighter, &mut timings)?,
+        Command::ImportFighter(fighter) => fighters::import(fighter, &mut timings)?,
+        Command::CheckLoader(check) => loader::check(check, &mut timings)?,
+    }
+    if opt.timings {
+        eprint!("{}", timings);
     }
-}
 
-fn main() -> Result<()> {
-    let opt = Opt::from_args();
+    Ok(())
+}
 
-    extract::data(opt)
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::BrokenPipe)
 }
@@ -0,0 +1,100 @@
+use crate::diff::fnv1a;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use ssbfile::{RelocInfo, TableFile};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Describes the files of an extracted resource table, so that they can be built back into a rom
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Manifest {
+    /// the version of the rom that the files were extracted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<String>,
+    /// the `--mode` that the files were extracted with; only decompressed files can be built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mode: Option<String>,
+    #[serde(rename = "file", default)]
+    pub(crate) files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct ManifestFile {
+    pub(crate) id: usize,
+    /// path to the decompressed file data, relative to the manifest
+    pub(crate) path: PathBuf,
+    /// store the file vpk0 compressed
+    #[serde(default)]
+    pub(crate) compressed: bool,
+    /// offset of the first internal relocation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) inreloc: Option<u32>,
+    /// offset of the first external relocation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) exreloc: Option<u32>,
+    /// the file ids for each external relocation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) externs: Vec<u16>,
+    /// 64-bit FNV-1a hash of the data at `path` when it was extracted, in hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<String>,
+}
+
+impl Manifest {
+    /// The file with `id`
+    pub(crate) fn file(&self, id: usize) -> Option<&ManifestFile> {
+        self.files.iter().find(|file| file.id == id)
+    }
+
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("issue opening manifest <{}>", path.display()))?;
+
+        toml::from_str(&text).with_context(|| format!("parsing manifest <{}>", path.display()))
+    }
+
+    pub(crate) fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).context("serializing manifest")
+    }
+}
+
+impl ManifestFile {
+    /// Describe the file in table `entry`, whose `data` is exported to `path`
+    pub(crate) fn for_entry(entry: &TableFile, path: PathBuf, data: &[u8]) -> Self {
+        let offset = |r: &Option<RelocInfo>| r.as_ref().map(|r| r.get_starting_offset() as u32);
+
+        Self {
+            id: entry.id,
+            path,
+            compressed: entry.compressed,
+            inreloc: offset(&entry.inreloc),
+            exreloc: offset(&entry.exreloc),
+            externs: entry.includes().map(<[u16]>::to_vec).unwrap_or_default(),
+            hash: Some(hash(data)),
+        }
+    }
+
+    /// The file is described the same as the table `entry` of the rom:
+    /// the same compression, relocations, and external files
+    pub(crate) fn matches_entry(&self, entry: &TableFile) -> bool {
+        let rom = Self::for_entry(entry, self.path.clone(), &[]);
+
+        self.compressed == rom.compressed
+            && self.inreloc == rom.inreloc
+            && self.exreloc == rom.exreloc
+            && self.externs == rom.externs
+    }
+
+    /// The file's `data` is the same as when it was extracted, and it is
+    /// described the same as the table `entry` of the rom
+    pub(crate) fn unchanged(&self, entry: &TableFile, data: &[u8]) -> bool {
+        self.hash.as_ref() == Some(&hash(data)) && self.matches_entry(entry)
+    }
+}
+
+/// The hash of file data that is recorded in a manifest
+fn hash(data: &[u8]) -> String {
+    format!("{:016x}", fnv1a(data))
+}
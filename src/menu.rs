@@ -0,0 +1,123 @@
+//! Menu layouts as toml: a `[[sprite]]` for each sprite of the file, like
+//! ```toml
+//! [[sprite]]
+//! offset = "0x0040"
+//! x = 24
+//! y = 180
+//! zdepth = 0
+//! color = [255, 255, 255, 255]
+//! attr = ["transparent", "cutout"]
+//! width = 64
+//! height = 16
+//! format = "ia8"
+//! ```
+use crate::{
+    cli::{self, ImportLayoutOpt, LayoutOpt},
+    inject,
+    timings::Timings,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use ssbfile::layout;
+use std::fs;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Layout {
+    #[serde(rename = "sprite", default)]
+    sprites: Vec<LayoutSprite>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LayoutSprite {
+    /// offset of the `Sprite` struct in the decompressed file, in hex
+    offset: String,
+    x: i16,
+    y: i16,
+    zdepth: i16,
+    color: [u8; 4],
+    attr: Vec<String>,
+    /// the size and format of the sprite, for reference; they aren't written back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+}
+
+pub(crate) fn print(opt: LayoutOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let placements = layout::layout(&data, &relocs);
+    if placements.is_empty() {
+        anyhow::bail!("found no sprites in file <{}>", opt.id);
+    }
+    let layout = Layout {
+        sprites: placements
+            .iter()
+            .map(|p| LayoutSprite {
+                offset: format!("{:#06X}", p.offset),
+                x: p.x,
+                y: p.y,
+                zdepth: p.zdepth,
+                color: p.color,
+                attr: p.attributes().into_iter().map(String::from).collect(),
+                width: Some(p.width),
+                height: Some(p.height),
+                format: Some(p.format.to_string()),
+            })
+            .collect(),
+    };
+    println!("# layout of file {}", opt.id);
+    print!(
+        "{}",
+        toml::to_string(&layout).context("serializing the layout")?
+    );
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportLayoutOpt, timings: &mut Timings) -> Result<()> {
+    let text = fs::read_to_string(&opt.layout)
+        .with_context(|| format!("issue opening <{}>", opt.layout.display()))?;
+    let layout: Layout =
+        toml::from_str(&text).with_context(|| format!("parsing <{}>", opt.layout.display()))?;
+
+    let (mut rom, version) = opt.rom.read(timings)?;
+    inject::edit_file(
+        &mut rom,
+        &version,
+        opt.id,
+        &opt.compress,
+        timings,
+        |data, relocs| {
+            let placements = layout::layout(data, relocs);
+            for sprite in &layout.sprites {
+                let offset = cli::parse_addr(&sprite.offset)? as usize;
+                let mut placement = placements
+                    .iter()
+                    .find(|p| p.offset == offset)
+                    .cloned()
+                    .with_context(|| {
+                        format!("there is no sprite at {:#X} of file <{}>", offset, opt.id)
+                    })?;
+                placement.x = sprite.x;
+                placement.y = sprite.y;
+                placement.zdepth = sprite.zdepth;
+                placement.color = sprite.color;
+                placement
+                    .set_attributes(sprite.attr.iter().map(String::as_str))
+                    .with_context(|| {
+                        format!("setting the attributes of the sprite at {:#X}", offset)
+                    })?;
+                placement.write(data)?;
+            }
+
+            Ok(())
+        },
+    )?;
+    eprintln!("placed {} sprites of file {}", layout.sprites.len(), opt.id);
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
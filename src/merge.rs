@@ -0,0 +1,71 @@
+use crate::{
+    cli::MergeOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{ensure, Context, Result};
+use ssbfile::MergedView;
+use std::fs;
+
+/// Roms that grow are padded to a multiple of this size
+const ROM_ALIGN: usize = 0x10_0000;
+
+pub(crate) fn roms(opt: MergeOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let db = opt.rom.version_db()?;
+    let others = opt
+        .from
+        .iter()
+        .map(|path| crate::cli::read_rom(path, &db, None, timings))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut view = MergedView::new(&rom, &version);
+    for (path, (other, info)) in opt.from.iter().zip(&others) {
+        view.add_rom(other, info)
+            .with_context(|| format!("adding <{}>", path.display()))?;
+    }
+    for (ids, source) in &opt.take {
+        ensure!(
+            (1..=others.len()).contains(source),
+            "there is no --from rom {}; the {} --from roms are numbered from 1",
+            source,
+            others.len()
+        );
+        for id in ids.ids() {
+            view.select(id, *source)?;
+        }
+    }
+
+    if opt.extract {
+        fs::create_dir_all(&opt.output)
+            .with_context(|| format!("creating output directory <{}>", opt.output.display()))?;
+        for id in 0..view.total_entries() {
+            let file = timings
+                .time(Stage::TableParse, || view.get(id))
+                .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+            let data = timings
+                .time(Stage::Decompress, || file.decompressed())
+                .with_context(|| format!("decompressing file <{}>", id))?;
+            let path = opt.output.join(format!("file-{:04}.bin", id));
+            timings
+                .time(Stage::Write, || fs::write(&path, &data))
+                .with_context(|| format!("writing <{}>", path.display()))?;
+        }
+        eprintln!(
+            "extracted {} files, {} of them from other roms",
+            view.total_entries(),
+            (0..view.total_entries())
+                .filter(|&id| view.source_of(id) != 0)
+                .count()
+        );
+        return Ok(());
+    }
+
+    let mut region = view.data_region()?;
+    let len = rom.len();
+    timings.time(Stage::Layout, || region.write(&mut rom, &version))?;
+    if rom.len() > len {
+        rom.resize(rom.len().next_multiple_of(ROM_ALIGN), 0);
+    }
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
@@ -0,0 +1,102 @@
+use crate::{versions::SSBInfo, DataRegion, Error, TableFile};
+use anyhow::{ensure, Context, Result};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+/// A view over several roms (e.g., different regions of the game),
+/// where each file in the resource table can come from any one of the roms.
+/// Files that are not selected come from the first (base) rom.
+#[derive(Debug, Clone)]
+pub struct MergedView<'r> {
+    sources: Vec<(&'r [u8], &'r SSBInfo)>,
+    selected: BTreeMap<usize, usize>,
+}
+
+impl<'r> MergedView<'r> {
+    pub fn new(base: &'r [u8], info: &'r SSBInfo) -> Self {
+        Self {
+            sources: vec![(base, info)],
+            selected: BTreeMap::new(),
+        }
+    }
+
+    /// Add another rom to draw files from, and get its index for [`MergedView::select`]
+    pub fn add_rom(&mut self, rom: &'r [u8], info: &'r SSBInfo) -> Result<usize> {
+        let base = self.sources[0].1;
+        ensure!(
+            info.total_entries() == base.total_entries(),
+            "rom version {} has {} files, but the base version {} has {}",
+            info.version,
+            info.total_entries(),
+            base.version,
+            base.total_entries()
+        );
+        self.sources.push((rom, info));
+
+        Ok(self.sources.len() - 1)
+    }
+
+    /// Use the data for file `id` from the rom at `source`
+    pub fn select(&mut self, id: usize, source: usize) -> Result<()> {
+        ensure!(
+            source < self.sources.len(),
+            "rom <{}> was selected, but only {} roms are open",
+            source,
+            self.sources.len()
+        );
+        ensure!(
+            id < self.total_entries(),
+            Error::FileIdOutOfRange {
+                id,
+                count: self.total_entries()
+            }
+        );
+
+        if source == 0 {
+            self.selected.remove(&id);
+        } else {
+            self.selected.insert(id, source);
+        }
+
+        Ok(())
+    }
+
+    pub fn total_entries(&self) -> usize {
+        self.sources[0].1.total_entries()
+    }
+
+    /// The index of the rom that file `id` comes from
+    pub fn source_of(&self, id: usize) -> usize {
+        self.selected.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn get(&self, id: usize) -> Result<TableFile<'r>> {
+        let (rom, info) = self.sources[self.source_of(id)];
+
+        TableFile::get(id, rom, info)
+    }
+
+    /// The data region of the base rom, with each selected file replaced by
+    /// the data from its source rom. Write it into a copy of the base rom
+    /// to build the merged rom.
+    pub fn data_region(&self) -> Result<DataRegion> {
+        let (base, info) = self.sources[0];
+        let mut region = DataRegion::read(base, info)?;
+
+        let mut others = HashMap::new();
+        for (&id, &source) in &self.selected {
+            let other = match others.entry(source) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let (rom, info) = self.sources[source];
+                    let other = DataRegion::read(rom, info)
+                        .with_context(|| format!("reading resource table of rom <{}>", source))?;
+                    e.insert(other)
+                }
+            };
+            let data = other.file(id).unwrap_or_default().to_vec();
+            region.replace(id, other.entries()[id], data)?;
+        }
+
+        Ok(region)
+    }
+}
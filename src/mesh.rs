@@ -0,0 +1,323 @@
+//! Model geometry: the triangles that the display lists of a file draw, found by running
+//! the lists like the RSP would, and writing them as Wavefront OBJ
+use crate::{
+    display_list::{self, Address, Command},
+    image::TexelFormat,
+    Relocation,
+};
+use alloc::{format, string::String, vec::Vec};
+use anyhow::Result;
+use core::fmt::Write;
+
+/// the size of the F3DEX2 vertex buffer
+const VERTEX_BUFFER: usize = 64;
+/// how deep the RSP can nest `gsSPDisplayList` calls
+const MAX_DEPTH: usize = 10;
+/// the `G_LIGHTING` bit of the F3DEX2 geometry mode
+const G_LIGHTING: u32 = 0x0020_0000;
+
+/// The triangles drawn by one or more display lists
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub triangles: Vec<Triangle>,
+    pub materials: Vec<Material>,
+}
+
+/// A vertex as it was loaded, with its texture coordinates scaled to the texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vertex {
+    /// offset of the `Vtx` in the file
+    pub offset: usize,
+    /// the index of the display list (in the starts given to [`mesh()`]) that loaded it
+    pub list: usize,
+    pub position: [i16; 3],
+    /// texture coordinates, from 0 to 1 across the texture, with v up
+    pub uv: [f32; 2],
+    pub color: [u8; 4],
+    /// the normal of a vertex that was loaded with lighting on, instead of a color
+    pub normal: Option<[i8; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Triangle {
+    /// indices into [`Mesh::vertices`]
+    pub vertices: [usize; 3],
+    /// index into [`Mesh::materials`]
+    pub material: usize,
+}
+
+/// The texture that triangles are drawn with, if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Material {
+    pub texture: Option<Texture>,
+}
+
+/// A texture that was loaded into TMEM, and the render tile it is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Texture {
+    pub addr: Address,
+    pub format: TexelFormat,
+    pub width: usize,
+    pub height: usize,
+    /// the palette of a color indexed texture
+    pub palette: Option<Address>,
+}
+
+/// The state of the RSP and RDP that affects the geometry
+#[derive(Debug)]
+struct State {
+    /// the index of the display list being run
+    list: usize,
+    buffer: [Option<usize>; VERTEX_BUFFER],
+    texture_on: bool,
+    /// the `gsSPTexture` scale of the s and t coordinates
+    scale: [f32; 2],
+    tile: u8,
+    lighting: bool,
+    /// the last `gsDPSetTextureImage`
+    image: Option<Address>,
+    loaded: Option<Address>,
+    palette: Option<Address>,
+    /// the format, and the width and height, of each tile
+    tiles: [(Option<TexelFormat>, usize, usize); 8],
+}
+
+impl State {
+    fn new(list: usize) -> Self {
+        Self {
+            list,
+            buffer: [None; VERTEX_BUFFER],
+            texture_on: false,
+            scale: [1.0; 2],
+            tile: 0,
+            lighting: false,
+            image: None,
+            loaded: None,
+            palette: None,
+            tiles: [(None, 0, 0); 8],
+        }
+    }
+
+    fn material(&self) -> Material {
+        let (format, width, height) = self.tiles[self.tile as usize & 7];
+        let texture = self
+            .loaded
+            .zip(format)
+            .filter(|_| self.texture_on && width > 0 && height > 0)
+            .map(|(addr, format)| Texture {
+                addr,
+                format,
+                width,
+                height,
+                palette: self.palette.filter(|_| format.has_palette()),
+            });
+
+        Material { texture }
+    }
+}
+
+/// Run the display lists at `starts` of `file`, and collect the triangles they draw.
+/// Lists that they call in the same file are followed; vertices and lists in other
+/// files (or in segments) are skipped.
+pub fn mesh(file: &[u8], starts: &[usize], relocs: &[Relocation]) -> Result<Mesh> {
+    let mut mesh = Mesh::default();
+    for (list, &start) in starts.iter().enumerate() {
+        let mut state = State::new(list);
+        run(file, start, relocs, &mut state, &mut mesh, 0)?;
+    }
+
+    Ok(mesh)
+}
+
+fn run(
+    file: &[u8],
+    start: usize,
+    relocs: &[Relocation],
+    state: &mut State,
+    mesh: &mut Mesh,
+    depth: usize,
+) -> Result<()> {
+    for (_, command) in display_list::parse(file, start, relocs)? {
+        match command {
+            Command::Vertex {
+                addr: Address::Internal(at),
+                count,
+                first,
+            } => {
+                let vertices = display_list::vertices(file, at as usize, count as usize)?;
+                let material = state.material();
+                for (i, v) in vertices.iter().enumerate() {
+                    let slot = first as usize + i;
+                    if slot >= VERTEX_BUFFER {
+                        break;
+                    }
+                    state.buffer[slot] = Some(mesh.vertices.len());
+                    mesh.vertices
+                        .push(vertex(at as usize + i * 16, v, &material, state));
+                }
+            }
+            Command::Triangle(a) => triangle(a, state, mesh),
+            Command::Triangles(a, b) => {
+                triangle(a, state, mesh);
+                triangle(b, state, mesh);
+            }
+            Command::Quad([a, b, c, d]) => {
+                triangle([a, b, c], state, mesh);
+                triangle([a, c, d], state, mesh);
+            }
+            Command::Texture { s, t, tile, on, .. } => {
+                state.texture_on = on;
+                state.scale = [s as f32 / 65536.0, t as f32 / 65536.0];
+                state.tile = tile;
+            }
+            Command::GeometryMode { clear, set } => {
+                state.lighting =
+                    (state.lighting && clear & G_LIGHTING == 0) || set & G_LIGHTING != 0;
+            }
+            Command::SetTextureImage { addr, .. } => state.image = Some(addr),
+            Command::LoadBlock { .. } | Command::LoadTile { .. } => state.loaded = state.image,
+            Command::LoadTlut { .. } => state.palette = state.image,
+            Command::SetTile {
+                format, size, tile, ..
+            } => state.tiles[tile as usize & 7].0 = TexelFormat::from_fmt_siz(format, size),
+            Command::SetTileSize {
+                tile,
+                uls,
+                ult,
+                lrs,
+                lrt,
+            } => {
+                // the corners are in 10.2 fixed point
+                let tile = &mut state.tiles[tile as usize & 7];
+                tile.1 = (lrs.saturating_sub(uls) >> 2) as usize + 1;
+                tile.2 = (lrt.saturating_sub(ult) >> 2) as usize + 1;
+            }
+            Command::DisplayList {
+                addr: Address::Internal(target),
+                branch,
+            } if depth < MAX_DEPTH => {
+                run(file, target as usize, relocs, state, mesh, depth + 1)?;
+                if branch {
+                    return Ok(());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn vertex(offset: usize, v: &display_list::Vertex, material: &Material, state: &State) -> Vertex {
+    // texture coordinates are 10.5 fixed point texels, scaled by gsSPTexture
+    let (width, height) = material.texture.map_or((1, 1), |t| (t.width, t.height));
+    let texel = |st: i16, scale: f32| st as f32 / 32.0 * scale;
+    let uv = [
+        texel(v.st[0], state.scale[0]) / width as f32,
+        1.0 - texel(v.st[1], state.scale[1]) / height as f32,
+    ];
+    let [x, y, z, _] = v.color.map(|c| c as i8);
+
+    Vertex {
+        offset,
+        list: state.list,
+        position: v.position,
+        uv,
+        color: v.color,
+        normal: state.lighting.then_some([x, y, z]),
+    }
+}
+
+fn triangle(slots: [u8; 3], state: &State, mesh: &mut Mesh) {
+    let vertices = slots.map(|s| state.buffer.get(s as usize).copied().flatten());
+    let [Some(a), Some(b), Some(c)] = vertices else {
+        return;
+    };
+    let material = state.material();
+    let material = match mesh.materials.iter().position(|m| *m == material) {
+        Some(i) => i,
+        None => {
+            mesh.materials.push(material);
+            mesh.materials.len() - 1
+        }
+    };
+    mesh.triangles.push(Triangle {
+        vertices: [a, b, c],
+        material,
+    });
+}
+
+/// The name of material `i` in the OBJ and MTL files
+pub fn material_name(i: usize) -> String {
+    format!("material_{}", i)
+}
+
+/// Write `mesh` as a Wavefront OBJ that uses the materials of `mtllib`. Vertex colors follow
+/// the positions (`v x y z r g b`), which Blender reads; lit vertices get normals instead.
+pub fn obj(mesh: &Mesh, mtllib: &str) -> String {
+    let mut s = format!("mtllib {}\n", mtllib);
+    for v in &mesh.vertices {
+        let [x, y, z] = v.position;
+        if v.normal.is_some() {
+            writeln!(s, "v {} {} {}", x, y, z).expect("write to string");
+        } else {
+            let [r, g, b] = [v.color[0], v.color[1], v.color[2]].map(|c| c as f32 / 255.0);
+            writeln!(s, "v {} {} {} {:.4} {:.4} {:.4}", x, y, z, r, g, b).expect("write to string");
+        }
+    }
+    for v in &mesh.vertices {
+        writeln!(s, "vt {:.6} {:.6}", v.uv[0], v.uv[1]).expect("write to string");
+    }
+    for v in &mesh.vertices {
+        let [x, y, z] = v.normal.unwrap_or([0, 0, 0]).map(|n| n as f32 / 127.0);
+        writeln!(s, "vn {:.4} {:.4} {:.4}", x, y, z).expect("write to string");
+    }
+
+    let mut material = None;
+    for t in &mesh.triangles {
+        if material != Some(t.material) {
+            material = Some(t.material);
+            writeln!(s, "usemtl {}", material_name(t.material)).expect("write to string");
+        }
+        // OBJ indices start at 1
+        let [a, b, c] = t.vertices.map(|v| v + 1);
+        writeln!(s, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}").expect("write to string");
+    }
+
+    s
+}
+
+/// Write the materials of `mesh` as an MTL file, with the image file of each texture
+/// from `texture` (or no image, if it gives none)
+pub fn mtl(mesh: &Mesh, texture: impl Fn(&Texture) -> Option<String>) -> String {
+    let mut s = String::new();
+    for (i, material) in mesh.materials.iter().enumerate() {
+        writeln!(s, "newmtl {}", material_name(i)).expect("write to string");
+        s += "Kd 1.0 1.0 1.0\n";
+        match material.texture {
+            Some(t) => match texture(&t) {
+                Some(path) => {
+                    writeln!(s, "map_Kd {}", path).expect("write to string");
+                }
+                None => {
+                    writeln!(
+                        s,
+                        "# {} {}x{} texture at {}",
+                        t.format, t.width, t.height, t.addr
+                    )
+                    .expect("write to string");
+                }
+            },
+            None => s += "# untextured\n",
+        }
+        s.push('\n');
+    }
+
+    s
+}
@@ -0,0 +1,79 @@
+use crate::{
+    cli::{ImportTextOpt, TextFormat, TextOpt},
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{text, TableFile};
+use std::{collections::BTreeMap, fs, io::Write};
+
+pub(crate) fn extract(opt: TextOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let ids = if opt.ids.is_empty() {
+        (0..version.total_entries()).collect()
+    } else {
+        opt.ids
+    };
+
+    let mut messages = Vec::new();
+    for id in ids {
+        let file = timings
+            .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let data = timings
+            .time(Stage::Decompress, || file.decompressed())
+            .with_context(|| format!("decompressing file <{}>", id))?;
+        messages.extend(text::messages(id, &data, opt.min_len));
+    }
+    let out = match opt.format {
+        TextFormat::Po => text::po(&messages),
+        TextFormat::Csv => text::csv(&messages),
+    };
+
+    match &opt.output {
+        Some(path) => fs::write(path, out)
+            .with_context(|| format!("writing messages to <{}>", path.display()))?,
+        None => std::io::stdout()
+            .write_all(out.as_bytes())
+            .context("writing messages to stdout")?,
+    }
+    eprintln!("{} messages", messages.len());
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportTextOpt, timings: &mut Timings) -> Result<()> {
+    let messages = fs::read_to_string(&opt.messages)
+        .with_context(|| format!("issue opening <{}>", opt.messages.display()))?;
+    let translations = match opt.format {
+        TextFormat::Po => text::read_po(&messages),
+        TextFormat::Csv => text::read_csv(&messages),
+    }
+    .with_context(|| format!("reading <{}>", opt.messages.display()))?;
+    let mut by_file = BTreeMap::<_, Vec<_>>::new();
+    for t in translations {
+        by_file.entry(t.file).or_default().push(t);
+    }
+
+    let (mut rom, version) = opt.rom.read(timings)?;
+    for (&id, translations) in &by_file {
+        inject::edit_file(
+            &mut rom,
+            &version,
+            id,
+            &opt.compress,
+            timings,
+            |data, relocs| {
+                text::reinsert(data, translations, relocs)
+                    .with_context(|| format!("writing the strings of file <{}>", id))
+            },
+        )?;
+    }
+    eprintln!(
+        "replaced {} strings in {} files",
+        by_file.values().map(Vec::len).sum::<usize>(),
+        by_file.len()
+    );
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
@@ -0,0 +1,389 @@
+//! A small interpreter of the R4300's 32-bit integer instructions, to run the game's own
+//! file loader over a file and check the pointers that [`relocate`](crate::relocate)
+//! writes against the ones that it writes.
+//!
+//! Only RDRAM is mapped, through KSEG0 and KSEG1: the loader is expected to only read and
+//! write memory, so coprocessor, TLB, doubleword, and trap instructions are errors.
+use crate::{
+    apply_relocations, relocations,
+    table::{RelocInfo, TableFile},
+    Error,
+};
+use alloc::{vec, vec::Vec};
+use anyhow::{bail, Result};
+
+/// The address that a call returns to: `call` stops when the pc gets there
+const RETURN: u32 = 0x7FFF_FFF0;
+
+/// `jr $ra`
+const JR_RA: u32 = 0x03E0_0008;
+
+/// A file with an internal chain at 0x4 -> 0xC, for [`find_loader`] to call each function over
+const PROBE: [u32; 4] = [0x1234_5678, 0x0003_0002, 0xCAFE_F00D, 0xFFFF_0001];
+
+/// The most instructions that [`find_loader`] runs of each function
+const PROBE_STEPS: usize = 100_000;
+
+/// The registers and memory of the cpu
+#[derive(Debug, Clone)]
+pub struct Cpu {
+    pub gpr: [u32; 32],
+    pub hi: u32,
+    pub lo: u32,
+    pub pc: u32,
+    next_pc: u32,
+    /// RDRAM, from physical address 0
+    pub ram: Vec<u8>,
+}
+
+impl Cpu {
+    /// A cpu with `ram_size` bytes of zeroed RDRAM
+    pub fn new(ram_size: usize) -> Self {
+        Self {
+            gpr: [0; 32],
+            hi: 0,
+            lo: 0,
+            pc: 0,
+            next_pc: 0,
+            ram: vec![0; ram_size],
+        }
+    }
+
+    /// The bytes `addr..addr + len` of memory
+    pub fn read(&self, addr: u32, len: usize) -> Result<&[u8]> {
+        let start = self.physical(addr, len)?;
+        Ok(&self.ram[start..start + len])
+    }
+
+    /// Copy `bytes` into memory at `addr`
+    pub fn write(&mut self, addr: u32, bytes: &[u8]) -> Result<()> {
+        let start = self.physical(addr, bytes.len())?;
+        self.ram[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Call the function at `addr` with `args` in a0 to a3, and run it until it returns,
+    /// or until it has run `max_steps` instructions. Returns v0.
+    pub fn call(&mut self, addr: u32, args: &[u32], max_steps: usize) -> Result<u32> {
+        if args.len() > 4 {
+            bail!("only four arguments are passed in registers");
+        }
+        for (reg, &arg) in self.gpr[4..].iter_mut().zip(args) {
+            *reg = arg;
+        }
+        self.gpr[29] = 0x8000_0000 + self.ram.len() as u32 - 0x10;
+        self.gpr[31] = RETURN;
+        self.pc = addr;
+        self.next_pc = addr.wrapping_add(4);
+
+        for _ in 0..max_steps {
+            if self.pc == RETURN {
+                return Ok(self.gpr[2]);
+            }
+            self.step()?;
+        }
+
+        bail!(Error::StepLimit { steps: max_steps })
+    }
+
+    /// Run the instruction at the pc
+    pub fn step(&mut self) -> Result<()> {
+        let pc = self.pc;
+        let word = self.load_word(pc, pc)?;
+        // the next instruction is the delay slot of a branch
+        self.pc = self.next_pc;
+        self.next_pc = self.next_pc.wrapping_add(4);
+
+        let rs = (word >> 21 & 0x1F) as usize;
+        let rt = (word >> 16 & 0x1F) as usize;
+        let rd = (word >> 11 & 0x1F) as usize;
+        let sa = word >> 6 & 0x1F;
+        let imm = word as u16;
+        let simm = imm as i16 as i32 as u32;
+        let s = self.gpr[rs];
+        let t = self.gpr[rt];
+        let branch = self.pc.wrapping_add(simm << 2);
+        let unknown = Error::UnknownInstruction { pc, word };
+
+        match word >> 26 {
+            0 => match word & 0x3F {
+                0x00 => self.set(rd, t << sa),
+                0x02 => self.set(rd, t >> sa),
+                0x03 => self.set(rd, ((t as i32) >> sa) as u32),
+                0x04 => self.set(rd, t << (s & 0x1F)),
+                0x06 => self.set(rd, t >> (s & 0x1F)),
+                0x07 => self.set(rd, ((t as i32) >> (s & 0x1F)) as u32),
+                0x08 => self.next_pc = s,
+                0x09 => {
+                    self.set(rd, self.pc.wrapping_add(4));
+                    self.next_pc = s;
+                }
+                0x10 => self.set(rd, self.hi),
+                0x11 => self.hi = s,
+                0x12 => self.set(rd, self.lo),
+                0x13 => self.lo = s,
+                0x18 => {
+                    let product = (s as i32 as i64) * (t as i32 as i64);
+                    (self.hi, self.lo) = ((product >> 32) as u32, product as u32);
+                }
+                0x19 => {
+                    let product = (s as u64) * (t as u64);
+                    (self.hi, self.lo) = ((product >> 32) as u32, product as u32);
+                }
+                // division by zero leaves hi and lo undefined; they are left as they are
+                0x1A if t != 0 => {
+                    let (s, t) = (s as i32, t as i32);
+                    (self.hi, self.lo) = (s.wrapping_rem(t) as u32, s.wrapping_div(t) as u32);
+                }
+                0x1B if t != 0 => (self.hi, self.lo) = (s % t, s / t),
+                0x1A | 0x1B => (),
+                0x20 | 0x21 => self.set(rd, s.wrapping_add(t)),
+                0x22 | 0x23 => self.set(rd, s.wrapping_sub(t)),
+                0x24 => self.set(rd, s & t),
+                0x25 => self.set(rd, s | t),
+                0x26 => self.set(rd, s ^ t),
+                0x27 => self.set(rd, !(s | t)),
+                0x2A => self.set(rd, ((s as i32) < (t as i32)) as u32),
+                0x2B => self.set(rd, (s < t) as u32),
+                _ => bail!(unknown),
+            },
+            1 => {
+                let taken = match rt & 0xF {
+                    0x0 | 0x2 => (s as i32) < 0,
+                    0x1 | 0x3 => (s as i32) >= 0,
+                    _ => bail!(unknown),
+                };
+                if rt & 0x10 != 0 {
+                    self.set(31, self.pc.wrapping_add(4));
+                }
+                self.branch(taken, branch, rt & 0x2 != 0);
+            }
+            2 | 3 => {
+                if word >> 26 == 3 {
+                    self.set(31, self.pc.wrapping_add(4));
+                }
+                self.next_pc = (self.pc & 0xF000_0000) | (word & 0x03FF_FFFF) << 2;
+            }
+            op @ (4 | 0x14) => self.branch(s == t, branch, op == 0x14),
+            op @ (5 | 0x15) => self.branch(s != t, branch, op == 0x15),
+            op @ (6 | 0x16) => self.branch(s as i32 <= 0, branch, op == 0x16),
+            op @ (7 | 0x17) => self.branch(s as i32 > 0, branch, op == 0x17),
+            8 | 9 => self.set(rt, s.wrapping_add(simm)),
+            10 => self.set(rt, ((s as i32) < (simm as i32)) as u32),
+            11 => self.set(rt, (s < simm) as u32),
+            12 => self.set(rt, s & imm as u32),
+            13 => self.set(rt, s | imm as u32),
+            14 => self.set(rt, s ^ imm as u32),
+            15 => self.set(rt, (imm as u32) << 16),
+            op @ (0x20 | 0x21 | 0x23 | 0x24 | 0x25) => {
+                let addr = s.wrapping_add(simm);
+                let value = match op {
+                    0x20 => self.load(pc, addr, 1)?[0] as i8 as u32,
+                    0x24 => self.load(pc, addr, 1)?[0] as u32,
+                    0x21 | 0x25 => {
+                        let bytes = self.load(pc, addr, 2)?;
+                        let half = u16::from_be_bytes([bytes[0], bytes[1]]);
+                        match op {
+                            0x21 => half as i16 as u32,
+                            _ => half as u32,
+                        }
+                    }
+                    _ => self.load_word(pc, addr)?,
+                };
+                self.set(rt, value);
+            }
+            0x28 => self.store(pc, s.wrapping_add(simm), &[t as u8])?,
+            0x29 => self.store(pc, s.wrapping_add(simm), &(t as u16).to_be_bytes())?,
+            0x2B => self.store(pc, s.wrapping_add(simm), &t.to_be_bytes())?,
+            // cache operations don't change what is in memory
+            0x2F => (),
+            _ => bail!(unknown),
+        }
+
+        Ok(())
+    }
+
+    fn set(&mut self, reg: usize, value: u32) {
+        if reg != 0 {
+            self.gpr[reg] = value;
+        }
+    }
+
+    /// Jump to `target` after the delay slot if the branch is `taken`. The delay slot of a
+    /// branch likely that isn't taken is skipped.
+    fn branch(&mut self, taken: bool, target: u32, likely: bool) {
+        if taken {
+            self.next_pc = target;
+        } else if likely {
+            self.pc = self.next_pc;
+            self.next_pc = self.next_pc.wrapping_add(4);
+        }
+    }
+
+    fn load(&self, pc: u32, addr: u32, len: usize) -> Result<&[u8]> {
+        self.check_alignment(pc, addr, len)?;
+        self.read(addr, len)
+    }
+
+    fn load_word(&self, pc: u32, addr: u32) -> Result<u32> {
+        let bytes = self.load(pc, addr, 4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn store(&mut self, pc: u32, addr: u32, bytes: &[u8]) -> Result<()> {
+        self.check_alignment(pc, addr, bytes.len())?;
+        self.write(addr, bytes)
+    }
+
+    fn check_alignment(&self, pc: u32, addr: u32, len: usize) -> Result<()> {
+        if !(addr as usize).is_multiple_of(len) {
+            bail!(Error::BadMemoryAccess { pc, addr });
+        }
+        Ok(())
+    }
+
+    /// The offset into RDRAM of `len` bytes at the KSEG0 or KSEG1 address `addr`
+    fn physical(&self, addr: u32, len: usize) -> Result<usize> {
+        let physical = (addr & 0x1FFF_FFFF) as usize;
+        if !(0x8000_0000..0xC000_0000).contains(&addr) || physical + len > self.ram.len() {
+            bail!(Error::BadMemoryAccess { pc: self.pc, addr });
+        }
+        Ok(physical)
+    }
+}
+
+/// A value passed to the loader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Arg {
+    /// the address that the file is loaded at
+    File,
+    /// the word index of the first internal relocation, or 0xFFFF if there is none
+    InReloc,
+    /// the size of the decompressed file
+    Size,
+    Value(u32),
+}
+
+/// Where the loader is, and how it is called
+#[derive(Debug, Clone)]
+pub struct Loader<'a> {
+    /// the code with the loader, and everything that it calls
+    pub code: &'a [u8],
+    /// the address that `code` is at
+    pub code_addr: u32,
+    /// the address of the loader's function
+    pub entry: u32,
+    pub args: Vec<Arg>,
+    /// the address that the file is loaded at
+    pub file_addr: u32,
+    /// bytes of RDRAM
+    pub ram_size: usize,
+    pub max_steps: usize,
+}
+
+/// A word that the loader and [`apply_relocations`] left different
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mismatch {
+    /// offset of the word in the file
+    pub offset: usize,
+    pub loader: u32,
+    pub relocated: u32,
+}
+
+/// Run the `loader` over the decompressed `file` of `entry`, and compare the file that it
+/// leaves in memory with the file that the internal relocations of `entry` are applied to
+pub fn check_loader(loader: &Loader, file: &[u8], entry: &TableFile) -> Result<Vec<Mismatch>> {
+    let mut cpu = Cpu::new(loader.ram_size);
+    cpu.write(loader.code_addr, loader.code)?;
+    cpu.write(loader.file_addr, file)?;
+    let in_reloc = entry
+        .inreloc
+        .as_ref()
+        .map_or(0xFFFF, |r| (r.get_starting_offset() / 4) as u32);
+    let args = loader
+        .args
+        .iter()
+        .map(|arg| match *arg {
+            Arg::File => loader.file_addr,
+            Arg::InReloc => in_reloc,
+            Arg::Size => file.len() as u32,
+            Arg::Value(value) => value,
+        })
+        .collect::<Vec<_>>();
+    cpu.call(loader.entry, &args, loader.max_steps)?;
+
+    let internal = relocations(file, entry)?
+        .into_iter()
+        .filter(|r| r.external_file.is_none())
+        .collect::<Vec<_>>();
+    let mut relocated = file.to_vec();
+    apply_relocations(&mut relocated, &internal, loader.file_addr, |_| None)?;
+
+    let loaded = cpu.read(loader.file_addr, file.len())?;
+    let word = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mismatches = loaded
+        .chunks_exact(4)
+        .zip(relocated.chunks_exact(4))
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(i, (a, b))| Mismatch {
+            offset: i * 4,
+            loader: word(a),
+            relocated: word(b),
+        })
+        .collect();
+
+    Ok(mismatches)
+}
+
+/// Find the loader in `loader.code` by what it does, like [`SSBInfo::detect`] finds the
+/// table by its layout: each function that uses 0xFFFF, the end of a relocation chain, is
+/// called over a small file with an internal chain, and the first one that leaves the same
+/// pointers as [`apply_relocations`] is returned. `loader.entry` is ignored.
+///
+/// [`SSBInfo::detect`]: crate::SSBInfo::detect
+pub fn find_loader(loader: &Loader) -> Option<u32> {
+    let words = loader
+        .code
+        .chunks_exact(4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+        .collect::<Vec<_>>();
+    let probe = PROBE
+        .iter()
+        .flat_map(|w| w.to_be_bytes())
+        .collect::<Vec<_>>();
+    let entry = TableFile {
+        id: 0,
+        offset: 0,
+        compressed: false,
+        decompressed_size: probe.len(),
+        raw: &probe,
+        inreloc: Some(RelocInfo::Internal(4)),
+        exreloc: None,
+    };
+
+    // functions start at the code, and after the delay slot of each `jr $ra`, past any nops
+    let starts = core::iter::once(0)
+        .chain((2..words.len()).filter(|&i| words[i - 2] == JR_RA))
+        .filter_map(|i| (i..words.len()).find(|&i| words[i] != 0));
+    let uses_chain_end = |start: usize| {
+        words[start..]
+            .iter()
+            .take_while(|&&w| w != JR_RA)
+            .any(|&w| !matches!(w >> 26, 0..=3) && w & 0xFFFF == 0xFFFF)
+    };
+
+    starts
+        .filter(|&start| uses_chain_end(start))
+        .map(|start| loader.code_addr.wrapping_add(start as u32 * 4))
+        .find(|&entry_addr| {
+            let candidate = Loader {
+                entry: entry_addr,
+                max_steps: loader.max_steps.min(PROBE_STEPS),
+                ..loader.clone()
+            };
+            matches!(check_loader(&candidate, &probe, &entry), Ok(m) if m.is_empty())
+        })
+}
@@ -0,0 +1,149 @@
+use crate::{
+    cli::{ModelFormat, ModelOpt},
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use ssbfile::{
+    display_list::{self, Address},
+    gltf::{Gltf, Skin},
+    image,
+    mesh::{self, Texture},
+    skeleton,
+};
+use std::{collections::BTreeMap, fs};
+
+pub(crate) fn export(opt: ModelOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    // a glTF model is posed by the bones of its skeleton, if the file has one
+    let bones = match (opt.format, opt.skeleton, opt.offset) {
+        (ModelFormat::Obj, Some(_), _) => bail!("only glTF models have a skeleton"),
+        (_, Some(offset), _) => Some(
+            skeleton::skeleton(&data, offset as usize, &relocs)
+                .with_context(|| format!("reading the skeleton of file <{}>", opt.id))?,
+        ),
+        (ModelFormat::Gltf | ModelFormat::Glb, None, None) => skeleton::find(&data, &relocs)
+            .first()
+            .map(|&at| skeleton::skeleton(&data, at, &relocs))
+            .transpose()?,
+        _ => None,
+    };
+    let lists = bones.as_deref().map(skeleton::display_lists);
+    let starts = match (&lists, opt.offset) {
+        (Some(lists), _) => lists.iter().map(|&(_, dl)| dl).collect(),
+        (None, Some(offset)) => vec![offset as usize],
+        (None, None) => display_list::find(&data, &relocs),
+    };
+    let mesh = mesh::mesh(&data, &starts, &relocs)
+        .with_context(|| format!("running the display lists of file <{}>", opt.id))?;
+    if mesh.triangles.is_empty() {
+        bail!("the display lists of file <{}> draw no triangles", opt.id);
+    }
+
+    fs::create_dir_all(&opt.output)
+        .with_context(|| format!("creating directory <{}>", opt.output.display()))?;
+    let name = format!("file-{:04}", opt.id);
+    if opt.format != ModelFormat::Obj {
+        let joints = lists
+            .iter()
+            .flatten()
+            .map(|&(bone, _)| bone)
+            .collect::<Vec<_>>();
+        let skin = bones.as_deref().map(|bones| Skin {
+            bones,
+            joints: &joints,
+        });
+        let model = Gltf::new(&mesh, skin, |texture| {
+            texture_png(&data, texture)
+                .map_err(|e| eprintln!("skipping the texture at {}: {:#}", texture.addr, e))
+                .ok()
+        });
+        let (path, bytes) = match opt.format {
+            ModelFormat::Glb => (format!("{}.glb", name), model.glb()?),
+            _ => (format!("{}.gltf", name), model.embedded()?.into_bytes()),
+        };
+        let path = opt.output.join(path);
+        timings
+            .time(Stage::Write, || fs::write(&path, bytes))
+            .with_context(|| format!("writing <{}>", path.display()))?;
+        eprintln!(
+            "wrote {} vertices, {} triangles, and {} bones of file {} to <{}>",
+            mesh.vertices.len(),
+            mesh.triangles.len(),
+            bones.map_or(0, |b| b.len()),
+            opt.id,
+            path.display()
+        );
+        return Ok(());
+    }
+
+    // textures in the file are written as PNGs; those in other files are only named
+    let mut textures = BTreeMap::new();
+    for texture in mesh.materials.iter().filter_map(|m| m.texture) {
+        let Address::Internal(at) = texture.addr else {
+            continue;
+        };
+        let png = format!("{}-tex-{:04X}.png", name, at);
+        let path = opt.output.join(&png);
+        let written = texture_png(&data, &texture).and_then(|bytes| {
+            timings
+                .time(Stage::Write, || fs::write(&path, bytes))
+                .with_context(|| format!("writing <{}>", path.display()))
+        });
+        match written {
+            Ok(()) => {
+                textures.insert(at, png);
+            }
+            Err(e) => eprintln!("skipping the texture at {:#X}: {:#}", at, e),
+        }
+    }
+
+    let mtl = format!("{}.mtl", name);
+    let obj = mesh::obj(&mesh, &mtl);
+    let materials = mesh::mtl(&mesh, |texture| match texture.addr {
+        Address::Internal(at) => textures.get(&at).cloned(),
+        _ => None,
+    });
+    for (path, text) in [(format!("{}.obj", name), obj), (mtl, materials)] {
+        let path = opt.output.join(path);
+        timings
+            .time(Stage::Write, || fs::write(&path, text))
+            .with_context(|| format!("writing <{}>", path.display()))?;
+    }
+    eprintln!(
+        "wrote {} vertices, {} triangles, and {} textures of file {}",
+        mesh.vertices.len(),
+        mesh.triangles.len(),
+        textures.len(),
+        opt.id
+    );
+
+    Ok(())
+}
+
+/// Decode `texture` of the file `data` as a PNG
+fn texture_png(data: &[u8], texture: &Texture) -> Result<Vec<u8>> {
+    let slice = |at: usize| data.get(at..).unwrap_or_default();
+    let palette = match texture.palette {
+        Some(Address::Internal(at)) => {
+            let palette = slice(at as usize);
+            Some(&palette[..(texture.format.palette_len() * 2).min(palette.len())])
+        }
+        Some(_) => bail!("its palette is in another file"),
+        None => None,
+    };
+    let Address::Internal(at) = texture.addr else {
+        bail!("it is in another file");
+    };
+    let decoded = image::decode(
+        slice(at as usize),
+        texture.format,
+        texture.width,
+        texture.height,
+        palette,
+    )?;
+
+    image::png(&decoded)
+}
@@ -0,0 +1,695 @@
+//! Fighter subaction scripts: the streams of motion events that make hitboxes, play
+//! sounds and effects, and set flags on the frames of a fighter's animations.
+//! Each command starts with a word whose top 6 bits are its opcode; the names and layouts
+//! follow the decompilation's motion events, and opcodes without a known layout are
+//! shown as single `.word`s.
+use crate::{
+    display_list::{self, Address},
+    Error, Relocation,
+};
+use alloc::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+use anyhow::Result;
+use core::fmt;
+
+/// how many commands a script is run for, at most, when following its timeline
+const MAX_STEPS: usize = 0x1000;
+
+/// A bit field of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    pub name: &'static str,
+    /// the index of the word of the command that the field is in
+    pub word: usize,
+    pub shift: u32,
+    pub bits: u32,
+    pub signed: bool,
+}
+
+/// The layout of the commands with an opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Spec {
+    pub opcode: u8,
+    pub name: &'static str,
+    /// the size of the command in words
+    pub words: usize,
+    /// the fields, without the opcode and the bits that are always 0
+    pub fields: &'static [Field],
+    /// the index of the word that is a pointer, which is a word of its own
+    pub pointer: Option<usize>,
+    /// the script doesn't go on to the next command after this one
+    pub ends: bool,
+    /// the pointer is to another script, which is run (or jumped to) from here
+    pub calls: bool,
+}
+
+pub(crate) const fn field(name: &'static str, word: usize, shift: u32, bits: u32) -> Field {
+    Field {
+        name,
+        word,
+        shift,
+        bits,
+        signed: false,
+    }
+}
+
+pub(crate) const fn signed(name: &'static str, word: usize, shift: u32, bits: u32) -> Field {
+    Field {
+        name,
+        word,
+        shift,
+        bits,
+        signed: true,
+    }
+}
+
+const fn spec(opcode: u8, name: &'static str, words: usize, fields: &'static [Field]) -> Spec {
+    Spec {
+        opcode,
+        name,
+        words,
+        fields,
+        pointer: None,
+        ends: false,
+        calls: false,
+    }
+}
+
+/// the 26 bits after the opcode, for commands with one argument
+const VALUE: &[Field] = &[field("value", 0, 0, 26)];
+const ATTACK_ID: Field = field("id", 0, 23, 3);
+/// the five words of a hitbox
+const ATTACK: &[Field] = &[
+    ATTACK_ID,
+    field("group", 0, 20, 3),
+    field("joint", 0, 13, 7),
+    field("damage", 0, 5, 8),
+    field("rebound", 0, 4, 1),
+    field("element", 0, 0, 4),
+    field("size", 1, 16, 16),
+    signed("x", 1, 0, 16),
+    signed("y", 2, 16, 16),
+    signed("z", 2, 0, 16),
+    signed("angle", 3, 22, 10),
+    field("knockback_scale", 3, 12, 10),
+    field("knockback_weight", 3, 2, 10),
+    field("ground_air", 3, 0, 2),
+    signed("shield_damage", 4, 24, 8),
+    field("sound_level", 4, 21, 3),
+    field("sound_kind", 4, 17, 4),
+    field("knockback_base", 4, 7, 10),
+];
+
+/// The commands with a known layout
+pub const SPECS: &[Spec] = &[
+    Spec {
+        ends: true,
+        ..spec(0, "end", 1, &[])
+    },
+    spec(1, "wait", 1, &[field("frames", 0, 0, 26)]),
+    spec(2, "wait_until", 1, &[field("frame", 0, 0, 26)]),
+    spec(3, "attack", 5, ATTACK),
+    spec(4, "attack_scaled", 5, ATTACK),
+    spec(
+        5,
+        "attack_offset",
+        2,
+        &[
+            ATTACK_ID,
+            signed("x", 0, 0, 16),
+            signed("y", 1, 16, 16),
+            signed("z", 1, 0, 16),
+        ],
+    ),
+    spec(
+        6,
+        "attack_damage",
+        1,
+        &[ATTACK_ID, field("damage", 0, 0, 8)],
+    ),
+    spec(7, "attack_size", 1, &[ATTACK_ID, field("size", 0, 0, 16)]),
+    spec(
+        8,
+        "attack_sound_level",
+        1,
+        &[ATTACK_ID, field("level", 0, 0, 3)],
+    ),
+    spec(9, "refresh_attack", 1, &[ATTACK_ID]),
+    spec(10, "clear_attack", 1, &[ATTACK_ID]),
+    spec(11, "clear_attacks", 1, &[]),
+    Spec {
+        pointer: Some(1),
+        ..spec(12, "throw", 2, &[field("kind", 0, 0, 26)])
+    },
+    spec(14, "sound", 1, &[field("sound", 0, 0, 26)]),
+    spec(17, "voice", 1, &[field("sound", 0, 0, 26)]),
+    spec(21, "flag0", 1, VALUE),
+    spec(22, "flag1", 1, VALUE),
+    spec(23, "flag2", 1, VALUE),
+    spec(24, "flag3", 1, VALUE),
+    spec(32, "loop", 1, &[field("count", 0, 0, 26)]),
+    spec(33, "loop_end", 1, &[]),
+    Spec {
+        pointer: Some(1),
+        calls: true,
+        ..spec(34, "subroutine", 2, &[])
+    },
+    Spec {
+        ends: true,
+        ..spec(35, "return", 1, &[])
+    },
+    Spec {
+        pointer: Some(1),
+        calls: true,
+        ends: true,
+        ..spec(36, "goto", 2, &[])
+    },
+    spec(
+        38,
+        "effect",
+        4,
+        &[
+            field("joint", 0, 19, 7),
+            field("effect", 0, 10, 9),
+            field("flags", 0, 0, 10),
+            signed("x", 1, 16, 16),
+            signed("y", 1, 0, 16),
+            signed("z", 2, 16, 16),
+            field("scatter_x", 2, 0, 16),
+            field("scatter_y", 3, 16, 16),
+            field("scatter_z", 3, 0, 16),
+        ],
+    ),
+];
+
+/// The layout of `opcode`, if it is known
+pub fn spec_of(opcode: u8) -> Option<&'static Spec> {
+    SPECS.iter().find(|s| s.opcode == opcode)
+}
+
+/// The layout of the command called `name`
+pub fn spec_named(name: &str) -> Option<&'static Spec> {
+    SPECS.iter().find(|s| s.name == name)
+}
+
+/// A command of a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Command {
+    /// offset of the command in the file
+    pub offset: usize,
+    pub words: Vec<u32>,
+    /// where the pointer of the command points, if it has one
+    pub pointer: Option<Address>,
+}
+
+impl Command {
+    pub fn opcode(&self) -> u8 {
+        (self.words[0] >> 26) as u8
+    }
+
+    /// The layout of the command, if its opcode is known
+    pub fn spec(&self) -> Option<&'static Spec> {
+        spec_of(self.opcode())
+    }
+
+    /// The value of the field called `name`
+    pub fn field(&self, name: &str) -> Option<i32> {
+        self.spec()?
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.get(&self.words))
+    }
+}
+
+impl Field {
+    /// The value of the field in `words`, sign extended if it is signed
+    pub fn get(&self, words: &[u32]) -> i32 {
+        let raw = (words[self.word] >> self.shift) & mask(self.bits);
+        if self.signed {
+            ((raw << (32 - self.bits)) as i32) >> (32 - self.bits)
+        } else {
+            raw as i32
+        }
+    }
+
+    /// Set the field in `words` to `value`, if it fits
+    pub fn set(&self, words: &mut [u32], value: i64) -> bool {
+        let fits = if self.signed {
+            let half = 1i64 << (self.bits - 1);
+            (-half..half).contains(&value)
+        } else {
+            (0..1i64 << self.bits).contains(&value)
+        };
+        if fits {
+            let m = mask(self.bits) << self.shift;
+            words[self.word] = (words[self.word] & !m) | ((value as u32) << self.shift & m);
+        }
+
+        fits
+    }
+}
+
+fn mask(bits: u32) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(spec) = self.spec() else {
+            return write!(f, ".word 0x{:08X}", self.words[0]);
+        };
+        f.write_str(spec.name)?;
+        for field in spec.fields {
+            write!(f, " {}={}", field.name, field.get(&self.words))?;
+        }
+        if let Some(word) = spec.pointer {
+            let addr = self.pointer.unwrap_or(Address::Raw(self.words[word]));
+            write!(f, " {}", addr)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode the script of `file` that starts at `start`, up to and including the command
+/// that ends it (`end`, `return`, or `goto`). The pointers of the commands are found in
+/// `relocs` (see [`relocations`](crate::relocations)).
+pub fn parse(file: &[u8], start: usize, relocs: &[Relocation]) -> Result<Vec<Command>> {
+    parse_with(file, start, &display_list::pointers(relocs))
+}
+
+fn parse_with(
+    file: &[u8],
+    start: usize,
+    pointers: &BTreeMap<usize, Address>,
+) -> Result<Vec<Command>> {
+    let word = |at: usize| {
+        file.get(at..at + 4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+    };
+    let outside = |end: usize| Error::OutsideFile {
+        start,
+        end,
+        len: file.len(),
+    };
+
+    let mut commands = Vec::new();
+    let mut at = start;
+    loop {
+        let first = word(at).ok_or_else(|| outside(at + 4))?;
+        let spec = spec_of((first >> 26) as u8);
+        let words = spec.map_or(1, |s| s.words);
+        let words = (0..words)
+            .map(|i| word(at + i * 4).ok_or_else(|| outside(at + (i + 1) * 4)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pointer = spec
+            .and_then(|s| s.pointer)
+            .and_then(|i| pointers.get(&(at + i * 4)).copied());
+        commands.push(Command {
+            offset: at,
+            words,
+            pointer,
+        });
+        at += spec.map_or(1, |s| s.words) * 4;
+
+        if spec.is_some_and(|s| s.ends) {
+            return Ok(commands);
+        }
+    }
+}
+
+/// Decode the scripts at `starts` of `file`, and the scripts in the same file that they
+/// call or jump to, by their offset
+pub fn scripts(
+    file: &[u8],
+    starts: &[usize],
+    relocs: &[Relocation],
+) -> Result<BTreeMap<usize, Vec<Command>>> {
+    let pointers = display_list::pointers(relocs);
+    let mut queue = starts.iter().copied().collect::<BTreeSet<_>>();
+    let mut scripts = BTreeMap::new();
+    while let Some(start) = queue.pop_first() {
+        if scripts.contains_key(&start) {
+            continue;
+        }
+        let commands = parse_with(file, start, &pointers)?;
+        for command in &commands {
+            if let (Some(spec), Some(Address::Internal(target))) = (command.spec(), command.pointer)
+            {
+                if spec.calls {
+                    queue.insert(target as usize);
+                }
+            }
+        }
+        scripts.insert(start, commands);
+    }
+
+    Ok(scripts)
+}
+
+/// A hitbox that a script makes, on the frame that it makes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hitbox {
+    /// offset of the `attack` command in the file
+    pub offset: usize,
+    /// the frame of the subaction that the hitbox is made on
+    pub frame: u32,
+    /// made by `attack_scaled`, which scales with the fighter's size
+    pub scaled: bool,
+    pub id: i32,
+    pub group: i32,
+    pub joint: i32,
+    pub damage: i32,
+    pub rebound: bool,
+    pub element: i32,
+    pub size: i32,
+    /// the offset of the hitbox from its joint
+    pub position: [i32; 3],
+    pub angle: i32,
+    pub knockback_scale: i32,
+    pub knockback_weight: i32,
+    pub knockback_base: i32,
+    pub shield_damage: i32,
+    pub sound_level: i32,
+    pub sound_kind: i32,
+    /// whether the hitbox hits grounded fighters (1), aerial ones (2), or both (3)
+    pub ground_air: i32,
+}
+
+impl Hitbox {
+    fn new(command: &Command, frame: u32) -> Option<Self> {
+        let name = command.spec()?.name;
+        if name != "attack" && name != "attack_scaled" {
+            return None;
+        }
+        let field = |name| command.field(name).unwrap_or_default();
+
+        Some(Self {
+            offset: command.offset,
+            frame,
+            scaled: name == "attack_scaled",
+            id: field("id"),
+            group: field("group"),
+            joint: field("joint"),
+            damage: field("damage"),
+            rebound: field("rebound") != 0,
+            element: field("element"),
+            size: field("size"),
+            position: [field("x"), field("y"), field("z")],
+            angle: field("angle"),
+            knockback_scale: field("knockback_scale"),
+            knockback_weight: field("knockback_weight"),
+            knockback_base: field("knockback_base"),
+            shield_damage: field("shield_damage"),
+            sound_level: field("sound_level"),
+            sound_kind: field("sound_kind"),
+            ground_air: field("ground_air"),
+        })
+    }
+}
+
+/// Run the script at `start` of `file` like the game does, frame by frame, and collect
+/// the hitboxes it makes. Subroutines, jumps, and loops in the same file are followed;
+/// those into other files are skipped.
+pub fn hitboxes(file: &[u8], start: usize, relocs: &[Relocation]) -> Result<Vec<Hitbox>> {
+    let pointers = display_list::pointers(relocs);
+    let mut scripts = BTreeMap::new();
+    let mut hitboxes = Vec::new();
+    let mut frame = 0u32;
+    // the script and the index of the command in it to run next
+    let mut at = (start, 0);
+    let mut calls = Vec::new();
+    // the start of each loop's body, and how many more times it runs
+    let mut loops: Vec<((usize, usize), u32)> = Vec::new();
+
+    for _ in 0..MAX_STEPS {
+        let script = match scripts.entry(at.0) {
+            Entry::Occupied(script) => script.into_mut(),
+            Entry::Vacant(entry) => entry.insert(parse_with(file, at.0, &pointers)?),
+        };
+        let Some(command) = script.get(at.1) else {
+            break;
+        };
+        let next = (at.0, at.1 + 1);
+        let target = match command.pointer {
+            Some(Address::Internal(target)) => Some((target as usize, 0)),
+            _ => None,
+        };
+        let value = |name| command.field(name).unwrap_or_default() as u32;
+        at = next;
+
+        match command.spec().map(|s| s.name) {
+            Some("end") => break,
+            Some("wait") => frame += value("frames"),
+            Some("wait_until") => frame = frame.max(value("frame")),
+            Some("attack" | "attack_scaled") => hitboxes.extend(Hitbox::new(command, frame)),
+            Some("subroutine") => {
+                if let Some(target) = target {
+                    calls.push(next);
+                    at = target;
+                }
+            }
+            Some("return") => match calls.pop() {
+                Some(back) => at = back,
+                None => break,
+            },
+            Some("goto") => match target {
+                Some(target) => at = target,
+                None => break,
+            },
+            Some("loop") => loops.push((next, value("count"))),
+            Some("loop_end") => {
+                if let Some((body, count)) = loops.last_mut() {
+                    *count = count.saturating_sub(1);
+                    if *count > 0 {
+                        at = *body;
+                    } else {
+                        loops.pop();
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(hitboxes)
+}
+
+/// A script assembled from text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assembled {
+    /// the offset from the script's `.script` line, where it goes in the file
+    pub start: Option<usize>,
+    pub words: Vec<u32>,
+    /// the pointers of the commands, by their offset from the start of the script
+    pub pointers: Vec<(usize, Address)>,
+}
+
+impl Assembled {
+    /// The size of the script in bytes
+    pub fn len(&self) -> usize {
+        self.words.len() * 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// Assemble scripts in the text format that [`Command`]s are displayed in: a command per
+/// line, as its name and `field=value` pairs (fields that are left out are 0), with the
+/// address of its pointer after them, or `.word` and a raw word. `.script OFFSET` starts
+/// a script that goes at `OFFSET` in the file; `#` starts a comment, and an `0xOFFSET:`
+/// before a command (as the disassembly prints) is ignored.
+pub fn assemble(text: &str) -> Result<Vec<Assembled>> {
+    let mut scripts = Vec::new();
+    let mut script = Assembled::default();
+    for (i, line) in text.lines().enumerate() {
+        let syntax = |reason: String| Error::ScriptSyntax {
+            line: i + 1,
+            reason,
+        };
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let line = match line.split_once(':') {
+            Some((offset, command)) if offset.starts_with("0x") => command.trim(),
+            _ => line,
+        };
+        let mut tokens = line.split_whitespace();
+        let Some(name) = tokens.next() else {
+            continue;
+        };
+
+        match name {
+            ".script" => {
+                let start = tokens
+                    .next()
+                    .and_then(number)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or_else(|| syntax(".script needs the offset of the script".into()))?;
+                if !script.is_empty() || script.start.is_some() {
+                    scripts.push(script);
+                }
+                script = Assembled {
+                    start: Some(start),
+                    ..Assembled::default()
+                };
+            }
+            ".word" => {
+                let word = tokens
+                    .next()
+                    .and_then(number)
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| syntax(".word needs a 32 bit value".into()))?;
+                script.words.push(word);
+            }
+            _ => {
+                let spec = spec_named(name)
+                    .ok_or_else(|| syntax(format!("unknown command <{}>", name)))?;
+                let mut words = vec![0u32; spec.words];
+                words[0] = (spec.opcode as u32) << 26;
+                let mut pointer = None;
+                for token in tokens {
+                    let Some((key, value)) = token.split_once('=') else {
+                        let addr = token
+                            .parse::<Address>()
+                            .map_err(|e| syntax(format!("{}", e)))?;
+                        pointer = Some(addr);
+                        continue;
+                    };
+                    let field =
+                        spec.fields.iter().find(|f| f.name == key).ok_or_else(|| {
+                            syntax(format!("{} has no field <{}>", spec.name, key))
+                        })?;
+                    let value =
+                        number(value).ok_or_else(|| syntax(format!("bad number <{}>", value)))?;
+                    if !field.set(&mut words, value) {
+                        return Err(syntax(format!(
+                            "{}={} doesn't fit in {} bits",
+                            key, value, field.bits
+                        ))
+                        .into());
+                    }
+                }
+                match (spec.pointer, pointer) {
+                    (Some(word), Some(addr)) => {
+                        // relocated pointers hold their target until the chains are linked
+                        words[word] = match addr {
+                            Address::Raw(raw) => raw,
+                            Address::Internal(offset) | Address::External { offset, .. } => {
+                                script.pointers.push((script.len() + word * 4, addr));
+                                offset
+                            }
+                        };
+                    }
+                    (Some(_), None) => {
+                        return Err(syntax(format!("{} needs an address", spec.name)).into())
+                    }
+                    (None, Some(addr)) => {
+                        return Err(
+                            syntax(format!("{} has no pointer for <{}>", spec.name, addr)).into(),
+                        )
+                    }
+                    (None, None) => (),
+                }
+                script.words.extend(words);
+            }
+        }
+    }
+    if !script.is_empty() || script.start.is_some() {
+        scripts.push(script);
+    }
+
+    Ok(scripts)
+}
+
+/// A decimal or `0x` hexadecimal number, which may be negative
+pub(crate) fn number(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let n = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+
+    Some(if negative { -n } else { n })
+}
+
+/// Replace the script at `start` of `file` with `script`, which has to fit in the place of
+/// the old one (up to the command that ends it). The rest of the place is filled with `end`
+/// commands. The pointers of the old script are removed from `relocs`, and those of the new
+/// one are added; internal pointers have to point to a word in the file.
+pub fn patch(
+    file: &mut [u8],
+    start: usize,
+    script: &Assembled,
+    relocs: &mut Vec<Relocation>,
+) -> Result<()> {
+    let old = parse(file, start, relocs)?;
+    let capacity = old
+        .last()
+        .map_or(0, |c| c.offset + c.words.len() * 4 - start);
+    if script.len() > capacity {
+        return Err(Error::ScriptTooLong {
+            start,
+            len: script.len(),
+            capacity,
+        }
+        .into());
+    }
+    for &(offset, addr) in &script.pointers {
+        if let Address::Internal(target) = addr {
+            if target as usize >= file.len() || target % 4 != 0 {
+                return Err(Error::ScriptPointer {
+                    offset: start + offset,
+                    target,
+                }
+                .into());
+            }
+        }
+    }
+
+    let end = start + capacity;
+    relocs.retain(|r| !(start..end).contains(&r.offset));
+    for (i, word) in script
+        .words
+        .iter()
+        .copied()
+        .chain(core::iter::repeat(0))
+        .take(capacity / 4)
+        .enumerate()
+    {
+        file[start + i * 4..start + i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    for &(offset, addr) in &script.pointers {
+        let (target, external_file) = match addr {
+            Address::Internal(offset) => (offset, None),
+            Address::External { file, offset } => (offset, Some(file)),
+            Address::Raw(_) => continue,
+        };
+        relocs.push(Relocation {
+            offset: start + offset,
+            target,
+            external_file,
+        });
+    }
+    relocs.sort_by_key(|r| (r.external_file.is_some(), r.offset));
+
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use crate::{
+    cli::{MusicOpt, SamplesOpt},
+    timings::Timings,
+};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use ssbfile::audio::{self, BankFile, Instrument, SeqBank, Sequence, Wave, WaveKind};
+use std::{collections::BTreeSet, fs};
+
+pub(crate) fn sequences(opt: MusicOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, _) = opt.rom.source(timings)?;
+    let offset = match opt.bank {
+        Some(offset) => offset as usize,
+        None => match audio::find_seq_banks(&rom).as_slice() {
+            [] => bail!("found no sequence bank in the rom; give its offset with --bank"),
+            [offset] => *offset,
+            banks => {
+                let banks = banks
+                    .iter()
+                    .map(|b| format!("{:#X}", b))
+                    .collect::<Vec<_>>();
+                bail!(
+                    "found sequence banks at {}; pick one with --bank",
+                    banks.join(", ")
+                )
+            }
+        },
+    };
+    let bank = SeqBank::read(&rom, offset)?;
+    let indices = if opt.sequences.is_empty() {
+        (0..bank.sequences.len()).collect()
+    } else {
+        opt.sequences.clone()
+    };
+
+    let Some(dir) = &opt.output else {
+        return print_sequences(&rom, &bank, &indices);
+    };
+
+    fs::create_dir_all(dir).with_context(|| format!("creating directory <{}>", dir.display()))?;
+    for &i in &indices {
+        let data = bank
+            .sequence(&rom, i)
+            .with_context(|| format!("no sequence <{}> in the bank", i))?;
+        let sequence =
+            Sequence::parse(data).with_context(|| format!("reading sequence <{}>", i))?;
+        let path = dir.join(format!("seq-{:03}.mid", i));
+        fs::write(&path, sequence.midi())
+            .with_context(|| format!("writing <{}>", path.display()))?;
+        if opt.raw {
+            let path = path.with_extension("cseq");
+            fs::write(&path, data).with_context(|| format!("writing <{}>", path.display()))?;
+        }
+    }
+    eprintln!("wrote {} sequences to <{}>", indices.len(), dir.display());
+
+    Ok(())
+}
+
+pub(crate) fn samples(opt: SamplesOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, _) = opt.rom.source(timings)?;
+    let ctl = match opt.ctl {
+        Some(offset) => offset as usize,
+        None => match audio::find_bank_files(&rom).as_slice() {
+            [] => bail!("found no bank file in the rom; give its offset with --ctl"),
+            [offset] => *offset,
+            files => {
+                let files = files
+                    .iter()
+                    .map(|b| format!("{:#X}", b))
+                    .collect::<Vec<_>>();
+                bail!(
+                    "found bank files at {}; pick one with --ctl",
+                    files.join(", ")
+                )
+            }
+        },
+    };
+    let banks = BankFile::read(&rom, ctl)?;
+    let tbl = opt.tbl.map_or_else(|| tbl_offset(&banks), |t| t as usize);
+    let samples = rom.get(tbl..).unwrap_or_default();
+
+    fs::create_dir_all(&opt.output)
+        .with_context(|| format!("creating directory <{}>", opt.output.display()))?;
+    let mut written = BTreeSet::new();
+    for bank in &banks.banks {
+        let instruments = bank
+            .percussion
+            .iter()
+            .chain(bank.instruments.iter().flatten());
+        for wave in instruments.flat_map(|i| i.sounds.iter().map(|s| &s.wave)) {
+            if !written.insert(wave.offset) {
+                continue;
+            }
+            let pcm = wave
+                .decode(samples)
+                .with_context(|| format!("decoding the sample at {:#X}", tbl + wave.base))?;
+            let path = opt.output.join(wave_name(wave));
+            fs::write(&path, audio::wav(&pcm, bank.sample_rate, wave.looped))
+                .with_context(|| format!("writing <{}>", path.display()))?;
+        }
+    }
+
+    let path = opt.output.join("banks.json");
+    fs::write(&path, manifest(&banks, tbl)?)
+        .with_context(|| format!("writing <{}>", path.display()))?;
+    eprintln!(
+        "wrote {} samples of {} banks to <{}>",
+        written.len(),
+        banks.banks.len(),
+        opt.output.display()
+    );
+
+    Ok(())
+}
+
+/// Print the rom offset, size, and number of tracks of the sequences `indices` of `bank`
+pub(crate) fn print_sequences(rom: &[u8], bank: &SeqBank, indices: &[usize]) -> Result<()> {
+    println!(
+        "sequence bank at {:#X}: {} sequences",
+        bank.offset,
+        bank.sequences.len()
+    );
+    println!("{:>5}  {:>10}  {:>8}  tracks", "seq", "offset", "size");
+    for &i in indices {
+        let &(start, len) = bank
+            .sequences
+            .get(i)
+            .with_context(|| format!("no sequence <{}> in the bank", i))?;
+        let tracks = bank
+            .sequence(rom, i)
+            .and_then(|data| Sequence::parse(data).ok())
+            .map_or_else(|| "?".into(), |s| s.tracks.len().to_string());
+        println!(
+            "{:5}  {:#10X}  {:#8X}  {}",
+            i,
+            bank.offset + start,
+            len,
+            tracks
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the banks of `banks`, and the rom offset, size, and kind of each of their samples
+pub(crate) fn print_banks(banks: &BankFile, tbl: usize) {
+    println!(
+        "bank file at {:#X} ({:#X} bytes), samples at {:#X}: {} banks",
+        banks.offset,
+        banks.len,
+        tbl,
+        banks.banks.len()
+    );
+    println!(
+        "{:>5}  {:>11}  {:>11}  percussion",
+        "bank", "sample rate", "instruments"
+    );
+    for (i, bank) in banks.banks.iter().enumerate() {
+        println!(
+            "{:5}  {:11}  {:11}  {}",
+            i,
+            bank.sample_rate,
+            bank.instruments.iter().flatten().count(),
+            if bank.percussion.is_some() {
+                "yes"
+            } else {
+                "no"
+            }
+        );
+    }
+    println!("{:>10}  {:>8}  {:5}  loop", "offset", "size", "kind");
+    for wave in banks.waves() {
+        let kind = match wave.kind {
+            WaveKind::Adpcm(_) => "adpcm",
+            WaveKind::Raw16 => "raw16",
+        };
+        let looped = wave
+            .looped
+            .map_or_else(String::new, |l| format!("{}..{}", l.start, l.end));
+        println!(
+            "{:#10X}  {:#8X}  {:5}  {}",
+            tbl + wave.base,
+            wave.len,
+            kind,
+            looped
+        );
+    }
+}
+
+/// The rom offset of the samples of `banks`: the first line after the bank file
+pub(crate) fn tbl_offset(banks: &BankFile) -> usize {
+    (banks.offset + banks.len).next_multiple_of(0x10)
+}
+
+fn wave_name(wave: &Wave) -> String {
+    format!("wave-{:06X}.wav", wave.base)
+}
+
+/// The banks of `banks` as json, with the WAV file of each sound
+fn manifest(banks: &BankFile, tbl: usize) -> Result<String> {
+    let json = ManifestJson {
+        ctl: banks.offset,
+        tbl,
+        banks: banks
+            .banks
+            .iter()
+            .enumerate()
+            .map(|(index, bank)| BankJson {
+                index,
+                sample_rate: bank.sample_rate,
+                percussion: bank.percussion.as_ref().map(InstrumentJson::from),
+                instruments: bank
+                    .instruments
+                    .iter()
+                    .map(|i| i.as_ref().map(InstrumentJson::from))
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    Ok(serde_json::to_string_pretty(&json)? + "\n")
+}
+
+#[derive(Serialize)]
+struct ManifestJson {
+    ctl: usize,
+    tbl: usize,
+    banks: Vec<BankJson>,
+}
+
+#[derive(Serialize)]
+struct BankJson {
+    index: usize,
+    sample_rate: u32,
+    percussion: Option<InstrumentJson>,
+    instruments: Vec<Option<InstrumentJson>>,
+}
+
+#[derive(Serialize)]
+struct InstrumentJson {
+    offset: usize,
+    volume: u8,
+    pan: u8,
+    sounds: Vec<SoundJson>,
+}
+
+impl From<&Instrument> for InstrumentJson {
+    fn from(i: &Instrument) -> Self {
+        Self {
+            offset: i.offset,
+            volume: i.volume,
+            pan: i.pan,
+            sounds: i
+                .sounds
+                .iter()
+                .map(|s| SoundJson {
+                    wave: wave_name(&s.wave),
+                    key_base: s.key_base,
+                    detune: s.detune,
+                    looped: s.wave.looped.as_ref().map(|l| LoopJson {
+                        start: l.start,
+                        end: l.end,
+                        count: l.count,
+                    }),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SoundJson {
+    wave: String,
+    key_base: u8,
+    detune: i8,
+    #[serde(rename = "loop")]
+    looped: Option<LoopJson>,
+}
+
+#[derive(Serialize)]
+struct LoopJson {
+    start: u32,
+    end: u32,
+    count: i32,
+}
@@ -0,0 +1,75 @@
+use alloc::{collections::BTreeMap, string::String};
+#[cfg(feature = "std")]
+use {
+    alloc::format,
+    anyhow::{Context, Result},
+    std::{fs, path::Path},
+};
+
+/// Names for the files of the resource table, like "Fox main file", loaded from yaml
+/// files that map ids to names:
+/// ```yaml
+/// 0x0C: my stage geometry
+/// 276: my fighter's main file
+/// ```
+/// No names are built in, so a `NameDb` starts empty and only holds the names that are
+/// loaded into it.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct NameDb {
+    names: BTreeMap<usize, String>,
+}
+
+impl NameDb {
+    /// Add the names in the yaml `text`. A loaded name replaces an earlier name for the same id.
+    #[cfg(feature = "std")]
+    pub fn load_yaml(&mut self, text: &str) -> Result<()> {
+        let names: BTreeMap<usize, String> =
+            serde_yaml::from_str(text).context("parsing file names")?;
+        self.names.extend(names);
+
+        Ok(())
+    }
+
+    /// Add the names in the yaml file at `path`
+    #[cfg(feature = "std")]
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("issue opening name file <{}>", path.display()))?;
+
+        self.load_yaml(&text)
+            .with_context(|| format!("loading name file <{}>", path.display()))
+    }
+
+    /// The name of file `id`, if it is known
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+
+    /// The name of file `id` for use in a filename: lowercase, with each run of
+    /// characters other than letters and digits replaced by a `-`
+    pub fn slug(&self, id: usize) -> Option<String> {
+        let name = self.get(id)?;
+        let mut slug = String::with_capacity(name.len());
+        for c in name.chars() {
+            if c.is_alphanumeric() {
+                slug.extend(c.to_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let len = slug.trim_end_matches('-').len();
+        slug.truncate(len);
+
+        Some(slug).filter(|s| !s.is_empty())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
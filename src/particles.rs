@@ -0,0 +1,35 @@
+use crate::{
+    cli::EffectOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::{effect, TableFile};
+
+pub(crate) fn disassemble(opt: EffectOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", opt.id))?;
+
+    let scripts = if opt.script.is_empty() {
+        println!("# effect bank of file {} at 0x{:04X}", opt.id, opt.bank);
+        effect::bank(&data, opt.bank as usize)
+            .with_context(|| format!("reading the effect bank of file <{}>", opt.id))?
+    } else {
+        println!("# effect scripts of file {}", opt.id);
+        opt.script
+            .iter()
+            .enumerate()
+            .map(|(i, &at)| {
+                effect::script(&data, at as usize, i)
+                    .with_context(|| format!("reading the effect script at {:#X}", at))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    print!("{}", effect::disassemble(&scripts));
+
+    Ok(())
+}
@@ -0,0 +1,503 @@
+//! Patches that turn a rom into a modified rom, so that mods can be shared without the rom
+use crate::{rom_info::crc32, Error};
+use alloc::{vec, vec::Vec};
+use anyhow::{ensure, Result};
+use core::fmt;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_FOOTER: &[u8] = b"EOF";
+/// offsets are 24-bit
+const IPS_MAX_OFFSET: usize = 0xFF_FFFF;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+/// a record at this offset would be read as the footer
+const IPS_EOF_OFFSET: usize = 0x45_4F46;
+/// the offset and size of a record
+const IPS_RECORD_HEADER: usize = 5;
+/// runs of the same byte that are at least this long are stored run-length encoded
+const IPS_MIN_RUN: usize = 8;
+
+/// Create an IPS patch that turns `original` into `modified`.
+///
+/// A rom that grows is extended by the patch, and a rom that shrinks is truncated with
+/// the common extension of a size after the footer. IPS offsets are 24-bit, so only the
+/// first 16 MiB of a rom can be patched.
+pub fn ips(original: &[u8], modified: &[u8]) -> Result<Vec<u8>> {
+    let mut patch = IPS_MAGIC.to_vec();
+    for (start, end) in changes(original, modified) {
+        let mut pos = start;
+        while pos < end {
+            // move the record back a byte, so that its offset isn't read as "EOF"
+            if pos == IPS_EOF_OFFSET {
+                ips_record(&mut patch, pos - 1, &modified[pos - 1..pos + 1])?;
+                pos += 1;
+                continue;
+            }
+
+            let run = run_length(&modified[pos..end]).min(IPS_MAX_RECORD);
+            if run >= IPS_MIN_RUN {
+                ips_run(&mut patch, pos, run as u16, modified[pos])?;
+                pos += run;
+            } else {
+                // copy up to the next run (or the "EOF" offset)
+                let mut len = 1;
+                while pos + len < end
+                    && len < IPS_MAX_RECORD
+                    && pos + len != IPS_EOF_OFFSET
+                    && run_length(&modified[pos + len..end]) < IPS_MIN_RUN
+                {
+                    len += 1;
+                }
+                ips_record(&mut patch, pos, &modified[pos..pos + len])?;
+                pos += len;
+            }
+        }
+    }
+    patch.extend_from_slice(IPS_FOOTER);
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&ips_offset(modified.len())?);
+    }
+
+    Ok(patch)
+}
+
+/// Apply an IPS `patch` to `rom`
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let malformed = |reason| Error::MalformedPatch { reason };
+    let mut patch = patch
+        .strip_prefix(IPS_MAGIC)
+        .ok_or(malformed("no \"PATCH\" header"))?;
+    let mut out = rom.to_vec();
+
+    loop {
+        if let Some(rest) = patch.strip_prefix(IPS_FOOTER) {
+            match *rest {
+                [] => break,
+                [a, b, c] => {
+                    out.truncate(u32::from_be_bytes([0, a, b, c]) as usize);
+                    break;
+                }
+                _ => return Err(malformed("data after the \"EOF\" footer").into()),
+            }
+        }
+        let mut take = |len: usize| -> Result<&[u8]> {
+            ensure!(len <= patch.len(), malformed("truncated record"));
+            let (head, rest) = patch.split_at(len);
+            patch = rest;
+            Ok(head)
+        };
+
+        let offset = take(3)?;
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        let size = take(2)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+        let (len, data) = match size {
+            0 => {
+                let run = take(3)?;
+                (u16::from_be_bytes([run[0], run[1]]) as usize, Some(run[2]))
+            }
+            _ => (size, None),
+        };
+        if out.len() < offset + len {
+            out.resize(offset + len, 0);
+        }
+        match data {
+            Some(byte) => out[offset..offset + len].fill(byte),
+            None => out[offset..offset + len].copy_from_slice(take(len)?),
+        }
+    }
+
+    Ok(out)
+}
+
+/// The ranges of `modified` that differ from `original` (or are past its end),
+/// with short unchanged gaps merged into the ranges around them
+fn changes(original: &[u8], modified: &[u8]) -> Vec<(usize, usize)> {
+    let mut changes: Vec<(usize, usize)> = Vec::new();
+    for (i, byte) in modified.iter().enumerate() {
+        if original.get(i) == Some(byte) {
+            continue;
+        }
+        match changes.last_mut() {
+            // a new record costs more than copying the unchanged bytes
+            Some((_, end)) if i - *end <= IPS_RECORD_HEADER => *end = i + 1,
+            _ => changes.push((i, i + 1)),
+        }
+    }
+
+    changes
+}
+
+/// How many times the first byte of `data` repeats at its start
+fn run_length(data: &[u8]) -> usize {
+    data.first()
+        .map_or(0, |first| data.iter().take_while(|b| *b == first).count())
+}
+
+fn ips_offset(offset: usize) -> Result<[u8; 3]> {
+    ensure!(offset <= IPS_MAX_OFFSET, Error::IpsOffset { offset });
+    let [_, a, b, c] = (offset as u32).to_be_bytes();
+
+    Ok([a, b, c])
+}
+
+fn ips_record(patch: &mut Vec<u8>, offset: usize, data: &[u8]) -> Result<()> {
+    patch.extend_from_slice(&ips_offset(offset)?);
+    patch.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    patch.extend_from_slice(data);
+
+    Ok(())
+}
+
+fn ips_run(patch: &mut Vec<u8>, offset: usize, len: u16, byte: u8) -> Result<()> {
+    patch.extend_from_slice(&ips_offset(offset)?);
+    patch.extend_from_slice(&[0, 0]);
+    patch.extend_from_slice(&len.to_be_bytes());
+    patch.push(byte);
+
+    Ok(())
+}
+
+/// A GameShark code that writes to RAM every frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameSharkCode {
+    /// `80XXXXXX 00YY`
+    Byte { addr: u32, value: u8 },
+    /// `81XXXXXX YYYY`, to an even address
+    Half { addr: u32, value: u16 },
+}
+
+impl fmt::Display for GameSharkCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Byte { addr, value } => write!(f, "80{:06X} {:04X}", addr & 0xFF_FFFF, value),
+            Self::Half { addr, value } => write!(f, "81{:06X} {:04X}", addr & 0xFF_FFFF, value),
+        }
+    }
+}
+
+/// The GameShark codes that change a file loaded at `base` in RAM from `original`
+/// to `modified`, for testing an edit without rebuilding the rom. The file has to
+/// be changed in place, so both have to be the same length.
+///
+/// Changes are written as halfwords where they can be, and bytes where they can't.
+pub fn gameshark(original: &[u8], modified: &[u8], base: u32) -> Result<Vec<GameSharkCode>> {
+    ensure!(
+        original.len() == modified.len(),
+        "the modified file is {:#X} bytes, and the original is {:#X}; only edits in place can be codes",
+        modified.len(),
+        original.len()
+    );
+
+    let mut codes = Vec::new();
+    let mut i = 0;
+    while i < modified.len() {
+        if original[i] == modified[i] {
+            i += 1;
+            continue;
+        }
+        let addr = (i as u32)
+            .checked_add(base)
+            .filter(|addr| (0x8000_0000..0x8100_0000).contains(addr))
+            .ok_or(Error::GameSharkAddress {
+                addr: base.wrapping_add(i as u32),
+            })?;
+        if addr % 2 == 0 && i + 1 < modified.len() {
+            let value = u16::from_be_bytes([modified[i], modified[i + 1]]);
+            codes.push(GameSharkCode::Half { addr, value });
+            i += 2;
+        } else {
+            let value = modified[i];
+            codes.push(GameSharkCode::Byte { addr, value });
+            i += 1;
+        }
+    }
+
+    Ok(codes)
+}
+
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// the three crc32s at the end of a patch
+const BPS_FOOTER: usize = 12;
+/// copies shorter than this are stored as literal bytes instead
+const BPS_MIN_COPY: usize = 16;
+/// the bytes hashed to find data that moved in the source
+const BPS_HASH_LEN: usize = 8;
+const BPS_HASH_BITS: u32 = 22;
+
+const SOURCE_READ: u64 = 0;
+const TARGET_READ: u64 = 1;
+const SOURCE_COPY: u64 = 2;
+const TARGET_COPY: u64 = 3;
+
+/// Create a BPS patch that turns `original` into `modified`.
+///
+/// Unlike IPS, data that moved (like the files after one that grew) is copied from
+/// its old position in `original`, and the patch checks the crc32s of both roms.
+pub fn bps(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let mut patch = BPS_MAGIC.to_vec();
+    write_varint(&mut patch, original.len() as u64);
+    write_varint(&mut patch, modified.len() as u64);
+    // no metadata
+    write_varint(&mut patch, 0);
+
+    let index = SourceIndex::new(original);
+    let mut encoder = BpsEncoder {
+        patch,
+        source_offset: 0,
+        target_offset: 0,
+    };
+    let mut literal = 0;
+    let mut pos = 0;
+    while pos < modified.len() {
+        let rest = &modified[pos..];
+        let same = original
+            .get(pos..)
+            .map_or(0, |source| common_len(source, rest));
+        let run = match pos {
+            0 => 0,
+            _ => rest.iter().take_while(|&&b| b == modified[pos - 1]).count(),
+        };
+        let moved = index
+            .find(rest)
+            .map(|at| (at, common_len(&original[at..], rest)))
+            .filter(|&(_, len)| len > same && len > run);
+
+        let (action, len) = if same >= BPS_MIN_COPY {
+            ((SOURCE_READ, 0), same)
+        } else if let Some((at, len)) = moved.filter(|&(_, len)| len >= BPS_MIN_COPY) {
+            ((SOURCE_COPY, at), len)
+        } else if run >= BPS_MIN_COPY {
+            // copying from the byte before repeats it
+            ((TARGET_COPY, pos - 1), run)
+        } else {
+            literal += 1;
+            pos += 1;
+            continue;
+        };
+
+        encoder.target_read(&modified[pos - literal..pos]);
+        literal = 0;
+        match action {
+            (SOURCE_READ, _) => encoder.action(SOURCE_READ, len),
+            (kind, at) => encoder.copy(kind, at, len),
+        }
+        pos += len;
+    }
+    encoder.target_read(&modified[pos - literal..pos]);
+
+    let mut patch = encoder.patch;
+    patch.extend_from_slice(&crc32(original).to_le_bytes());
+    patch.extend_from_slice(&crc32(modified).to_le_bytes());
+    let crc = crc32(&patch);
+    patch.extend_from_slice(&crc.to_le_bytes());
+
+    patch
+}
+
+/// Apply a BPS `patch` to `rom`, checking that `rom` is the rom that the patch was made for
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let malformed = |reason| Error::MalformedPatch { reason };
+    let body_len = patch
+        .len()
+        .checked_sub(BPS_FOOTER)
+        .ok_or(malformed("too short"))?;
+    let (body, footer) = patch.split_at(body_len);
+    let footer_crc =
+        |i: usize| u32::from_le_bytes([footer[i], footer[i + 1], footer[i + 2], footer[i + 3]]);
+    ensure!(
+        crc32(&patch[..patch.len() - 4]) == footer_crc(8),
+        malformed("the patch's crc32 doesn't match")
+    );
+    let mut body = body
+        .strip_prefix(BPS_MAGIC)
+        .ok_or(malformed("no \"BPS1\" header"))?;
+
+    let source_len = read_varint(&mut body)? as usize;
+    let target_len = read_varint(&mut body)? as usize;
+    ensure!(
+        source_len == rom.len() && crc32(rom) == footer_crc(0),
+        Error::WrongPatchSource
+    );
+    let metadata = read_varint(&mut body)? as usize;
+    body = body
+        .get(metadata..)
+        .ok_or(malformed("truncated metadata"))?;
+
+    let mut out = vec![0; target_len];
+    let (mut pos, mut source_offset, mut target_offset) = (0usize, 0i64, 0i64);
+    while !body.is_empty() {
+        let action = read_varint(&mut body)?;
+        let len = (action >> 2) as usize + 1;
+        let end = pos
+            .checked_add(len)
+            .filter(|&end| end <= target_len)
+            .ok_or(malformed("writes past the end of the rom"))?;
+        match action & 3 {
+            SOURCE_READ => {
+                let source = rom
+                    .get(pos..end)
+                    .ok_or(malformed("reads past the source"))?;
+                out[pos..end].copy_from_slice(source);
+            }
+            TARGET_READ => {
+                let data = body.get(..len).ok_or(malformed("truncated data"))?;
+                out[pos..end].copy_from_slice(data);
+                body = &body[len..];
+            }
+            SOURCE_COPY => {
+                source_offset += read_signed(&mut body)?;
+                let start = usize::try_from(source_offset)
+                    .map_err(|_| malformed("copies from before the source"))?;
+                let source = start
+                    .checked_add(len)
+                    .and_then(|end| rom.get(start..end))
+                    .ok_or(malformed("copies past the source"))?;
+                out[pos..end].copy_from_slice(source);
+                source_offset += len as i64;
+            }
+            _ => {
+                target_offset += read_signed(&mut body)?;
+                let start = usize::try_from(target_offset)
+                    .ok()
+                    .filter(|&start| start < pos)
+                    .ok_or(malformed("copies from the target that isn't written yet"))?;
+                // the copy can overlap what it writes, so go byte by byte
+                for i in 0..len {
+                    out[pos + i] = out[start + i];
+                }
+                target_offset += len as i64;
+            }
+        }
+        pos = end;
+    }
+    ensure!(pos == target_len, malformed("doesn't write the whole rom"));
+    ensure!(
+        crc32(&out) == footer_crc(4),
+        malformed("the patched rom's crc32 doesn't match")
+    );
+
+    Ok(out)
+}
+
+struct BpsEncoder {
+    patch: Vec<u8>,
+    source_offset: usize,
+    target_offset: usize,
+}
+
+impl BpsEncoder {
+    fn action(&mut self, kind: u64, len: usize) {
+        write_varint(&mut self.patch, ((len as u64 - 1) << 2) | kind);
+    }
+
+    fn target_read(&mut self, data: &[u8]) {
+        if !data.is_empty() {
+            self.action(TARGET_READ, data.len());
+            self.patch.extend_from_slice(data);
+        }
+    }
+
+    /// Copy `len` bytes from `at` in the source or target; the offset is stored
+    /// relative to the end of the last copy of the same kind
+    fn copy(&mut self, kind: u64, at: usize, len: usize) {
+        self.action(kind, len);
+        let last = match kind {
+            SOURCE_COPY => &mut self.source_offset,
+            _ => &mut self.target_offset,
+        };
+        let delta = at as i64 - *last as i64;
+        write_varint(
+            &mut self.patch,
+            (delta.unsigned_abs() << 1) | (delta < 0) as u64,
+        );
+        *last = at + len;
+    }
+}
+
+/// Where each hash of [`BPS_HASH_LEN`] bytes was last seen in the source,
+/// at word aligned offsets (files are word aligned, so they move by whole words)
+struct SourceIndex<'a> {
+    source: &'a [u8],
+    table: Vec<u32>,
+}
+
+impl<'a> SourceIndex<'a> {
+    const EMPTY: u32 = u32::MAX;
+
+    fn new(source: &'a [u8]) -> Self {
+        let mut table = vec![Self::EMPTY; 1 << BPS_HASH_BITS];
+        for (i, window) in source.windows(BPS_HASH_LEN).enumerate().step_by(4) {
+            table[hash(window)] = i as u32;
+        }
+
+        Self { source, table }
+    }
+
+    /// An offset of the source that may start with the same bytes as `data`
+    fn find(&self, data: &[u8]) -> Option<usize> {
+        let window = data.get(..BPS_HASH_LEN)?;
+        let at = self.table[hash(window)];
+
+        (at != Self::EMPTY && self.source[at as usize..].starts_with(window)).then_some(at as usize)
+    }
+}
+
+fn hash(window: &[u8]) -> usize {
+    let word = u64::from_be_bytes(window.try_into().expect("hash window"));
+
+    (word.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - BPS_HASH_BITS)) as usize
+}
+
+/// How many bytes `a` and `b` have in common at their start
+fn common_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// BPS numbers: 7 bits at a time, with the high bit marking the last byte
+fn write_varint(patch: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let bits = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | bits);
+            break;
+        }
+        patch.push(bits);
+        value -= 1;
+    }
+}
+
+fn read_varint(data: &mut &[u8]) -> Result<u64> {
+    let too_large = Error::MalformedPatch {
+        reason: "number is too large",
+    };
+    let (mut value, mut shift) = (0u64, 1u64);
+    loop {
+        let (&byte, rest) = data.split_first().ok_or(Error::MalformedPatch {
+            reason: "truncated number",
+        })?;
+        *data = rest;
+        value = (byte as u64 & 0x7F)
+            .checked_mul(shift)
+            .and_then(|bits| value.checked_add(bits))
+            .ok_or(too_large.clone())?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift.checked_mul(0x80).ok_or(too_large.clone())?;
+        value = value.checked_add(shift).ok_or(too_large.clone())?;
+    }
+}
+
+/// A relative offset: the magnitude, and the sign in the low bit
+fn read_signed(data: &mut &[u8]) -> Result<i64> {
+    let value = read_varint(data)?;
+    let magnitude = (value >> 1) as i64;
+
+    Ok(if value & 1 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
@@ -0,0 +1,726 @@
+use crate::{versions::SSBInfo, Error, RelocHeads};
+use alloc::{collections::BTreeMap, format, vec, vec::Vec};
+use anyhow::{bail, ensure, Context, Result};
+
+/// A raw 12 byte entry in the resource table
+/// ```text
+/// u32 offset       // from the end of the table; 0x80000000 is set for vpk0 files
+/// u16 inreloc      // (offset / 4) of the first internal relocation, or 0xFFFF
+/// u16 size         // (size / 4) of the data stored in the rom
+/// u16 exreloc      // (offset / 4) of the first external relocation, or 0xFFFF
+/// u16 decompressed // (size / 4) of the data once decompressed
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TableEntry {
+    pub offset: u32,
+    pub compressed: bool,
+    pub inreloc: u16,
+    pub size: u16,
+    pub exreloc: u16,
+    pub decompressed_size: u16,
+}
+
+impl TableEntry {
+    pub const SIZE: usize = 12;
+    const COMPRESS_BIT: u32 = 0x80000000;
+
+    pub fn from_bytes(raw: &[u8; Self::SIZE]) -> Self {
+        let offset = u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]);
+
+        Self {
+            offset: offset & !Self::COMPRESS_BIT,
+            compressed: offset & Self::COMPRESS_BIT > 0,
+            inreloc: u16::from_be_bytes([raw[4], raw[5]]),
+            size: u16::from_be_bytes([raw[6], raw[7]]),
+            exreloc: u16::from_be_bytes([raw[8], raw[9]]),
+            decompressed_size: u16::from_be_bytes([raw[10], raw[11]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let offset = if self.compressed {
+            self.offset | Self::COMPRESS_BIT
+        } else {
+            self.offset
+        };
+
+        let mut raw = [0; Self::SIZE];
+        raw[0..4].copy_from_slice(&offset.to_be_bytes());
+        raw[4..6].copy_from_slice(&self.inreloc.to_be_bytes());
+        raw[6..8].copy_from_slice(&self.size.to_be_bytes());
+        raw[8..10].copy_from_slice(&self.exreloc.to_be_bytes());
+        raw[10..12].copy_from_slice(&self.decompressed_size.to_be_bytes());
+        raw
+    }
+}
+
+/// The dummy entry at the end of the resource table
+/// ```text
+/// u32 data_end      // offset from the end of the table to the end of the file data
+/// u32 next_table    // offset from the end of the table to the secondary (image) table
+/// u32 unknown
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TerminalEntry {
+    pub data_end: u32,
+    pub next_table: u32,
+    pub unknown: u32,
+}
+
+impl TerminalEntry {
+    pub fn from_bytes(raw: &[u8; TableEntry::SIZE]) -> Self {
+        Self {
+            data_end: u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]),
+            next_table: u32::from_be_bytes([raw[4], raw[5], raw[6], raw[7]]),
+            unknown: u32::from_be_bytes([raw[8], raw[9], raw[10], raw[11]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; TableEntry::SIZE] {
+        let mut raw = [0; TableEntry::SIZE];
+        raw[0..4].copy_from_slice(&self.data_end.to_be_bytes());
+        raw[4..8].copy_from_slice(&self.next_table.to_be_bytes());
+        raw[8..12].copy_from_slice(&self.unknown.to_be_bytes());
+        raw
+    }
+}
+
+/// Read every entry of the resource table, and the dummy entry at its end
+pub fn read_table(rom: &[u8], info: &SSBInfo) -> Result<(Vec<TableEntry>, TerminalEntry)> {
+    let table = rom
+        .get(info.table_start..info.table_end)
+        .ok_or(Error::OutsideRom {
+            start: info.table_start,
+            end: info.table_end,
+            len: rom.len(),
+        })?;
+    let mut raw_entries = table
+        .chunks_exact(TableEntry::SIZE)
+        .map(|raw| raw.try_into().unwrap());
+
+    let entries = raw_entries
+        .by_ref()
+        .take(info.total_entries())
+        .map(TableEntry::from_bytes)
+        .collect::<Vec<_>>();
+    let terminal = raw_entries
+        .next()
+        .map(TerminalEntry::from_bytes)
+        .ok_or_else(|| anyhow::anyhow!("resource table is missing its terminal entry"))?;
+
+    Ok((entries, terminal))
+}
+
+/// Serialize every entry of the resource table, followed by the dummy entry at its end
+pub fn table_bytes(entries: &[TableEntry], terminal: &TerminalEntry) -> Vec<u8> {
+    entries
+        .iter()
+        .map(TableEntry::to_bytes)
+        .chain(core::iter::once(terminal.to_bytes()))
+        .flatten()
+        .collect()
+}
+
+/// Write the resource table (`entries` and the `terminal` entry) back into `rom`
+pub fn write_table(
+    rom: &mut [u8],
+    info: &SSBInfo,
+    entries: &[TableEntry],
+    terminal: &TerminalEntry,
+) -> Result<()> {
+    ensure!(
+        entries.len() == info.total_entries(),
+        Error::EntryCount {
+            expected: info.total_entries(),
+            found: entries.len()
+        }
+    );
+    let len = rom.len();
+    let table = rom
+        .get_mut(info.table_start..info.table_end)
+        .ok_or(Error::OutsideRom {
+            start: info.table_start,
+            end: info.table_end,
+            len,
+        })?;
+    table.copy_from_slice(&table_bytes(entries, terminal));
+
+    Ok(())
+}
+
+/// Pack a file's stored data and its list of external file ids into
+/// the bytes that are stored in the rom
+pub fn pack_file(stored: &[u8], externs: &[u16]) -> Vec<u8> {
+    let mut packed = stored.to_vec();
+    packed.resize(stored.len().next_multiple_of(4), 0);
+    packed.extend(externs.iter().flat_map(|e| e.to_be_bytes()));
+    packed.resize(packed.len().next_multiple_of(4), 0);
+
+    packed
+}
+
+/// The earlier file whose data each file of `entries` shares, if any: a file whose
+/// offset goes back to the offset of a file before it, instead of following the
+/// files before it (see [`DataRegion::share_duplicates`])
+pub fn shared_files(entries: &[TableEntry]) -> Vec<Option<usize>> {
+    let mut shared = vec![None; entries.len()];
+    let mut offsets = BTreeMap::new();
+    let mut last = 0;
+    for (id, entry) in entries.iter().enumerate() {
+        if entry.offset < last {
+            if let Some(&first) = offsets.get(&entry.offset) {
+                shared[id] = Some(first);
+                continue;
+            }
+        }
+        // the last file at an offset is the one that isn't empty
+        offsets.insert(entry.offset, id);
+        last = entry.offset;
+    }
+
+    shared
+}
+
+/// Where the bytes of each file of `entries` end, as an offset from the end of the table:
+/// the offset of the next file with data of its own, or the end of the stored data of a
+/// file that shares another's
+pub fn file_ends(entries: &[TableEntry], terminal: &TerminalEntry) -> Vec<u32> {
+    let shared = shared_files(entries);
+    let mut ends = vec![terminal.data_end; entries.len()];
+    let mut next = terminal.data_end;
+    for (id, entry) in entries.iter().enumerate().rev() {
+        if shared[id].is_some() {
+            ends[id] = entry.offset + entry.size as u32 * 4;
+        } else {
+            ends[id] = next;
+            next = entry.offset;
+        }
+    }
+
+    ends
+}
+
+/// The resource table and the file data that follows it, split up so that
+/// files can be replaced and the whole region laid out again.
+#[derive(Debug, Clone)]
+pub struct DataRegion {
+    entries: Vec<TableEntry>,
+    /// everything from a file's offset to the next file's offset:
+    /// the data, the list of external file ids, and any padding
+    files: Vec<Vec<u8>>,
+    /// the earlier file whose data a file shares, in place of bytes of its own
+    shared: Vec<Option<usize>>,
+    terminal: TerminalEntry,
+}
+
+impl DataRegion {
+    pub fn read(rom: &[u8], info: &SSBInfo) -> Result<Self> {
+        let (entries, terminal) = read_table(rom, info)?;
+        let shared = shared_files(&entries);
+
+        let ends = file_ends(&entries, &terminal);
+        let files = entries
+            .iter()
+            .zip(ends)
+            .enumerate()
+            .map(|(id, (entry, end))| {
+                if shared[id].is_some() {
+                    return Ok(Vec::new());
+                }
+                let start = info.table_end + entry.offset as usize;
+                let end = info.table_end + end as usize;
+                rom.get(start..end)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| {
+                        anyhow::Error::from(Error::OutsideRom {
+                            start,
+                            end,
+                            len: rom.len(),
+                        })
+                    })
+                    .with_context(|| format!("reading data of file <{}>", id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            entries,
+            files,
+            shared,
+            terminal,
+        })
+    }
+
+    pub fn entries(&self) -> &[TableEntry] {
+        &self.entries
+    }
+
+    pub fn terminal(&self) -> &TerminalEntry {
+        &self.terminal
+    }
+
+    /// The stored bytes of file `id`, including its external file list and padding
+    /// (or the bytes of the file whose data it shares)
+    pub fn file(&self, id: usize) -> Option<&[u8]> {
+        let id = self.shared.get(id).copied()?.unwrap_or(id);
+        self.files.get(id).map(Vec::as_slice)
+    }
+
+    /// The earlier file whose data file `id` shares, if it doesn't have its own
+    pub fn shares(&self, id: usize) -> Option<usize> {
+        self.shared.get(id).copied().flatten()
+    }
+
+    /// Replace the stored bytes and table entry of file `id`.
+    /// The offset of `entry` is ignored, as it is recalculated on [`DataRegion::write`].
+    pub fn replace(&mut self, id: usize, entry: TableEntry, data: Vec<u8>) -> Result<()> {
+        ensure!(
+            id < self.entries.len(),
+            Error::FileIdOutOfRange {
+                id,
+                count: self.entries.len()
+            }
+        );
+        // the files that share the old data keep a copy of it
+        for other in 0..self.shared.len() {
+            if self.shared[other] == Some(id) {
+                self.unshare(other);
+            }
+        }
+        self.entries[id] = entry;
+        self.files[id] = data;
+        self.shared[id] = None;
+
+        Ok(())
+    }
+
+    /// Point each file whose table entry and stored data are the same as an earlier file's
+    /// at the data of that file, and return the bytes that this saves.
+    ///
+    /// The game finds the list of external file ids of a file at the end of its data, and
+    /// the end of a file's data from the offset of the next file, so a file only shares
+    /// data when neither it nor the file before it has external files. The file that
+    /// shares data has to follow a file with data of its own that comes after the shared
+    /// data, so that its offset going back marks it as shared (see [`shared_files`]).
+    pub fn share_duplicates(&mut self) -> usize {
+        let mut first = BTreeMap::new();
+        let mut saved = 0;
+        for id in 0..self.entries.len() {
+            let entry = self.entries[id];
+            if self.shared[id].is_some() || entry.size == 0 {
+                continue;
+            }
+            let stored = &self.files[id][..(entry.size as usize * 4).min(self.files[id].len())];
+            let key = (
+                TableEntry { offset: 0, ..entry }.to_bytes(),
+                stored.to_vec(),
+            );
+            match first.get(&key) {
+                Some(&target) if self.can_share(target, id) => {
+                    saved += self.files[id].len();
+                    self.files[id] = Vec::new();
+                    self.shared[id] = Some(target);
+                }
+                Some(_) => (),
+                None => {
+                    first.insert(key, id);
+                }
+            }
+        }
+
+        saved
+    }
+
+    /// If file `id` can share the data of the earlier file `target`
+    fn can_share(&self, target: usize, id: usize) -> bool {
+        self.entries[id].exreloc == NO_RELOCS
+            && self.entries[id - 1].exreloc == NO_RELOCS
+            && (target + 1..id).any(|f| self.shared[f].is_none() && !self.files[f].is_empty())
+    }
+
+    /// Give file `id` a copy of the data that it shares
+    fn unshare(&mut self, id: usize) {
+        if let Some(target) = self.shared[id].take() {
+            let size = self.entries[id].size as usize * 4;
+            let stored = &self.files[target][..size.min(self.files[target].len())];
+            self.files[id] = stored.to_vec();
+        }
+    }
+
+    /// Add a new file at the end of the table, and return its id. The offset of `entry`
+    /// is ignored, as it is recalculated on [`DataRegion::write`].
+    ///
+    /// The data of the files starts after the table, so growing it moves the data of every
+    /// file, and the game's code has the size of the table built in: it won't load any file
+    /// of the rom unless it is patched. The rom's version info also changes; see
+    /// [`DataRegion::table_info`].
+    pub fn push(&mut self, entry: TableEntry, data: Vec<u8>) -> usize {
+        self.entries.push(entry);
+        self.files.push(data);
+        self.shared.push(None);
+
+        self.entries.len() - 1
+    }
+
+    /// `info`, with the end of the table moved to fit every entry of this region
+    pub fn table_info(&self, info: &SSBInfo) -> SSBInfo {
+        SSBInfo {
+            table_end: info.table_start + (self.entries.len() + 1) * TableEntry::SIZE,
+            ..info.clone()
+        }
+    }
+
+    /// Lay out the files one after another, and write the table and data back into `rom`.
+    ///
+    /// The terminal entry is recalculated to mark the new end of the data. Anything after the
+    /// old end of the data (like the secondary table) is moved to follow the new end,
+    /// and the rom is expanded if there is not enough space. If files were added with
+    /// [`DataRegion::push`], the table grows into the start of the data, and
+    /// everything after it is moved down.
+    pub fn write(&mut self, rom: &mut Vec<u8>, info: &SSBInfo) -> Result<()> {
+        let old_data_end = self.terminal.data_end;
+        let old_end = info.table_end + old_data_end as usize;
+        ensure!(
+            old_end <= rom.len(),
+            Error::OutsideRom {
+                start: info.table_end,
+                end: old_end,
+                len: rom.len()
+            }
+        );
+        let new_info = self.table_info(info);
+        let growth = new_info.table_end.saturating_sub(info.table_end);
+        let trailing = rom.split_off(old_end);
+        rom.truncate(info.table_end);
+        rom.resize(new_info.table_end, 0);
+
+        // files that were replaced since they were shared get their own copy
+        for id in 0..self.shared.len() {
+            if let Some(target) = self.shared[id] {
+                if !self.can_share(target, id) {
+                    self.unshare(id);
+                }
+            }
+        }
+        for id in 0..self.entries.len() {
+            if let Some(target) = self.shared[id] {
+                self.entries[id].offset = self.entries[target].offset;
+                continue;
+            }
+            let file = &mut self.files[id];
+            while !file.len().is_multiple_of(4) {
+                file.push(0);
+            }
+            self.entries[id].offset = (rom.len() - new_info.table_end) as u32;
+            rom.extend_from_slice(file);
+        }
+
+        self.terminal.data_end = (rom.len() - new_info.table_end) as u32;
+        // if the data shrunk, pad so that everything after it stays in place
+        if rom.len() < old_end {
+            rom.resize(old_end, 0);
+        }
+        let shift = rom.len() - old_end;
+        if self.terminal.next_table >= old_data_end {
+            // the offset is from the end of the table, which moved by `growth`
+            self.terminal.next_table = (self.terminal.next_table as usize + shift - growth)
+                .try_into()
+                .ok()
+                .context("the secondary table was moved past the 4 GiB offset limit")?;
+        }
+        rom.extend_from_slice(&trailing);
+
+        write_table(rom, &new_info, &self.entries, &self.terminal)
+    }
+}
+
+const NO_RELOCS: u16 = 0xFFFF;
+
+/// Where file `id` sits in the rom: from the start of its data to the next file
+struct Slot {
+    entry: TableEntry,
+    start: usize,
+    end: usize,
+    /// the list of external file ids that sits between the data and the next file
+    externs: Vec<u8>,
+}
+
+impl Slot {
+    fn find(rom: &[u8], info: &SSBInfo, id: usize) -> Result<Self> {
+        let (entries, terminal) = read_table(rom, info)?;
+        let Some(&entry) = entries.get(id) else {
+            bail!(Error::FileIdOutOfRange {
+                id,
+                count: entries.len()
+            });
+        };
+        let next = file_ends(&entries, &terminal)[id];
+
+        let start = info.table_end + entry.offset as usize;
+        let end = info.table_end + next as usize;
+        ensure!(
+            start + entry.size as usize * 4 <= end && end <= rom.len(),
+            Error::OutsideRom {
+                start,
+                end,
+                len: rom.len()
+            }
+        );
+        let externs = if entry.exreloc != NO_RELOCS {
+            rom[start + entry.size as usize * 4..end].to_vec()
+        } else {
+            Vec::new()
+        };
+        // the data of a file that is shared with others can't be replaced in place
+        let shared = shared_files(&entries);
+        if shared[id].is_some() || shared.contains(&Some(id)) {
+            return Ok(Self {
+                entry,
+                start,
+                end: start,
+                externs: Vec::new(),
+            });
+        }
+
+        Ok(Self {
+            entry,
+            start,
+            end,
+            externs,
+        })
+    }
+
+    /// The bytes available for the stored data
+    fn capacity(&self) -> usize {
+        self.end - self.start - self.externs.len()
+    }
+}
+
+/// The most bytes that the stored data of file `id` can take up, to be
+/// replaced in place with [`inject`]
+pub fn slot_capacity(rom: &[u8], info: &SSBInfo, id: usize) -> Result<usize> {
+    Slot::find(rom, info, id).map(|slot| slot.capacity())
+}
+
+/// Replace the data of file `id` in its current place in the rom.
+///
+/// `stored` is the data as it will be stored in the rom (so vpk0 compressed, if the
+/// entry is compressed), and `decompressed_size` is the size of the data once decompressed.
+/// The list of external file ids that follows the data is kept, and the size fields
+/// of the table entry are updated. Returns the updated table entry.
+pub fn inject(
+    rom: &mut [u8],
+    info: &SSBInfo,
+    id: usize,
+    stored: &[u8],
+    decompressed_size: usize,
+) -> Result<TableEntry> {
+    let slot = Slot::find(rom, info, id)?;
+
+    let size = stored.len().next_multiple_of(4);
+    let decompressed_size = decompressed_size.next_multiple_of(4);
+    for s in [size, decompressed_size] {
+        ensure!(
+            s / 4 <= u16::MAX as usize,
+            Error::FileTooLarge { id, size: s }
+        );
+    }
+
+    let needed = size + slot.externs.len();
+    ensure!(
+        size <= slot.capacity(),
+        Error::DoesNotFit {
+            id,
+            needed,
+            available: slot.end - slot.start
+        }
+    );
+
+    let data = &mut rom[slot.start..slot.end];
+    data.fill(0);
+    data[..stored.len()].copy_from_slice(stored);
+    data[size..needed].copy_from_slice(&slot.externs);
+
+    let entry = TableEntry {
+        size: (size / 4) as u16,
+        decompressed_size: (decompressed_size / 4) as u16,
+        ..slot.entry
+    };
+    write_entry(rom, info, id, &entry)?;
+
+    Ok(entry)
+}
+
+/// Set whether file `id` is stored vpk0 compressed, for after its data was replaced
+/// with data stored the other way. Returns the updated table entry.
+pub fn set_compressed(
+    rom: &mut [u8],
+    info: &SSBInfo,
+    id: usize,
+    compressed: bool,
+) -> Result<TableEntry> {
+    let entry = TableEntry {
+        compressed,
+        ..Slot::find(rom, info, id)?.entry
+    };
+    write_entry(rom, info, id, &entry)?;
+
+    Ok(entry)
+}
+
+/// Check that `entry` can be the table entry of file `id` without moving any data: it
+/// starts between the files around it, its data fits before the next file, and its
+/// relocation chains start inside of its decompressed data
+pub fn check_entry(rom: &[u8], info: &SSBInfo, id: usize, entry: &TableEntry) -> Result<()> {
+    let (entries, terminal) = read_table(rom, info)?;
+    ensure!(
+        id < entries.len(),
+        Error::FileIdOutOfRange {
+            id,
+            count: entries.len()
+        }
+    );
+    let inconsistent = |reason| Error::InconsistentEntry { id, reason };
+    let prev = id.checked_sub(1).map_or(0, |p| entries[p].offset);
+    let next = entries.get(id + 1).map_or(terminal.data_end, |e| e.offset);
+
+    ensure!(
+        entry.offset.is_multiple_of(4),
+        inconsistent("the offset isn't word aligned")
+    );
+    ensure!(
+        prev <= entry.offset && entry.offset <= next,
+        inconsistent("the offset isn't between the offsets of the files around it")
+    );
+    ensure!(
+        entry.offset as usize + entry.size as usize * 4 <= next as usize,
+        inconsistent("the data runs into the next file")
+    );
+    for (head, what) in [
+        (
+            entry.inreloc,
+            "the internal relocations start past the end of the data",
+        ),
+        (
+            entry.exreloc,
+            "the external relocations start past the end of the data",
+        ),
+    ] {
+        ensure!(
+            head == NO_RELOCS || head < entry.decompressed_size,
+            inconsistent(what)
+        );
+    }
+
+    Ok(())
+}
+
+/// Overwrite the table entry of file `id` with `entry`, without touching the file's data
+pub fn write_entry(rom: &mut [u8], info: &SSBInfo, id: usize, entry: &TableEntry) -> Result<()> {
+    ensure!(
+        id < info.total_entries(),
+        Error::FileIdOutOfRange {
+            id,
+            count: info.total_entries()
+        }
+    );
+    let start = info.table_start + id * TableEntry::SIZE;
+    let end = start + TableEntry::SIZE;
+    let len = rom.len();
+    rom.get_mut(start..end)
+        .ok_or(Error::OutsideRom { start, end, len })?
+        .copy_from_slice(&entry.to_bytes());
+
+    Ok(())
+}
+
+/// Roms that grow are padded to a multiple of this size
+const ROM_ALIGN: usize = 0x100000;
+
+/// Replace the data of file `id`, like [`inject`]. If the new data doesn't fit in the
+/// file's slot, the files after it are moved to make room (see [`DataRegion::write`]),
+/// and a rom that grows is padded to the next MiB. Returns the updated table entry.
+pub fn inject_resized(
+    rom: &mut Vec<u8>,
+    info: &SSBInfo,
+    id: usize,
+    stored: &[u8],
+    decompressed_size: usize,
+) -> Result<TableEntry> {
+    match inject(rom, info, id, stored, decompressed_size) {
+        Err(e) if matches!(e.downcast_ref(), Some(Error::DoesNotFit { .. })) => (),
+        fits => return fits,
+    }
+
+    let mut region = DataRegion::read(rom, info)?;
+    let old = region.entries()[id];
+    // keep the list of external file ids that follows the data
+    let externs = match region.file(id) {
+        Some(slot) if old.exreloc != NO_RELOCS => &slot[old.size as usize * 4..],
+        _ => &[],
+    };
+    let size = stored.len().next_multiple_of(4);
+    let mut packed = stored.to_vec();
+    packed.resize(size, 0);
+    packed.extend_from_slice(externs);
+    let entry = TableEntry {
+        size: (size / 4) as u16,
+        decompressed_size: decompressed_size.div_ceil(4) as u16,
+        ..old
+    };
+    region.replace(id, entry, packed)?;
+
+    let len = rom.len();
+    region.write(rom, info)?;
+    if rom.len() > len {
+        rom.resize(rom.len().next_multiple_of(ROM_ALIGN), 0);
+    }
+
+    Ok(region.entries()[id])
+}
+
+/// Set the heads of the relocation chains, and the list of external file ids, of file `id`
+/// to `heads`, for after its data was replaced with data whose chains were linked with
+/// [`unrelocate`](crate::unrelocate). If the list of external files grows, the files
+/// after it are moved to make room, like [`inject_resized`]. Returns the updated table entry.
+pub fn set_relocations(
+    rom: &mut Vec<u8>,
+    info: &SSBInfo,
+    id: usize,
+    heads: &RelocHeads,
+) -> Result<TableEntry> {
+    let mut region = DataRegion::read(rom, info)?;
+    let Some(&old) = region.entries().get(id) else {
+        bail!(Error::FileIdOutOfRange {
+            id,
+            count: region.entries().len()
+        });
+    };
+    let head = |offset: Option<usize>| offset.map_or(NO_RELOCS, |o| (o / 4) as u16);
+    let slot = region.file(id).unwrap_or_default();
+    let stored = slot
+        .get(..old.size as usize * 4)
+        .with_context(|| format!("file <{}> is larger than its slot", id))?;
+    let mut packed = pack_file(stored, &heads.externs);
+    // keep any padding after the file, so that the files after it stay in place
+    if packed.len() < slot.len() {
+        packed.resize(slot.len(), 0);
+    }
+    let entry = TableEntry {
+        inreloc: head(heads.internal),
+        exreloc: head(heads.external),
+        ..old
+    };
+    region.replace(id, entry, packed)?;
+
+    let len = rom.len();
+    region.write(rom, info)?;
+    if rom.len() > len {
+        rom.resize(rom.len().next_multiple_of(ROM_ALIGN), 0);
+    }
+
+    Ok(region.entries()[id])
+}
@@ -0,0 +1,359 @@
+use crate::{
+    table::{RelocInfo, TableFile},
+    Error,
+};
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
+use anyhow::{bail, ensure, Result};
+use core::fmt;
+
+/// A pointer in a file, found by following the file's relocation chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Relocation {
+    /// offset of the pointer in the file
+    pub offset: usize,
+    /// offset into the file that is pointed to
+    pub target: u32,
+    /// the file that is pointed to, or `None` for a pointer into the same file
+    pub external_file: Option<u16>,
+}
+
+/// Follow the relocation chains of `entry` in its decompressed `file`,
+/// and get every pointer: first the internal pointers, then the external pointers
+pub fn relocations(file: &[u8], entry: &TableFile) -> Result<Vec<Relocation>> {
+    let mut relocations = Vec::with_capacity(64);
+    for reloc in entry.inreloc.iter().chain(&entry.exreloc) {
+        walk_chain(file, reloc, &mut relocations)?;
+    }
+
+    Ok(relocations)
+}
+
+/// Write the address of each pointer in `relocs` into `file`. Internal pointers are
+/// offset by `base_addr`, and external pointers by the address that `externals`
+/// gives for their file.
+pub fn apply_relocations(
+    file: &mut [u8],
+    relocs: &[Relocation],
+    base_addr: u32,
+    externals: impl Fn(u16) -> Option<u32>,
+) -> Result<()> {
+    for reloc in relocs {
+        let base = match reloc.external_file {
+            None => base_addr,
+            Some(id) => externals(id).ok_or(Error::MissingExternal { id })?,
+        };
+        let ptr = pointer_mut(file, reloc.offset)?;
+        ptr.copy_from_slice(&base.wrapping_add(reloc.target).to_be_bytes());
+    }
+
+    Ok(())
+}
+
+/// Follow the relocation chains of `entry`, and write the pointers into `file`.
+/// Internal pointers are offset by `base`, the address that the file is loaded at;
+/// external pointers are left as offsets into their external file.
+pub fn relocate(mut file: Vec<u8>, entry: &TableFile, base: u32) -> Result<(Vec<u8>, FileReloc)> {
+    let relocations = relocations(&file, entry)?;
+    apply_relocations(&mut file, &relocations, base, |_| Some(0))?;
+
+    let (internal, external): (Vec<&Relocation>, Vec<&Relocation>) =
+        relocations.iter().partition(|r| r.external_file.is_none());
+    let relocs = FileReloc {
+        internal: entry.inreloc.as_ref().map(|_| {
+            internal
+                .iter()
+                .map(|r| (0, r.offset, base.wrapping_add(r.target)))
+                .collect()
+        }),
+        external: entry.exreloc.as_ref().map(|_| {
+            external
+                .iter()
+                .map(|r| (r.external_file.unwrap_or(0), r.offset, r.target))
+                .collect()
+        }),
+    };
+
+    Ok((file, relocs))
+}
+
+/// The heads of a file's relocation chains, as byte offsets into the file,
+/// and the list of external file ids that follows the file's data
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RelocHeads {
+    pub internal: Option<usize>,
+    pub external: Option<usize>,
+    pub externs: Vec<u16>,
+}
+
+/// Get the [`Relocation`]s for a file whose pointers have been resolved.
+/// The pointers at the `internal` offsets point into the file loaded at `base`,
+/// and the pointers at the `external` offsets are offsets into the given external file.
+pub fn resolved_relocations(
+    file: &[u8],
+    base: u32,
+    internal: &[usize],
+    external: &[(usize, u16)],
+) -> Result<Vec<Relocation>> {
+    let read = |offset: usize| -> Result<u32> {
+        let ptr = offset
+            .checked_add(4)
+            .and_then(|end| file.get(offset..end))
+            .ok_or(Error::RelocationOutsideFile {
+                offset,
+                len: file.len(),
+            })?;
+
+        Ok(u32::from_be_bytes([ptr[0], ptr[1], ptr[2], ptr[3]]))
+    };
+
+    let internal = internal.iter().map(|&offset| {
+        Ok(Relocation {
+            offset,
+            target: read(offset)?.wrapping_sub(base),
+            external_file: None,
+        })
+    });
+    let external = external.iter().map(|&(offset, id)| {
+        Ok(Relocation {
+            offset,
+            target: read(offset)?,
+            external_file: Some(id),
+        })
+    });
+
+    internal.chain(external).collect()
+}
+
+/// The internal pointer offsets, and the external pointer offsets with their file ids,
+/// of a relocation list in the csv format of [`FileReloc::csv`], to be given to
+/// [`resolved_relocations`]. The pointer values and file ids can be left out of internal
+/// rows, and the values are ignored, as the pointers are read from the file itself.
+pub fn pointer_offsets_from_csv(csv: &str) -> Result<PointerOffsets> {
+    let number = |s: &str| match s.trim().strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.trim().parse().ok(),
+    };
+
+    let mut internal = Vec::new();
+    let mut external = Vec::new();
+    for (i, row) in csv.lines().enumerate() {
+        let row = row.trim();
+        if row.is_empty() || row.starts_with('#') || row.starts_with("kind,") {
+            continue;
+        }
+        let malformed = Error::MalformedRelocationList { line: i + 1 };
+        let mut columns = row.split(',');
+        let kind = columns.next().map(str::trim);
+        let offset = columns.next().and_then(number).ok_or(malformed.clone())?;
+        let _value = columns.next();
+        let file = columns.next().map(str::trim).filter(|f| !f.is_empty());
+        match (kind, file) {
+            (Some("internal"), None) => internal.push(offset),
+            (Some("external"), Some(file)) => {
+                let id = number(file)
+                    .and_then(|id| u16::try_from(id).ok())
+                    .ok_or(malformed)?;
+                external.push((offset, id));
+            }
+            _ => bail!(malformed),
+        }
+    }
+
+    Ok(PointerOffsets { internal, external })
+}
+
+/// Rewrite the pointers of `relocs` in `file` into the game's relocation chains:
+/// each pointer becomes `{u16 next; u16 ptrOffset}`, linked in order of offset.
+/// This is the inverse of [`relocate`]. A pointer that is listed more than once (as from
+/// a merged list) is linked once, but two different pointers at one offset are an error,
+/// since the word at that offset can only hold one of them.
+pub fn unrelocate(file: &mut [u8], relocs: &[Relocation]) -> Result<RelocHeads> {
+    let mut relocs = relocs.iter().collect::<Vec<_>>();
+    relocs.sort_by_key(|r| r.offset);
+    relocs.dedup();
+    if let Some(pair) = relocs
+        .windows(2)
+        .find(|pair| pair[0].offset == pair[1].offset)
+    {
+        bail!(Error::DuplicateRelocation {
+            offset: pair[0].offset
+        });
+    }
+    let (internal, external): (Vec<&Relocation>, Vec<&Relocation>) =
+        relocs.into_iter().partition(|r| r.external_file.is_none());
+
+    Ok(RelocHeads {
+        internal: link_chain(file, &internal)?,
+        external: link_chain(file, &external)?,
+        externs: external.iter().filter_map(|r| r.external_file).collect(),
+    })
+}
+
+/// Write one relocation chain, and return the offset of its head
+fn link_chain(file: &mut [u8], chain: &[&Relocation]) -> Result<Option<usize>> {
+    const END: u16 = 0xFFFF;
+
+    let words = |bytes: usize, what: &'static str| {
+        ensure!(
+            bytes.is_multiple_of(4) && bytes / 4 < END as usize,
+            Error::UnencodableRelocation { what, value: bytes }
+        );
+        Ok((bytes / 4) as u16)
+    };
+
+    for (i, reloc) in chain.iter().enumerate() {
+        let next = match chain.get(i + 1) {
+            Some(next) => words(next.offset, "pointer offset")?,
+            None => END,
+        };
+        let target = words(reloc.target as usize, "pointer target")?;
+        let raw = pointer_mut(file, reloc.offset)?;
+        raw[0..2].copy_from_slice(&next.to_be_bytes());
+        raw[2..4].copy_from_slice(&target.to_be_bytes());
+    }
+
+    Ok(chain.first().map(|r| r.offset))
+}
+
+/// The four bytes of the pointer at `offset` in `file`
+fn pointer_mut(file: &mut [u8], offset: usize) -> Result<&mut [u8]> {
+    let len = file.len();
+    offset
+        .checked_add(4)
+        .and_then(|end| file.get_mut(offset..end))
+        .ok_or_else(|| Error::RelocationOutsideFile { offset, len }.into())
+}
+
+/// Follow one relocation chain, and add its pointers to `relocations`
+fn walk_chain(file: &[u8], reloc: &RelocInfo, relocations: &mut Vec<Relocation>) -> Result<()> {
+    // relocation data stored as BE {u16 next; u16 ptrOffset}
+    // next * 4 is the location of the next relocation
+    // ptrOffset * 4 is the offset that the pointer points to
+    const END: usize = 0xFFFF * 4;
+
+    let start = reloc.get_starting_offset();
+    let mut ex = reloc.get_external_files().map(|x| x.iter());
+    let mut next = start;
+    let mut count = 0;
+    while next != END {
+        let raw = file
+            .get(next..next + 4)
+            .ok_or(Error::RelocationOutsideFile {
+                offset: next,
+                len: file.len(),
+            })?;
+        let raw_next = u16::from_be_bytes([raw[0], raw[1]]);
+        let raw_ptr = u16::from_be_bytes([raw[2], raw[3]]);
+
+        // lazy, but whatever; if the list of external file ids runs out, use 0
+        let external_file = ex.as_mut().map(|x| x.next().copied().unwrap_or(0));
+        relocations.push(Relocation {
+            offset: next,
+            target: raw_ptr as u32 * 4,
+            external_file,
+        });
+
+        // every word of the file can only be a relocation once
+        count += 1;
+        if count > file.len() / 4 {
+            bail!(Error::RelocationLoop { start });
+        }
+        next = raw_next as usize * 4;
+    }
+
+    Ok(())
+}
+
+/// The offsets of internal pointers, and of external pointers with their file ids
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointerOffsets {
+    pub internal: Vec<usize>,
+    pub external: Vec<(usize, u16)>,
+}
+
+/// (file, &ptr, ptr)
+pub type Relocations = Vec<(u16, usize, u32)>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct FileReloc {
+    pub internal: Option<Relocations>,
+    pub external: Option<Relocations>,
+}
+
+impl FileReloc {
+    /// The relocations as csv, with the columns
+    /// `kind,source_offset,pointer_value,external_file_id`
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("kind,source_offset,pointer_value,external_file_id\n");
+        for &(_, offset, ptr) in self.internal.iter().flatten() {
+            csv += &format!("internal,0x{:06X},0x{:08X},\n", offset, ptr);
+        }
+        for &(fid, offset, ptr) in self.external.iter().flatten() {
+            csv += &format!("external,0x{:06X},0x{:08X},{}\n", offset, ptr, fid);
+        }
+
+        csv
+    }
+}
+
+impl FileReloc {
+    /// A GNU ld script fragment that `PROVIDE`s a symbol for each pointer target,
+    /// relative to the start symbol of the file (e.g., `file0012_start`).
+    /// `base` is the base address that the internal pointers were relocated with.
+    pub fn linker_script(&self, id: usize, base: u32) -> String {
+        let internal = self
+            .internal
+            .iter()
+            .flatten()
+            .map(|&(_, _, ptr)| (id, ptr.wrapping_sub(base)));
+        let external = self
+            .external
+            .iter()
+            .flatten()
+            .map(|&(fid, _, ptr)| (fid as usize, ptr));
+        let targets = internal.chain(external).collect::<BTreeSet<_>>();
+
+        let mut script = format!("/* pointer targets of file {} */\n", id);
+        for (fid, offset) in targets {
+            script += &format!(
+                "PROVIDE({} = file{:04}_start + 0x{:X});\n",
+                target_symbol(fid, offset),
+                fid,
+                offset
+            );
+        }
+
+        script
+    }
+}
+
+/// The symbol name for `offset` in file `id`
+pub fn target_symbol(id: usize, offset: u32) -> String {
+    format!("file{:04}_off_{:04X}", id, offset)
+}
+
+impl fmt::Display for FileReloc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Relocations")?;
+        if let Some(internal) = &self.internal {
+            writeln!(f, "## Internal Relocations")?;
+            for &(_, offset, ptr) in internal {
+                writeln!(f, "* {:06X} -> {:08X}", offset, ptr)?;
+            }
+        }
+        writeln!(f)?;
+        if let Some(external) = &self.external {
+            writeln!(f, "## External Relocations")?;
+            for &(fid, offset, ptr) in external {
+                writeln!(f, "* {:06X} -> {:08X} from {}", offset, ptr, fid)?;
+            }
+        }
+        Ok(())
+    }
+}
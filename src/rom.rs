@@ -0,0 +1,185 @@
+use crate::{
+    file_ends, read_table, relocate,
+    rom_info::RomFormat,
+    versions::{self, SSBInfo, VersionDb},
+    Error, FileLoader, FileReloc, TableEntry, TableFile, TerminalEntry,
+};
+use anyhow::{bail, ensure, Result};
+use std::{borrow::Cow, io::Write};
+
+/// A big-endian SSB64 rom, with its resource table parsed once up front
+#[derive(Debug, Clone)]
+pub struct SsbRom<'r> {
+    rom: Cow<'r, [u8]>,
+    info: SSBInfo,
+    entries: Vec<TableEntry>,
+    terminal: TerminalEntry,
+}
+
+impl SsbRom<'static> {
+    /// Take a rom dump in any byte order, convert it to big-endian, and find its version
+    /// among the built-in versions
+    pub fn from_vec(mut rom: Vec<u8>) -> Result<Self> {
+        crate::rom_info::normalize(&mut rom)?;
+
+        Self::with_db(Cow::Owned(rom), &VersionDb::builtin())
+    }
+}
+
+impl<'r> SsbRom<'r> {
+    /// Borrow a big-endian rom, and find its version among the built-in versions
+    pub fn new(rom: &'r [u8]) -> Result<Self> {
+        Self::with_db(Cow::Borrowed(rom), &VersionDb::builtin())
+    }
+
+    /// Use a big-endian rom, and find its version in `db` by its crc, or else find
+    /// its resource table
+    pub fn with_db(rom: Cow<'r, [u8]>, db: &VersionDb) -> Result<Self> {
+        let format = RomFormat::detect(&rom)?;
+        if format != RomFormat::BigEndian {
+            bail!(Error::NotBigEndian(format));
+        }
+        let info = db
+            .find(&rom)
+            .cloned()
+            .or_else(|| SSBInfo::detect(&rom))
+            .ok_or_else(|| {
+                let (crc1, crc2) = versions::read_crc(&rom).unwrap_or_default();
+                Error::UnknownVersion { crc1, crc2 }
+            })?;
+
+        Self::with_info(rom, info)
+    }
+
+    /// Use a big-endian rom of a known version
+    pub fn with_info(rom: Cow<'r, [u8]>, info: SSBInfo) -> Result<Self> {
+        let (entries, terminal) = read_table(&rom, &info)?;
+
+        Ok(Self {
+            rom,
+            info,
+            entries,
+            terminal,
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.rom
+    }
+
+    pub fn version(&self) -> &SSBInfo {
+        &self.info
+    }
+
+    /// The number of files in the resource table
+    pub fn file_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Every entry of the resource table
+    pub fn entries(&self) -> &[TableEntry] {
+        &self.entries
+    }
+
+    /// The dummy entry at the end of the resource table
+    pub fn terminal(&self) -> TerminalEntry {
+        self.terminal
+    }
+
+    /// The file whose slot in the data region holds the rom offset `offset`, as the file id
+    /// and the offset into the slot. The slot is the file's stored data, followed by its
+    /// list of external file ids and any padding, up to the start of the next file. Of
+    /// files that share their data, the first is returned.
+    pub fn file_at_rom_offset(&self, offset: usize) -> Option<(usize, usize)> {
+        let offset = offset.checked_sub(self.info.table_end)?;
+        if offset >= self.terminal.data_end as usize {
+            return None;
+        }
+        // shared files go back to an earlier offset, so the offsets aren't sorted
+        let ends = file_ends(&self.entries, &self.terminal);
+        let id = self
+            .entries
+            .iter()
+            .zip(ends)
+            .position(|(e, end)| (e.offset as usize..end as usize).contains(&offset))?;
+
+        Some((id, offset - self.entries[id].offset as usize))
+    }
+
+    /// The rom offset of the secondary (image) table that the dummy entry points to
+    pub fn secondary_table_offset(&self) -> usize {
+        self.info.table_end + self.terminal.next_table as usize
+    }
+
+    /// The secondary (image) table, whose entries have the same layout as the resource
+    /// table (offset and compression bit, sizes, and relocation heads) and are followed by
+    /// the data of its files, so that it is read like the resource table. Its end isn't
+    /// stored anywhere, so it is found from the data of the files.
+    pub fn secondary_table(&self) -> Result<SsbRom<'_>> {
+        let table_start = self.secondary_table_offset();
+        let table_end = versions::secondary_table_end(&self.rom, table_start).ok_or(
+            Error::NoSecondaryTable {
+                offset: table_start,
+            },
+        )?;
+        let info = SSBInfo {
+            table_start,
+            table_end,
+            ..self.info.clone()
+        };
+
+        SsbRom::with_info(Cow::Borrowed(&self.rom), info)
+    }
+
+    /// The table entry of file `id`
+    pub fn entry(&self, id: usize) -> Result<&TableEntry> {
+        ensure!(
+            id < self.entries.len(),
+            Error::FileIdOutOfRange {
+                id,
+                count: self.entries.len()
+            }
+        );
+
+        Ok(&self.entries[id])
+    }
+
+    /// The rom offset of file `id`
+    pub fn rom_offset(&self, id: usize) -> Result<usize> {
+        self.entry(id)
+            .map(|e| self.info.table_end + e.offset as usize)
+    }
+
+    /// File `id`, with its stored data and relocation info
+    pub fn file(&self, id: usize) -> Result<TableFile<'_>> {
+        TableFile::get(id, &self.rom, &self.info)
+    }
+
+    /// The data of file `id`, decompressed if necessary
+    pub fn data(&self, id: usize) -> Result<Cow<'_, [u8]>> {
+        self.file(id)?.decompressed()
+    }
+
+    /// The list of external file ids of file `id`; see [`TableFile::includes`]
+    pub fn includes(&self, id: usize) -> Result<Option<Vec<u16>>> {
+        Ok(self.file(id)?.includes().map(<[u16]>::to_vec))
+    }
+
+    /// Write the data of file `id` to `wtr`, decompressed if necessary,
+    /// and return the number of bytes written
+    pub fn write_file<W: Write>(&self, id: usize, wtr: W) -> Result<usize> {
+        self.file(id)?.write_data(wtr)
+    }
+
+    /// The data of file `id` with its pointers relocated, with internal pointers based on `base`
+    pub fn relocated(&self, id: usize, base: u32) -> Result<(Vec<u8>, FileReloc)> {
+        let file = self.file(id)?;
+
+        relocate(file.data()?, &file, base)
+    }
+
+    /// A loader to follow the external references of files in this rom
+    pub fn loader(&self) -> FileLoader<'_> {
+        FileLoader::new(&self.rom, &self.info)
+    }
+}
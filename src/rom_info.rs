@@ -0,0 +1,330 @@
+use crate::Error;
+use anyhow::{bail, Result};
+
+/// The byte order of a rom dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RomFormat {
+    /// big-endian (.z64), the native order
+    BigEndian,
+    /// 16-bit byte-swapped (.v64)
+    ByteSwapped,
+    /// 32-bit little-endian (.n64)
+    LittleEndian,
+}
+
+impl RomFormat {
+    /// Determine the byte order from the first word of the rom's header
+    pub fn detect(rom: &[u8]) -> Result<Self> {
+        let Some(magic) = rom.get(0..4) else {
+            bail!(Error::NoHeader { len: rom.len() });
+        };
+
+        match u32::from_be_bytes(magic.try_into().unwrap()) {
+            0x80371240 => Ok(Self::BigEndian),
+            0x37804012 => Ok(Self::ByteSwapped),
+            0x40123780 => Ok(Self::LittleEndian),
+            magic => bail!(Error::UnknownFormat { magic }),
+        }
+    }
+}
+
+/// Convert `rom` in place to big-endian (.z64) order, and return the original format
+pub fn normalize(rom: &mut [u8]) -> Result<RomFormat> {
+    let format = RomFormat::detect(rom)?;
+    match format {
+        RomFormat::BigEndian => (),
+        RomFormat::ByteSwapped => rom.chunks_exact_mut(2).for_each(|half| half.swap(0, 1)),
+        RomFormat::LittleEndian => rom.chunks_exact_mut(4).for_each(|word| word.reverse()),
+    }
+
+    Ok(format)
+}
+
+/// The bootcode (IPL3) that a rom was made for, which is also the lockout chip (CIC)
+/// it needs. Detected by the crc32 of the bootcode in the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cic {
+    Cic6101,
+    /// also NUS-7101, the PAL version
+    Cic6102,
+    Cic6103,
+    Cic6105,
+    Cic6106,
+    Cic7102,
+}
+
+impl Cic {
+    const BOOTCODE_CRCS: &[(u32, Cic)] = &[
+        (0x6170A4A1, Cic::Cic6101),
+        (0x90BB6CB5, Cic::Cic6102),
+        (0x0B050EE0, Cic::Cic6103),
+        (0x98BC2C86, Cic::Cic6105),
+        (0xACC8580A, Cic::Cic6106),
+        (0x009E9EA3, Cic::Cic7102),
+    ];
+
+    fn detect(bootcode: &[u8]) -> Option<Self> {
+        let crc = crc32(bootcode);
+
+        Self::BOOTCODE_CRCS
+            .iter()
+            .find(|&&(known, _)| known == crc)
+            .map(|&(_, cic)| cic)
+    }
+
+    /// The part number of the chip, e.g. `"6102"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cic6101 => "6101",
+            Self::Cic6102 => "6102",
+            Self::Cic6103 => "6103",
+            Self::Cic6105 => "6105",
+            Self::Cic6106 => "6106",
+            Self::Cic7102 => "7102",
+        }
+    }
+}
+
+/// The 0x1000 byte header at the start of a big-endian rom
+/// ```text
+/// 0x00 u32 pi_bsd_dom1  // PI timings: 0x80, release and page size, pulse width, latency
+/// 0x04 u32 clock_rate
+/// 0x08 u32 boot_address // entry point of the game's code
+/// 0x0C u32 libultra     // version of the sdk, e.g. 0x1446 for 2.0F
+/// 0x10 u32 crc1
+/// 0x14 u32 crc2
+/// 0x20 [u8; 20] title
+/// 0x3B [u8; 4] game_code // category, two letter id, region
+/// 0x3F u8 rom_version
+/// 0x40 [u8; 0xFC0] bootcode (IPL3)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct N64Header {
+    pi_bsd_dom1: u32,
+    clock_rate: u32,
+    boot_address: u32,
+    libultra: u32,
+    crc: (u32, u32),
+    title: [u8; 20],
+    game_code: [u8; 4],
+    rom_version: u8,
+    cic: Option<Cic>,
+}
+
+/// The PI bus timings for the cartridge domain, from the first word of the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PiSettings {
+    pub latency: u8,
+    pub pulse_width: u8,
+    pub page_size: u8,
+    pub release: u8,
+}
+
+impl N64Header {
+    pub const SIZE: usize = 0x1000;
+
+    /// Parse the header of a big-endian rom
+    pub fn parse(rom: &[u8]) -> Result<Self> {
+        let Some(raw) = rom.get(..Self::SIZE) else {
+            bail!(Error::NoHeader { len: rom.len() });
+        };
+        let word = |at: usize| u32::from_be_bytes([raw[at], raw[at + 1], raw[at + 2], raw[at + 3]]);
+
+        Ok(Self {
+            pi_bsd_dom1: word(0x00),
+            clock_rate: word(0x04),
+            boot_address: word(0x08),
+            libultra: word(0x0C),
+            crc: (word(0x10), word(0x14)),
+            title: raw[TITLE].try_into().unwrap(),
+            game_code: raw[GAME_CODE].try_into().unwrap(),
+            rom_version: raw[0x3F],
+            cic: Cic::detect(&raw[0x40..Self::SIZE]),
+        })
+    }
+
+    pub fn pi_settings(&self) -> PiSettings {
+        let [_, rls_pgs, pwd, lat] = self.pi_bsd_dom1.to_be_bytes();
+
+        PiSettings {
+            latency: lat,
+            pulse_width: pwd,
+            page_size: rls_pgs & 0xF,
+            release: rls_pgs >> 4,
+        }
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// The address that the bootcode loads the game's code to, and jumps to
+    pub fn boot_address(&self) -> u32 {
+        self.boot_address
+    }
+
+    /// The libultra version as (major, minor, revision), e.g. `(2, 0, Some('F'))`
+    pub fn libultra_version(&self) -> (u8, u8, Option<char>) {
+        let [_, _, version, revision] = self.libultra.to_be_bytes();
+        let revision = Some(revision as char).filter(char::is_ascii_alphabetic);
+
+        (version / 10, version % 10, revision)
+    }
+
+    pub fn crc(&self) -> (u32, u32) {
+        self.crc
+    }
+
+    /// The game's title, without the trailing padding
+    pub fn title(&self) -> &str {
+        let title = core::str::from_utf8(&self.title).unwrap_or_default();
+
+        title.trim_end_matches([' ', '\0'])
+    }
+
+    /// The four letter game code, e.g. `"NALE"`
+    pub fn game_code(&self) -> &str {
+        core::str::from_utf8(&self.game_code).unwrap_or_default()
+    }
+
+    pub fn rom_version(&self) -> u8 {
+        self.rom_version
+    }
+
+    /// The lockout chip, if the bootcode is a known one
+    pub fn cic(&self) -> Option<Cic> {
+        self.cic
+    }
+}
+
+/// The bytes of the title in the header, and those of the game code
+const TITLE: core::ops::Range<usize> = 0x20..0x34;
+const GAME_CODE: core::ops::Range<usize> = 0x3B..0x3F;
+
+/// Write `title` into the header of a big-endian rom, padded with spaces.
+/// The game doesn't read the title, so it can be any UTF-8 that fits in 20 bytes.
+pub fn set_title(rom: &mut [u8], title: &str) -> Result<()> {
+    let Some(field) = rom.get_mut(TITLE) else {
+        bail!(Error::NoHeader { len: rom.len() });
+    };
+    if title.len() > field.len() {
+        bail!(Error::TitleTooLong { len: title.len() });
+    }
+    field.fill(b' ');
+    field[..title.len()].copy_from_slice(title.as_bytes());
+
+    Ok(())
+}
+
+/// Write the four letter game `code` (e.g. `"NALE"`) into the header of a big-endian rom
+pub fn set_game_code(rom: &mut [u8], code: &str) -> Result<()> {
+    let Some(field) = rom.get_mut(GAME_CODE) else {
+        bail!(Error::NoHeader { len: rom.len() });
+    };
+    if code.len() != field.len() || !code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        bail!(Error::BadGameCode { code: code.into() });
+    }
+    field.copy_from_slice(code.as_bytes());
+
+    Ok(())
+}
+
+/// crc32 (as in zlib) of `data`
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The first byte of the rom that the crcs are calculated over, and how many bytes
+const CRC_START: usize = 0x1000;
+const CRC_LENGTH: usize = 0x100000;
+
+/// Calculate the two crcs of the header over the first MiB of the game's data,
+/// the way the bootcode checks them. The algorithm depends on the bootcode, so it is
+/// detected from the header; an unknown bootcode is treated as a 6102.
+/// A rom that is shorter than the checked range is read as if it was padded with zeros.
+pub fn recalc_crc(rom: &[u8]) -> (u32, u32) {
+    let cic = rom
+        .get(0x40..N64Header::SIZE)
+        .and_then(Cic::detect)
+        .unwrap_or(Cic::Cic6102);
+    let word = |at: usize| match rom.get(at..at + 4) {
+        Some(w) => u32::from_be_bytes([w[0], w[1], w[2], w[3]]),
+        None => 0,
+    };
+
+    let seed: u32 = match cic {
+        Cic::Cic6101 | Cic::Cic6102 | Cic::Cic7102 => 0xF8CA4DDC,
+        Cic::Cic6103 => 0xA3886759,
+        Cic::Cic6105 => 0xDF26F436,
+        Cic::Cic6106 => 0x1FEA617A,
+    };
+    let [mut t1, mut t2, mut t3, mut t4, mut t5, mut t6] = [seed; 6];
+
+    for i in (CRC_START..CRC_START + CRC_LENGTH).step_by(4) {
+        let d = word(i);
+        let (sum, carry) = t6.overflowing_add(d);
+        if carry {
+            t4 = t4.wrapping_add(1);
+        }
+        t6 = sum;
+        t3 ^= d;
+        let r = d.rotate_left(d & 0x1F);
+        t5 = t5.wrapping_add(r);
+        if t2 > d {
+            t2 ^= r;
+        } else {
+            t2 ^= t6 ^ d;
+        }
+        t1 = t1.wrapping_add(match cic {
+            Cic::Cic6105 => word(0x40 + 0x0710 + (i & 0xFF)) ^ d,
+            _ => t5 ^ d,
+        });
+    }
+
+    match cic {
+        Cic::Cic6103 => ((t6 ^ t4).wrapping_add(t3), (t5 ^ t2).wrapping_add(t1)),
+        Cic::Cic6106 => (
+            t6.wrapping_mul(t4).wrapping_add(t3),
+            t5.wrapping_mul(t2).wrapping_add(t1),
+        ),
+        _ => (t6 ^ t4 ^ t3, t5 ^ t2 ^ t1),
+    }
+}
+
+/// Recalculate the crcs of a big-endian rom, and write them into its header
+pub fn fix_crc(rom: &mut [u8]) -> Result<(u32, u32)> {
+    if rom.len() < 0x18 {
+        bail!(Error::NoHeader { len: rom.len() });
+    }
+    let (crc1, crc2) = recalc_crc(rom);
+    rom[0x10..0x14].copy_from_slice(&crc1.to_be_bytes());
+    rom[0x14..0x18].copy_from_slice(&crc2.to_be_bytes());
+
+    Ok((crc1, crc2))
+}
@@ -0,0 +1,130 @@
+use crate::{
+    cli::RoundtripOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, Context, Result};
+use ssbfile::{relocate, resolved_relocations, unrelocate, RelocInfo, TableFile};
+
+pub(crate) fn files(opt: RoundtripOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let ids = if opt.all {
+        (0..version.total_entries()).collect()
+    } else {
+        opt.ids.iter().flat_map(|r| r.ids()).collect::<Vec<_>>()
+    };
+
+    let (mut diverged, mut recompressed) = (0, 0);
+    for &id in &ids {
+        let entry = timings
+            .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let check = check(&opt, &entry, timings)
+            .with_context(|| format!("round tripping file <{}>", id))?;
+        diverged += !check.problems.is_empty() as usize;
+        recompressed += check.recompressed.is_some() as usize;
+        if !check.problems.is_empty() || check.recompressed.is_some() {
+            let notes = check.problems.into_iter().chain(check.recompressed);
+            println!("{:4}  {}", id, notes.collect::<Vec<_>>().join(", "));
+        }
+    }
+    println!("{} of {} files diverged", diverged, ids.len());
+    if recompressed > 0 {
+        println!(
+            "{} files were recompressed to other bytes than the original",
+            recompressed
+        );
+    }
+
+    if diverged > 0 {
+        bail!("{} files didn't round trip", diverged);
+    }
+    Ok(())
+}
+
+/// How a file differs from the original after it is extracted and encoded again
+#[derive(Default)]
+struct Check {
+    /// the ways that the encoded file doesn't hold the same data
+    problems: Vec<String>,
+    /// how a compressed file was recompressed to other bytes than the original's, like a
+    /// different size from matches that the game's encoder didn't pick
+    recompressed: Option<String>,
+}
+
+/// Extract the file and encode it again, and describe where it differs from the original
+fn check(opt: &RoundtripOpt, entry: &TableFile, timings: &mut Timings) -> Result<Check> {
+    let mut check = Check::default();
+    let problems = &mut check.problems;
+    let data = timings.time(Stage::Decompress, || entry.data())?;
+
+    // resolve the pointers, and then link them back into chains
+    let (mut file, relocs) = timings
+        .time(Stage::Relocate, || relocate(data.clone(), entry, 0))
+        .context("relocating pointers")?;
+    let internal = relocs
+        .internal
+        .iter()
+        .flatten()
+        .map(|&(_, offset, _)| offset)
+        .collect::<Vec<_>>();
+    let external = relocs
+        .external
+        .iter()
+        .flatten()
+        .map(|&(id, offset, _)| (offset, id))
+        .collect::<Vec<_>>();
+    let resolved = resolved_relocations(&file, 0, &internal, &external)?;
+    let heads = timings
+        .time(Stage::Relocate, || unrelocate(&mut file, &resolved))
+        .context("linking relocation chains")?;
+
+    let head = |r: &Option<RelocInfo>| r.as_ref().map(RelocInfo::get_starting_offset);
+    if heads.internal != head(&entry.inreloc) || heads.external != head(&entry.exreloc) {
+        problems.push("relocation chain heads".to_string());
+    }
+    // any ids after the last external relocation are padding
+    let externs = entry.includes().unwrap_or_default();
+    if externs.get(..heads.externs.len()) != Some(&heads.externs[..]) {
+        problems.push("external file ids".to_string());
+    }
+    if let Some(offset) = first_difference(&file, &data) {
+        problems.push(format!("re-chained data at {:#X}", offset));
+    }
+
+    if entry.compressed {
+        let settings = opt.compress.settings(Some(entry.raw))?;
+        let mut compressed = timings.time(Stage::Compress, || {
+            ssbfile::compress_with(&data, entry.id, &settings)
+        })?;
+        let decompressed = timings.time(Stage::Decompress, || {
+            ssbfile::decompress(&compressed, entry.id)
+        })?;
+        if let Some(offset) = first_difference(&decompressed, &data) {
+            problems.push(format!(
+                "recompressed data decompresses differently at {:#X}",
+                offset
+            ));
+        }
+        // the table stores whole words
+        compressed.resize(compressed.len().next_multiple_of(4), 0);
+        check.recompressed = if compressed.len() != entry.raw.len() {
+            Some(format!(
+                "recompressed to {:#X} bytes, not {:#X}",
+                compressed.len(),
+                entry.raw.len()
+            ))
+        } else {
+            first_difference(&compressed, entry.raw)
+                .map(|offset| format!("recompressed bytes differ at {:#X}", offset))
+        };
+    }
+
+    Ok(check)
+}
+
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
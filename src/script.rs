@@ -0,0 +1,154 @@
+use crate::{
+    cli::{HitboxesOpt, ImportScriptOpt, ScriptOpt},
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use ssbfile::moveset::{self, Hitbox};
+use std::fs;
+
+pub(crate) fn disassemble(opt: ScriptOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let starts = opt.offset.iter().map(|&o| o as usize).collect::<Vec<_>>();
+    let scripts = moveset::scripts(&data, &starts, &relocs)
+        .with_context(|| format!("reading the scripts of file <{}>", opt.id))?;
+    println!("# subaction scripts of file {}", opt.id);
+    for (start, commands) in &scripts {
+        println!();
+        println!(".script 0x{:04X}", start);
+        for command in commands {
+            println!("0x{:04X}: {}", command.offset, command);
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportScriptOpt, timings: &mut Timings) -> Result<()> {
+    let text = fs::read_to_string(&opt.script)
+        .with_context(|| format!("issue opening <{}>", opt.script.display()))?;
+    let scripts = moveset::assemble(&text)
+        .with_context(|| format!("assembling <{}>", opt.script.display()))?;
+
+    let (mut rom, version) = opt.rom.read(timings)?;
+    inject::edit_file(
+        &mut rom,
+        &version,
+        opt.id,
+        &opt.compress,
+        timings,
+        |data, relocs| {
+            for script in &scripts {
+                let start = script.start.with_context(|| {
+                    format!(
+                        "the commands at the top of <{}> need a `.script OFFSET` line",
+                        opt.script.display()
+                    )
+                })?;
+                moveset::patch(data, start, script, relocs)
+                    .with_context(|| format!("replacing the script at {:#X}", start))?;
+            }
+
+            Ok(())
+        },
+    )?;
+    eprintln!("replaced {} scripts of file {}", scripts.len(), opt.id);
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
+
+pub(crate) fn hitboxes(opt: HitboxesOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let mut moves = Vec::with_capacity(opt.moves.len());
+    for (name, offset) in &opt.moves {
+        let hitboxes = moveset::hitboxes(&data, *offset as usize, &relocs)
+            .with_context(|| format!("running the script at {:#X}", offset))?;
+        moves.push(MoveJson {
+            name: name.clone().unwrap_or_else(|| format!("0x{:04X}", offset)),
+            script: *offset,
+            hitboxes: hitboxes.iter().map(HitboxJson::from).collect(),
+        });
+    }
+    let json = Json {
+        file: opt.id,
+        moves,
+    };
+    let json = serde_json::to_string_pretty(&json)? + "\n";
+
+    match &opt.output {
+        Some(path) => timings
+            .time(Stage::Write, || fs::write(path, json))
+            .with_context(|| format!("writing <{}>", path.display())),
+        None => {
+            print!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Json {
+    file: usize,
+    moves: Vec<MoveJson>,
+}
+
+#[derive(Serialize)]
+struct MoveJson {
+    name: String,
+    script: u32,
+    hitboxes: Vec<HitboxJson>,
+}
+
+#[derive(Serialize)]
+struct HitboxJson {
+    offset: usize,
+    frame: u32,
+    scaled: bool,
+    id: i32,
+    group: i32,
+    joint: i32,
+    damage: i32,
+    size: i32,
+    position: [i32; 3],
+    angle: i32,
+    knockback_base: i32,
+    knockback_scale: i32,
+    knockback_weight: i32,
+    shield_damage: i32,
+    element: i32,
+    rebound: bool,
+    ground_air: i32,
+    sound_level: i32,
+    sound_kind: i32,
+}
+
+impl From<&Hitbox> for HitboxJson {
+    fn from(h: &Hitbox) -> Self {
+        Self {
+            offset: h.offset,
+            frame: h.frame,
+            scaled: h.scaled,
+            id: h.id,
+            group: h.group,
+            joint: h.joint,
+            damage: h.damage,
+            size: h.size,
+            position: h.position,
+            angle: h.angle,
+            knockback_base: h.knockback_base,
+            knockback_scale: h.knockback_scale,
+            knockback_weight: h.knockback_weight,
+            shield_damage: h.shield_damage,
+            element: h.element,
+            rebound: h.rebound,
+            ground_air: h.ground_air,
+            sound_level: h.sound_level,
+            sound_kind: h.sound_kind,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    cli::SearchOpt,
+    timings::{Stage, Timings},
+};
+use anyhow::{ensure, Context, Result};
+use ssbfile::TableFile;
+
+pub(crate) fn files(opt: SearchOpt, timings: &mut Timings) -> Result<()> {
+    let pattern = opt.bytes()?;
+    ensure!(!pattern.is_empty(), "search pattern is empty");
+    let (rom, version) = opt.rom.source(timings)?;
+
+    let mut hits = 0;
+    for id in 0..version.total_entries() {
+        let entry = timings
+            .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        let data = timings
+            .time(Stage::Decompress, || entry.decompressed())
+            .with_context(|| format!("decompressing file <{}>", id))?;
+
+        for (offset, window) in data.windows(pattern.len()).enumerate() {
+            if window == pattern.as_slice() {
+                println!("{:4}  {:#06X}", id, offset);
+                hits += 1;
+            }
+        }
+    }
+    eprintln!("{} matches", hits);
+
+    Ok(())
+}
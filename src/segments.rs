@@ -0,0 +1,86 @@
+use crate::{
+    cli::{SegmentFormat, SegmentsOpt},
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use std::{fmt::Write, fs};
+
+/// A range of the rom, and what it is
+struct Segment<'a> {
+    start: usize,
+    end: usize,
+    kind: &'a str,
+    name: String,
+}
+
+pub(crate) fn yaml(opt: SegmentsOpt, timings: &mut Timings) -> Result<()> {
+    let (source, version) = opt.rom.source(timings)?;
+    let rom = opt.rom.table(&source, version, timings)?;
+    let version = rom.version();
+
+    let mut segments = vec![Segment {
+        start: version.table_start,
+        end: version.table_end,
+        kind: "bin",
+        name: "resource_table".to_string(),
+    }];
+    // each file runs up to the next one, with its list of external file ids; a file
+    // that shares the data of another has no range of its own
+    let ends = ssbfile::file_ends(rom.entries(), &rom.terminal());
+    let shared = ssbfile::shared_files(rom.entries());
+    for (id, (entry, end)) in rom.entries().iter().zip(ends).enumerate() {
+        if shared[id].is_some() {
+            continue;
+        }
+        segments.push(Segment {
+            start: version.table_end + entry.offset as usize,
+            end: version.table_end + end as usize,
+            kind: if entry.compressed {
+                &opt.vpk0_type
+            } else {
+                "bin"
+            },
+            name: format!("file_{:04}", id),
+        });
+    }
+    let data_end = version.table_end + rom.terminal().data_end as usize;
+
+    let mut out = format!(
+        "# the resource table of {} and its {} files, from ssbfile\n",
+        version.version,
+        rom.file_count()
+    );
+    match opt.format {
+        SegmentFormat::Splat => {
+            out += "segments:\n";
+            for s in &segments {
+                writeln!(out, "  - [{:#X}, {}, {}]", s.start, s.kind, s.name)?;
+            }
+            writeln!(
+                out,
+                "  # the end of the file data, and the start of what follows it"
+            )?;
+            writeln!(out, "  - [{:#X}]", data_end)?;
+        }
+        SegmentFormat::N64Split => {
+            out += "ranges:\n";
+            for s in &segments {
+                writeln!(
+                    out,
+                    "   - [{:#X}, {:#X}, \"{}\", \"{}\"]",
+                    s.start, s.end, s.kind, s.name
+                )?;
+            }
+        }
+    }
+
+    match &opt.output {
+        Some(path) => timings
+            .time(Stage::Write, || fs::write(path, out))
+            .with_context(|| format!("writing segments to <{}>", path.display())),
+        None => {
+            print!("{}", out);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Bone hierarchies: the arrays of `DObjDesc` structs that the game builds the
+//! trees of display objects (the parts of a model) from
+use crate::{Error, Relocation};
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use anyhow::Result;
+
+/// the size of a `DObjDesc`: an id, a display list, and a translation, rotation, and scale
+const DESC_SIZE: usize = 0x2C;
+/// the id that ends a `DObjDesc` array (`DOBJ_ARRAY_MAX`)
+const DESC_END: u32 = 18;
+/// the bits of the id that hold the depth in the tree; the rest are flags
+const DEPTH_MASK: u32 = 0xFFF;
+/// floats past this are not positions, angles, or scales of a model
+const MAX_FLOAT: f32 = 1.0e6;
+
+/// A column-major 4x4 matrix, as glTF stores them
+pub type Matrix = [f32; 16];
+
+/// One part of a model, from a `DObjDesc`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bone {
+    /// offset of the `DObjDesc` in the file
+    pub offset: usize,
+    /// the index of the parent bone in the skeleton
+    pub parent: Option<usize>,
+    /// offset of the bone's display list in the file, if it has one in the same file
+    pub display_list: Option<usize>,
+    pub translate: [f32; 3],
+    /// rotation around the x, y, and z axes, in radians
+    pub rotate: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl Bone {
+    /// The rotation as a quaternion (x, y, z, w). The game rotates around x, then y, then z.
+    #[cfg(feature = "std")]
+    pub fn rotation(&self) -> [f32; 4] {
+        let [x, y, z] = self.rotate.map(|a| a / 2.0);
+        let ((sx, cx), (sy, cy), (sz, cz)) = (x.sin_cos(), y.sin_cos(), z.sin_cos());
+        [
+            sx * cy * cz - cx * sy * sz,
+            cx * sy * cz + sx * cy * sz,
+            cx * cy * sz - sx * sy * cz,
+            cx * cy * cz + sx * sy * sz,
+        ]
+    }
+
+    /// The transform from the bone to its parent: translate * rotate * scale
+    #[cfg(feature = "std")]
+    pub fn local_matrix(&self) -> Matrix {
+        let [x, y, z, w] = self.rotation();
+        let [sx, sy, sz] = self.scale;
+        let [tx, ty, tz] = self.translate;
+        [
+            (1.0 - 2.0 * (y * y + z * z)) * sx,
+            (2.0 * (x * y + z * w)) * sx,
+            (2.0 * (x * z - y * w)) * sx,
+            0.0,
+            (2.0 * (x * y - z * w)) * sy,
+            (1.0 - 2.0 * (x * x + z * z)) * sy,
+            (2.0 * (y * z + x * w)) * sy,
+            0.0,
+            (2.0 * (x * z + y * w)) * sz,
+            (2.0 * (y * z - x * w)) * sz,
+            (1.0 - 2.0 * (x * x + y * y)) * sz,
+            0.0,
+            tx,
+            ty,
+            tz,
+            1.0,
+        ]
+    }
+}
+
+/// Read the `DObjDesc` array of `file` at `offset`, up to the entry with the id 18.
+/// Each entry's id is its depth in the tree, and its parent is the last entry before it
+/// that is one level up. The display lists are found in `relocs`.
+pub fn skeleton(file: &[u8], offset: usize, relocs: &[Relocation]) -> Result<Vec<Bone>> {
+    let pointers = relocs
+        .iter()
+        .map(|r| (r.offset, r))
+        .collect::<BTreeMap<_, _>>();
+    let word = |at: usize| {
+        file.get(at..at + 4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+    };
+    let bad = || Error::BadSkeleton { offset };
+
+    let mut bones = Vec::new();
+    // the last bone at each depth
+    let mut parents = Vec::<usize>::new();
+    for at in (offset..).step_by(DESC_SIZE) {
+        let id = word(at).ok_or_else(bad)?;
+        if id == DESC_END {
+            break;
+        }
+        let depth = (id & DEPTH_MASK) as usize;
+        if depth > parents.len() || depth >= DESC_END as usize {
+            return Err(bad().into());
+        }
+        parents.truncate(depth);
+
+        let display_list = match pointers.get(&(at + 4)) {
+            Some(r) if r.external_file.is_none() => Some(r.target as usize),
+            Some(_) => None,
+            None if word(at + 4) == Some(0) => None,
+            None => return Err(bad().into()),
+        };
+        let mut floats = [0f32; 9];
+        for (i, f) in floats.iter_mut().enumerate() {
+            *f = f32::from_bits(word(at + 8 + i * 4).ok_or_else(bad)?);
+            if !f.is_finite() || f.abs() > MAX_FLOAT {
+                return Err(bad().into());
+            }
+        }
+
+        parents.push(bones.len());
+        bones.push(Bone {
+            offset: at,
+            parent: depth.checked_sub(1).map(|d| parents[d]),
+            display_list,
+            translate: [floats[0], floats[1], floats[2]],
+            rotate: [floats[3], floats[4], floats[5]],
+            scale: [floats[6], floats[7], floats[8]],
+        });
+    }
+
+    if bones.is_empty() {
+        return Err(bad().into());
+    }
+    Ok(bones)
+}
+
+/// Find the `DObjDesc` arrays that the internal pointers of `file` point to,
+/// with at least one bone that has a display list
+pub fn find(file: &[u8], relocs: &[Relocation]) -> Vec<usize> {
+    let mut targets = relocs
+        .iter()
+        .filter(|r| r.external_file.is_none())
+        .map(|r| r.target as usize)
+        .collect::<Vec<_>>();
+    targets.sort_unstable();
+    targets.dedup();
+
+    targets
+        .into_iter()
+        .filter(|&t| {
+            skeleton(file, t, relocs)
+                .is_ok_and(|bones| bones.iter().any(|b| b.display_list.is_some()))
+        })
+        .collect()
+}
+
+/// The transform of each bone of `bones` to the model: its local transform, after
+/// those of its parents
+#[cfg(feature = "std")]
+pub fn world_matrices(bones: &[Bone]) -> Vec<Matrix> {
+    let mut world: Vec<Matrix> = Vec::with_capacity(bones.len());
+    for bone in bones {
+        let local = bone.local_matrix();
+        let matrix = match bone.parent {
+            Some(parent) => multiply(&world[parent], &local),
+            None => local,
+        };
+        world.push(matrix);
+    }
+
+    world
+}
+
+/// `a * b` of column-major matrices
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            m[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+
+    m
+}
+
+/// The inverse of an affine matrix `m`
+pub fn invert(m: &Matrix) -> Matrix {
+    // the inverse of the 3x3 part, from its cofactors
+    let a = |col: usize, row: usize| m[col * 4 + row];
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| a(c0, r0) * a(c1, r1) - a(c1, r0) * a(c0, r1);
+    let det = a(0, 0) * cofactor(1, 2, 1, 2) - a(1, 0) * cofactor(1, 2, 0, 2)
+        + a(2, 0) * cofactor(1, 2, 0, 1);
+    let det = if det == 0.0 { 1.0 } else { det };
+    let inv3 = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+
+    let mut inv = [0.0; 16];
+    for col in 0..3 {
+        for row in 0..3 {
+            inv[col * 4 + row] = inv3[row][col] / det;
+        }
+    }
+    // the translation is moved back by the inverse rotation and scale
+    for row in 0..3 {
+        inv[12 + row] = -(0..3).map(|k| inv[k * 4 + row] * m[12 + k]).sum::<f32>();
+    }
+    inv[15] = 1.0;
+
+    inv
+}
+
+/// Transform `point` by the matrix `m`
+pub fn transform(m: &Matrix, point: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = point;
+    [
+        m[0] * x + m[4] * y + m[8] * z + m[12],
+        m[1] * x + m[5] * y + m[9] * z + m[13],
+        m[2] * x + m[6] * y + m[10] * z + m[14],
+    ]
+}
+
+/// The display lists of `bones`, as (index of the bone, offset of the list) pairs
+pub fn display_lists(bones: &[Bone]) -> Vec<(usize, usize)> {
+    bones
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.display_list.map(|dl| (i, dl)))
+        .collect()
+}
+
+/// The indices of the children of each bone
+pub fn children(bones: &[Bone]) -> Vec<Vec<usize>> {
+    let mut children = vec![Vec::new(); bones.len()];
+    for (i, bone) in bones.iter().enumerate() {
+        if let Some(parent) = bone.parent {
+            children[parent].push(i);
+        }
+    }
+
+    children
+}
+
+/// The bones as an indented tree, a line for each: its index, the offset of its
+/// `DObjDesc` and display list, and its translation, rotation (in degrees), and scale
+pub fn tree(bones: &[Bone]) -> String {
+    fn line(bones: &[Bone], children: &[Vec<usize>], i: usize, depth: usize, out: &mut String) {
+        let bone = &bones[i];
+        let dl = bone
+            .display_list
+            .map_or_else(|| String::from("none"), |dl| format!("0x{:04X}", dl));
+        let [x, y, z] = bone.rotate.map(f32::to_degrees);
+        *out += &format!(
+            "{:indent$}bone {} at 0x{:04X}: display list {}, translate ({}, {}, {}), rotate ({}, {}, {}), scale ({}, {}, {})\n",
+            "",
+            i,
+            bone.offset,
+            dl,
+            bone.translate[0],
+            bone.translate[1],
+            bone.translate[2],
+            x,
+            y,
+            z,
+            bone.scale[0],
+            bone.scale[1],
+            bone.scale[2],
+            indent = depth * 2
+        );
+        for &child in &children[i] {
+            line(bones, children, child, depth + 1, out);
+        }
+    }
+
+    let children = children(bones);
+    let mut out = String::new();
+    for (i, bone) in bones.iter().enumerate() {
+        if bone.parent.is_none() {
+            line(bones, &children, i, 0, &mut out);
+        }
+    }
+
+    out
+}
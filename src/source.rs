@@ -0,0 +1,202 @@
+use crate::{
+    rom_info::{normalize, RomFormat},
+    versions::SSBInfo,
+    Error, TableFile,
+};
+use alloc::collections::BTreeMap;
+use anyhow::{ensure, Context, Result};
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+    ops::Deref,
+    path::Path,
+};
+
+/// the bytes of the rom header that are read when a rom is opened in part
+const HEADER_SIZE: usize = 0x40;
+
+/// The bytes of a big-endian rom, either read into memory or, with the `memmap2` feature,
+/// memory-mapped from the file so that only the parts that are used are read from disk
+#[derive(Debug)]
+pub enum RomSource {
+    Owned(Vec<u8>),
+    #[cfg(feature = "memmap2")]
+    Mapped(memmap2::Mmap),
+}
+
+impl RomSource {
+    /// Open the rom at `path`. A big-endian rom is memory-mapped (if the `memmap2` feature
+    /// is enabled), while a rom in another byte order is read and converted to big-endian.
+    pub fn open(path: &Path) -> Result<Self> {
+        #[cfg(feature = "memmap2")]
+        {
+            let file = fs::File::open(path)?;
+            // SAFETY: the map is read-only, and the rom file is not expected
+            // to be changed by another process while it is open
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            if RomFormat::detect(&map)? == RomFormat::BigEndian {
+                return Ok(Self::Mapped(map));
+            }
+        }
+
+        Self::read(path)
+    }
+
+    /// Read the whole rom at `path` into memory, converting it to big-endian
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut rom = fs::read(path)?;
+        normalize(&mut rom).context("reading rom header")?;
+
+        Ok(Self::Owned(rom))
+    }
+
+    /// Get the rom as an owned buffer, copying it if it is mapped
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::Owned(rom) => rom,
+            #[cfg(feature = "memmap2")]
+            Self::Mapped(map) => map.to_vec(),
+        }
+    }
+}
+
+/// A big-endian rom that is only read in part: its header when it is opened, and then the
+/// ranges that are read with [`read_range`](Self::read_range) or
+/// [`read_files`](Self::read_files). Only the ranges that were read are held, and getting
+/// any other bytes of the rom is an error.
+#[derive(Debug)]
+pub struct PartialRom {
+    file: fs::File,
+    len: usize,
+    /// the ranges of the rom that were read, by their start; no two overlap or touch
+    spans: BTreeMap<usize, Vec<u8>>,
+}
+
+impl PartialRom {
+    /// Open the rom at `path`, and read its header. A rom in another byte order is read
+    /// whole and converted to big-endian.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = fs::File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        let mut header = vec![0; len.min(HEADER_SIZE)];
+        file.read_exact(&mut header).context("reading rom header")?;
+        let span = if RomFormat::detect(&header)? == RomFormat::BigEndian {
+            header
+        } else {
+            RomSource::read(path)?.into_vec()
+        };
+
+        Ok(Self {
+            file,
+            len,
+            spans: BTreeMap::from([(0, span)]),
+        })
+    }
+
+    /// The length of the whole rom
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The rom header, which is read when the rom is opened
+    pub fn header(&self) -> &[u8] {
+        &self.spans[&0]
+    }
+
+    /// The bytes `start..end` of the rom, if they were read
+    pub fn get(&self, start: usize, end: usize) -> Result<&[u8]> {
+        let len = self.len;
+        ensure!(
+            start <= end && end <= len,
+            Error::OutsideRom { start, end, len }
+        );
+
+        self.spans
+            .range(..=start)
+            .next_back()
+            .and_then(|(&at, span)| span.get(start - at..end - at))
+            .ok_or_else(|| Error::NotRead { start, end }.into())
+    }
+
+    /// Read `start..end` of the rom file, if it wasn't read already
+    pub fn read_range(&mut self, start: usize, end: usize) -> Result<()> {
+        let len = self.len;
+        ensure!(
+            start <= end && end <= len,
+            Error::OutsideRom { start, end, len }
+        );
+        if self.get(start, end).is_ok() {
+            return Ok(());
+        }
+
+        // the spans that overlap or touch `start..end` are read again as one span with it
+        let touching = self
+            .spans
+            .range(..=end)
+            .rev()
+            .take_while(|(&at, span)| at + span.len() >= start)
+            .map(|(&at, span)| (at, at + span.len()))
+            .collect::<Vec<_>>();
+        let (start, end) = touching
+            .iter()
+            .fold((start, end), |(lo, hi), &(at, to)| (lo.min(at), hi.max(to)));
+        let mut span = vec![0; end - start];
+        self.file.seek(SeekFrom::Start(start as u64))?;
+        self.file
+            .read_exact(&mut span)
+            .with_context(|| format!("reading {:#X}..{:#X} of the rom", start, end))?;
+        for (at, _) in touching {
+            self.spans.remove(&at);
+        }
+        self.spans.insert(start, span);
+
+        Ok(())
+    }
+
+    /// Read the resource table of `info`, and the data and external file lists of
+    /// the files `ids`, so that [`file`](Self::file) can get those files
+    pub fn read_files(&mut self, info: &SSBInfo, ids: &[usize]) -> Result<()> {
+        self.read_range(info.table_start, info.table_end)?;
+        for &id in ids {
+            let span = TableFile::span(id, info, |start, end| self.get(start, end))?;
+            self.read_range(span.start, span.end)
+                .with_context(|| format!("reading file <{}>", id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get file `id` from the table of `info`, like [`TableFile::get`]; its table entry
+    /// and data have to have been read
+    pub fn file(&self, id: usize, info: &SSBInfo) -> Result<TableFile<'_>> {
+        TableFile::get_with(id, info, |start, end| self.get(start, end))
+    }
+
+    /// Read the rest of the rom, and get all of it
+    pub fn read_all(&mut self) -> Result<&[u8]> {
+        self.read_range(0, self.len)?;
+        self.get(0, self.len)
+    }
+}
+
+impl Deref for RomSource {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(rom) => rom,
+            #[cfg(feature = "memmap2")]
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+impl AsRef<[u8]> for RomSource {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
@@ -0,0 +1,203 @@
+//! `.ssbmod` archives: a tar archive of the files that a mod replaces or adds, with an
+//! `ssbmod.toml` that lists them like a manifest, along with the versions of the rom that
+//! the mod is for and the vpk0 settings to compress its files with:
+//! ```toml
+//! name = "New Stage"
+//! versions = ["NALE"]
+//!
+//! [compress]
+//! vpk_method = 2
+//! window_bits = 12
+//!
+//! [[file]]
+//! id = 12
+//! path = "files/file-0012.bin"
+//! compressed = true
+//! ```
+use crate::{
+    build::{self, EncodedFiles},
+    cli::{self, ApplyModOpt, CompressOpt, PackModOpt},
+    manifest::{Manifest, ManifestFile},
+    timings::{Stage, Timings},
+};
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use ssbfile::{TableFile, VpkMethod};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+const MOD_INFO: &str = "ssbmod.toml";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ModInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// the versions of the rom that the mod can be applied to; any version, if empty
+    #[serde(default)]
+    versions: Vec<String>,
+    #[serde(default)]
+    compress: ModCompress,
+    #[serde(rename = "file", default)]
+    files: Vec<ManifestFile>,
+}
+
+/// The vpk0 settings of [`CompressOpt`]; the method is `1` or `2`
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ModCompress {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vpk_method: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    window_bits: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length_bits: Option<usize>,
+}
+
+impl ModCompress {
+    fn new(opt: &CompressOpt) -> Self {
+        Self {
+            vpk_method: opt.vpk_method.map(|method| match method {
+                VpkMethod::OneSample => 1,
+                VpkMethod::TwoSample => 2,
+            }),
+            window_bits: opt.window_bits,
+            length_bits: opt.length_bits,
+        }
+    }
+
+    fn to_opt(&self) -> Result<CompressOpt> {
+        Ok(CompressOpt {
+            vpk_method: self
+                .vpk_method
+                .map(|method| cli::parse_method(&method.to_string()))
+                .transpose()?,
+            window_bits: self.window_bits,
+            length_bits: self.length_bits,
+        })
+    }
+}
+
+pub(crate) fn pack(opt: PackModOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let manifest = Manifest::read(&opt.manifest)?;
+    let root = opt.manifest.parent().unwrap_or_else(|| Path::new("."));
+    build::check_manifest(&manifest, &version)?;
+
+    let f = fs::File::create(&opt.output)
+        .with_context(|| format!("creating mod archive <{}>", opt.output.display()))?;
+    let mut archive = tar::Builder::new(f);
+    let mut append = |path: &Path, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        timings
+            .time(Stage::Write, || {
+                archive.append_data(&mut header, path, data)
+            })
+            .with_context(|| format!("writing <{}> to the mod archive", path.display()))
+    };
+
+    let mut files = Vec::new();
+    for file in &manifest.files {
+        let data = build::read_file(&root.join(&file.path))?;
+        let original = TableFile::get(file.id, &rom, &version).ok();
+        if original.is_some_and(|entry| file.unchanged(&entry, &data)) {
+            continue;
+        }
+        let path = PathBuf::from(format!("files/file-{:04}.bin", file.id));
+        append(&path, &data)?;
+        files.push(ManifestFile {
+            path,
+            ..file.clone()
+        });
+    }
+    ensure!(
+        !files.is_empty(),
+        "none of the files of the manifest differ from the rom"
+    );
+    eprintln!(
+        "bundled {} of the {} files of the manifest",
+        files.len(),
+        manifest.files.len()
+    );
+
+    let info = ModInfo {
+        name: opt.name,
+        versions: vec![version.version.to_string()],
+        compress: ModCompress::new(&opt.compress),
+        files,
+    };
+    let text = toml::to_string(&info).context("serializing the mod info")?;
+    append(Path::new(MOD_INFO), text.as_bytes())?;
+    archive
+        .into_inner()
+        .map(drop)
+        .with_context(|| format!("writing mod archive <{}>", opt.output.display()))
+}
+
+pub(crate) fn apply(opt: ApplyModOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let mut bundle = read_bundle(&opt.bundle)
+        .with_context(|| format!("reading mod archive <{}>", opt.bundle.display()))?;
+    let text = bundle
+        .remove(Path::new(MOD_INFO))
+        .with_context(|| format!("<{}> has no {}", opt.bundle.display(), MOD_INFO))?;
+    let info: ModInfo = std::str::from_utf8(&text)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| Ok(toml::from_str(text)?))
+        .with_context(|| format!("parsing the {} of the mod", MOD_INFO))?;
+
+    ensure!(
+        info.versions.is_empty() || info.versions.iter().any(|v| *v == version.version),
+        "the mod is for {} roms, and this rom is {}",
+        info.versions.join(" or "),
+        version.version
+    );
+    if let Some(name) = &info.name {
+        eprintln!("applying {}", name);
+    }
+
+    let manifest = Manifest {
+        version: None,
+        mode: None,
+        files: info.files,
+    };
+    opt.grow.check(&manifest, &version)?;
+    build::apply_manifest(
+        &mut rom,
+        &version,
+        &manifest,
+        |path| {
+            bundle
+                .get(path)
+                .cloned()
+                .with_context(|| format!("the mod archive has no <{}>", path.display()))
+        },
+        &info.compress.to_opt()?,
+        &mut EncodedFiles::new(),
+        timings,
+    )?;
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
+
+/// The files of the tar archive at `path`, by their path in the archive
+fn read_bundle(path: &Path) -> Result<HashMap<PathBuf, Vec<u8>>> {
+    let f = fs::File::open(path).context("issue opening archive")?;
+    let mut archive = tar::Archive::new(f);
+    let mut files = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .with_context(|| format!("reading <{}>", path.display()))?;
+        files.insert(path, data);
+    }
+
+    Ok(files)
+}
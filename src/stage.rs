@@ -0,0 +1,480 @@
+//! Stage collision: the geometry that fighters stand on and bump into, the map objects
+//! that mark where they (and items) spawn, and the camera and blast zone bounds.
+//! The targets and platforms of the bonus stages are map objects too.
+//!
+//! The geometry is the decompilation's `MPGeometryData`: a count of collision groups (the
+//! parts of a stage that move together), and pointers to the vertex positions, the
+//! vertex ids of each line, the lines, the lines of each group, and the map objects.
+//! The lines, vertex ids, and vertices are counted from the ids that point into them;
+//! the map objects run up to the next structure that a pointer of the file points to.
+use crate::{Error, Relocation};
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
+use anyhow::Result;
+use core::fmt;
+
+/// the size of the geometry header: the group count, padding, and five pointers
+const GEOMETRY_SIZE: usize = 0x18;
+/// the size of a vertex: its position and flags
+const VERTEX_SIZE: usize = 6;
+/// the size of a map object: its kind and position
+const MAP_OBJECT_SIZE: usize = 6;
+
+/// A vertex of the collision lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vertex {
+    pub x: i16,
+    pub y: i16,
+    pub flags: u16,
+}
+
+impl Vertex {
+    /// fighters can drop through the line that starts at the vertex
+    pub const PASS: u16 = 0x4000;
+    /// the end of the line is a ledge that fighters can grab
+    pub const LEDGE: u16 = 0x8000;
+
+    /// The material of the surface after the vertex (grass, rock, ice, ...), which sets
+    /// its sounds and friction
+    pub fn surface(&self) -> u8 {
+        self.flags as u8
+    }
+}
+
+/// Which side of a line is solid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineKind {
+    Ground,
+    Ceiling,
+    RightWall,
+    LeftWall,
+}
+
+impl LineKind {
+    /// the kinds in the order that a group lists its lines
+    pub const ALL: [Self; 4] = [Self::Ground, Self::Ceiling, Self::RightWall, Self::LeftWall];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ground => "ground",
+            Self::Ceiling => "ceiling",
+            Self::RightWall => "right wall",
+            Self::LeftWall => "left wall",
+        }
+    }
+}
+
+/// A collision line: the path through its vertices
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Line {
+    /// the collision group that the line moves with
+    pub group: usize,
+    pub kind: LineKind,
+    /// indices into [`Geometry::vertices`]
+    pub vertices: Vec<usize>,
+}
+
+/// A marker on the stage, like a spawn point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapObject {
+    pub kind: u16,
+    pub x: i16,
+    pub y: i16,
+}
+
+impl MapObject {
+    /// a target of a Break the Targets stage
+    pub const TARGET: u16 = 0x14;
+    /// a platform of a Board the Platforms stage
+    pub const PLATFORM: u16 = 0x15;
+
+    /// The player that spawns at the object, for the kinds 0 to 3
+    pub fn player_spawn(&self) -> Option<u16> {
+        (self.kind < 4).then_some(self.kind + 1)
+    }
+
+    /// What the object places on a bonus stage: a `"target"` or a `"platform"`
+    pub fn bonus(&self) -> Option<&'static str> {
+        match self.kind {
+            Self::TARGET => Some("target"),
+            Self::PLATFORM => Some("platform"),
+            _ => None,
+        }
+    }
+}
+
+/// The collision of a stage
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Geometry {
+    pub vertices: Vec<Vertex>,
+    pub lines: Vec<Line>,
+    pub objects: Vec<MapObject>,
+    /// offset of the map objects in the file
+    pub objects_offset: usize,
+}
+
+impl Geometry {
+    /// Write the kinds and positions of the map objects back into `file`, which is how
+    /// spawn points, and the targets and platforms of the bonus stages, are moved
+    pub fn write_objects(&self, file: &mut [u8]) -> Result<()> {
+        let start = self.objects_offset;
+        let end = start + self.objects.len() * MAP_OBJECT_SIZE;
+        let len = file.len();
+        let objects = file
+            .get_mut(start..end)
+            .ok_or(Error::OutsideFile { start, end, len })?;
+        for (object, at) in self
+            .objects
+            .iter()
+            .zip(objects.chunks_exact_mut(MAP_OBJECT_SIZE))
+        {
+            at[0..2].copy_from_slice(&object.kind.to_be_bytes());
+            at[2..4].copy_from_slice(&object.x.to_be_bytes());
+            at[4..6].copy_from_slice(&object.y.to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+/// An axis-aligned rectangle, in the order that the game stores bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub top: i16,
+    pub bottom: i16,
+    pub right: i16,
+    pub left: i16,
+}
+
+/// The camera bounds and blast zones of a stage: eight `s16`s, the top, bottom,
+/// right, and left of the camera, then of the blast zones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds {
+    pub camera: Rect,
+    pub blast_zones: Rect,
+}
+
+/// A reader of the big-endian fields of `file`, that names the structure at `start`
+/// in its errors
+struct Reader<'a> {
+    file: &'a [u8],
+    start: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, at: usize, len: usize) -> Result<&[u8]> {
+        self.file.get(at..at + len).ok_or_else(|| {
+            Error::OutsideFile {
+                start: self.start,
+                end: at + len,
+                len: self.file.len(),
+            }
+            .into()
+        })
+    }
+
+    fn u16(&self, at: usize) -> Result<u16> {
+        self.bytes(at, 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&self, at: usize) -> Result<i16> {
+        self.u16(at).map(|h| h as i16)
+    }
+}
+
+impl Bounds {
+    /// Read the bounds at `offset` of `file`
+    pub fn read(file: &[u8], offset: usize) -> Result<Self> {
+        let r = Reader {
+            file,
+            start: offset,
+        };
+        let rect = |at: usize| -> Result<Rect> {
+            Ok(Rect {
+                top: r.i16(at)?,
+                bottom: r.i16(at + 2)?,
+                right: r.i16(at + 4)?,
+                left: r.i16(at + 6)?,
+            })
+        };
+
+        Ok(Self {
+            camera: rect(offset)?,
+            blast_zones: rect(offset + 8)?,
+        })
+    }
+}
+
+/// Read the collision geometry at `offset` of `file`, following its pointers in `relocs`
+pub fn geometry(file: &[u8], offset: usize, relocs: &[Relocation]) -> Result<Geometry> {
+    let r = Reader {
+        file,
+        start: offset,
+    };
+    let bad = || Error::BadGeometry { offset };
+    let targets = relocs
+        .iter()
+        .filter(|r| r.external_file.is_none())
+        .map(|r| r.target as usize)
+        .collect::<BTreeSet<_>>();
+    // the target of a pointer of the header
+    let pointer = |field: usize| -> Result<usize> {
+        relocs
+            .iter()
+            .find(|r| r.offset == offset + field && r.external_file.is_none())
+            .map(|r| r.target as usize)
+            .ok_or_else(|| bad().into())
+    };
+
+    r.bytes(offset, GEOMETRY_SIZE)?;
+    let groups = r.u16(offset)? as usize;
+    let positions = pointer(0x04)?;
+    let ids = pointer(0x08)?;
+    let lines = pointer(0x0C)?;
+    let group_lines = pointer(0x10)?;
+    let objects = pointer(0x14)?;
+    let objects_end = targets
+        .range(objects + 1..)
+        .next()
+        .copied()
+        .unwrap_or(file.len())
+        .min(file.len());
+    let object_count = objects_end.saturating_sub(objects) / MAP_OBJECT_SIZE;
+
+    let mut geometry_lines = Vec::new();
+    for group in 0..groups {
+        for (k, kind) in LineKind::ALL.into_iter().enumerate() {
+            let at = group_lines + group * 0x10 + k * 4;
+            let (first, count) = (r.u16(at)? as usize, r.u16(at + 2)? as usize);
+            for line in first..first + count {
+                let at = lines + line * 4;
+                let (start, len) = (r.u16(at)? as usize, r.u16(at + 2)? as usize);
+                let vertices = (start..start + len)
+                    .map(|i| r.u16(ids + i * 2).map(usize::from))
+                    .collect::<Result<Vec<_>>>()?;
+                geometry_lines.push(Line {
+                    group,
+                    kind,
+                    vertices,
+                });
+            }
+        }
+    }
+
+    let vertex_count = geometry_lines
+        .iter()
+        .flat_map(|l| l.vertices.iter().map(|v| v + 1))
+        .max()
+        .unwrap_or(0);
+    let vertices = (0..vertex_count)
+        .map(|i| {
+            let at = positions + i * VERTEX_SIZE;
+            Ok(Vertex {
+                x: r.i16(at)?,
+                y: r.i16(at + 2)?,
+                flags: r.u16(at + 4)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let map_objects = (0..object_count)
+        .map(|i| {
+            let at = objects + i * MAP_OBJECT_SIZE;
+            Ok(MapObject {
+                kind: r.u16(at)?,
+                x: r.i16(at + 2)?,
+                y: r.i16(at + 4)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Geometry {
+        vertices,
+        lines: geometry_lines,
+        objects: map_objects,
+        objects_offset: objects,
+    })
+}
+
+/// A stage's collision and bounds, displayed as a report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stage {
+    pub geometry: Geometry,
+    pub bounds: Option<Bounds>,
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "top {}, bottom {}, left {}, right {}",
+            self.top, self.bottom, self.left, self.right
+        )
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let g = &self.geometry;
+        if let Some(bounds) = &self.bounds {
+            writeln!(f, "camera:      {}", bounds.camera)?;
+            writeln!(f, "blast zones: {}", bounds.blast_zones)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "{} lines:", g.lines.len())?;
+        for line in &g.lines {
+            write!(f, "* group {} {}:", line.group, line.kind.name())?;
+            for &v in &line.vertices {
+                let vertex = g.vertices[v];
+                write!(f, " ({}, {})", vertex.x, vertex.y)?;
+                if vertex.flags & Vertex::PASS != 0 {
+                    f.write_str(" pass")?;
+                }
+                if vertex.flags & Vertex::LEDGE != 0 {
+                    f.write_str(" ledge")?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{} map objects:", g.objects.len())?;
+        for object in &g.objects {
+            match (object.player_spawn(), object.bonus()) {
+                (Some(player), _) => write!(f, "* player {} spawn", player)?,
+                (None, Some(bonus)) => write!(f, "* {}", bonus)?,
+                (None, None) => write!(f, "* kind {:#04X}", object.kind)?,
+            }
+            writeln!(f, " at ({}, {})", object.x, object.y)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Stage {
+    /// Draw the stage from the side as an SVG: ground in green, ceilings in gray, and walls
+    /// in blue (dashed where fighters can drop through), ledges as dots, the camera bounds
+    /// in orange, the blast zones in red, and the spawn points as labeled markers
+    pub fn svg(&self) -> String {
+        const MARGIN: i32 = 200;
+
+        let g = &self.geometry;
+        let points = g
+            .lines
+            .iter()
+            .flat_map(|l| &l.vertices)
+            .map(|&v| (g.vertices[v].x as i32, g.vertices[v].y as i32))
+            .chain(g.objects.iter().map(|o| (o.x as i32, o.y as i32)));
+        let corners = self.bounds.iter().flat_map(|b| {
+            [b.camera, b.blast_zones].map(|r| {
+                [
+                    (r.left as i32, r.top as i32),
+                    (r.right as i32, r.bottom as i32),
+                ]
+            })
+        });
+        let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+        for (x, y) in points.chain(corners.flatten()) {
+            left = left.min(x);
+            right = right.max(x);
+            bottom = bottom.min(y);
+            top = top.max(y);
+        }
+        let (left, top) = (left - MARGIN, top + MARGIN);
+        let (width, height) = (right + MARGIN - left, top - (bottom - MARGIN));
+
+        // the game's y is up, and SVG's is down
+        let mut s = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n\
+             <g transform=\"scale(1,-1)\" fill=\"none\" stroke-width=\"{}\">\n",
+            left,
+            -top,
+            width,
+            height,
+            (width.max(height) / 500).max(1)
+        );
+        let mut write = |line: String| {
+            s += &line;
+            s.push('\n');
+        };
+
+        if let Some(b) = &self.bounds {
+            for (rect, color) in [(b.camera, "orange"), (b.blast_zones, "red")] {
+                write(format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" stroke=\"{}\" stroke-dasharray=\"20\"/>",
+                    rect.left,
+                    rect.bottom,
+                    rect.right as i32 - rect.left as i32,
+                    rect.top as i32 - rect.bottom as i32,
+                    color
+                ));
+            }
+        }
+
+        for line in &g.lines {
+            let color = match line.kind {
+                LineKind::Ground => "green",
+                LineKind::Ceiling => "gray",
+                LineKind::RightWall | LineKind::LeftWall => "blue",
+            };
+            for pair in line.vertices.windows(2) {
+                let (a, b) = (g.vertices[pair[0]], g.vertices[pair[1]]);
+                let dash = if a.flags & Vertex::PASS != 0 {
+                    " stroke-dasharray=\"10\""
+                } else {
+                    ""
+                };
+                write(format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"{}/>",
+                    a.x, a.y, b.x, b.y, color, dash
+                ));
+            }
+        }
+        let ledges = g
+            .lines
+            .iter()
+            .flat_map(|l| &l.vertices)
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for v in ledges {
+            let vertex = g.vertices[v];
+            if vertex.flags & Vertex::LEDGE != 0 {
+                write(format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"15\" fill=\"black\"/>",
+                    vertex.x, vertex.y
+                ));
+            }
+        }
+
+        for object in &g.objects {
+            let label = match (object.player_spawn(), object.bonus()) {
+                (Some(player), _) => format!("P{}", player),
+                (None, Some(bonus)) => bonus.into(),
+                (None, None) => format!("{:#04X}", object.kind),
+            };
+            write(format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"25\" stroke=\"purple\"/>",
+                object.x, object.y
+            ));
+            // text is flipped back, so that it isn't upside down
+            write(format!(
+                "<text x=\"{}\" y=\"{}\" transform=\"scale(1,-1)\" font-size=\"60\" fill=\"purple\" stroke=\"none\">{}</text>",
+                object.x as i32 + 30,
+                -(object.y as i32) - 30,
+                label
+            ));
+        }
+
+        s += "</g>\n</svg>\n";
+        s
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{cli::StatsOpt, timings::Timings};
+use anyhow::Result;
+
+const NO_RELOCS: u16 = 0xFFFF;
+const LARGEST: usize = 10;
+
+pub(crate) fn table(opt: StatsOpt, timings: &mut Timings) -> Result<()> {
+    let (source, version) = opt.rom.source(timings)?;
+    let rom = opt.rom.table(&source, version, timings)?;
+    let (entries, version) = (rom.entries(), rom.version());
+
+    let bytes = |words: u16| words as usize * 4;
+    let compressed = entries.iter().filter(|e| e.compressed);
+    let stored = compressed.clone().map(|e| bytes(e.size)).sum::<usize>();
+    let expanded = compressed
+        .clone()
+        .map(|e| bytes(e.decompressed_size))
+        .sum::<usize>();
+    let total = entries.iter().map(|e| bytes(e.size)).sum::<usize>();
+    let total_decompressed = entries
+        .iter()
+        .map(|e| bytes(e.decompressed_size))
+        .sum::<usize>();
+
+    println!("# {} resource table", version.version);
+    println!("* files:              {}", entries.len());
+    println!(
+        "* compressed files:   {} ({:#X} bytes, {:#X} decompressed)",
+        compressed.count(),
+        stored,
+        expanded
+    );
+    println!("* stored bytes:       {:#X}", total);
+    println!("* decompressed bytes: {:#X}", total_decompressed);
+    println!("* data region:        {:#X}", rom.terminal().data_end);
+    match rom.secondary_table() {
+        Ok(secondary) => println!(
+            "* secondary table:    rom {:#X} ({} files)",
+            rom.secondary_table_offset(),
+            secondary.file_count()
+        ),
+        Err(_) => println!(
+            "* secondary table:    rom {:#X} (no entries found)",
+            rom.secondary_table_offset()
+        ),
+    }
+    if expanded > 0 {
+        println!(
+            "* compression ratio:  {:.1}%",
+            stored as f64 / expanded as f64 * 100.0
+        );
+    }
+    println!(
+        "* internal relocs:    {} files",
+        entries.iter().filter(|e| e.inreloc != NO_RELOCS).count()
+    );
+    println!(
+        "* external relocs:    {} files",
+        entries.iter().filter(|e| e.exreloc != NO_RELOCS).count()
+    );
+
+    let mut largest = entries.iter().enumerate().collect::<Vec<_>>();
+    largest.sort_by_key(|(id, e)| (std::cmp::Reverse(e.decompressed_size), *id));
+    println!();
+    println!("# Largest files (decompressed)");
+    for (id, e) in largest.into_iter().take(LARGEST) {
+        println!("* {:4}: {:#X}", id, bytes(e.decompressed_size));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::{versions::SSBInfo, Error};
+use alloc::{format, vec::Vec};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "std")]
+use {crate::decompress, alloc::borrow::Cow, core::ops::Range, std::io::Write};
+
+/// The start of the runtime relocation list in a file.
+/// If the relocations are for pointers into external files,
+/// there is the processed list of external file ids.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum RelocInfo {
+    Internal(usize),
+    External(usize, Vec<u16>),
+}
+
+impl RelocInfo {
+    pub fn get_starting_offset(&self) -> usize {
+        match self {
+            Self::Internal(o) => *o,
+            Self::External(o, _) => *o,
+        }
+    }
+
+    pub fn get_external_files(&self) -> Option<&[u16]> {
+        match self {
+            Self::Internal(..) => None,
+            Self::External(_, ex) => Some(ex.as_slice()),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TableFile<'r> {
+    pub id: usize,
+    /// offset from the end of the table
+    pub offset: usize,
+    pub compressed: bool,
+    /// size of the data once decompressed
+    pub decompressed_size: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: &'r [u8],
+    pub inreloc: Option<RelocInfo>,
+    pub exreloc: Option<RelocInfo>,
+}
+
+impl<'r> TableFile<'r> {
+    const ENTRY_SIZE: usize = 12;
+    const COMPRESS_BIT: u32 = 0x80000000;
+
+    pub fn get(id: usize, rom: &'r [u8], info: &SSBInfo) -> Result<Self> {
+        Self::get_with(id, info, |start, end| checked_slice(rom, start, end))
+    }
+
+    /// [`get`](Self::get) file `id` from a rom whose bytes `start..end` are returned by
+    /// `read`, or an error if they can't be read
+    pub(crate) fn get_with(
+        id: usize,
+        info: &SSBInfo,
+        read: impl Fn(usize, usize) -> Result<&'r [u8]>,
+    ) -> Result<Self> {
+        fn read_checked_u16(raw: [u8; 2]) -> Option<u16> {
+            Some(u16::from_be_bytes(raw)).filter(|&val| val != 0xFFFF)
+        }
+
+        let entry = Self::read_entry(id, info, &read)?;
+        let offset = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+        let compressed = offset & Self::COMPRESS_BIT > 0;
+        let offset = (offset & !Self::COMPRESS_BIT) as usize;
+        let size = u16::from_be_bytes([entry[6], entry[7]]) as usize * 4;
+        let decompressed_size = u16::from_be_bytes([entry[10], entry[11]]) as usize * 4;
+
+        let raw = {
+            let fstart = info.table_end + offset;
+            let fend = fstart + size;
+            read(fstart, fend).with_context(|| format!("reading data of file <{}>", id))?
+        };
+        let inreloc = read_checked_u16([entry[4], entry[5]])
+            .map(|x| x as usize * 4)
+            .map(RelocInfo::Internal);
+        let exreloc = read_checked_u16([entry[8], entry[9]])
+            .map(|x| x as usize * 4)
+            .map(|start| {
+                Self::get_next_entry_offset(id, info, &read)
+                    .and_then(|next_start| {
+                        let exstart = info.table_end + offset + size;
+                        let exend = info.table_end + next_start;
+
+                        read(exstart, exend)
+                            .and_then(Self::parse_externs)
+                            .with_context(|| format!("reading external files of file <{}>", id))
+                    })
+                    .map(|externs| RelocInfo::External(start, externs))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            id,
+            offset,
+            compressed,
+            decompressed_size,
+            raw,
+            inreloc,
+            exreloc,
+        })
+    }
+
+    /// The bytes of the rom that [`get`](Self::get) reads for file `id`: its data, and the
+    /// list of external files after it up to the data of the next file. Only the resource
+    /// table has to be readable with `read`.
+    #[cfg(feature = "std")]
+    pub(crate) fn span(
+        id: usize,
+        info: &SSBInfo,
+        read: impl Fn(usize, usize) -> Result<&'r [u8]>,
+    ) -> Result<Range<usize>> {
+        let entry = Self::read_entry(id, info, &read)?;
+        let offset = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+        let offset = (offset & !Self::COMPRESS_BIT) as usize;
+        let size = u16::from_be_bytes([entry[6], entry[7]]) as usize * 4;
+        let next = Self::get_next_entry_offset(id, info, &read)?;
+
+        Ok(info.table_end + offset..info.table_end + next.max(offset + size))
+    }
+
+    /// The table entry of file `id`
+    fn read_entry(
+        id: usize,
+        info: &SSBInfo,
+        read: &impl Fn(usize, usize) -> Result<&'r [u8]>,
+    ) -> Result<&'r [u8]> {
+        if id >= info.total_entries() {
+            bail!(Error::FileIdOutOfRange {
+                id,
+                count: info.total_entries()
+            });
+        }
+
+        let start = info.table_start + id * Self::ENTRY_SIZE;
+        read(start, start + Self::ENTRY_SIZE)
+            .with_context(|| format!("reading table entry for file <{}>", id))
+    }
+
+    fn parse_externs(raw: &[u8]) -> Result<Vec<u16>> {
+        if !raw.len().is_multiple_of(2) {
+            bail!("expected list of BE u16, got slice of size {}", raw.len());
+        }
+
+        Ok(raw
+            .chunks(2)
+            .map(|e| u16::from_be_bytes(e.try_into().unwrap()))
+            .collect())
+    }
+
+    /// The offset of the entry after `id`, read straight from the table, so that getting
+    /// one file doesn't parse every following file. The dummy entry at the end of the table
+    /// holds the end of the data instead.
+    fn get_next_entry_offset(
+        id: usize,
+        info: &SSBInfo,
+        read: &impl Fn(usize, usize) -> Result<&'r [u8]>,
+    ) -> Result<usize> {
+        let next = id + 1;
+        let start = info.table_start + (next * Self::ENTRY_SIZE);
+        let raw = read(start, start + 4)
+            .with_context(|| format!("reading table entry for file <{}>", next))?;
+        let offset = u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]);
+
+        if next >= info.total_entries() {
+            Ok(offset as usize)
+        } else {
+            Ok((offset & !Self::COMPRESS_BIT) as usize)
+        }
+    }
+
+    /// The list of external file ids that follows the file's data, if the file
+    /// has external relocations. Each external relocation takes the next id in
+    /// the list, so any ids after the last relocation are padding.
+    pub fn includes(&self) -> Option<&[u16]> {
+        self.exreloc
+            .as_ref()
+            .and_then(RelocInfo::get_external_files)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r> TableFile<'r> {
+    /// Get the file's data, decompressing it if necessary
+    pub fn data(&self) -> Result<Vec<u8>> {
+        self.decompressed().map(Cow::into_owned)
+    }
+
+    /// Write the file's data to `wtr`, decompressing it if necessary, and return the
+    /// number of bytes written. An uncompressed file is written straight from the rom.
+    pub fn write_data<W: Write>(&self, mut wtr: W) -> Result<usize> {
+        let data = self.decompressed()?;
+        wtr.write_all(&data)
+            .with_context(|| format!("writing data of file <{}>", self.id))?;
+
+        Ok(data.len())
+    }
+
+    /// Get the file's data, decompressing it if necessary. An uncompressed file
+    /// is borrowed from the rom, so only compressed files are copied.
+    pub fn decompressed(&self) -> Result<Cow<'r, [u8]>> {
+        if self.compressed {
+            decompress(self.raw, self.id).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(self.raw))
+        }
+    }
+}
+
+/// `rom[start..end]`, or an error if the range is backwards or past the end of the rom
+fn checked_slice(rom: &[u8], start: usize, end: usize) -> Result<&[u8]> {
+    let len = rom.len();
+    rom.get(start..end)
+        .ok_or(Error::OutsideRom { start, end, len })
+        .map_err(Into::into)
+}
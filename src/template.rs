@@ -0,0 +1,144 @@
+use crate::cli::{DataFormat, Mode};
+use anyhow::{anyhow, bail, Result};
+use ssbfile::{names::NameDb, TableFile};
+use std::{fmt::Write, str::FromStr};
+
+/// A template for the names of exported files, like `{kind}-{id:04}.{ext}`
+///
+/// ```text
+/// {id}           the file id; `{id:04}` pads it with zeros to four digits
+/// {kind}         `raw` when exporting raw data, otherwise `file`
+/// {mode}         the export mode: raw, decompress, reloc, elf, or asm
+/// {compression}  `vpk0` if the file is compressed in the rom, otherwise `none`
+/// {ext}          the default extension: vpk, bin, o, s, or c
+/// {name}         `-` and the file's known name, like `-fox-main-file`, or nothing if
+///                the name isn't known
+/// ```
+/// Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NameTemplate(Vec<Segment>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Id { width: usize },
+    Kind,
+    Mode,
+    Compression,
+    Ext,
+    Name,
+}
+
+impl NameTemplate {
+    pub(crate) const DEFAULT: &'static str = "{kind}-{id:04}{name}.{ext}";
+
+    pub(crate) fn render(
+        &self,
+        entry: &TableFile,
+        mode: Mode,
+        format: DataFormat,
+        names: &NameDb,
+    ) -> String {
+        let mut name = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(s) => name.push_str(s),
+                Segment::Id { width } => {
+                    write!(name, "{:0width$}", entry.id, width = width).expect("write to string")
+                }
+                Segment::Kind => name.push_str(match mode {
+                    Mode::RawBytes => "raw",
+                    _ => "file",
+                }),
+                Segment::Mode => name.push_str(mode.name()),
+                Segment::Compression => {
+                    name.push_str(if entry.compressed { "vpk0" } else { "none" })
+                }
+                Segment::Ext => name.push_str(match mode {
+                    _ if format == DataFormat::C => "c",
+                    Mode::RawBytes if entry.compressed => "vpk",
+                    Mode::RawBytes | Mode::Decompressed | Mode::Relocated => "bin",
+                    Mode::Elf => "o",
+                    Mode::Asm => "s",
+                }),
+                Segment::Name => {
+                    if let Some(slug) = names.slug(entry.id) {
+                        name.push('-');
+                        name.push_str(&slug);
+                    }
+                }
+            }
+        }
+
+        name
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (field, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| anyhow!("Unclosed placeholder in name template <{}>", s))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_field(field)?);
+                    chars = rest.chars();
+                }
+                '}' => bail!("Unmatched `}}` in name template <{}>", s),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+fn parse_field(field: &str) -> Result<Segment> {
+    let (name, format) = match field.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (field, None),
+    };
+
+    let segment = match name {
+        "id" => Segment::Id {
+            width: format
+                .map(|f| f.parse())
+                .transpose()
+                .map_err(|_| anyhow!("Bad width for placeholder <{{{}}}>", field))?
+                .unwrap_or(0),
+        },
+        "kind" => Segment::Kind,
+        "mode" => Segment::Mode,
+        "compression" => Segment::Compression,
+        "ext" => Segment::Ext,
+        "name" => Segment::Name,
+        _ => bail!("Unknown placeholder <{{{}}}> in name template", field),
+    };
+    if format.is_some() && !matches!(segment, Segment::Id { .. }) {
+        bail!("Only {{id}} takes a width, not <{{{}}}>", field);
+    }
+
+    Ok(segment)
+}
@@ -0,0 +1,429 @@
+//! Strings in the data of files: runs of text that end with a NUL, like the game's C
+//! strings, written as PO or CSV for translation and read back again.
+//!
+//! The two byte Shift-JIS characters of the Japanese release are written as UTF-8, and
+//! encoded back to Shift-JIS when they are read. Any other byte outside of printable ASCII
+//! is kept as a `\xNN` escape, so that every message round trips to the same bytes.
+//! Translations are written back with [`reinsert`]: in the place of the original string if
+//! they fit, or at the end of the file, with the pointers to the string moved to them.
+use crate::{Error, Relocation};
+use alloc::{format, string::String, vec::Vec};
+use anyhow::Result;
+use core::fmt::Write;
+use encoding_rs::SHIFT_JIS;
+
+/// A string of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    pub file: usize,
+    /// offset of the string in the decompressed file
+    pub offset: usize,
+    /// the bytes of the string, without its NUL
+    pub bytes: Vec<u8>,
+}
+
+impl Message {
+    /// The id of the message in PO and CSV files: the file id and offset
+    pub fn id(&self) -> String {
+        format!("{:04}:{:04X}", self.file, self.offset)
+    }
+
+    /// The message as text, with quotes, backslashes, and other bytes escaped
+    pub fn escaped(&self) -> String {
+        escape(&self.bytes)
+    }
+}
+
+/// Find the strings of `file` (with the id `id`): the runs of at least `min_len` printable
+/// bytes that start on a word and end with a NUL, after another NUL or a word boundary.
+/// Bytes of two byte Shift-JIS characters count as printable.
+pub fn messages(id: usize, file: &[u8], min_len: usize) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut at = 0;
+    while at < file.len() {
+        let len = text_len(&file[at..]);
+        let ends = file.get(at + len) == Some(&0);
+        if len >= min_len.max(1) && ends {
+            messages.push(Message {
+                file: id,
+                offset: at,
+                bytes: file[at..at + len].to_vec(),
+            });
+            // the next string starts on the word after the NUL
+            at = (at + len + 1).next_multiple_of(4);
+        } else {
+            at += 4;
+        }
+    }
+
+    messages
+}
+
+/// The number of bytes of text at the start of `data`
+fn text_len(data: &[u8]) -> usize {
+    let mut i = 0;
+    while let Some(&b) = data.get(i) {
+        match b {
+            b'\n' | b'\t' | 0x20..=0x7E => i += 1,
+            _ if is_shift_jis(&data[i..]) => i += 2,
+            _ => break,
+        }
+    }
+
+    i
+}
+
+/// If `data` starts with a Shift-JIS lead byte and the byte after it
+fn is_shift_jis(data: &[u8]) -> bool {
+    matches!(data, [0x81..=0x9F | 0xE0..=0xEF, 0x40..=0xFC, ..])
+}
+
+/// The character of the two byte Shift-JIS at the start of `data`, if it is one that is
+/// encoded back to the same bytes
+fn shift_jis_char(data: &[u8]) -> Option<char> {
+    if !is_shift_jis(data) {
+        return None;
+    }
+    let decoded = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(&data[..2])?;
+    let mut chars = decoded.chars();
+    let c = chars.next().filter(|_| chars.next().is_none())?;
+
+    (encode_char(c)? == data[..2]).then_some(c)
+}
+
+/// The Shift-JIS bytes of `c`, if it has them
+fn encode_char(c: char) -> Option<Vec<u8>> {
+    let mut buf = [0; 4];
+    let (bytes, _, unmappable) = SHIFT_JIS.encode(c.encode_utf8(&mut buf));
+
+    (!unmappable).then(|| bytes.into_owned())
+}
+
+/// Escape `bytes` like a C string: quotes, backslashes, newlines, and tabs. Two byte
+/// Shift-JIS characters are decoded, and any other byte outside of printable ASCII is
+/// written as `\xNN`.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        if let Some(c) = shift_jis_char(&bytes[i..]) {
+            s.push(c);
+            i += 2;
+            continue;
+        }
+        match b {
+            b'"' => s.push_str("\\\""),
+            b'\\' => s.push_str("\\\\"),
+            b'\n' => s.push_str("\\n"),
+            b'\t' => s.push_str("\\t"),
+            0x20..=0x7E => s.push(b as char),
+            _ => write!(s, "\\x{:02X}", b).expect("write to string"),
+        }
+        i += 1;
+    }
+
+    s
+}
+
+/// The bytes of `text` with the escapes of [`escape`] undone, and the characters other
+/// than ASCII encoded as Shift-JIS. A character that Shift-JIS doesn't have can still be
+/// written as the `\xNN` escapes of its bytes.
+pub fn unescape(text: &str) -> Result<Vec<u8>> {
+    let bad = || Error::BadEscape { text: text.into() };
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            if c.is_ascii() {
+                bytes.push(c as u8);
+            } else {
+                bytes.extend(encode_char(c).ok_or(Error::TextChar { c })?);
+            }
+            continue;
+        }
+        match chars.next().ok_or_else(bad)? {
+            '"' => bytes.push(b'"'),
+            '\\' => bytes.push(b'\\'),
+            'n' => bytes.push(b'\n'),
+            't' => bytes.push(b'\t'),
+            'x' => {
+                let hex = [chars.next(), chars.next()]
+                    .into_iter()
+                    .collect::<Option<String>>()
+                    .ok_or_else(bad)?;
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| bad())?);
+            }
+            _ => return Err(bad().into()),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Write `messages` as a gettext PO template, with each message's id as its context
+pub fn po(messages: &[Message]) -> String {
+    let mut s =
+        String::from("msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    for m in messages {
+        let text = m.escaped();
+        write!(
+            s,
+            "\n#: file {} at {:#X}\nmsgctxt \"{}\"\nmsgid \"{}\"\nmsgstr \"\"\n",
+            m.file,
+            m.offset,
+            m.id(),
+            text
+        )
+        .expect("write to string");
+    }
+
+    s
+}
+
+/// Write `messages` as CSV: the id, the text, and an empty column for its translation
+pub fn csv(messages: &[Message]) -> String {
+    let mut s = String::from("id,text,translation\n");
+    for m in messages {
+        // csv quotes are doubled, and the text's own escapes are kept
+        let text = m.escaped().replace("\\\"", "\"\"");
+        writeln!(s, "{},\"{}\",", m.id(), text).expect("write to string");
+    }
+
+    s
+}
+
+/// The edited text of a string
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Translation {
+    pub file: usize,
+    /// offset of the original string in the decompressed file
+    pub offset: usize,
+    /// the bytes of the new string, without its NUL
+    pub bytes: Vec<u8>,
+}
+
+/// The file id and offset of a message id, like `0012:01A0`
+pub fn parse_id(id: &str) -> Option<(usize, usize)> {
+    let (file, offset) = id.split_once(':')?;
+    Some((file.parse().ok()?, usize::from_str_radix(offset, 16).ok()?))
+}
+
+fn translation(line: usize, id: &str, text: &str) -> Result<Translation> {
+    let (file, offset) = parse_id(id).ok_or_else(|| Error::TextSyntax {
+        line,
+        reason: format!("bad message id <{}>; expected FILE:OFFSET", id),
+    })?;
+
+    Ok(Translation {
+        file,
+        offset,
+        bytes: unescape(text)?,
+    })
+}
+
+/// Read the translated messages of a PO file that [`po`] wrote: the entries with a
+/// `msgctxt` and a `msgstr` that isn't empty
+pub fn read_po(text: &str) -> Result<Vec<Translation>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Field {
+        Context,
+        Id,
+        Str,
+    }
+
+    let mut translations = Vec::new();
+    // the line that the entry starts on, and its context and translation
+    let mut entry: Option<(usize, String, String)> = None;
+    let mut field = None;
+    let mut finish = |entry: Option<(usize, String, String)>| -> Result<()> {
+        match entry {
+            Some((line, context, msgstr)) if !context.is_empty() && !msgstr.is_empty() => {
+                translations.push(translation(line, &context, &msgstr)?);
+            }
+            _ => (),
+        }
+        Ok(())
+    };
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            finish(entry.take())?;
+            field = None;
+            continue;
+        }
+        if raw.starts_with('#') {
+            continue;
+        }
+        let syntax = |reason: &str| Error::TextSyntax {
+            line,
+            reason: reason.into(),
+        };
+
+        let (keyword, quoted) = match raw.split_once(' ') {
+            Some((keyword, rest)) if !raw.starts_with('"') => (Some(keyword), rest.trim()),
+            _ => (None, raw),
+        };
+        let string = quoted
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .filter(|_| quoted.len() >= 2)
+            .ok_or_else(|| syntax("expected a quoted string"))?;
+        match keyword {
+            Some("msgctxt") => {
+                // a context starts a new entry, even without a blank line before it
+                finish(entry.take())?;
+                field = Some(Field::Context);
+            }
+            Some("msgid") if field != Some(Field::Context) => {
+                finish(entry.take())?;
+                field = Some(Field::Id);
+            }
+            Some("msgid") => field = Some(Field::Id),
+            Some("msgstr") => field = Some(Field::Str),
+            Some(other) => return Err(syntax(&format!("unknown keyword <{}>", other)).into()),
+            None if field.is_none() => return Err(syntax("a string outside of an entry").into()),
+            None => (),
+        }
+
+        let (_, context, msgstr) =
+            entry.get_or_insert_with(|| (line, String::new(), String::new()));
+        match field {
+            Some(Field::Context) => context.push_str(string),
+            Some(Field::Str) => msgstr.push_str(string),
+            _ => (),
+        }
+    }
+    finish(entry.take())?;
+
+    Ok(translations)
+}
+
+/// Read the translated messages of a CSV file that [`csv`] wrote: the rows with a third,
+/// translation column that isn't empty
+pub fn read_csv(text: &str) -> Result<Vec<Translation>> {
+    let mut translations = Vec::new();
+    for (line, row) in csv_rows(text)?.into_iter().skip(1) {
+        match row.as_slice() {
+            [id, _, translated, ..] if !translated.is_empty() => {
+                // the quotes that csv doubled are quotes of the text again
+                translations.push(translation(line, id, translated)?);
+            }
+            [_, _, ..] | [] => (),
+            [_] => {
+                return Err(Error::TextSyntax {
+                    line,
+                    reason: "expected the id, text, and translation columns".into(),
+                }
+                .into())
+            }
+        }
+    }
+
+    Ok(translations)
+}
+
+/// The rows of a CSV file, with the line that each starts on
+fn csv_rows(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(core::mem::take(&mut cell)),
+            '\r' if !quoted => (),
+            '\n' if !quoted => {
+                row.push(core::mem::take(&mut cell));
+                if row.iter().any(|c| !c.is_empty()) {
+                    rows.push((start, core::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                start = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                cell.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(Error::TextSyntax {
+            line: start,
+            reason: "a quote that doesn't end".into(),
+        }
+        .into());
+    }
+    row.push(cell);
+    if row.iter().any(|c| !c.is_empty()) {
+        rows.push((start, row));
+    }
+
+    Ok(rows)
+}
+
+/// Write `translations` of strings into `file`. A translation that fits in the place of its
+/// string (up to the word after its NUL) replaces it there; one that doesn't is added to
+/// the end of the file, and the pointers in `relocs` to the string are moved to it.
+pub fn reinsert(
+    file: &mut Vec<u8>,
+    translations: &[Translation],
+    relocs: &mut [Relocation],
+) -> Result<()> {
+    // the places of the strings, before any of them are changed
+    let mut places = Vec::with_capacity(translations.len());
+    for t in translations {
+        let start = file.get(t.offset..).unwrap_or_default();
+        let len = text_len(start);
+        if len == 0 || start.get(len) != Some(&0) {
+            return Err(Error::NoText { offset: t.offset }.into());
+        }
+        let capacity = ((t.offset + len + 1).next_multiple_of(4)).min(file.len()) - t.offset;
+        places.push(capacity);
+    }
+
+    for (t, capacity) in translations.iter().zip(places) {
+        let len = t.bytes.len() + 1;
+        if len <= capacity {
+            let place = &mut file[t.offset..t.offset + capacity];
+            place.fill(0);
+            place[..t.bytes.len()].copy_from_slice(&t.bytes);
+            continue;
+        }
+
+        let pointers = relocs
+            .iter_mut()
+            .filter(|r| r.external_file.is_none() && r.target as usize == t.offset)
+            .collect::<Vec<_>>();
+        if pointers.is_empty() {
+            return Err(Error::TextTooLong {
+                offset: t.offset,
+                len,
+                capacity,
+            }
+            .into());
+        }
+        let end = file.len().next_multiple_of(4);
+        file.resize(end, 0);
+        file.extend_from_slice(&t.bytes);
+        file.resize((end + len).next_multiple_of(4), 0);
+        for r in pointers {
+            r.target = end as u32;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,508 @@
+use crate::{
+    cli::{FontOpt, ImportPaletteOpt, ImportTextureOpt, PaletteOpt, TextureOpt},
+    inject,
+    timings::{Stage, Timings},
+};
+use anyhow::{bail, ensure, Context, Result};
+use serde::Serialize;
+use ssbfile::{
+    image::{self, Image, Palette, Sprite},
+    versions::SSBInfo,
+    TableFile,
+};
+use std::{fs, path::Path};
+
+pub(crate) fn export(opt: TextureOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", opt.id))?;
+
+    if let Some(offset) = opt.offset {
+        // --offset requires the format and size
+        let format = opt.format.expect("format is required with offset");
+        let (width, height) = (opt.width.unwrap_or(0), opt.height.unwrap_or(0));
+        let palette = opt.palette.map(|at| {
+            let palette = data.get(at as usize..).unwrap_or_default();
+            &palette[..(format.palette_len() * 2).min(palette.len())]
+        });
+        let texels = data.get(offset as usize..).unwrap_or_default();
+        let image = image::decode(texels, format, width, height, palette).with_context(|| {
+            format!(
+                "decoding the {} texture at {:#X} of file <{}>",
+                format, offset, opt.id
+            )
+        })?;
+        return write_png(&opt.output, &image, timings);
+    }
+
+    let relocs = timings
+        .time(Stage::Relocate, || ssbfile::relocations(&data, &file))
+        .with_context(|| format!("reading the relocations of file <{}>", opt.id))?;
+    let sprites = image::sprites(&data, &relocs);
+    if sprites.is_empty() {
+        bail!(
+            "found no sprites in file <{}>; give the --offset, --format, --width, and --height of a texture",
+            opt.id
+        );
+    }
+    fs::create_dir_all(&opt.output)
+        .with_context(|| format!("creating directory <{}>", opt.output.display()))?;
+    let mut images = Vec::with_capacity(sprites.len());
+    for sprite in &sprites {
+        let image = image::decode_sprite(&data, sprite).with_context(|| {
+            format!(
+                "decoding the sprite at {:#X} of file <{}>",
+                sprite.offset, opt.id
+            )
+        })?;
+        println!(
+            "* {:#06X}: {}x{} {}",
+            sprite.offset, sprite.width, sprite.height, sprite.format
+        );
+        if !opt.sheet {
+            let name = format!("file-{:04}-sprite-{:04X}.png", opt.id, sprite.offset);
+            write_png(&opt.output.join(name), &image, timings)?;
+        }
+        images.push(image);
+    }
+    if opt.sheet {
+        write_sheet(&opt, &sprites, &images, timings)?;
+    }
+    eprintln!("wrote {} sprites of file {}", sprites.len(), opt.id);
+
+    Ok(())
+}
+
+pub(crate) fn import(opt: ImportTextureOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let (mut data, relocs) = {
+        let file = timings
+            .time(Stage::TableParse, || TableFile::get(opt.id, &rom, &version))
+            .with_context(|| format!("issue getting table entry for file <{}>", opt.id))?;
+        let data = timings
+            .time(Stage::Decompress, || file.decompressed())
+            .with_context(|| format!("decompressing file <{}>", opt.id))?;
+        let relocs = match opt.sprite {
+            Some(_) => timings
+                .time(Stage::Relocate, || ssbfile::relocations(&data, &file))
+                .with_context(|| format!("reading the relocations of file <{}>", opt.id))?,
+            None => Vec::new(),
+        };
+        (data.into_owned(), relocs)
+    };
+    let png =
+        fs::read(&opt.png).with_context(|| format!("issue opening <{}>", opt.png.display()))?;
+    let image =
+        image::read_png(&png).with_context(|| format!("reading PNG <{}>", opt.png.display()))?;
+
+    if let Some(at) = opt.sprite {
+        let sprites = image::sprites(&data, &relocs);
+        let sprite = sprites
+            .iter()
+            .find(|s| s.offset == at as usize)
+            .with_context(|| format!("there is no sprite at {:#X} of file <{}>", at, opt.id))?;
+        image::encode_sprite(&mut data, sprite, &image, opt.keep_palette).with_context(|| {
+            format!(
+                "encoding <{}> into the sprite at {:#X}",
+                opt.png.display(),
+                at
+            )
+        })?;
+    } else {
+        // --sprite or --offset and --format are required
+        let offset = opt.offset.expect("offset is required without sprite") as usize;
+        let format = opt.format.expect("format is required with offset");
+        let palette_at = opt.palette.map(|at| at as usize);
+        ensure!(
+            !format.has_palette() || palette_at.is_some(),
+            "a {} texture needs the --palette offset",
+            format
+        );
+        let current = match palette_at {
+            Some(at) if opt.keep_palette => Some(
+                data.get(at..at + format.palette_len() * 2)
+                    .with_context(|| format!("the palette at {:#X} is outside of the file", at))?,
+            ),
+            _ => None,
+        };
+        let encoded = image::encode(&image, format, current)?;
+        write_at(&mut data, offset, &encoded.texels)?;
+        if let (Some(at), Some(palette), false) = (palette_at, &encoded.palette, opt.keep_palette) {
+            write_at(&mut data, at, palette)?;
+        }
+    }
+
+    inject::replace(&mut rom, &version, opt.id, &data, &opt.compress, timings)
+        .with_context(|| format!("injecting file <{}> with <{}>", opt.id, opt.png.display()))?;
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
+
+pub(crate) fn palettes(opt: PaletteOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    let (data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+
+    let found = image::palettes(&data, &relocs);
+    let palettes = match opt.offset {
+        Some(at) => {
+            let at = at as usize;
+            let colors = palette_size(&found, at, opt.colors)?;
+            vec![Palette {
+                offset: at,
+                colors,
+                users: Vec::new(),
+            }]
+        }
+        None => found,
+    };
+    if palettes.is_empty() {
+        bail!(
+            "found no palettes in file <{}>; give the --offset and --colors of one",
+            opt.id
+        );
+    }
+
+    fs::create_dir_all(&opt.output)
+        .with_context(|| format!("creating directory <{}>", opt.output.display()))?;
+    for palette in &palettes {
+        let colors = image::palette_colors(palette_bytes(&data, palette.offset, palette.colors)?);
+        let users = palette
+            .users
+            .iter()
+            .map(|u| format!("{:#06X}", u))
+            .collect::<Vec<_>>();
+        if users.is_empty() {
+            println!("* {:#06X}: {} colors", palette.offset, palette.colors);
+        } else {
+            println!(
+                "* {:#06X}: {} colors, loaded by {}",
+                palette.offset,
+                palette.colors,
+                users.join(", ")
+            );
+        }
+
+        let name = format!("file-{:04}-pal-{:04X}", opt.id, palette.offset);
+        let path = opt.output.join(format!("{}.pal", name));
+        timings
+            .time(Stage::Write, || fs::write(&path, image::jasc_pal(&colors)))
+            .with_context(|| format!("writing <{}>", path.display()))?;
+        let swatch = image::swatch(&colors);
+        write_png(&opt.output.join(format!("{}.png", name)), &swatch, timings)?;
+    }
+    eprintln!("wrote {} palettes of file {}", palettes.len(), opt.id);
+
+    Ok(())
+}
+
+pub(crate) fn import_palette(opt: ImportPaletteOpt, timings: &mut Timings) -> Result<()> {
+    let (mut rom, version) = opt.rom.read(timings)?;
+    let (mut data, relocs) = inject::read_relocated(&rom, &version, opt.id, timings)?;
+    let at = opt.offset as usize;
+    let found = image::palettes(&data, &relocs);
+    let input = fs::read(&opt.palette)
+        .with_context(|| format!("issue opening <{}>", opt.palette.display()))?;
+
+    let is_pal = opt
+        .palette
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pal"));
+    let colors = if is_pal {
+        let rgb = std::str::from_utf8(&input)
+            .map_err(anyhow::Error::from)
+            .and_then(image::read_jasc_pal)
+            .with_context(|| format!("reading palette <{}>", opt.palette.display()))?;
+        let expected = palette_size(&found, at, opt.colors).unwrap_or(rgb.len());
+        ensure!(
+            rgb.len() == expected,
+            ssbfile::Error::PaletteSize {
+                colors: rgb.len(),
+                expected
+            }
+        );
+        // .pal files have no alpha, so keep that of the current colors
+        let current = image::palette_colors(palette_bytes(&data, at, expected)?);
+        rgb.iter()
+            .zip(current)
+            .map(|(&[r, g, b], [.., a])| [r, g, b, a])
+            .collect::<Vec<_>>()
+    } else {
+        let swatch = image::read_png(&input)
+            .with_context(|| format!("reading PNG <{}>", opt.palette.display()))?;
+        let expected = palette_size(&found, at, opt.colors)?;
+        image::read_swatch(&swatch, expected)
+            .with_context(|| format!("reading the swatch <{}>", opt.palette.display()))?
+    };
+    write_at(&mut data, at, &image::encode_palette(&colors))?;
+
+    inject::replace(&mut rom, &version, opt.id, &data, &opt.compress, timings).with_context(
+        || {
+            format!(
+                "injecting file <{}> with <{}>",
+                opt.id,
+                opt.palette.display()
+            )
+        },
+    )?;
+
+    opt.out.write(&opt.output, &mut rom, timings)
+}
+
+/// The number of colors of the palette at `at`: `colors` if it is given, or else the
+/// size that the file loads it with
+fn palette_size(found: &[Palette], at: usize, colors: Option<usize>) -> Result<usize> {
+    colors
+        .or_else(|| found.iter().find(|p| p.offset == at).map(|p| p.colors))
+        .with_context(|| format!("the file loads no palette at {:#X}; give its --colors", at))
+}
+
+/// The `colors` RGBA16 colors at `at` of the decompressed file `data`
+fn palette_bytes(data: &[u8], at: usize, colors: usize) -> Result<&[u8]> {
+    data.get(at..at + colors * 2).with_context(|| {
+        format!(
+            "the {} colors at {:#X} don't fit in the file ({:#X} bytes)",
+            colors,
+            at,
+            data.len()
+        )
+    })
+}
+
+pub(crate) fn font(opt: FontOpt, timings: &mut Timings) -> Result<()> {
+    let (rom, version) = opt.rom.source(timings)?;
+    if opt.list {
+        return list_fonts(&rom, &version, timings);
+    }
+    let id = opt.id.expect("id is required without list");
+    let output = opt
+        .output
+        .as_deref()
+        .expect("output is required without list");
+    let file = timings
+        .time(Stage::TableParse, || TableFile::get(id, &rom, &version))
+        .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+    let data = timings
+        .time(Stage::Decompress, || file.decompressed())
+        .with_context(|| format!("decompressing file <{}>", id))?;
+
+    // each glyph, with the offset of its texels or sprite
+    let mut glyphs = Vec::new();
+    if let Some(offset) = opt.offset {
+        // --offset requires the format, size, and count
+        let format = opt.format.expect("format is required with offset");
+        let (width, height) = (opt.width.unwrap_or(0), opt.height.unwrap_or(0));
+        let palette = opt.palette.map(|at| {
+            let palette = data.get(at as usize..).unwrap_or_default();
+            &palette[..(format.palette_len() * 2).min(palette.len())]
+        });
+        for i in 0..opt.count.unwrap_or(0) {
+            let at = offset as usize + i * format.size(width, height);
+            let texels = data.get(at..).unwrap_or_default();
+            let glyph = image::decode(texels, format, width, height, palette)
+                .with_context(|| format!("decoding glyph {} at {:#X}", i, at))?;
+            glyphs.push((at, glyph));
+        }
+    } else {
+        let relocs = timings
+            .time(Stage::Relocate, || ssbfile::relocations(&data, &file))
+            .with_context(|| format!("reading the relocations of file <{}>", id))?;
+        for sprite in image::sprites(&data, &relocs) {
+            let glyph = image::decode_sprite(&data, &sprite)
+                .with_context(|| format!("decoding the sprite at {:#X}", sprite.offset))?;
+            glyphs.push((sprite.offset, glyph));
+        }
+    }
+    if glyphs.is_empty() {
+        bail!(
+            "found no glyphs in file <{}>; give the --offset, --format, --width, --height, and --count of them",
+            id
+        );
+    }
+    let chars = opt
+        .chars
+        .as_deref()
+        .unwrap_or_default()
+        .chars()
+        .collect::<Vec<_>>();
+    if !chars.is_empty() && chars.len() != glyphs.len() {
+        eprintln!(
+            "warning: {} characters for {} glyphs",
+            chars.len(),
+            glyphs.len()
+        );
+    }
+
+    fs::create_dir_all(output)
+        .with_context(|| format!("creating directory <{}>", output.display()))?;
+    let images = glyphs.into_iter().map(|(_, g)| g).collect::<Vec<_>>();
+    let (atlas, positions) = image::pack(&images);
+    let name = format!("file-{:04}-font", id);
+    write_png(&output.join(format!("{}.png", name)), &atlas, timings)?;
+
+    let json = FontJson {
+        image: format!("{}.png", name),
+        width: atlas.width,
+        height: atlas.height,
+        glyphs: images
+            .iter()
+            .zip(&positions)
+            .enumerate()
+            .map(|(index, (glyph, &(x, y)))| GlyphJson {
+                index,
+                char: chars.get(index).copied(),
+                x,
+                y,
+                width: glyph.width,
+                height: glyph.height,
+            })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&json)? + "\n";
+    let path = output.join(format!("{}.json", name));
+    timings
+        .time(Stage::Write, || fs::write(&path, json))
+        .with_context(|| format!("writing <{}>", path.display()))?;
+    eprintln!("wrote {} glyphs of file {}", images.len(), id);
+
+    Ok(())
+}
+
+/// Print the files of `rom` whose sprites are the glyphs of a font (see
+/// [`image::is_font`]), with the number, size, and format of their glyphs
+fn list_fonts(rom: &[u8], version: &SSBInfo, timings: &mut Timings) -> Result<()> {
+    let mut found = 0;
+    for id in 0..version.total_entries() {
+        let file = timings
+            .time(Stage::TableParse, || TableFile::get(id, rom, version))
+            .with_context(|| format!("issue getting table entry for file <{}>", id))?;
+        // a file that can't be decompressed or relocated has no sprites to find
+        let Ok(data) = timings.time(Stage::Decompress, || file.decompressed()) else {
+            continue;
+        };
+        let Ok(relocs) = timings.time(Stage::Relocate, || ssbfile::relocations(&data, &file))
+        else {
+            continue;
+        };
+        let sprites = image::sprites(&data, &relocs);
+        if !image::is_font(&sprites) {
+            continue;
+        }
+        let widest = sprites.iter().map(|s| s.width).max().unwrap_or(0);
+        println!(
+            "file {:4}: {} glyphs of {} up to {}x{}",
+            id,
+            sprites.len(),
+            sprites[0].format,
+            widest,
+            sprites[0].height
+        );
+        found += 1;
+    }
+    if found == 0 {
+        println!("found no fonts");
+    }
+
+    Ok(())
+}
+
+/// Pack the `images` of `sprites` into `file-NNNN-sheet.png`, with `file-NNNN-sheet.json`
+/// listing the offset, format, and place in the sheet of each sprite
+fn write_sheet(
+    opt: &TextureOpt,
+    sprites: &[Sprite],
+    images: &[Image],
+    timings: &mut Timings,
+) -> Result<()> {
+    let (sheet, positions) = image::pack(images);
+    let name = format!("file-{:04}-sheet", opt.id);
+    write_png(&opt.output.join(format!("{}.png", name)), &sheet, timings)?;
+
+    let json = SheetJson {
+        image: format!("{}.png", name),
+        width: sheet.width,
+        height: sheet.height,
+        sprites: sprites
+            .iter()
+            .zip(images)
+            .zip(&positions)
+            .map(|((sprite, image), &(x, y))| SpriteJson {
+                offset: sprite.offset,
+                format: sprite.format.to_string(),
+                x,
+                y,
+                width: image.width,
+                height: image.height,
+            })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&json)? + "\n";
+
+    let path = opt.output.join(format!("{}.json", name));
+    timings
+        .time(Stage::Write, || fs::write(&path, json))
+        .with_context(|| format!("writing <{}>", path.display()))
+}
+
+#[derive(Serialize)]
+struct FontJson {
+    image: String,
+    width: usize,
+    height: usize,
+    glyphs: Vec<GlyphJson>,
+}
+
+#[derive(Serialize)]
+struct GlyphJson {
+    index: usize,
+    char: Option<char>,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+#[derive(Serialize)]
+struct SheetJson {
+    image: String,
+    width: usize,
+    height: usize,
+    sprites: Vec<SpriteJson>,
+}
+
+#[derive(Serialize)]
+struct SpriteJson {
+    offset: usize,
+    format: String,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Copy `bytes` into the decompressed file `data` at `at`
+fn write_at(data: &mut [u8], at: usize, bytes: &[u8]) -> Result<()> {
+    let len = data.len();
+    data.get_mut(at..at + bytes.len())
+        .with_context(|| {
+            format!(
+                "{:#X} bytes at {:#X} don't fit in the file ({:#X} bytes)",
+                bytes.len(),
+                at,
+                len
+            )
+        })?
+        .copy_from_slice(bytes);
+
+    Ok(())
+}
+
+fn write_png(path: &Path, image: &Image, timings: &mut Timings) -> Result<()> {
+    let png = image::png(image)?;
+    timings
+        .time(Stage::Write, || fs::write(path, png))
+        .with_context(|| format!("writing <{}>", path.display()))
+}
@@ -0,0 +1,85 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stage {
+    RomRead,
+    TableParse,
+    Decompress,
+    Relocate,
+    Compress,
+    Layout,
+    Write,
+}
+
+impl Stage {
+    const ALL: [Self; 7] = [
+        Self::RomRead,
+        Self::TableParse,
+        Self::Decompress,
+        Self::Relocate,
+        Self::Compress,
+        Self::Layout,
+        Self::Write,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::RomRead => "rom read",
+            Self::TableParse => "table parse",
+            Self::Decompress => "decompression",
+            Self::Relocate => "relocation",
+            Self::Compress => "compression",
+            Self::Layout => "layout",
+            Self::Write => "write",
+        }
+    }
+}
+
+/// Total wall time spent in each stage of a command
+#[derive(Debug, Default)]
+pub(crate) struct Timings {
+    totals: [Duration; Stage::ALL.len()],
+}
+
+impl Timings {
+    pub(crate) fn time<T>(&mut self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.totals[stage as usize] += start.elapsed();
+
+        out
+    }
+
+    /// Merge the times of `other`, which ran alongside these (such as those of a worker
+    /// thread): the wall times overlap, so each stage takes the longer of the two
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for (total, t) in self.totals.iter_mut().zip(other.totals) {
+            *total = (*total).max(t);
+        }
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Timings")?;
+        for stage in Stage::ALL {
+            let t = self.totals[stage as usize];
+            writeln!(
+                f,
+                "* {:<14} {:>10.3} ms",
+                stage.name(),
+                t.as_secs_f64() * 1000.0
+            )?;
+        }
+        let total: Duration = self.totals.iter().sum();
+        writeln!(
+            f,
+            "* {:<14} {:>10.3} ms",
+            "total",
+            total.as_secs_f64() * 1000.0
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use crate::cli::UndoOpt;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The `n`th backup of `path`: `ssb.z64.bak`, then `ssb.z64.bak.1`, and so on
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    if n > 0 {
+        name.push(format!(".{}", n));
+    }
+    path.with_file_name(name)
+}
+
+/// The newest backup of `path`, if it has any
+fn newest_backup(path: &Path) -> Option<PathBuf> {
+    (0..)
+        .map(|n| backup_path(path, n))
+        .take_while(|backup| backup.is_file())
+        .last()
+}
+
+/// Move the file at `path` to its next backup path, keeping the older backups, and return
+/// the backup path. The file is written again right after, so it is moved, not copied.
+pub(crate) fn backup(path: &Path) -> Result<PathBuf> {
+    let backup = (0..)
+        .map(|n| backup_path(path, n))
+        .find(|backup| !backup.exists())
+        .expect("a free backup path");
+    fs::rename(path, &backup)
+        .with_context(|| format!("backing up <{}> to <{}>", path.display(), backup.display()))?;
+
+    Ok(backup)
+}
+
+pub(crate) fn restore(opt: UndoOpt) -> Result<()> {
+    let backup = newest_backup(&opt.rom).with_context(|| {
+        format!(
+            "<{}> has no backup at <{}>",
+            opt.rom.display(),
+            backup_path(&opt.rom, 0).display()
+        )
+    })?;
+    fs::rename(&backup, &opt.rom).with_context(|| {
+        format!(
+            "restoring <{}> from <{}>",
+            opt.rom.display(),
+            backup.display()
+        )
+    })?;
+    eprintln!(
+        "restored <{}> from <{}>",
+        opt.rom.display(),
+        backup.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::{
+    file_ends, read_table,
+    rom_info::{recalc_crc, Cic, N64Header},
+    versions::SSBInfo,
+};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// What was found checking a big-endian rom dump; see [`validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Validation {
+    /// the crcs stored in the header
+    pub header_crc: (u32, u32),
+    /// the crcs calculated from the rom's data
+    pub computed_crc: (u32, u32),
+    /// the lockout chip of the bootcode, if it is a known bootcode
+    pub cic: Option<Cic>,
+    /// the version that the rom was checked as
+    pub version: Option<SSBInfo>,
+    pub problems: Vec<Problem>,
+}
+
+impl Validation {
+    /// The rom has no problems
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Something wrong with a rom dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Problem {
+    /// the rom is too small to have a header
+    NoHeader { len: usize },
+    /// the crcs in the header don't match the rom's data
+    CrcMismatch,
+    /// the version of the rom isn't known
+    UnknownVersion,
+    /// the resource table isn't within the rom
+    TableOutsideRom,
+    /// a file's data isn't within the rom
+    FileOutsideRom { id: usize, start: usize, end: usize },
+    /// the file after file `id` (or the end of the data) starts before file `id`
+    FilesOutOfOrder { id: usize },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoHeader { len } => {
+                write!(f, "rom is too small to have a header ({:#X} bytes)", len)
+            }
+            Self::CrcMismatch => write!(f, "the header crcs don't match the rom's data"),
+            Self::UnknownVersion => write!(f, "the version of the rom isn't known"),
+            Self::TableOutsideRom => write!(f, "the resource table is outside of the rom"),
+            Self::FileOutsideRom { id, start, end } => write!(
+                f,
+                "file <{}> at {:#X}..{:#X} is outside of the rom",
+                id, start, end
+            ),
+            Self::FilesOutOfOrder { id } => write!(
+                f,
+                "the file after file <{}> (or the end of the data) starts before it",
+                id
+            ),
+        }
+    }
+}
+
+/// Check a big-endian rom dump: that the header's crcs match its data, that its
+/// `version` (usually found with [`VersionDb::find`](crate::versions::VersionDb::find))
+/// is known, and that the resource table and every file are within the rom
+pub fn validate(rom: &[u8], version: Option<&SSBInfo>) -> Validation {
+    let mut problems = Vec::new();
+
+    let header = N64Header::parse(rom).ok();
+    let header_crc = header.as_ref().map_or((0, 0), N64Header::crc);
+    let computed_crc = recalc_crc(rom);
+    match header {
+        None => problems.push(Problem::NoHeader { len: rom.len() }),
+        Some(_) if header_crc != computed_crc => problems.push(Problem::CrcMismatch),
+        Some(_) => (),
+    }
+
+    match version {
+        Some(info) => check_table(rom, info, &mut problems),
+        None => problems.push(Problem::UnknownVersion),
+    }
+
+    Validation {
+        header_crc,
+        computed_crc,
+        cic: header.as_ref().and_then(N64Header::cic),
+        version: version.cloned(),
+        problems,
+    }
+}
+
+fn check_table(rom: &[u8], info: &SSBInfo, problems: &mut Vec<Problem>) {
+    let Ok((entries, terminal)) = read_table(rom, info) else {
+        problems.push(Problem::TableOutsideRom);
+        return;
+    };
+
+    let ends = file_ends(&entries, &terminal);
+    for (id, (entry, end)) in entries.iter().zip(ends).enumerate() {
+        if end < entry.offset {
+            problems.push(Problem::FilesOutOfOrder { id });
+        }
+        let start = info.table_end + entry.offset as usize;
+        let end = start + entry.size as usize * 4;
+        if end > rom.len() {
+            problems.push(Problem::FileOutsideRom { id, start, end });
+        }
+    }
+}
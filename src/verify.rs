@@ -0,0 +1,37 @@
+use crate::{cli::VerifyOpt, timings::Timings};
+use anyhow::{bail, Result};
+
+pub(crate) fn rom(opt: VerifyOpt, timings: &mut Timings) -> Result<()> {
+    let path = &opt.rom.path;
+    // an unknown version is reported, instead of stopping here
+    let (rom, version) = opt.rom.open(timings)?;
+
+    let report = ssbfile::validate(&rom, version.as_ref());
+    let crc = |(crc1, crc2): (u32, u32)| format!("{:08X} {:08X}", crc1, crc2);
+    println!("# {}", path.display());
+    println!("* header crc:   {}", crc(report.header_crc));
+    println!("* computed crc: {}", crc(report.computed_crc));
+    println!(
+        "* cic:          {}",
+        report.cic.map_or("unknown bootcode", |cic| cic.name())
+    );
+    println!(
+        "* version:      {}",
+        report.version.as_ref().map_or("unknown", |v| &v.version)
+    );
+    if report.is_ok() {
+        println!("* no problems found");
+        return Ok(());
+    }
+
+    println!();
+    println!("# Problems");
+    for problem in &report.problems {
+        println!("* {}", problem);
+    }
+    bail!(
+        "<{}> has {} problems",
+        path.display(),
+        report.problems.len()
+    )
+}
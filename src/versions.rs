@@ -1,39 +1,317 @@
-#[derive(Debug, Copy, Clone)]
-pub(crate) struct SSBInfo {
-    pub(crate) version: &'static str,
-    pub(crate) crc: (u32, u32),
-    pub(crate) table_start: usize,
-    pub(crate) table_end: usize,
+use crate::{fighter::FighterLocation, Error};
+use alloc::{borrow::Cow, vec::Vec};
+use anyhow::{ensure, Result};
+#[cfg(feature = "std")]
+use {
+    alloc::{format, string::String},
+    anyhow::Context,
+    std::{fs, path::Path},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SSBInfo {
+    #[cfg_attr(feature = "serde", serde(rename = "name"))]
+    pub version: Cow<'static, str>,
+    pub crc: (u32, u32),
+    pub table_start: usize,
+    pub table_end: usize,
 }
 
 impl SSBInfo {
+    /// Info for a rom whose version isn't known, with its resource table at
+    /// `table_start..table_end` (including the dummy entry at the end)
+    pub fn custom(rom: &[u8], table_start: usize, table_end: usize) -> Result<Self> {
+        ensure!(
+            table_start < table_end && table_end <= rom.len(),
+            Error::OutsideRom {
+                start: table_start,
+                end: table_end,
+                len: rom.len()
+            }
+        );
+        ensure!(
+            (table_end - table_start).is_multiple_of(12) && table_end - table_start >= 24,
+            Error::BadTableBounds {
+                start: table_start,
+                end: table_end
+            }
+        );
+
+        Ok(Self {
+            version: Cow::Borrowed("custom"),
+            crc: read_crc(rom).unwrap_or_default(),
+            table_start,
+            table_end,
+        })
+    }
+
+    /// Find the resource table of a rom that isn't a known version, like another region's
+    /// release or a hack, by its layout: entries with rising offsets into the data after
+    /// the table, where each compressed file starts with the `vpk0` magic. The version is
+    /// named by the game code in the rom header (e.g., `NALJ`).
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        let (table_start, table_end) = (0..rom.len().saturating_sub(ENTRY_SIZE))
+            .step_by(4)
+            .find_map(|start| table_at(rom, start))?;
+        let version = rom
+            .get(GAME_CODE)
+            .and_then(|code| core::str::from_utf8(code).ok())
+            .filter(|code| code.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("unknown");
+
+        Some(Self {
+            version: Cow::Owned(version.into()),
+            crc: read_crc(rom).unwrap_or_default(),
+            table_start,
+            table_end,
+        })
+    }
+
     /// each entry is 12 (0xC) bytes long,
     /// and there is a dummy entry at the end of the table
     pub const fn total_entries(&self) -> usize {
-        ((self.table_end - self.table_start) / 12) - 1
+        (self.table_end.saturating_sub(self.table_start) / 12).saturating_sub(1)
+    }
+}
+
+const ENTRY_SIZE: usize = 12;
+const COMPRESS_BIT: u32 = 0x8000_0000;
+/// where the game code is in the rom header
+const GAME_CODE: core::ops::Range<usize> = 0x3B..0x3F;
+/// the fewest entries that a table found by [`SSBInfo::detect`] has
+const MIN_DETECTED_ENTRIES: usize = 16;
+/// the most compressed files whose magic is checked for each possible end of a table
+const CHECKED_MAGICS: usize = 8;
+
+/// The bounds of the resource table that starts at `start` of `rom`, if there is one
+fn table_at(rom: &[u8], start: usize) -> Option<(usize, usize)> {
+    table_end(rom, start, MIN_DETECTED_ENTRIES, false).map(|end| (start, end))
+}
+
+/// The end of the secondary table that starts at `start` of `rom`, if there is one.
+/// It has the layout of the resource table, but it may not hold any compressed files,
+/// so a table of only uncompressed files is found by their sizes instead.
+#[cfg(feature = "std")]
+pub(crate) fn secondary_table_end(rom: &[u8], start: usize) -> Option<usize> {
+    table_end(rom, start, 2, true)
+}
+
+/// The end of a table of at least `min_entries` (with the dummy entry) at `start`: the
+/// data of its compressed files has to line up with the end, and if there are none and
+/// `uncompressed` is allowed, every file has to have the same stored and decompressed size
+fn table_end(rom: &[u8], start: usize, min_entries: usize, uncompressed: bool) -> Option<usize> {
+    let word = |at: usize| {
+        rom.get(at..at + 4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+    };
+    // the first file is at the start of the data, and isn't empty
+    if word(start)? & !COMPRESS_BIT != 0 || word(start + 4)? & 0xFFFF == 0 {
+        return None;
     }
+
+    // (offset, compressed, stored size == decompressed size) of the entries that follow
+    // each other; any of them could be the dummy entry, whose offset is the end of the data
+    let mut entries = Vec::new();
+    // the entries that share the data of an earlier file, which can't be the dummy entry
+    let mut shared = Vec::new();
+    let mut data_end = 0;
+    for at in (start..rom.len().saturating_sub(ENTRY_SIZE)).step_by(ENTRY_SIZE) {
+        let raw = word(at)?;
+        let offset = (raw & !COMPRESS_BIT) as usize;
+        let size = u16::from_be_bytes([rom[at + 6], rom[at + 7]]);
+        let decompressed_size = u16::from_be_bytes([rom[at + 10], rom[at + 11]]);
+        if offset < data_end {
+            if size == 0 || !entries.iter().any(|&(o, _, _)| o == offset) {
+                break;
+            }
+            shared.push(entries.len());
+            entries.push((offset, raw & COMPRESS_BIT != 0, size == decompressed_size));
+            continue;
+        }
+        entries.push((offset, raw & COMPRESS_BIT != 0, size == decompressed_size));
+        if size == 0 {
+            break;
+        }
+        data_end = offset + size as usize * 4;
+    }
+
+    // the data of the compressed files only lines up with the right end of the table
+    (min_entries.max(2)..=entries.len())
+        .rev()
+        .find_map(|count| {
+            if shared.contains(&(count - 1)) {
+                return None;
+            }
+            let table_end = start + count * ENTRY_SIZE;
+            let (files, dummy) = entries[..count].split_at(count - 1);
+            if table_end + dummy[0].0 > rom.len() {
+                return None;
+            }
+            let mut compressed = files.iter().filter(|(_, c, _)| *c).take(CHECKED_MAGICS);
+            let Some(first) = compressed.next() else {
+                return (uncompressed && files.iter().all(|(_, _, same)| *same))
+                    .then_some(table_end);
+            };
+            core::iter::once(first)
+                .chain(compressed)
+                .all(|&(offset, _, _)| {
+                    rom.get(table_end + offset..table_end + offset + 4) == Some(b"vpk0")
+                })
+                .then_some(table_end)
+        })
 }
 
+/// The versions whose crcs and table bounds were checked against a dump. The tables of
+/// the other retail releases (`NALJ`, `NALP`, and `NALU`) are found with [`SSBInfo::detect`],
+/// or can be added with a [`VersionDb`] file.
 const SSB_ROMS_INFO: &[SSBInfo] = &[SSBInfo {
-    version: "NALE", // US NTSC
+    version: Cow::Borrowed("NALE"), // US NTSC
     crc: (0x916B8B5B, 0x780B85A4),
     table_start: 0x1AC870,
     table_end: 0x1B2C6C,
 }];
 
-pub(crate) fn find_version(rom: &[u8]) -> Option<&'static SSBInfo> {
-    let crc1_bytes: [u8; 4] = rom[0x10..0x14].try_into().expect("valid rom");
-    let crc2_bytes: [u8; 4] = rom[0x14..0x18].try_into().expect("valid rom");
-    let crc = (
-        u32::from_be_bytes(crc1_bytes),
-        u32::from_be_bytes(crc2_bytes),
-    );
+/// The known versions of the game: the built-in versions, plus any
+/// versions (e.g., prototypes or hacks) loaded from a toml file like:
+/// ```toml
+/// [[version]]
+/// name = "NALE"
+/// crc = [0x916B8B5B, 0x780B85A4]
+/// table_start = 0x1AC870
+/// table_end = 0x1B2C6C
+/// ```
+/// The file can also say where the attributes of the fighters are in a version, with
+/// `[[fighter]]` tables of the `version`, the `fighter`, the id of its main `file`, and
+/// the `offset` of the attributes in the decompressed file (see [`FighterLocation`]).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionDb {
+    versions: Vec<SSBInfo>,
+    fighters: Vec<FighterLocation>,
+}
 
-    for info in SSB_ROMS_INFO {
-        if info.crc == crc {
-            return Some(info);
+#[cfg(feature = "std")]
+#[derive(serde::Deserialize)]
+struct VersionFile {
+    #[serde(rename = "version", default)]
+    versions: Vec<VersionDef>,
+    #[serde(rename = "fighter", default)]
+    fighters: Vec<FighterDef>,
+}
+
+/// A `[[version]]` of a version file
+#[cfg(feature = "std")]
+#[derive(serde::Deserialize)]
+struct VersionDef {
+    name: String,
+    crc: (u32, u32),
+    table_start: usize,
+    table_end: usize,
+}
+
+/// A `[[fighter]]` of a version file
+#[cfg(feature = "std")]
+#[derive(serde::Deserialize)]
+struct FighterDef {
+    version: String,
+    fighter: String,
+    file: usize,
+    offset: usize,
+}
+
+impl VersionDb {
+    /// The versions that are built into the library
+    pub fn builtin() -> Self {
+        Self {
+            versions: SSB_ROMS_INFO.to_vec(),
+            fighters: Vec::new(),
+        }
+    }
+
+    /// Add the versions and fighter locations in the toml `text`. A loaded version
+    /// replaces a known version with the same crc, and a loaded location a known location
+    /// of the same fighter in the same version.
+    #[cfg(feature = "std")]
+    pub fn load_toml(&mut self, text: &str) -> Result<()> {
+        let file: VersionFile = toml::from_str(text).context("parsing version definitions")?;
+        for def in file.versions {
+            let info = SSBInfo {
+                version: Cow::Owned(def.name),
+                crc: def.crc,
+                table_start: def.table_start,
+                table_end: def.table_end,
+            };
+            if info.table_start >= info.table_end
+                || !(info.table_end - info.table_start).is_multiple_of(12)
+            {
+                return Err(Error::BadTableBounds {
+                    start: info.table_start,
+                    end: info.table_end,
+                })
+                .with_context(|| format!("loading version {}", info.version));
+            }
+            self.versions.retain(|known| known.crc != info.crc);
+            self.versions.push(info);
+        }
+        for def in file.fighters {
+            let location = FighterLocation {
+                version: Cow::Owned(def.version),
+                fighter: Cow::Owned(def.fighter),
+                file: def.file,
+                offset: def.offset,
+            };
+            self.fighters.retain(|known| {
+                (&known.version, &known.fighter) != (&location.version, &location.fighter)
+            });
+            self.fighters.push(location);
         }
+
+        Ok(())
+    }
+
+    /// Add the versions in the toml file at `path`
+    #[cfg(feature = "std")]
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("issue opening version file <{}>", path.display()))?;
+
+        self.load_toml(&text)
+            .with_context(|| format!("loading version file <{}>", path.display()))
+    }
+
+    pub fn versions(&self) -> &[SSBInfo] {
+        &self.versions
     }
 
-    None
+    /// Where the attributes of `fighter` are in `version`, if it is known
+    pub fn fighter(&self, version: &str, fighter: &str) -> Option<&FighterLocation> {
+        self.fighters
+            .iter()
+            .find(|l| l.version == version && l.fighter.eq_ignore_ascii_case(fighter))
+    }
+
+    /// Find the version of `rom` by the crc in its header
+    pub fn find(&self, rom: &[u8]) -> Option<&SSBInfo> {
+        let crc = read_crc(rom)?;
+
+        self.versions.iter().find(|info| info.crc == crc)
+    }
+}
+
+impl Default for VersionDb {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// The two crcs in the rom header, if the rom is large enough to have a header
+pub(crate) fn read_crc(rom: &[u8]) -> Option<(u32, u32)> {
+    let crc1_bytes: [u8; 4] = rom.get(0x10..0x14)?.try_into().ok()?;
+    let crc2_bytes: [u8; 4] = rom.get(0x14..0x18)?.try_into().ok()?;
+
+    Some((
+        u32::from_be_bytes(crc1_bytes),
+        u32::from_be_bytes(crc2_bytes),
+    ))
 }
@@ -0,0 +1,107 @@
+use crate::{
+    build::{self, EncodedFiles},
+    cli::WatchOpt,
+    manifest::Manifest,
+    timings::{Stage, Timings},
+};
+use anyhow::{Context, Result};
+use ssbfile::versions::SSBInfo;
+use std::{
+    fs,
+    path::Path,
+    process::{Child, Command},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+pub(crate) fn project(opt: WatchOpt, timings: &mut Timings) -> Result<()> {
+    let (base, version) = opt.rom.read(timings)?;
+    let root = opt.manifest.parent().unwrap_or_else(|| Path::new("."));
+    // files are only compressed again when their data changes
+    let mut encoded = EncodedFiles::new();
+    let mut emulator = None;
+    let mut last = None;
+
+    eprintln!("watching <{}>", opt.manifest.display());
+    loop {
+        let snapshot = snapshot(&opt.manifest, root);
+        if last.as_ref() != Some(&snapshot) {
+            last = Some(snapshot);
+            match rebuild(&opt, &base, &version, root, &mut encoded, timings) {
+                Ok(()) => {
+                    eprintln!("rebuilt <{}>", opt.output.display());
+                    if let Err(e) = restart(&opt, &mut emulator) {
+                        eprintln!("{:#}", e);
+                    }
+                }
+                Err(e) => eprintln!("build failed: {:#}", e),
+            }
+        }
+        thread::sleep(Duration::from_millis(opt.interval));
+    }
+}
+
+/// Build the manifest into a copy of the `base` rom, and write it out
+fn rebuild(
+    opt: &WatchOpt,
+    base: &[u8],
+    version: &SSBInfo,
+    root: &Path,
+    encoded: &mut EncodedFiles,
+    timings: &mut Timings,
+) -> Result<()> {
+    let manifest = Manifest::read(&opt.manifest)?;
+    build::check_manifest(&manifest, version)?;
+    opt.grow.check(&manifest, version)?;
+
+    let mut rom = base.to_vec();
+    build::apply_manifest(
+        &mut rom,
+        version,
+        &manifest,
+        |path| build::read_file(&root.join(path)),
+        &opt.compress,
+        encoded,
+        timings,
+    )?;
+    ssbfile::rom_info::fix_crc(&mut rom)?;
+
+    timings
+        .time(Stage::Write, || fs::write(&opt.output, &rom))
+        .with_context(|| format!("writing rom to <{}>", opt.output.display()))
+}
+
+/// The modification times of the manifest and its files, to notice when one changes
+fn snapshot(manifest: &Path, root: &Path) -> Vec<Option<SystemTime>> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let files = Manifest::read(manifest)
+        .map(|m| m.files)
+        .unwrap_or_default();
+
+    std::iter::once(modified(manifest))
+        .chain(files.iter().map(|file| modified(&root.join(&file.path))))
+        .collect()
+}
+
+/// Stop the running emulator, and start it again with the rebuilt rom
+fn restart(opt: &WatchOpt, emulator: &mut Option<Child>) -> Result<()> {
+    let Some(cmd) = &opt.emulator else {
+        return Ok(());
+    };
+    if let Some(mut child) = emulator.take() {
+        // it may have been closed already
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    let mut args = cmd.split_whitespace();
+    let program = args.next().context("the emulator command is empty")?;
+    let child = Command::new(program)
+        .args(args)
+        .arg(&opt.output)
+        .spawn()
+        .with_context(|| format!("starting emulator <{}>", cmd))?;
+    *emulator = Some(child);
+
+    Ok(())
+}
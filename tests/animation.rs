@@ -0,0 +1,77 @@
+mod common;
+
+use common::{reloc, words};
+use ssbfile::{animation, Relocation};
+
+/// An animation of three joints, where the second has no stream: the first sets a rotation
+/// and a translation with their rates, waits, and loops; the third sets a scale and ends
+fn animation() -> (Vec<u8>, Vec<Relocation>) {
+    let file = words(&[
+        0x0C,
+        0,
+        0x2C,
+        5 << 25 | 0b1_0001 << 15 | 10,
+        1.5f32.to_bits(),
+        0.25f32.to_bits(),
+        2.0f32.to_bits(),
+        0,
+        2 << 25 | 4,
+        1 << 25,
+        0x0C,
+        3 << 25 | 1 << 23,
+        1.0f32.to_bits(),
+        0,
+    ]);
+    (
+        file,
+        vec![reloc(0x00, 0x0C), reloc(0x08, 0x2C), reloc(0x28, 0x0C)],
+    )
+}
+
+#[test]
+fn reads_and_round_trips_streams() {
+    let (file, relocs) = animation();
+    let streams = animation::animation(&file, 0, &relocs).unwrap();
+    assert_eq!(streams.len(), 2);
+    assert_eq!((streams[0].joint, streams[1].joint), (0, 2));
+    assert_eq!(streams[0].events.len(), 3);
+    assert_eq!(streams[0].events[2].target, Some(0x0C));
+
+    let text = animation::disassemble(&streams);
+    assert!(text.contains("\n.joint 0 0x000C\n"));
+    assert!(text.contains("0x000C: set_rate_block 10 rotate_x=1.5,0.25 translate_y=2.0,0.0\n"));
+    assert!(text.contains("0x0020: wait 4\n0x0024: jump to=0x000C\n"));
+    assert!(text.contains("0x002C: set_block scale_z=1.0\n0x0034: end\n"));
+    assert_eq!(animation::assemble(&text).unwrap(), streams);
+
+    assert!(animation::animation(&file, 0x0C, &relocs).is_err());
+    assert!(animation::assemble("end").is_err());
+    assert!(animation::assemble(".joint 0 0x0C\nset_rate rotate_x=1.0").is_err());
+    assert!(animation::assemble(".joint 0 0x0C\njump 2").is_err());
+}
+
+#[test]
+fn patches_streams_in_place() {
+    let (mut file, mut relocs) = animation();
+    let edited = animation::assemble(".joint 0 0x0C\nset_block 3 rotate_z=0.5\nend\n").unwrap();
+    animation::patch(&mut file, &edited[0], &mut relocs).unwrap();
+    assert_eq!(
+        &file[0x0C..0x2C],
+        words(&[
+            3 << 25 | 0b100 << 15 | 3,
+            0.5f32.to_bits(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            0
+        ])
+    );
+    // the jump's pointer is gone with it
+    assert_eq!(relocs.len(), 2);
+
+    let long = ".joint 2 0x2C\nset_rate scale_x=1.0,0.0 scale_y=1.0,0.0\nend\n";
+    let long = animation::assemble(long).unwrap();
+    assert!(animation::patch(&mut file, &long[0], &mut relocs).is_err());
+}
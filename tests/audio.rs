@@ -0,0 +1,150 @@
+use ssbfile::audio::{self, BankFile, Event, Loop, SeqBank, Sequence, WaveKind};
+
+/// A sequence with one track: a tempo, then a note with a duration, the same note again with
+/// running status, and a repeat of its four bytes (note, velocity, duration, and delta)
+fn sequence() -> Vec<u8> {
+    let mut seq = 0x44u32.to_be_bytes().to_vec();
+    seq.resize(0x40, 0);
+    seq.extend_from_slice(&0x30u32.to_be_bytes());
+    seq.extend_from_slice(&[
+        0x00, 0xFF, 0x51, 0x07, 0xA1, 0x20, // tempo of 500000 us
+        0x00, 0x90, 0x3C, 0x64, 0x60, // note on, with a duration of 0x60
+        0x60, 0x3E, 0x64, 0x30, // running status
+        0x30, 0xFE, 0x00, 0x04, 0x04, // repeat the four bytes before
+        0xFF, 0x2F,
+    ]);
+    seq
+}
+
+#[test]
+fn finds_banks_and_reads_sequences() {
+    let seq = sequence();
+    let mut rom = vec![0; 0x10];
+    rom.extend_from_slice(&[0x53, 0x31, 0, 1]);
+    rom.extend_from_slice(&0x10u32.to_be_bytes());
+    rom.extend_from_slice(&(seq.len() as u32).to_be_bytes());
+    rom.extend_from_slice(&[0; 4]);
+    rom.extend_from_slice(&seq);
+
+    assert_eq!(audio::find_seq_banks(&rom), [0x10]);
+    let bank = SeqBank::read(&rom, 0x10).unwrap();
+    assert_eq!(bank.sequences, [(0x10, seq.len())]);
+    assert_eq!(bank.sequence(&rom, 0), Some(seq.as_slice()));
+    assert!(SeqBank::read(&rom, 0).is_err());
+
+    let sequence = Sequence::parse(&seq).unwrap();
+    assert_eq!(sequence.division, 0x30);
+    assert_eq!(sequence.tracks.len(), 1);
+    let event = |tick, bytes: &[u8]| Event {
+        tick,
+        bytes: bytes.to_vec(),
+    };
+    assert_eq!(
+        sequence.tracks[0].events,
+        [
+            event(0, &[0xFF, 0x51, 3, 0x07, 0xA1, 0x20]),
+            event(0, &[0x90, 0x3C, 0x64]),
+            event(0x60, &[0x80, 0x3C, 0]),
+            event(0x60, &[0x90, 0x3E, 0x64]),
+            event(0x90, &[0x80, 0x3E, 0]),
+            event(0x90, &[0x90, 0x3E, 0x64]),
+            event(0xC0, &[0x80, 0x3E, 0]),
+        ]
+    );
+
+    let midi = sequence.midi();
+    assert_eq!(&midi[..14], b"MThd\0\0\0\x06\0\x01\0\x01\0\x30");
+    assert_eq!(&midi[14..18], b"MTrk");
+    assert!(midi.ends_with(&[0x30, 0x80, 0x3E, 0, 0, 0xFF, 0x2F, 0]));
+
+    let mut bad = seq.clone();
+    bad[0x54 + 2] = 0x40;
+    assert!(Sequence::parse(&bad).is_err());
+}
+
+fn put(file: &mut Vec<u8>, at: usize, bytes: &[u8]) {
+    if file.len() < at + bytes.len() {
+        file.resize(at + bytes.len(), 0);
+    }
+    file[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+/// A bank file with one bank of one instrument, with a sound of a looped VADPCM sample
+/// whose predictor adds the value just before each sample to it; the samples follow at 0xC0
+fn bank_file() -> Vec<u8> {
+    let mut ctl = vec![0x42, 0x31, 0, 1, 0, 0, 0, 0x10];
+    put(&mut ctl, 0x10, &[0, 1, 0, 0]);
+    put(&mut ctl, 0x14, &22050u32.to_be_bytes());
+    put(&mut ctl, 0x1C, &0x20u32.to_be_bytes());
+    put(&mut ctl, 0x20, &[0x7F, 0x40]);
+    put(&mut ctl, 0x2E, &1u16.to_be_bytes());
+    put(&mut ctl, 0x30, &0x40u32.to_be_bytes());
+    put(&mut ctl, 0x44, &0x50u32.to_be_bytes());
+    put(&mut ctl, 0x48, &0x60u32.to_be_bytes());
+    put(&mut ctl, 0x50, &[0, 127, 0, 127, 64, 0xFB]);
+    put(&mut ctl, 0x64, &18u32.to_be_bytes());
+    put(&mut ctl, 0x6C, &0x80u32.to_be_bytes());
+    put(&mut ctl, 0x70, &0x90u32.to_be_bytes());
+    put(
+        &mut ctl,
+        0x80,
+        &[0, 0, 0, 4, 0, 0, 0, 32, 0xFF, 0xFF, 0xFF, 0xFF],
+    );
+    put(&mut ctl, 0x90, &[0, 0, 0, 2, 0, 0, 0, 1]);
+    // the coefficient of the sample before for the first sample: 1.0, as 2048
+    put(&mut ctl, 0x98 + 8 * 2, &2048i16.to_be_bytes());
+    put(&mut ctl, 0xB6, &[0, 0]);
+    // two frames: residuals of 1 and 2 with a scale of 1, then -1 with a scale of 2
+    put(&mut ctl, 0xC0, &[0x00, 0x12, 0, 0, 0, 0, 0, 0, 0]);
+    put(&mut ctl, 0xC9, &[0x10, 0xF0, 0, 0, 0, 0, 0, 0, 0]);
+    ctl
+}
+
+#[test]
+fn reads_bank_files_and_decodes_samples() {
+    let ctl = bank_file();
+    assert_eq!(audio::find_bank_files(&ctl), [0]);
+    let banks = BankFile::read(&ctl, 0).unwrap();
+    assert_eq!(banks.len, 0xB8);
+    assert_eq!(banks.banks.len(), 1);
+    let bank = &banks.banks[0];
+    assert_eq!(bank.sample_rate, 22050);
+    assert!(bank.percussion.is_none());
+    let instrument = bank.instruments[0].as_ref().unwrap();
+    assert_eq!((instrument.volume, instrument.pan), (0x7F, 0x40));
+    let sound = &instrument.sounds[0];
+    assert_eq!((sound.key_base, sound.detune), (64, -5));
+    let wave = &sound.wave;
+    assert_eq!((wave.base, wave.len), (0, 18));
+    assert_eq!(
+        wave.looped,
+        Some(Loop {
+            start: 4,
+            end: 32,
+            count: -1
+        })
+    );
+    assert!(matches!(&wave.kind, WaveKind::Adpcm(book) if book.order == 2));
+    assert_eq!(banks.waves().len(), 1);
+
+    let pcm = wave.decode(&ctl[0xC0..]).unwrap();
+    let mut expected = vec![0; 32];
+    expected[..3].copy_from_slice(&[1, 3, 2]);
+    expected[16..18].copy_from_slice(&[-2, -2]);
+    assert_eq!(pcm, expected);
+    assert!(wave.decode(&ctl[0xC8..]).is_err());
+
+    let wav = audio::wav(&pcm, 22050, wave.looped);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(
+        u32::from_le_bytes(wav[4..8].try_into().unwrap()) as usize,
+        wav.len() - 8
+    );
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(&wav[44..48], &[1, 0, 3, 0]);
+    assert_eq!(&wav[108..112], b"smpl");
+
+    let mut bad = ctl.clone();
+    put(&mut bad, 0x48, &0x1000u32.to_be_bytes());
+    assert!(BankFile::read(&bad, 0).is_err());
+}
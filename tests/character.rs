@@ -0,0 +1,92 @@
+use ssbfile::{
+    character::{self, Model},
+    fighter::{Kind, ATTRIBUTES},
+    Relocation,
+};
+
+/// A pointer at `offset` to `target` in the same file
+fn reloc(offset: usize, target: u32) -> Relocation {
+    Relocation {
+        offset,
+        target,
+        external_file: None,
+    }
+}
+
+/// A display list at 0x00 with its vertices at 0x40, a `DObjDesc` array at 0x70 of two
+/// bones that draw it, and attributes at 0xD0. The pointer at 0x16C points to the array,
+/// and the two after it into file 7.
+fn main_file() -> (Vec<u8>, Vec<Relocation>) {
+    let commands: [u64; 3] = [
+        0x0100_3006_0000_0040, // gsSPVertex(0x40, 3, 0)
+        0x0500_0204_0000_0000, // gsSP1Triangle(0, 1, 2, 0)
+        0xDF00_0000_0000_0000, // gsSPEndDisplayList()
+    ];
+    let mut file = commands
+        .iter()
+        .flat_map(|c| c.to_be_bytes())
+        .collect::<Vec<_>>();
+    file.resize(0x40, 0);
+    for (x, y) in [(0i16, 0i16), (1, 0), (0, 1)] {
+        for v in [x, y, 0, 0, 0, 0] {
+            file.extend_from_slice(&v.to_be_bytes());
+        }
+        file.extend_from_slice(&[0xFF; 4]);
+    }
+    for id in [0u32, 1] {
+        file.extend_from_slice(&id.to_be_bytes());
+        file.extend_from_slice(&[0; 4]);
+        for f in [0.0f32, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0] {
+            file.extend_from_slice(&f.to_be_bytes());
+        }
+    }
+    file.extend_from_slice(&18u32.to_be_bytes());
+    file.resize(0xD0, 0);
+    for attribute in ATTRIBUTES {
+        let word = match (attribute.kind, attribute.name) {
+            (Kind::Int, "jumps_max") => 2,
+            (Kind::Int, _) => 0,
+            (Kind::Float, _) => 1.0f32.to_bits(),
+        };
+        file.extend_from_slice(&word.to_be_bytes());
+    }
+    file.resize(0x178, 0);
+
+    let external = |offset| Relocation {
+        offset,
+        target: 0,
+        external_file: Some(7),
+    };
+    let relocs = vec![
+        reloc(0x04, 0x40),
+        reloc(0x74, 0x00),
+        reloc(0xA0, 0x00),
+        reloc(0x16C, 0x70),
+        external(0x170),
+        external(0x174),
+    ];
+
+    (file, relocs)
+}
+
+#[test]
+fn finds_the_parts_of_a_main_file() {
+    let (file, relocs) = main_file();
+    let character = character::character(&file, &relocs);
+
+    assert_eq!(character.attributes, Some(0xD0));
+    assert_eq!(
+        character.models,
+        [Model {
+            offset: 0x70,
+            bones: 2,
+            parts: 2
+        }]
+    );
+    assert_eq!(character.display_lists, [0x00]);
+    assert_eq!(character.linked_files, [(7, 2)]);
+
+    let empty = character::character(&[0; 0x10], &[]);
+    assert_eq!(empty.attributes, None);
+    assert!(empty.models.is_empty() && empty.linked_files.is_empty());
+}
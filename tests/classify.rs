@@ -0,0 +1,30 @@
+mod common;
+
+use common::{commands, reloc, words};
+use ssbfile::classify::{classify, FileKind};
+
+#[test]
+fn display_lists_are_geometry() {
+    let file = commands(&[
+        0xE700_0000_0000_0000, // G_RDPPIPESYNC
+        0xD900_0000_0002_0405, // G_GEOMETRYMODE
+        0x0101_0002_0000_0100, // G_VTX
+        0x0500_0204_0000_0000, // G_TRI1
+        0xDF00_0000_0000_0000, // G_ENDDL
+    ]);
+
+    assert_eq!(classify(&file, &[]), FileKind::Geometry);
+}
+
+#[test]
+fn files_by_their_words() {
+    assert_eq!(classify(&[0; 16], &[]), FileKind::Empty);
+
+    let floats = words(&[0x3F80_0000, 0x4120_0000, 0xC2C8_0000, 0x1234_5678]);
+    assert_eq!(classify(&floats, &[]), FileKind::Floats);
+
+    let pointers = words(&[0x0001_0003, 0xFFFF_0000, 0x1234_5678, 0]);
+    let relocs = [reloc(0, 0), reloc(4, 0)];
+    assert_eq!(classify(&pointers, &relocs), FileKind::Pointers);
+    assert_eq!(classify(&pointers, &[]), FileKind::Data);
+}
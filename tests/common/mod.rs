@@ -0,0 +1,108 @@
+//! Helpers for building the files that the tests read; not every test uses all of them
+#![allow(dead_code)]
+
+use ssbfile::{pack_file, versions::SSBInfo, Relocation, TableEntry, TerminalEntry};
+use std::borrow::Cow;
+
+/// Big endian words
+pub fn words(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_be_bytes()).collect()
+}
+
+/// Big endian halfwords
+pub fn halves(values: &[i16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+/// Big endian display list commands
+pub fn commands(commands: &[u64]) -> Vec<u8> {
+    commands.iter().flat_map(|c| c.to_be_bytes()).collect()
+}
+
+/// A `Vtx` at `(x, y, 0)` with texture coordinates `(s, t)` and a color
+pub fn vertex(x: i16, y: i16, s: i16, t: i16, color: [u8; 4]) -> Vec<u8> {
+    let mut vertex = halves(&[x, y, 0, 0, s, t]);
+    vertex.extend_from_slice(&color);
+    vertex
+}
+
+/// Copy `bytes` into `file` at `at`
+pub fn put(file: &mut [u8], at: usize, bytes: &[u8]) {
+    file[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+/// A pointer at `offset` to `target` in the same file
+pub fn reloc(offset: usize, target: u32) -> Relocation {
+    Relocation {
+        offset,
+        target,
+        external_file: None,
+    }
+}
+
+/// A file of a test rom: its data, the heads of its relocation chains (in words, or
+/// `0xFFFF`), and the ids of the external files that follow it
+pub struct TestFile<'a> {
+    pub data: &'a [u8],
+    pub inreloc: u16,
+    pub exreloc: u16,
+    pub externs: &'a [u16],
+}
+
+/// An uncompressed file without relocations
+pub fn plain(data: &[u8]) -> TestFile<'_> {
+    TestFile {
+        data,
+        inreloc: 0xFFFF,
+        exreloc: 0xFFFF,
+        externs: &[],
+    }
+}
+
+/// Where the table of a test rom with `files` files is
+pub fn test_info(files: usize) -> SSBInfo {
+    SSBInfo {
+        version: Cow::Borrowed("TEST"),
+        crc: (0, 0),
+        table_start: 0x40,
+        table_end: 0x40 + (files + 1) * TableEntry::SIZE,
+    }
+}
+
+/// A big-endian rom with the uncompressed `files` in a table at [`test_info`]
+pub fn table_rom(files: &[TestFile]) -> Vec<u8> {
+    let info = test_info(files.len());
+    let mut rom = vec![0; info.table_end];
+    rom[..4].copy_from_slice(&0x80371240u32.to_be_bytes());
+    let mut data = Vec::new();
+    for (id, file) in files.iter().enumerate() {
+        let size = file.data.len().div_ceil(4) as u16;
+        let entry = TableEntry {
+            offset: data.len() as u32,
+            compressed: false,
+            inreloc: file.inreloc,
+            size,
+            exreloc: file.exreloc,
+            decompressed_size: size,
+        };
+        put(
+            &mut rom,
+            info.table_start + id * TableEntry::SIZE,
+            &entry.to_bytes(),
+        );
+        data.extend_from_slice(&pack_file(file.data, file.externs));
+    }
+    let terminal = TerminalEntry {
+        data_end: data.len() as u32,
+        next_table: data.len() as u32,
+        unknown: 0,
+    };
+    put(
+        &mut rom,
+        info.table_end - TableEntry::SIZE,
+        &terminal.to_bytes(),
+    );
+    rom.extend_from_slice(&data);
+
+    rom
+}
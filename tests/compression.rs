@@ -0,0 +1,141 @@
+use ssbfile::{
+    compress_smallest, compress_with, decompress, CompressSettings, Error, Trees, VpkMethod,
+};
+
+/// data with matches of many lengths and distances, like a file of display lists
+fn file() -> Vec<u8> {
+    (0..0x180u32)
+        .flat_map(|i| {
+            let word = match i % 7 {
+                0 => 0xE700_0000,
+                3 => 0x0600_0000 | (i / 5 * 8),
+                _ => ((i * 0x9E37) >> (i % 5)) ^ (i / 64),
+            };
+            word.to_be_bytes()
+        })
+        .collect()
+}
+
+#[test]
+fn recompressing_with_the_trees_of_a_file_is_the_same_every_time() {
+    let data = file();
+    for method in [VpkMethod::OneSample, VpkMethod::TwoSample] {
+        let settings = CompressSettings {
+            method,
+            ..CompressSettings::default()
+        };
+        let original = compress_with(&data, 1, &settings).unwrap();
+        let matching = CompressSettings::matching(&original).unwrap();
+        assert_eq!(matching.method, method);
+        assert!(matching.trees.is_some());
+
+        let first = compress_with(&data, 1, &matching).unwrap();
+        assert_eq!(decompress(&first, 1).unwrap(), data);
+        for _ in 0..8 {
+            assert_eq!(compress_with(&data, 1, &matching).unwrap(), first);
+        }
+    }
+}
+
+#[test]
+fn matching_settings_can_encode_any_match_of_their_window() {
+    let data = file();
+    let original = compress_with(&data, 1, &CompressSettings::default()).unwrap();
+    let matching = CompressSettings::matching(&original).unwrap();
+
+    // other data, with matches that the original didn't have
+    let other = data.iter().rev().copied().collect::<Vec<_>>();
+    let compressed = compress_with(&other, 2, &matching).unwrap();
+    assert_eq!(decompress(&compressed, 2).unwrap(), other);
+}
+
+#[test]
+fn compressing_without_trees_is_the_same_every_time() {
+    let data = file();
+    // the 16 bit window of each method
+    let candidates = || CompressSettings::candidates().step_by(3);
+    for settings in candidates() {
+        let first = compress_with(&data, 1, &settings).unwrap();
+        assert_eq!(decompress(&first, 1).unwrap(), data);
+        for _ in 0..2 {
+            assert_eq!(compress_with(&data, 1, &settings).unwrap(), first);
+        }
+    }
+
+    let smallest = compress_smallest(&data, 1, candidates()).unwrap();
+    assert_eq!(compress_smallest(&data, 1, candidates()).unwrap(), smallest);
+}
+
+#[test]
+fn data_without_repeats_is_not_compressed() {
+    let data = (0..=255u8).collect::<Vec<_>>();
+    let error = compress_with(&data, 1, &CompressSettings::default()).unwrap_err();
+    assert_eq!(error.root_cause().downcast_ref(), Some(&Error::NoRepeats));
+
+    // but it can be encoded with trees that are given
+    let original = compress_with(&file(), 1, &CompressSettings::default()).unwrap();
+    let matching = CompressSettings::matching(&original).unwrap();
+    let compressed = compress_with(&data, 2, &matching).unwrap();
+    assert_eq!(decompress(&compressed, 2).unwrap(), data);
+}
+
+#[test]
+fn settings_that_vpk0_cant_encode_are_errors() {
+    let data = file();
+    let error = |settings: &CompressSettings| {
+        compress_with(&data, 1, settings)
+            .unwrap_err()
+            .root_cause()
+            .downcast_ref::<Error>()
+            .cloned()
+    };
+
+    let narrow = CompressSettings {
+        trees: Some(Trees {
+            offsets: "(1, 4)".into(),
+            lengths: "(2, 8)".into(),
+        }),
+        ..CompressSettings::default()
+    };
+    assert!(matches!(
+        error(&narrow),
+        Some(Error::UnencodableValue {
+            tree: "offset",
+            widest: 4,
+            ..
+        })
+    ));
+
+    let deep = (1..=32).fold("16".to_string(), |tree, bits| {
+        format!("({}, {})", bits, tree)
+    });
+    let deep = CompressSettings {
+        trees: Some(Trees {
+            offsets: format!("({}, {})", deep, deep),
+            lengths: "8".into(),
+        }),
+        ..CompressSettings::default()
+    };
+    assert_eq!(
+        error(&deep),
+        Some(Error::TreeTooDeep {
+            tree: "offset",
+            depth: 33
+        })
+    );
+
+    for window_bits in [0, 64] {
+        let wide = CompressSettings {
+            window_bits,
+            ..CompressSettings::default()
+        };
+        assert_eq!(
+            error(&wide),
+            Some(Error::CompressBits {
+                what: "windows",
+                bits: window_bits,
+                max: 24
+            })
+        );
+    }
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::commands;
+use ssbfile::{
+    display_list::{self, Address, Command},
+    Relocation,
+};
+
+#[test]
+fn decodes_commands_as_gbi_macros() {
+    let raw = Address::Raw(0x0E00_0010);
+    let cases = [
+        (0x0100_8010_0E00_0010, "gsSPVertex(0x0E000010, 8, 0)"),
+        (
+            0x0600_0204_0004_0600,
+            "gsSP2Triangles(0, 1, 2, 0, 2, 3, 0, 0)",
+        ),
+        (
+            0xD700_0002_FFFF_FFFF,
+            "gsSPTexture(0xFFFF, 0xFFFF, 0, 0, G_ON)",
+        ),
+        (
+            0xF540_0200_0001_4050,
+            "gsDPSetTile(G_IM_FMT_CI, G_IM_SIZ_4b, 1, 0x000, 0, 0, 0, 5, 0, 0, 5, 0)",
+        ),
+        (
+            0xE300_0A01_0010_0000,
+            "gsSPSetOtherMode(G_SETOTHERMODE_H, 20, 2, 0x00100000)",
+        ),
+        (
+            0xFA00_0080_FF00_00FF,
+            "gsDPSetPrimColor(0, 128, 255, 0, 0, 255)",
+        ),
+        (0xDF00_0000_0000_0000, "gsSPEndDisplayList()"),
+    ];
+    for (cmd, text) in cases {
+        assert_eq!(Command::decode(cmd, raw).to_string(), text);
+    }
+}
+
+#[test]
+fn resolves_pointers_and_finds_lists() {
+    // a list at 0x10 that loads vertices at 0x40 and calls a list at 0x28 in file 4
+    let mut file = commands(&[
+        0,
+        0x0001_0000,
+        0x0100_4008_0000_0000,
+        0x0500_0204_0000_0000,
+        0xDE00_0000_0000_0000,
+        0xDF00_0000_0000_0000,
+        0,
+        0,
+    ]);
+    file.extend_from_slice(&[
+        0x00, 0x01, 0xFF, 0xFE, 0x00, 0x03, 0, 0, 0, 0, 0, 0x20, 1, 2, 3, 4,
+    ]);
+    file.extend_from_slice(&[0; 48]);
+    let reloc = |offset, target, external_file| Relocation {
+        offset,
+        target,
+        external_file,
+    };
+    let relocs = [
+        reloc(0x0C, 0x10, None),
+        reloc(0x14, 0x40, None),
+        reloc(0x24, 0x80, Some(4)),
+    ];
+
+    assert_eq!(display_list::find(&file, &relocs), [0x10]);
+    let list = display_list::parse(&file, 0x10, &relocs).unwrap();
+    assert_eq!(list.len(), 4);
+    assert_eq!(
+        list[0],
+        (
+            0x10,
+            Command::Vertex {
+                addr: Address::Internal(0x40),
+                count: 4,
+                first: 0
+            }
+        )
+    );
+    assert_eq!(list[2].1.to_string(), "gsSPDisplayList(file0004+0x0080)");
+
+    let vertices = display_list::vertices(&file, 0x40, 4).unwrap();
+    assert_eq!(vertices[0].position, [1, -2, 3]);
+    assert_eq!(vertices[0].st, [0, 0x20]);
+    assert_eq!(vertices[0].color, [1, 2, 3, 4]);
+    assert!(display_list::vertices(&file, 0x40, 8).is_err());
+    assert!(display_list::parse(&file, 0x30, &relocs).is_err());
+}
@@ -0,0 +1,67 @@
+use ssbfile::effect::{self, Value};
+
+/// A bank at 0x00 of one script at 0x08
+fn bank(commands: &[u8]) -> Vec<u8> {
+    let mut file = vec![0, 0, 0, 1, 0, 0, 0, 8];
+    let mut header = vec![0u8; effect::HEADER_SIZE];
+    header[0..8].copy_from_slice(&[0, 2, 0, 7, 0, 30, 0, 12]);
+    header[0x0C..0x10].copy_from_slice(&(-0.5f32).to_be_bytes());
+    header[0x2C..0x30].copy_from_slice(&4f32.to_be_bytes());
+    file.extend_from_slice(&header);
+    file.extend_from_slice(commands);
+    file
+}
+
+#[test]
+fn decodes_effect_scripts() {
+    let mut commands = vec![0x45, 0x10, 0x8B];
+    commands.extend_from_slice(&1f32.to_be_bytes());
+    commands.extend_from_slice(&2f32.to_be_bytes());
+    commands.extend_from_slice(&[0xC1, 0, 10, 0xFF, 0x80, 0, 0xFF]);
+    commands.extend_from_slice(&[0xC6, 0, 3, 0x02, 0xFF]);
+    let file = bank(&commands);
+
+    let scripts = effect::bank(&file, 0).unwrap();
+    assert_eq!(scripts.len(), 1);
+    let script = &scripts[0];
+    assert_eq!((script.index, script.offset), (0, 8));
+    assert_eq!(script.header.texture, 7);
+    assert_eq!(script.header.particle_lifetime, 12);
+    assert_eq!((script.header.gravity, script.header.size), (-0.5, 4.0));
+
+    let lines = script
+        .commands
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "wait frames=1296",
+            "add_position x=1.0 y=2.0",
+            "color frames=10 r=255 g=128 b=0 a=255",
+            "spawn effect=3",
+            "wait frames=2",
+            "end",
+        ]
+    );
+    assert_eq!(script.commands[2].args[1], ("r", Value::Int(0xFF)));
+    assert_eq!(script.commands[1].offset, 0x3A);
+
+    let text = effect::disassemble(&scripts);
+    assert!(text.starts_with("\n.effect 0 0x0008\n# kind=2 texture=7"));
+    assert!(text.contains("0x0038: wait frames=1296\n"));
+}
+
+#[test]
+fn stops_at_unknown_commands() {
+    let file = bank(&[0x01, 0xE0, 0x01, 0xFF]);
+    let scripts = effect::bank(&file, 0).unwrap();
+    assert_eq!(scripts[0].commands.len(), 2);
+    assert_eq!(scripts[0].commands[1].to_string(), ".byte 0xE0");
+    assert!(effect::disassemble(&scripts).contains("isn't decoded"));
+
+    // no end
+    assert!(effect::bank(&bank(&[0x01]), 0).is_err());
+    assert!(effect::bank(&[0, 0, 0, 0], 0).is_err());
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::{plain, table_rom, test_info, words, TestFile};
+use ssbfile::FileLoader;
+use std::rc::Rc;
+
+/// file 0 points into itself, file 1 points into files 0 and 2
+fn rom() -> Vec<u8> {
+    let target = words(&[0xFFFF_0002, 0, 0xAAAA_AAAA, 0xBBBB_BBBB]);
+    let pointers = words(&[0x0002_0001, 0x1111_1111, 0xFFFF_0003, 0x2222_2222]);
+    table_rom(&[
+        TestFile {
+            inreloc: 0,
+            ..plain(&target)
+        },
+        TestFile {
+            exreloc: 0,
+            externs: &[0, 2],
+            ..plain(&pointers)
+        },
+        plain(b"the third file.."),
+    ])
+}
+
+#[test]
+fn loaded_files_are_relocated_and_cached() {
+    let rom = rom();
+    let info = test_info(3);
+    let loader = FileLoader::new(&rom, &info);
+
+    let first = loader.load(0).unwrap();
+    assert_eq!(&first[..], &words(&[8, 0, 0xAAAA_AAAA, 0xBBBB_BBBB])[..]);
+    // a second load is the same data, and not extracted again
+    assert!(Rc::ptr_eq(&first, &loader.load(0).unwrap()));
+    assert_eq!(&loader.load(2).unwrap()[..], b"the third file..");
+    assert!(loader.load(3).is_err());
+}
+
+#[test]
+fn external_refs_resolve_their_files() {
+    let rom = rom();
+    let info = test_info(3);
+    let loader = FileLoader::new(&rom, &info);
+
+    let refs = loader.external_refs(1).unwrap();
+    let found = refs
+        .iter()
+        .map(|r| (r.file_id(), r.site(), r.target()))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(0, 0x0, 0x4), (2, 0x8, 0xC)]);
+
+    // the files that are pointed to come from the same cache
+    assert!(Rc::ptr_eq(
+        &refs[0].load().unwrap(),
+        &loader.load(0).unwrap()
+    ));
+    assert_eq!(&refs[1].load().unwrap()[..], b"the third file..");
+    assert!(loader.external_refs(0).unwrap().is_empty());
+}
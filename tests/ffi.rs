@@ -0,0 +1,230 @@
+use ssbfile::{
+    ffi::{
+        ssb_close_rom, ssb_extract_file, ssb_file_count, ssb_free_buffer, ssb_last_error,
+        ssb_open_rom,
+    },
+    TableEntry, TerminalEntry,
+};
+use std::{
+    ffi::{CStr, CString},
+    path::PathBuf,
+    ptr,
+};
+
+const FILES: usize = 20;
+
+/// a rom of an unknown version, whose table is found by its layout: the even files
+/// are compressed (with only the vpk0 magic), and the odd ones are `DATADATA`
+fn rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x1000];
+    rom[..4].copy_from_slice(&0x80371240u32.to_be_bytes());
+    let mut data = Vec::new();
+    for id in 0..FILES {
+        let compressed = id % 2 == 0;
+        let entry = TableEntry {
+            offset: data.len() as u32,
+            compressed,
+            inreloc: 0xFFFF,
+            size: 2,
+            exreloc: 0xFFFF,
+            decompressed_size: 2,
+        };
+        rom.extend_from_slice(&entry.to_bytes());
+        data.extend_from_slice(if compressed {
+            b"vpk0\0\0\0\x08"
+        } else {
+            b"DATADATA"
+        });
+    }
+    let terminal = TerminalEntry {
+        data_end: data.len() as u32,
+        next_table: data.len() as u32,
+        unknown: 0,
+    };
+    rom.extend_from_slice(&terminal.to_bytes());
+    rom.extend_from_slice(&data);
+
+    rom
+}
+
+fn write_rom(name: &str, rom: &[u8]) -> (PathBuf, CString) {
+    let path = std::env::temp_dir().join(format!("ssbfile-ffi-{}-{}", name, std::process::id()));
+    std::fs::write(&path, rom).unwrap();
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+    (path, c_path)
+}
+
+fn last_error() -> String {
+    let msg = ssb_last_error();
+    assert!(!msg.is_null());
+    unsafe { CStr::from_ptr(msg) }.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn extracts_files_through_the_c_interface() {
+    // a byte-swapped dump is converted when it's opened
+    let swapped = rom()
+        .chunks_exact(2)
+        .flat_map(|h| [h[1], h[0]])
+        .collect::<Vec<_>>();
+    let (path, c_path) = write_rom("v64", &swapped);
+
+    unsafe {
+        let rom = ssb_open_rom(c_path.as_ptr());
+        assert!(!rom.is_null());
+        assert_eq!(ssb_file_count(rom), FILES);
+
+        let mut len = 0;
+        let data = ssb_extract_file(rom, 1, &mut len);
+        assert!(!data.is_null());
+        assert_eq!(std::slice::from_raw_parts(data, len), b"DATADATA");
+        ssb_free_buffer(data, len);
+
+        // the length is optional
+        let data = ssb_extract_file(rom, 3, ptr::null_mut());
+        assert!(!data.is_null());
+        ssb_free_buffer(data, 8);
+
+        ssb_close_rom(rom);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn errors_are_null_with_a_message() {
+    let (path, c_path) = write_rom("z64", &rom());
+
+    unsafe {
+        assert!(ssb_open_rom(ptr::null()).is_null());
+        assert!(last_error().contains("NULL"));
+        let missing = CString::new("/nonexistent/ssbfile.z64").unwrap();
+        assert!(ssb_open_rom(missing.as_ptr()).is_null());
+        assert!(last_error().contains("/nonexistent/ssbfile.z64"));
+
+        // a NULL rom has no files, and none to extract
+        assert_eq!(ssb_file_count(ptr::null()), 0);
+        let mut len = 7;
+        assert!(ssb_extract_file(ptr::null(), 0, &mut len).is_null());
+        assert!(last_error().contains("NULL"));
+        assert_eq!(len, 7);
+
+        let rom = ssb_open_rom(c_path.as_ptr());
+        assert!(!rom.is_null());
+        // past the end of the table, and data that doesn't decompress
+        assert!(ssb_extract_file(rom, FILES, &mut len).is_null());
+        assert!(last_error().contains(&format!("<{}>", FILES)));
+        assert!(ssb_extract_file(rom, 0, &mut len).is_null());
+        assert!(last_error().contains("<0>"));
+        assert_eq!(len, 7);
+
+        // freeing NULL does nothing
+        ssb_free_buffer(ptr::null_mut(), 0);
+        ssb_close_rom(ptr::null_mut());
+        ssb_close_rom(rom);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+/// The name, parameter types, and return type of each function, with the types in Rust
+type Signature = (String, Vec<String>, String);
+
+/// Each `extern "C" fn` of the Rust source of the interface
+fn exported(source: &str) -> Vec<Signature> {
+    source
+        .split("extern \"C\" fn ")
+        .skip(1)
+        .map(|f| {
+            let decl = f[..f.find('{').unwrap()]
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let decl = decl.join(" ");
+            let (name, rest) = decl.split_once('(').unwrap();
+            let (params, ret) = rest.rsplit_once(')').unwrap();
+            let params = params
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| p.split_once(": ").unwrap().1.to_owned())
+                .collect();
+            let ret = ret.trim().strip_prefix("-> ").unwrap_or("()").to_owned();
+
+            (name.to_owned(), params, ret)
+        })
+        .collect()
+}
+
+/// The Rust type of a C type of the header
+fn rust_type(c: &str) -> String {
+    let c = c.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (c, pointer) = match c.strip_suffix('*') {
+        Some(pointee) => (pointee.trim(), true),
+        None => (c.as_str(), false),
+    };
+    let (c, constant) = match c.strip_prefix("const ") {
+        Some(c) => (c, true),
+        None => (c, false),
+    };
+    let base = match c {
+        "void" => "()",
+        "char" => "c_char",
+        "size_t" => "usize",
+        "uint8_t" => "u8",
+        other => other,
+    };
+    match (pointer, constant) {
+        (false, _) => base.to_owned(),
+        (true, true) => format!("*const {}", base),
+        (true, false) => format!("*mut {}", base),
+    }
+}
+
+/// Each function declared by the C header
+fn declared(header: &str) -> Vec<Signature> {
+    let mut code = String::new();
+    let mut rest = header;
+    while let Some(start) = rest.find("/*") {
+        code.push_str(&rest[..start]);
+        rest = &rest[start + rest[start..].find("*/").unwrap() + 2..];
+    }
+    code.push_str(rest);
+
+    code.split(';')
+        .map(str::trim)
+        .filter_map(|decl| {
+            decl.lines()
+                .rfind(|l| l.contains("ssb_") && l.contains('('))
+        })
+        .map(|decl| {
+            let (ret_name, params) = decl.split_once('(').unwrap();
+            let name_at = ret_name.rfind([' ', '*']).unwrap() + 1;
+            let params = params
+                .trim_end_matches(')')
+                .split(',')
+                .map(str::trim)
+                .filter(|p| *p != "void")
+                .map(|p| {
+                    let name_at = p.rfind([' ', '*']).unwrap() + 1;
+                    rust_type(&p[..name_at])
+                })
+                .collect();
+
+            (
+                ret_name[name_at..].to_owned(),
+                params,
+                rust_type(&ret_name[..name_at]),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn the_header_declares_every_exported_function() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let source = std::fs::read_to_string(format!("{}/src/ffi.rs", root)).unwrap();
+    let header = std::fs::read_to_string(format!("{}/include/ssbfile.h", root)).unwrap();
+
+    let exported = exported(&source);
+    assert_eq!(exported.len(), 6);
+    assert_eq!(declared(&header), exported);
+}
@@ -0,0 +1,65 @@
+use ssbfile::{
+    fighter::{Attributes, Kind, Value, ATTRIBUTES, ATTRIBUTES_SIZE},
+    versions::VersionDb,
+};
+
+#[test]
+fn reads_and_writes_attributes() {
+    let mut file = vec![0u8; 0x10 + ATTRIBUTES_SIZE];
+    file[0x10..0x14].copy_from_slice(&1.5f32.to_be_bytes());
+    file[0x10 + 0x64..0x10 + 0x68].copy_from_slice(&2i32.to_be_bytes());
+
+    let mut attributes = Attributes::read(&file, 0x10).unwrap();
+    let values = attributes.values();
+    assert_eq!(values.len(), ATTRIBUTES.len());
+    assert_eq!(values[0], ("size_mul", Value::Float(1.5)));
+    assert!(values.contains(&("jumps_max", Value::Int(2))));
+
+    attributes.set("weight", 1.25).unwrap();
+    attributes.set("jumps_max", 6.0).unwrap();
+    assert!(attributes.set("jumps_max", 2.5).is_err());
+    assert!(attributes.set("height", 1.0).is_err());
+    attributes.write(&mut file).unwrap();
+    assert_eq!(&file[0x10 + 0x68..0x10 + 0x6C], &1.25f32.to_be_bytes());
+    assert_eq!(&file[0x10 + 0x64..0x10 + 0x68], &6i32.to_be_bytes());
+
+    assert!(Attributes::read(&file, 0x20).is_err());
+}
+
+#[test]
+fn finds_attributes_by_their_values() {
+    let mut file = vec![0xFFu8; 0x40 + ATTRIBUTES_SIZE + 0x10];
+    let block = &mut file[0x40..0x40 + ATTRIBUTES_SIZE];
+    block.fill(0);
+    for attribute in ATTRIBUTES.iter().filter(|a| a.kind == Kind::Float) {
+        let at = attribute.offset;
+        block[at..at + 4].copy_from_slice(&1.5f32.to_be_bytes());
+    }
+    block[0x64..0x68].copy_from_slice(&2i32.to_be_bytes());
+    assert_eq!(Attributes::find(&file), Some(0x40));
+
+    // a fighter can't have no jumps
+    file[0x40 + 0x64..0x40 + 0x68].copy_from_slice(&0i32.to_be_bytes());
+    assert_eq!(Attributes::find(&file), None);
+    assert_eq!(Attributes::find(&[0; 0x20]), None);
+}
+
+#[test]
+fn finds_fighter_locations_by_version() {
+    let mut db = VersionDb::builtin();
+    db.load_toml(
+        r#"
+        [[fighter]]
+        version = "NALE"
+        fighter = "fox"
+        file = 0x10
+        offset = 0x200
+        "#,
+    )
+    .unwrap();
+
+    let fox = db.fighter("NALE", "Fox").unwrap();
+    assert_eq!((fox.file, fox.offset), (0x10, 0x200));
+    assert!(db.fighter("NALE", "mario").is_none());
+    assert!(db.fighter("NJ", "fox").is_none());
+}
@@ -0,0 +1,120 @@
+use ssbfile::{
+    rom_info::{fix_crc, normalize, recalc_crc, set_game_code, set_title, N64Header, RomFormat},
+    Error,
+};
+
+fn header() -> Vec<u8> {
+    let mut rom = vec![0; N64Header::SIZE];
+    rom[0x00..0x10].copy_from_slice(&[
+        0x80, 0x37, 0x12, 0x40, // pi timings
+        0x00, 0x00, 0x00, 0x0F, // clock rate
+        0x80, 0x00, 0x04, 0x00, // boot address
+        0x00, 0x00, 0x14, 0x4B, // libultra 2.0K
+    ]);
+    rom[0x10..0x18].copy_from_slice(&[0x91, 0x6B, 0x8B, 0x5B, 0x78, 0x0B, 0x85, 0xA4]);
+    rom[0x20..0x34].copy_from_slice(b"SMASH BROTHERS      ");
+    rom[0x3B..0x3F].copy_from_slice(b"NALE");
+
+    rom
+}
+
+#[test]
+fn header_fields() {
+    let h = N64Header::parse(&header()).unwrap();
+
+    assert_eq!(h.boot_address(), 0x8000_0400);
+    assert_eq!(h.clock_rate(), 0xF);
+    assert_eq!(h.libultra_version(), (2, 0, Some('K')));
+    assert_eq!(h.crc(), (0x916B8B5B, 0x780B85A4));
+    assert_eq!(h.title(), "SMASH BROTHERS");
+    assert_eq!(h.game_code(), "NALE");
+    assert_eq!(h.rom_version(), 0);
+
+    let pi = h.pi_settings();
+    assert_eq!(
+        (pi.latency, pi.pulse_width, pi.page_size, pi.release),
+        (0x40, 0x12, 7, 3)
+    );
+    // a blank bootcode isn't a known one
+    assert_eq!(h.cic(), None);
+}
+
+#[test]
+fn byte_orders_are_normalized() {
+    let z64 = header();
+    let v64 = z64
+        .chunks_exact(2)
+        .flat_map(|h| [h[1], h[0]])
+        .collect::<Vec<_>>();
+    let n64 = z64
+        .chunks_exact(4)
+        .flat_map(|w| [w[3], w[2], w[1], w[0]])
+        .collect::<Vec<_>>();
+
+    for (mut rom, format) in [
+        (z64.clone(), RomFormat::BigEndian),
+        (v64, RomFormat::ByteSwapped),
+        (n64, RomFormat::LittleEndian),
+    ] {
+        assert_eq!(RomFormat::detect(&rom).unwrap(), format);
+        assert_eq!(normalize(&mut rom).unwrap(), format);
+        assert_eq!(rom, z64);
+        assert_eq!(normalize(&mut rom).unwrap(), RomFormat::BigEndian);
+    }
+}
+
+#[test]
+fn unknown_byte_orders_are_errors() {
+    let mut rom = header();
+    rom[..4].copy_from_slice(&[0x12, 0x40, 0x80, 0x37]);
+    let error = normalize(&mut rom).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::UnknownFormat { magic: 0x1240_8037 })
+    );
+    // the rom is left as it was
+    assert_eq!(&rom[4..], &header()[4..]);
+
+    let error = RomFormat::detect(&[0x80, 0x37]).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::NoHeader { len: 2 })
+    );
+}
+
+#[test]
+fn short_rom_has_no_header() {
+    assert!(N64Header::parse(&header()[..0x40]).is_err());
+}
+
+#[test]
+fn crc_of_6102_rom() {
+    // a pseudo-random rom, with the expected crcs from a reference implementation
+    let mut x = 1u32;
+    let mut rom = (0..0x101000)
+        .map(|_| {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            (x >> 16) as u8
+        })
+        .collect::<Vec<_>>();
+    rom[0..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+
+    assert_eq!(fix_crc(&mut rom).unwrap(), (0xECC1E9D8, 0xAD9B78AE));
+    assert_eq!(N64Header::parse(&rom).unwrap().crc(), recalc_crc(&rom));
+}
+
+#[test]
+fn edit_title_and_game_code() {
+    let mut rom = header();
+    set_title(&mut rom, "SMASH MOD").unwrap();
+    set_game_code(&mut rom, "NAXE").unwrap();
+
+    let h = N64Header::parse(&rom).unwrap();
+    assert_eq!(h.title(), "SMASH MOD");
+    assert_eq!(&rom[0x20..0x34], b"SMASH MOD           ");
+    assert_eq!(h.game_code(), "NAXE");
+
+    assert!(set_title(&mut rom, "A TITLE THAT IS TOO LONG").is_err());
+    assert!(set_game_code(&mut rom, "NA-E").is_err());
+    assert!(set_game_code(&mut rom, "NALEX").is_err());
+}
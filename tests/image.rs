@@ -0,0 +1,224 @@
+mod common;
+
+use common::{put, reloc};
+use ssbfile::image::{self, Image, Sprite, TexelFormat};
+
+#[test]
+fn decodes_texel_formats() {
+    // white and opaque, then a 50% red with no alpha
+    let rgba16 = image::decode(&[0xFF, 0xFF, 0x80, 0x00], TexelFormat::Rgba16, 2, 1, None).unwrap();
+    assert_eq!(rgba16.pixels, [0xFF, 0xFF, 0xFF, 0xFF, 0x84, 0, 0, 0]);
+
+    let i4 = image::decode(&[0xF0], TexelFormat::I4, 2, 1, None).unwrap();
+    assert_eq!(i4.pixels, [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+
+    let ia8 = image::decode(&[0xF8], TexelFormat::Ia8, 1, 1, None).unwrap();
+    assert_eq!(ia8.pixels, [0xFF, 0xFF, 0xFF, 0x88]);
+
+    let ia4 = image::decode(&[0xE1], TexelFormat::Ia4, 2, 1, None).unwrap();
+    assert_eq!(ia4.pixels, [0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0xFF]);
+
+    let palette = [0x00, 0x01, 0xF8, 0x01];
+    let ci4 = image::decode(&[0x10], TexelFormat::Ci4, 2, 1, Some(&palette)).unwrap();
+    assert_eq!(ci4.pixels, [0xFF, 0, 0, 0xFF, 0, 0, 0, 0xFF]);
+
+    assert!(image::decode(&[0x10], TexelFormat::Ci4, 2, 1, None).is_err());
+    assert!(image::decode(&[0; 7], TexelFormat::Rgba16, 2, 2, None).is_err());
+    assert_eq!("IA16".parse::<TexelFormat>().unwrap(), TexelFormat::Ia16);
+}
+
+#[test]
+fn finds_and_decodes_sprites() {
+    // a 2x2 i8 sprite at 0x10 made of two 2x1 bitmaps at 0x60, with texels at 0x80
+    let mut file = vec![0u8; 0x90];
+    let sprite = 0x10;
+    put(&mut file, sprite + 0x04, &[0, 2, 0, 2]);
+    put(&mut file, sprite + 0x08, &1f32.to_be_bytes());
+    put(&mut file, sprite + 0x0C, &1f32.to_be_bytes());
+    put(&mut file, sprite + 0x28, &[0, 2]);
+    put(&mut file, sprite + 0x2C, &[0, 1]);
+    put(&mut file, sprite + 0x30, &[4, 1]);
+    for (i, texels) in [0x80, 0x82].into_iter().enumerate() {
+        let bitmap = 0x60 + i * 0x10;
+        put(&mut file, bitmap, &[0, 2, 0, 2]);
+        put(&mut file, bitmap + 0x0C, &[0, 1]);
+        put(&mut file, texels, &[0x11 * (i as u8 + 1), 0xFF]);
+    }
+    let relocs = [
+        reloc(0x00, sprite as u32),
+        reloc(sprite + 0x34, 0x60),
+        reloc(0x68, 0x80),
+        reloc(0x78, 0x82),
+    ];
+
+    let sprites = image::sprites(&file, &relocs);
+    assert_eq!(sprites.len(), 1);
+    assert_eq!(sprites[0].offset, sprite);
+    assert_eq!(sprites[0].format, TexelFormat::I8);
+    assert_eq!(sprites[0].bitmaps.len(), 2);
+
+    let decoded = image::decode_sprite(&file, &sprites[0]).unwrap();
+    let intensities = decoded.pixels.chunks(4).map(|p| p[0]).collect::<Vec<_>>();
+    assert_eq!(intensities, [0x11, 0xFF, 0x22, 0xFF]);
+
+    // encoding the sprite writes the same texels back
+    let mut encoded = file.clone();
+    image::encode_sprite(&mut encoded, &sprites[0], &decoded, false).unwrap();
+    assert_eq!(encoded, file);
+
+    let png = image::png(&decoded).unwrap();
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(pixels, decoded.pixels);
+}
+
+#[test]
+fn fonts_are_runs_of_small_sprites() {
+    let glyph = |width, height, format| Sprite {
+        offset: 0,
+        width,
+        height,
+        format,
+        palette: None,
+        palette_colors: 0,
+        bitmap_height: height,
+        bitmaps: Vec::new(),
+    };
+    let mut glyphs = (1..=10)
+        .map(|width| glyph(width, 12, TexelFormat::Ia4))
+        .collect::<Vec<_>>();
+    assert!(image::is_font(&glyphs));
+    assert!(!image::is_font(&glyphs[..9]));
+    assert!(!image::is_font(&[]));
+
+    glyphs[3] = glyph(4, 12, TexelFormat::I8);
+    assert!(!image::is_font(&glyphs));
+    glyphs[3] = glyph(64, 12, TexelFormat::Ia4);
+    assert!(!image::is_font(&glyphs));
+}
+
+#[test]
+fn encoding_inverts_decoding() {
+    // texels that survive a decode and encode unchanged in each format
+    let cases: &[(TexelFormat, &[u8])] = &[
+        (
+            TexelFormat::Rgba16,
+            &[0xF8, 0x01, 0x07, 0xC0, 0x00, 0x3F, 0xFF, 0xFF],
+        ),
+        (
+            TexelFormat::Rgba32,
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+        ),
+        (TexelFormat::Ia4, &[0x1F, 0xE0]),
+        (TexelFormat::Ia8, &[0x0F, 0xF0, 0x88, 0xFF]),
+        (
+            TexelFormat::Ia16,
+            &[0x00, 0xFF, 0x80, 0x40, 0xFF, 0x00, 0x12, 0x34],
+        ),
+        (TexelFormat::I4, &[0x0F, 0x7A]),
+        (TexelFormat::I8, &[0x00, 0x40, 0x80, 0xFF]),
+    ];
+    for &(format, texels) in cases {
+        let decoded = image::decode(texels, format, 2, 2, None).unwrap();
+        let encoded = image::encode(&decoded, format, None).unwrap();
+        assert_eq!(encoded.texels, texels, "{}", format);
+        assert_eq!(encoded.palette, None);
+    }
+
+    // a new palette has every color of an image with few enough colors
+    let palette = [0xF8, 0x01, 0x07, 0xC1, 0x00, 0x3F];
+    let decoded = image::decode(&[0x01, 0x21], TexelFormat::Ci4, 4, 1, Some(&palette)).unwrap();
+    let encoded = image::encode(&decoded, TexelFormat::Ci4, None).unwrap();
+    let palette = encoded.palette.unwrap();
+    assert_eq!(palette.len(), 3 * 2);
+    let again = image::decode(&encoded.texels, TexelFormat::Ci4, 4, 1, Some(&palette)).unwrap();
+    assert_eq!(again, decoded);
+}
+
+#[test]
+fn quantizing_limits_the_palette() {
+    // 64 shades of gray and opaque
+    let pixels = (0..64u8)
+        .flat_map(|i| [i * 4, i * 4, i * 4, 0xFF])
+        .collect();
+    let gradient = image::Image {
+        width: 64,
+        height: 1,
+        pixels,
+    };
+    let palette = image::quantize(&gradient, 16);
+    assert_eq!(palette.len(), 16 * 2);
+
+    let encoded = image::encode(&gradient, TexelFormat::Ci4, Some(&palette)).unwrap();
+    let decoded = image::decode(&encoded.texels, TexelFormat::Ci4, 64, 1, Some(&palette)).unwrap();
+    for (original, quantized) in gradient.pixels.iter().zip(&decoded.pixels) {
+        assert!(original.abs_diff(*quantized) <= 0x10);
+    }
+}
+
+#[test]
+fn packs_images_into_sheets() {
+    let image = |width: usize, height: usize, shade: u8| Image {
+        width,
+        height,
+        pixels: vec![shade; width * height * 4],
+    };
+    let images = [image(4, 2, 1), image(2, 6, 2), image(4, 4, 3)];
+    let (sheet, positions) = image::pack(&images);
+
+    // the tallest first, in rows no narrower than the widest image
+    assert_eq!(positions, [(0, 7), (0, 0), (3, 0)]);
+    assert_eq!((sheet.width, sheet.height), (7, 9));
+    let pixel = |x: usize, y: usize| sheet.pixels[(y * sheet.width + x) * 4];
+    assert_eq!(pixel(1, 1), 2);
+    assert_eq!(pixel(6, 3), 3);
+    assert_eq!(pixel(3, 8), 1);
+    assert_eq!(pixel(2, 0), 0);
+}
+
+#[test]
+fn finds_and_converts_palettes() {
+    // a display list at 0x08 that loads the 16 color palette at 0x40
+    let commands: [u64; 5] = [
+        0x0000_0008,
+        0xFD10_0000_0000_0040, // gsDPSetTextureImage(RGBA, 16b, 1, 0x40)
+        0xE600_0000_0000_0000, // gsDPLoadSync()
+        0xF000_0000_0703_C000, // gsDPLoadTLUTCmd(7, 15)
+        0xDF00_0000_0000_0000, // gsSPEndDisplayList()
+    ];
+    let mut file = common::commands(&commands);
+    file.resize(0x40, 0);
+    file.extend_from_slice(&[0xF8, 0x01, 0x07, 0xC0]);
+    file.resize(0x60, 0);
+    let relocs = [reloc(0x04, 0x08), reloc(0x0C, 0x40)];
+
+    let palettes = image::palettes(&file, &relocs);
+    assert_eq!(palettes.len(), 1);
+    assert_eq!(
+        (
+            palettes[0].offset,
+            palettes[0].colors,
+            &palettes[0].users[..]
+        ),
+        (0x40, 16, &[0x18][..])
+    );
+
+    let colors = image::palette_colors(&file[0x40..0x60]);
+    assert_eq!(colors[..2], [[0xFF, 0, 0, 0xFF], [0, 0xFF, 0, 0]]);
+    assert_eq!(image::encode_palette(&colors), file[0x40..0x60]);
+
+    let swatch = image::swatch(&colors);
+    assert_eq!((swatch.width, swatch.height), (128, 8));
+    assert_eq!(image::read_swatch(&swatch, 16).unwrap(), colors);
+    assert!(image::read_swatch(&swatch, 17).is_err());
+
+    let pal = image::jasc_pal(&colors[..2]);
+    assert_eq!(pal, "JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 255 0\r\n");
+    assert_eq!(
+        image::read_jasc_pal(&pal).unwrap(),
+        [[0xFF, 0, 0], [0, 0xFF, 0]]
+    );
+    assert!(image::read_jasc_pal("JASC-PAL\n0100\n2\n255 0 0\n").is_err());
+    assert!(image::read_jasc_pal("JASC-PAL\n0100\n1\n256 0 0\n").is_err());
+}
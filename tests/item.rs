@@ -0,0 +1,49 @@
+mod common;
+
+use ssbfile::item::{Attributes, Weights, ATTRIBUTE_WORDS, ITEMS};
+
+#[test]
+fn reads_and_edits_item_attributes() {
+    let mut file = vec![0; 8];
+    let mut words = [0u32; ATTRIBUTE_WORDS];
+    words[2] = 0x120;
+    words[7] = 0xFFF6_0014; // hit offset x of -10, y of 20
+    words[13] = (361 << 22) | (100 << 12) | (12 << 4) | 1;
+    words[14] = 0xF8 << 14; // shield damage of -8
+    file.extend(common::words(&words));
+
+    let mut attributes = Attributes::read(&file, 8).unwrap();
+    let fields = attributes.fields();
+    let get = |fields: &[(&str, i64)], name| fields.iter().find(|f| f.0 == name).unwrap().1;
+    assert_eq!(get(&fields, "dobj_setup"), 0x120);
+    assert_eq!(get(&fields, "hit_offset_x"), -10);
+    assert_eq!(get(&fields, "hit_offset_y"), 20);
+    assert_eq!(get(&fields, "angle"), 361);
+    assert_eq!(get(&fields, "knockback_scale"), 100);
+    assert_eq!(get(&fields, "damage"), 12);
+    assert_eq!(get(&fields, "element"), 1);
+    assert_eq!(get(&fields, "shield_damage"), -8);
+
+    attributes.set("damage", 30).unwrap();
+    assert!(attributes.set("damage", 256).is_err());
+    assert!(attributes.set("nope", 1).is_err());
+    attributes.write(&mut file).unwrap();
+    let edited = Attributes::read(&file, 8).unwrap();
+    assert_eq!(get(&edited.fields(), "damage"), 30);
+    assert_eq!(get(&edited.fields(), "angle"), 361);
+    assert!(Attributes::read(&file, 0x10).is_err());
+}
+
+#[test]
+fn reads_and_edits_spawn_weights() {
+    let mut file = (0..ITEMS.len() as u8).collect::<Vec<_>>();
+    let mut weights = Weights::read(&file, 0).unwrap();
+    assert_eq!(weights.items().nth(4), Some(("maxim_tomato", 4)));
+
+    weights.set("bob_omb", 0).unwrap();
+    assert!(weights.set("bob_omb", 300).is_err());
+    assert!(weights.set("pikachu", 1).is_err());
+    weights.write(&mut file).unwrap();
+    assert_eq!(file[15], 0);
+    assert!(Weights::read(&file, 1).is_err());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{put, reloc};
+use ssbfile::{image::TexelFormat, layout, Relocation};
+
+/// A 2x1 i8 sprite at 0x10, drawn at (-4, 20) in half transparent red, with one bitmap at
+/// 0x60 and its texels at 0x70
+fn menu() -> (Vec<u8>, Vec<Relocation>) {
+    let mut file = vec![0u8; 0x80];
+    let sprite = 0x10;
+    put(&mut file, sprite, &[0xFF, 0xFC, 0, 20, 0, 2, 0, 1]);
+    put(&mut file, sprite + 0x08, &1f32.to_be_bytes());
+    put(&mut file, sprite + 0x0C, &1f32.to_be_bytes());
+    put(
+        &mut file,
+        sprite + 0x14,
+        &[0x08, 0x05, 0, 3, 0xFF, 0, 0, 0x80],
+    );
+    put(&mut file, sprite + 0x28, &[0, 1]);
+    put(&mut file, sprite + 0x2C, &[0, 1]);
+    put(&mut file, sprite + 0x30, &[4, 1]);
+    put(&mut file, 0x60, &[0, 2, 0, 2]);
+    put(&mut file, 0x6C, &[0, 1]);
+
+    (
+        file,
+        vec![
+            reloc(0x00, sprite as u32),
+            reloc(sprite + 0x34, 0x60),
+            reloc(0x68, 0x70),
+        ],
+    )
+}
+
+#[test]
+fn reads_and_writes_placements() {
+    let (mut file, relocs) = menu();
+    let placements = layout::layout(&file, &relocs);
+    assert_eq!(placements.len(), 1);
+    let mut placement = placements[0].clone();
+    assert_eq!((placement.offset, placement.x, placement.y), (0x10, -4, 20));
+    assert_eq!((placement.zdepth, placement.color), (3, [0xFF, 0, 0, 0x80]));
+    assert_eq!((placement.width, placement.height), (2, 1));
+    assert_eq!(placement.format, TexelFormat::I8);
+    assert_eq!(placement.attributes(), ["transparent", "hidden"]);
+
+    placement.x = 100;
+    placement.color = [0, 0, 0xFF, 0xFF];
+    // the bit without a name is kept
+    placement.set_attributes(["cutout"]).unwrap();
+    assert_eq!(placement.attr, 0x0802);
+    assert!(placement.set_attributes(["invisible"]).is_err());
+    placement.write(&mut file).unwrap();
+
+    let moved = &layout::layout(&file, &relocs)[0];
+    assert_eq!(moved, &placement);
+    assert_eq!(&file[0x10..0x12], &[0, 100]);
+}
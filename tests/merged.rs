@@ -0,0 +1,80 @@
+mod common;
+
+use common::{plain, table_rom, test_info};
+use ssbfile::{DataRegion, MergedView};
+
+const FILES: usize = 3;
+
+fn base() -> Vec<u8> {
+    table_rom(&[
+        plain(b"base file 0."),
+        plain(b"base file 1."),
+        plain(b"base 2.."),
+    ])
+}
+
+fn other() -> Vec<u8> {
+    table_rom(&[
+        plain(b"other 0."),
+        plain(b"the other rom's file 1 is longer"),
+        plain(b"other 2."),
+    ])
+}
+
+#[test]
+fn selected_files_come_from_their_rom() {
+    let (base, other) = (base(), other());
+    let info = test_info(FILES);
+    let mut view = MergedView::new(&base, &info);
+    let source = view.add_rom(&other, &info).unwrap();
+    view.select(1, source).unwrap();
+
+    assert_eq!(view.source_of(1), source);
+    assert_eq!(
+        view.get(1).unwrap().raw,
+        b"the other rom's file 1 is longer"
+    );
+    // the rest fall back to the base rom
+    assert_eq!(view.source_of(0), 0);
+    assert_eq!(view.get(0).unwrap().raw, b"base file 0.");
+    assert_eq!(view.get(2).unwrap().raw, b"base 2..");
+
+    // selecting the base again undoes the selection
+    view.select(1, 0).unwrap();
+    assert_eq!(view.get(1).unwrap().raw, b"base file 1.");
+}
+
+#[test]
+fn merged_region_rebuilds_the_base_rom() {
+    let (base, other) = (base(), other());
+    let info = test_info(FILES);
+    let mut view = MergedView::new(&base, &info);
+    let source = view.add_rom(&other, &info).unwrap();
+    view.select(1, source).unwrap();
+
+    let mut merged = base.clone();
+    view.data_region()
+        .unwrap()
+        .write(&mut merged, &info)
+        .unwrap();
+    let region = DataRegion::read(&merged, &info).unwrap();
+    assert_eq!(region.file(0), Some(&b"base file 0."[..]));
+    assert_eq!(
+        region.file(1),
+        Some(&b"the other rom's file 1 is longer"[..])
+    );
+    assert_eq!(region.file(2), Some(&b"base 2.."[..]));
+}
+
+#[test]
+fn bad_selections_are_errors() {
+    let base = base();
+    let info = test_info(FILES);
+    let smaller = test_info(FILES - 1);
+    let mut view = MergedView::new(&base, &info);
+
+    assert!(view.select(0, 1).is_err());
+    assert!(view.select(FILES, 0).is_err());
+    let other = table_rom(&[plain(b"one."), plain(b"two.")]);
+    assert!(view.add_rom(&other, &smaller).is_err());
+}
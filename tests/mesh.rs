@@ -0,0 +1,72 @@
+mod common;
+
+use common::{reloc, vertex};
+use ssbfile::{
+    display_list::Address,
+    image::TexelFormat,
+    mesh::{self, Texture},
+    Relocation,
+};
+
+/// A display list at 0x08 that draws a triangle with a 2x2 RGBA16 texture at 0x60
+/// and vertices at 0x80; the pointer at 0x00 points to the list
+fn textured_triangle() -> (Vec<u8>, Vec<Relocation>) {
+    let commands: [u64; 10] = [
+        0x0000_0008,
+        0xFD10_0000_0000_0060, // gsDPSetTextureImage(RGBA, 16b, 1, 0x60)
+        0xF510_0000_0700_0000, // gsDPSetTile(RGBA, 16b, 0, 0, 7, ...)
+        0xF300_0000_0700_3800, // gsDPLoadBlock(7, 0, 0, 3, 0x800)
+        0xF510_0200_0000_0000, // gsDPSetTile(RGBA, 16b, 1, 0, 0, ...)
+        0xF200_0000_0000_4004, // gsDPSetTileSize(0, 0, 0, 4, 4)
+        0xD700_0002_FFFF_FFFF, // gsSPTexture(0xFFFF, 0xFFFF, 0, 0, G_ON)
+        0x0100_3006_0000_0080, // gsSPVertex(0x80, 3, 0)
+        0x0500_0204_0000_0000, // gsSP1Triangle(0, 1, 2, 0)
+        0xDF00_0000_0000_0000, // gsSPEndDisplayList()
+    ];
+    let mut file = common::commands(&commands);
+    file.resize(0x60, 0);
+    file.extend_from_slice(&[0xF8, 0x01, 0x07, 0xC1, 0x00, 0x3F, 0xFF, 0xFF]);
+    file.resize(0x80, 0);
+    for (x, y, s, t) in [(0, 0, 0, 0), (10, 0, 64, 0), (0, 10, 0, 64)] {
+        file.extend(vertex(x, y, s, t, [0xFF, 0x80, 0x00, 0xFF]));
+    }
+
+    (
+        file,
+        vec![reloc(0x04, 0x08), reloc(0x0C, 0x60), reloc(0x3C, 0x80)],
+    )
+}
+
+#[test]
+fn runs_display_lists_into_meshes() {
+    let (file, relocs) = textured_triangle();
+    let mesh = mesh::mesh(&file, &[0x08], &relocs).unwrap();
+
+    assert_eq!(mesh.vertices.len(), 3);
+    assert_eq!(mesh.triangles.len(), 1);
+    assert_eq!(mesh.triangles[0].vertices, [0, 1, 2]);
+    assert_eq!(
+        mesh.materials[0].texture,
+        Some(Texture {
+            addr: Address::Internal(0x60),
+            format: TexelFormat::Rgba16,
+            width: 2,
+            height: 2,
+            palette: None,
+        })
+    );
+    // 64 in 10.5 fixed point is 2 texels, the width of the texture
+    let uv = |i: usize| mesh.vertices[i].uv.map(|c| (c * 1000.0).round() as i32);
+    assert_eq!(uv(1), [1000, 1000]);
+    assert_eq!(uv(2), [0, 0]);
+    assert_eq!(mesh.vertices[1].position, [10, 0, 0]);
+    assert_eq!(mesh.vertices[1].normal, None);
+
+    let obj = mesh::obj(&mesh, "model.mtl");
+    assert!(obj.starts_with("mtllib model.mtl\n"));
+    assert!(obj.contains("v 10 0 0 1.0000 0.5020 0.0000\n"));
+    assert!(obj.contains("usemtl material_0\nf 1/1/1 2/2/2 3/3/3\n"));
+    let mtl = mesh::mtl(&mesh, |_| Some("texture.png".into()));
+    assert!(mtl.contains("newmtl material_0\n"));
+    assert!(mtl.contains("map_Kd texture.png\n"));
+}
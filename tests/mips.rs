@@ -0,0 +1,163 @@
+mod common;
+
+use common::words;
+use ssbfile::{
+    mips::{check_loader, find_loader, Arg, Cpu, Loader, Mismatch},
+    Error, RelocInfo, TableFile,
+};
+
+const CODE_ADDR: u32 = 0x8000_1000;
+const FILE_ADDR: u32 = 0x8010_0000;
+
+/// `relocate(file, index)`: follow the chain of `index`, writing `file + target * 4` into
+/// each word of it
+const LOADER: [u32; 13] = [
+    0x3408_FFFF, // loop: ori $t0, $zero, 0xFFFF
+    0x10A8_0009, //       beq $a1, $t0, done
+    0x0005_4880, //       sll $t1, $a1, 2
+    0x0089_4821, //       addu $t1, $a0, $t1
+    0x8D2A_0000, //       lw $t2, 0($t1)
+    0x000A_2C02, //       srl $a1, $t2, 16
+    0x314B_FFFF, //       andi $t3, $t2, 0xFFFF
+    0x000B_5880, //       sll $t3, $t3, 2
+    0x008B_5821, //       addu $t3, $a0, $t3
+    0x0800_0400, //       j loop
+    0xAD2B_0000, //       sw $t3, 0($t1)
+    0x03E0_0008, // done: jr $ra
+    0x0000_0000, //       nop
+];
+
+/// a file with an internal chain at 0x4 -> 0xC, and a word that isn't a pointer at 0x8
+const FILE: [u32; 4] = [
+    0x1234_5678,
+    0x0003_0002, // 0x4: next 0xC, points to 0x8
+    0xCAFE_F00D,
+    0xFFFF_0001, // 0xC: end, points to 0x4
+];
+
+fn entry(raw: &[u8]) -> TableFile<'_> {
+    TableFile {
+        id: 1,
+        offset: 0,
+        compressed: false,
+        decompressed_size: raw.len(),
+        raw,
+        inreloc: Some(RelocInfo::Internal(4)),
+        exreloc: None,
+    }
+}
+
+fn loader(code: &[u8]) -> Loader<'_> {
+    Loader {
+        code,
+        code_addr: CODE_ADDR,
+        entry: CODE_ADDR,
+        args: vec![Arg::File, Arg::InReloc],
+        file_addr: FILE_ADDR,
+        ram_size: 0x20_0000,
+        max_steps: 1000,
+    }
+}
+
+#[test]
+fn the_loader_writes_the_same_pointers() {
+    let code = words(&LOADER);
+    let file = words(&FILE);
+
+    assert_eq!(
+        check_loader(&loader(&code), &file, &entry(&file)).unwrap(),
+        []
+    );
+}
+
+#[test]
+fn a_different_loader_is_reported() {
+    // a loader that forgets to scale the target to bytes
+    let mut broken = LOADER;
+    broken[7] = 0;
+    let code = words(&broken);
+    let file = words(&FILE);
+
+    assert_eq!(
+        check_loader(&loader(&code), &file, &entry(&file)).unwrap(),
+        [
+            Mismatch {
+                offset: 0x4,
+                loader: FILE_ADDR + 0x2,
+                relocated: FILE_ADDR + 0x8
+            },
+            Mismatch {
+                offset: 0xC,
+                loader: FILE_ADDR + 0x1,
+                relocated: FILE_ADDR + 0x4
+            },
+        ]
+    );
+}
+
+/// `LOADER`, with its jump moved to `addr`
+fn loader_at(addr: u32, mut code: [u32; 13]) -> [u32; 13] {
+    code[9] = 0x0800_0000 | (addr >> 2 & 0x03FF_FFFF);
+    code
+}
+
+#[test]
+fn the_loader_is_found_by_what_it_does() {
+    let mut broken = LOADER;
+    broken[7] = 0;
+    let mut code = vec![
+        0x2402_0001, // addiu $v0, $zero, 1
+        0x03E0_0008, // jr $ra
+        0x0000_0000, // nop
+        0x0000_0000, // padding
+    ];
+    // a function that uses 0xFFFF too, but doesn't relocate like the loader
+    code.extend(loader_at(CODE_ADDR + 0x10, broken));
+    let found = CODE_ADDR + code.len() as u32 * 4;
+    code.extend(loader_at(found, LOADER));
+    let code = words(&code);
+
+    let located = find_loader(&loader(&code)).unwrap();
+    assert_eq!(located, found);
+
+    // and the located loader relocates the file
+    let file = words(&FILE);
+    let loader = Loader {
+        entry: located,
+        ..loader(&code)
+    };
+    assert_eq!(check_loader(&loader, &file, &entry(&file)).unwrap(), []);
+
+    assert_eq!(find_loader(&self::loader(&words(&[0x03E0_0008, 0]))), None);
+}
+
+#[test]
+fn runs_branches_calls_and_their_delay_slots() {
+    let mut cpu = Cpu::new(0x10_0000);
+    let code = words(&[
+        0x03E0_8021, // addu $s0, $ra, $zero
+        0x0C00_0405, // jal 0x80001014
+        0x2404_0005, // addiu $a0, $zero, 5 (delay slot)
+        0x0200_0008, // jr $s0
+        0x0000_0000, // nop
+        0x2402_0000, // 0x80001014: addiu $v0, $zero, 0
+        0x0044_1021, // loop: addu $v0, $v0, $a0
+        0x2084_FFFF, //       addi $a0, $a0, -1
+        0x5C80_FFFD, //       bgtzl $a0, loop
+        0x2442_0001, //       addiu $v0, $v0, 1 (skipped when not taken)
+        0x03E0_0008, //       jr $ra
+        0x0000_0000, //       nop
+    ]);
+    cpu.write(CODE_ADDR, &code).unwrap();
+
+    // 5 + 4 + 3 + 2 + 1, and 1 for each of the 4 delay slots of the taken branches
+    assert_eq!(cpu.call(CODE_ADDR, &[], 100).unwrap(), 19);
+
+    let looping = words(&[0x1000_FFFF, 0x0000_0000]); // b . ; nop
+    cpu.write(CODE_ADDR, &looping).unwrap();
+    let error = cpu.call(CODE_ADDR, &[], 100).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::StepLimit { steps: 100 })
+    );
+}
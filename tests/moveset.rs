@@ -0,0 +1,137 @@
+mod common;
+
+use common::{reloc, words};
+use ssbfile::{
+    display_list::Address,
+    moveset::{self, Command},
+    Relocation,
+};
+
+/// A script at 0x00 that makes a hitbox, waits, and calls the script at 0x24, which plays
+/// a sound; the pointer to it is at 0x1C
+fn script() -> (Vec<u8>, Vec<Relocation>) {
+    let words: [u32; 11] = [
+        0x0C00_0000 | 1 << 23 | 12 << 13 | 15 << 5, // attack id=1 joint=12 damage=15
+        0x0120_FFF6,                                // size=288 x=-10
+        0x0000_0005,                                // y=0 z=5
+        (361 << 22) | 100 << 12,                    // angle=361 knockback_scale=100
+        30 << 7,                                    // knockback_base=30
+        0x0400_0003,                                // wait frames=3
+        0x8800_0000,                                // subroutine
+        0x0000_0024,
+        0x0000_0000, // end
+        0x3800_0098, // sound sound=0x98
+        0x8C00_0000, // return
+    ];
+    let file = common::words(&words);
+    let relocs = vec![reloc(0x1C, 0x24)];
+
+    (file, relocs)
+}
+
+#[test]
+fn disassembles_scripts() {
+    let (file, relocs) = script();
+    let commands = moveset::parse(&file, 0, &relocs).unwrap();
+    let offsets = commands.iter().map(|c| c.offset).collect::<Vec<_>>();
+    assert_eq!(offsets, [0x00, 0x14, 0x18, 0x20]);
+
+    let attack = &commands[0];
+    assert_eq!(attack.spec().unwrap().name, "attack");
+    assert_eq!(attack.field("joint"), Some(12));
+    assert_eq!(attack.field("x"), Some(-10));
+    assert_eq!(attack.field("angle"), Some(361));
+    assert_eq!(commands[1].to_string(), "wait frames=3");
+    assert_eq!(commands[2].pointer, Some(Address::Internal(0x24)));
+    assert_eq!(commands[2].to_string(), "subroutine file+0x0024");
+
+    let scripts = moveset::scripts(&file, &[0], &relocs).unwrap();
+    assert_eq!(scripts.keys().copied().collect::<Vec<_>>(), [0x00, 0x24]);
+    assert_eq!(
+        scripts[&0x24]
+            .iter()
+            .map(Command::to_string)
+            .collect::<Vec<_>>(),
+        ["sound sound=152", "return"]
+    );
+}
+
+#[test]
+fn shows_unknown_commands_as_words() {
+    let file = [0xFC, 0x00, 0x00, 0x01, 0, 0, 0, 0];
+    let commands = moveset::parse(&file, 0, &[]).unwrap();
+    assert_eq!(commands[0].to_string(), ".word 0xFC000001");
+    assert!(moveset::parse(&file[..4], 0, &[]).is_err());
+}
+
+#[test]
+fn assembles_the_disassembly() {
+    let (file, relocs) = script();
+    let mut text = String::new();
+    for (start, commands) in moveset::scripts(&file, &[0], &relocs).unwrap() {
+        text += &format!(".script 0x{:04X}\n", start);
+        for command in commands {
+            text += &format!("0x{:04X}: {}  # comment\n", command.offset, command);
+        }
+    }
+
+    let scripts = moveset::assemble(&text).unwrap();
+    assert_eq!(scripts.len(), 2);
+    assert_eq!(scripts[0].start, Some(0));
+    assert_eq!(scripts[0].pointers, [(0x1C, Address::Internal(0x24))]);
+    let words = scripts
+        .iter()
+        .flat_map(|s| s.words.iter().flat_map(|w| w.to_be_bytes()))
+        .collect::<Vec<_>>();
+    assert_eq!(words, file);
+}
+
+#[test]
+fn patches_scripts_in_place() {
+    let (mut file, mut relocs) = script();
+    let edited = moveset::assemble(".script 0x24\nvoice sound=0x10\nend\n").unwrap();
+    moveset::patch(&mut file, 0x24, &edited[0], &mut relocs).unwrap();
+    assert_eq!(&file[0x24..], [0x44, 0, 0, 0x10, 0, 0, 0, 0]);
+
+    // the subroutine is replaced by a wait, so its pointer goes
+    let edited = moveset::assemble("wait frames=1\nwait frames=2\nwait frames=3\nend").unwrap();
+    moveset::patch(&mut file, 0x14, &edited[0], &mut relocs).unwrap();
+    assert!(relocs.is_empty());
+
+    let long = moveset::assemble("sound sound=1\nsound sound=2\nend").unwrap();
+    assert!(moveset::patch(&mut file, 0x24, &long[0], &mut relocs).is_err());
+    assert!(moveset::assemble("attack damage=256").is_err());
+    assert!(moveset::assemble("goto").is_err());
+    assert!(moveset::assemble("jump file+0x10").is_err());
+}
+
+#[test]
+fn follows_scripts_to_the_frames_of_hitboxes() {
+    let (file, relocs) = script();
+    let hitboxes = moveset::hitboxes(&file, 0, &relocs).unwrap();
+    assert_eq!(hitboxes.len(), 1);
+    assert_eq!(hitboxes[0].frame, 0);
+    assert_eq!(hitboxes[0].joint, 12);
+    assert_eq!(hitboxes[0].damage, 15);
+    assert_eq!(hitboxes[0].position, [-10, 0, 5]);
+    assert_eq!(hitboxes[0].knockback_base, 30);
+
+    // a loop that waits 2 frames, twice, and then calls the script with the hitbox
+    let mut looped = words(&[
+        0x8000_0002, // loop count=2
+        0x0400_0002, // wait frames=2
+        0x8400_0000, // loop_end
+        0x8800_0000, // subroutine
+        0x0000_0020,
+        0x0000_0000, // end
+        0,
+        0,
+    ]);
+    looped.extend_from_slice(&file[..0x14]);
+    looped.extend_from_slice(&[0x8C, 0, 0, 0]); // return
+    let relocs = [reloc(0x10, 0x20)];
+    let hitboxes = moveset::hitboxes(&looped, 0, &relocs).unwrap();
+    assert_eq!(hitboxes.len(), 1);
+    assert_eq!(hitboxes[0].frame, 4);
+    assert_eq!(hitboxes[0].offset, 0x20);
+}
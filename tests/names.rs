@@ -0,0 +1,20 @@
+use ssbfile::names::NameDb;
+
+#[test]
+fn loaded_names_and_slugs() {
+    let mut names = NameDb::default();
+    names
+        .load_yaml("0x0C: Dream Land geometry\n276: \"Fox's main file\"\n")
+        .unwrap();
+
+    assert_eq!(names.get(0x0C), Some("Dream Land geometry"));
+    assert_eq!(names.slug(276).as_deref(), Some("fox-s-main-file"));
+    assert_eq!(names.get(1), None);
+    assert_eq!(names.slug(1), None);
+}
+
+#[test]
+fn bad_name_file() {
+    let mut names = NameDb::default();
+    assert!(names.load_yaml("- not a map").is_err());
+}
@@ -0,0 +1,132 @@
+use ssbfile::{
+    patch::{apply_bps, apply_ips, bps, gameshark, ips, GameSharkCode},
+    Error,
+};
+
+/// a rom past the offset that reads as "EOF", with some structure to it
+fn test_rom() -> Vec<u8> {
+    (0..0x48_0000u32)
+        .map(|i| ((i / 7) ^ (i >> 12)) as u8)
+        .collect()
+}
+
+#[test]
+fn ips_round_trip() {
+    let original = test_rom();
+    let mut modified = original.clone();
+    modified[0x10..0x14].copy_from_slice(b"SSB!");
+    // two changes close together, and a long run
+    modified[0x1000] ^= 0xFF;
+    modified[0x1003] ^= 0xFF;
+    modified[0x2_0000..0x3_8000].fill(0);
+    // a change right at the offset that reads as "EOF"
+    modified[0x45_4F46] ^= 0xFF;
+    // and growing the rom
+    modified.resize(0x50_0000, 0xAA);
+
+    let patch = ips(&original, &modified).unwrap();
+    assert!(patch.starts_with(b"PATCH") && patch.ends_with(b"EOF"));
+    // the runs are stored run-length encoded
+    assert!(patch.len() < 0x400);
+    assert_eq!(apply_ips(&original, &patch).unwrap(), modified);
+
+    let unchanged = ips(&original, &original).unwrap();
+    assert_eq!(unchanged, b"PATCHEOF");
+}
+
+#[test]
+fn ips_truncates_a_smaller_rom() {
+    let original = test_rom();
+    let mut modified = original[..0x40_0000].to_vec();
+    modified[0x100] = 0;
+
+    let patch = ips(&original, &modified).unwrap();
+    assert!(patch.ends_with(b"EOF\x40\x00\x00"));
+    assert_eq!(apply_ips(&original, &patch).unwrap(), modified);
+}
+
+#[test]
+fn ips_is_limited_to_16_mib() {
+    let original = vec![0; 0x100_0000];
+    let mut modified = original.clone();
+    modified.push(1);
+
+    let err = ips(&original, &modified).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::IpsOffset { offset: 0x100_0000 })
+    );
+    assert!(apply_ips(&original, b"PATCH\x00\x00").is_err());
+}
+
+#[test]
+fn bps_round_trip() {
+    let original = test_rom();
+    // grow a "file", which moves everything after it, and grow the rom
+    let mut modified = original[..0x10_0000].to_vec();
+    modified.extend_from_slice(&[0x55; 0x204]);
+    modified.extend_from_slice(&original[0x10_0000..]);
+    modified[0x20] = 0;
+    modified.resize(0x50_0000, 0);
+
+    let patch = bps(&original, &modified);
+    assert!(patch.starts_with(b"BPS1"));
+    // the moved data is copied from the original
+    assert!(patch.len() < 0x400);
+    assert_eq!(apply_bps(&original, &patch).unwrap(), modified);
+
+    let unchanged = bps(&original, &original);
+    assert_eq!(apply_bps(&original, &unchanged).unwrap(), original);
+}
+
+#[test]
+fn bps_checks_the_source() {
+    let original = test_rom();
+    let mut modified = original.clone();
+    modified[0x100] ^= 1;
+    let patch = bps(&original, &modified);
+
+    let err = apply_bps(&modified, &patch).unwrap_err();
+    assert_eq!(err.downcast_ref::<Error>(), Some(&Error::WrongPatchSource));
+    let mut corrupt = patch.clone();
+    corrupt[6] ^= 1;
+    assert!(apply_bps(&original, &corrupt).is_err());
+}
+
+#[test]
+fn gameshark_codes() {
+    let original = [0u8; 8];
+    let modified = [0, 0x12, 0x34, 0, 0xAB, 0xCD, 0, 0xEF];
+
+    let codes = gameshark(&original, &modified, 0x8013_0000).unwrap();
+    assert_eq!(
+        codes,
+        [
+            GameSharkCode::Byte {
+                addr: 0x8013_0001,
+                value: 0x12
+            },
+            GameSharkCode::Half {
+                addr: 0x8013_0002,
+                value: 0x3400
+            },
+            GameSharkCode::Half {
+                addr: 0x8013_0004,
+                value: 0xABCD
+            },
+            GameSharkCode::Byte {
+                addr: 0x8013_0007,
+                value: 0xEF
+            },
+        ]
+    );
+    let text = codes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    assert_eq!(text[..2], ["80130001 0012", "81130002 3400"]);
+
+    assert!(gameshark(&original, &modified[1..], 0x8013_0000).is_err());
+    let err = gameshark(&original, &modified, 0x80FF_FFFC).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::GameSharkAddress { addr: 0x8100_0000 })
+    );
+}
@@ -0,0 +1,82 @@
+//! Random roms and files should give errors, not panics
+use ssbfile::{
+    apply_relocations, read_table, relocations, unrelocate, versions::SSBInfo, DataRegion,
+    RelocInfo, Relocation, TableEntry, TableFile,
+};
+use std::borrow::Cow;
+
+/// xorshift, so that the inputs are the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[test]
+fn random_tables() {
+    let mut rng = Rng(0x5353_4236_3446_494C);
+    for _ in 0..200 {
+        let entries = 1 + rng.next() as usize % 16;
+        let info = SSBInfo {
+            version: Cow::Borrowed("RANDOM"),
+            crc: (0, 0),
+            table_start: 0x40,
+            table_end: 0x40 + (entries + 1) * TableEntry::SIZE,
+        };
+        let len = rng.next() as usize % 0x400;
+        let rom = rng.bytes(len);
+
+        let _ = read_table(&rom, &info);
+        let _ = DataRegion::read(&rom, &info);
+        for id in 0..=entries {
+            if let Ok(file) = TableFile::get(id, &rom, &info) {
+                let _ = file.includes();
+                if !file.compressed {
+                    let _ = relocations(file.raw, &file);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn random_relocations() {
+    let mut rng = Rng(0x0123_4567_89AB_CDEF);
+    for _ in 0..500 {
+        let len = rng.next() as usize % 0x40;
+        let mut file = rng.bytes(len);
+        let entry = TableFile {
+            id: 0,
+            offset: 0,
+            compressed: false,
+            decompressed_size: len,
+            raw: &[],
+            inreloc: Some(RelocInfo::Internal(rng.next() as usize % 0x50)),
+            exreloc: Some(RelocInfo::External(rng.next() as usize % 0x50, vec![1])),
+        };
+        let _ = relocations(&file, &entry);
+
+        let relocs = (0..4)
+            .map(|_| Relocation {
+                // in and around the file, and right at the end of the address space
+                offset: match rng.next() % 3 {
+                    0 => usize::MAX - rng.next() as usize % 4,
+                    _ => rng.next() as usize % 0x48,
+                },
+                target: rng.next() as u32,
+                external_file: Some(rng.next() as u16).filter(|id| id % 2 == 0),
+            })
+            .collect::<Vec<_>>();
+        let _ = apply_relocations(&mut file, &relocs, 0x8000_0000, |_| Some(0));
+        let _ = unrelocate(&mut file, &relocs);
+    }
+}
@@ -0,0 +1,425 @@
+use ssbfile::{
+    check_entry, inject_resized, read_table, remap_externs, set_compressed, set_relocations,
+    slot_capacity, table_bytes, validate, versions::SSBInfo, write_entry, write_table, DataRegion,
+    Error, PartialRom, Problem, RelocHeads, SsbRom, TableEntry, TerminalEntry,
+};
+use std::borrow::Cow;
+
+const INFO: SSBInfo = SSBInfo {
+    version: Cow::Borrowed("TEST"),
+    crc: (0, 0),
+    table_start: 0x40,
+    table_end: 0x40 + 4 * TableEntry::SIZE,
+};
+const NEXT_TABLE: &[u8] = b"NEXTTABLEDATA...";
+
+/// a rom with three files, followed by the (fake) secondary table
+fn test_rom() -> Vec<u8> {
+    let files: [&[u8]; 3] = [b"file", b"second file data", b"ext\0\0\x01\0\0"];
+    let mut rom = vec![0xAA; INFO.table_end];
+    let mut offset = 0;
+    for (id, file) in files.iter().enumerate() {
+        let entry = TableEntry {
+            offset,
+            compressed: false,
+            inreloc: 0xFFFF,
+            size: (file.len() / 4) as u16,
+            exreloc: 0xFFFF,
+            decompressed_size: (file.len() / 4) as u16,
+        };
+        let start = INFO.table_start + id * TableEntry::SIZE;
+        rom[start..start + TableEntry::SIZE].copy_from_slice(&entry.to_bytes());
+        rom.extend_from_slice(file);
+        offset += file.len() as u32;
+    }
+
+    let terminal = TerminalEntry {
+        data_end: offset,
+        next_table: offset + 8,
+        unknown: 0x1234,
+    };
+    rom[INFO.table_end - TableEntry::SIZE..INFO.table_end].copy_from_slice(&terminal.to_bytes());
+    rom.extend_from_slice(&[0xFF; 8]);
+    rom.extend_from_slice(NEXT_TABLE);
+
+    rom
+}
+
+fn next_table<'a>(rom: &'a [u8], terminal: &TerminalEntry) -> &'a [u8] {
+    let start = INFO.table_end + terminal.next_table as usize;
+    &rom[start..start + NEXT_TABLE.len()]
+}
+
+#[test]
+fn unmodified_rebuild_is_bit_exact() {
+    let original = test_rom();
+    let mut rom = original.clone();
+
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let terminal = *region.terminal();
+    region.write(&mut rom, &INFO).unwrap();
+
+    assert_eq!(*region.terminal(), terminal);
+    assert_eq!(rom, original);
+}
+
+#[test]
+fn growing_a_file_moves_terminal_entry() {
+    let mut rom = test_rom();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let old = *region.terminal();
+
+    let mut entry = region.entries()[1];
+    entry.size = 8;
+    entry.decompressed_size = 8;
+    region.replace(1, entry, vec![0x55; 32]).unwrap();
+    region.write(&mut rom, &INFO).unwrap();
+
+    let reread = DataRegion::read(&rom, &INFO).unwrap();
+    let terminal = *reread.terminal();
+    assert_eq!(terminal.data_end, old.data_end + 16);
+    assert_eq!(terminal.next_table, old.next_table + 16);
+    assert_eq!(terminal.unknown, old.unknown);
+    assert_eq!(reread.entries()[2].offset, region.entries()[2].offset);
+    assert_eq!(reread.file(2), Some(&b"ext\0\0\x01\0\0"[..]));
+    assert_eq!(next_table(&rom, &terminal), NEXT_TABLE);
+}
+
+#[test]
+fn shrinking_a_file_keeps_next_table_in_place() {
+    let mut rom = test_rom();
+    let len = rom.len();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let old = *region.terminal();
+
+    let mut entry = region.entries()[1];
+    entry.size = 1;
+    entry.decompressed_size = 1;
+    region.replace(1, entry, b"tiny".to_vec()).unwrap();
+    region.write(&mut rom, &INFO).unwrap();
+
+    let terminal = *DataRegion::read(&rom, &INFO).unwrap().terminal();
+    assert_eq!(terminal.data_end, old.data_end - 12);
+    assert_eq!(terminal.next_table, old.next_table);
+    assert_eq!(rom.len(), len);
+    assert_eq!(next_table(&rom, &terminal), NEXT_TABLE);
+}
+
+#[test]
+fn table_bytes_round_trip() {
+    let rom = test_rom();
+    let (entries, terminal) = read_table(&rom, &INFO).unwrap();
+
+    assert_eq!(
+        table_bytes(&entries, &terminal),
+        &rom[INFO.table_start..INFO.table_end]
+    );
+
+    let mut copy = rom.clone();
+    copy[INFO.table_start..INFO.table_end].fill(0);
+    write_table(&mut copy, &INFO, &entries, &terminal).unwrap();
+    assert_eq!(copy, rom);
+}
+
+#[test]
+fn errors_can_be_downcast() {
+    let rom = test_rom();
+    let (entries, terminal) = read_table(&rom, &INFO).unwrap();
+
+    let mut short = rom[..INFO.table_end - 4].to_vec();
+    let err = write_table(&mut short, &INFO, &entries, &terminal).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::OutsideRom { .. })
+    ));
+
+    let mut copy = rom.clone();
+    let err = write_table(&mut copy, &INFO, &entries[1..], &terminal).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::EntryCount {
+            expected: 3,
+            found: 2
+        })
+    );
+}
+
+#[test]
+fn validate_finds_truncated_files() {
+    let rom = test_rom();
+    let report = validate(&rom, Some(&INFO));
+    assert_eq!(report.problems, [Problem::NoHeader { len: rom.len() }]);
+
+    let short = &rom[..rom.len() - NEXT_TABLE.len() - 12];
+    let report = validate(short, None);
+    assert_eq!(
+        report.problems,
+        [
+            Problem::NoHeader { len: short.len() },
+            Problem::UnknownVersion
+        ]
+    );
+    let report = validate(short, Some(&INFO));
+    assert_eq!(
+        report.problems[1],
+        Problem::FileOutsideRom {
+            id: 2,
+            start: INFO.table_end + 20,
+            end: INFO.table_end + 28
+        }
+    );
+}
+
+#[test]
+fn inject_resized_moves_following_files() {
+    let mut rom = test_rom();
+    let old = read_table(&rom, &INFO).unwrap().1;
+
+    let entry = inject_resized(&mut rom, &INFO, 0, b"a bigger file", 13).unwrap();
+    assert_eq!((entry.size, entry.decompressed_size), (4, 4));
+    assert_eq!(rom.len(), 0x100000);
+
+    let region = DataRegion::read(&rom, &INFO).unwrap();
+    let terminal = *region.terminal();
+    assert_eq!(region.file(0), Some(&b"a bigger file\0\0\0"[..]));
+    assert_eq!(region.file(1), Some(&b"second file data"[..]));
+    assert_eq!(region.file(2), Some(&b"ext\0\0\x01\0\0"[..]));
+    assert_eq!(terminal.data_end, old.data_end + 12);
+    assert_eq!(next_table(&rom, &terminal), NEXT_TABLE);
+
+    // a file that fits stays in place, and the rom doesn't grow
+    let entry = inject_resized(&mut rom, &INFO, 1, b"tiny", 4).unwrap();
+    assert_eq!(entry.offset, region.entries()[1].offset);
+    assert_eq!(rom.len(), 0x100000);
+}
+
+#[test]
+fn pushed_files_grow_the_table() {
+    let mut rom = test_rom();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let old = *region.terminal();
+
+    let entry = TableEntry {
+        offset: 0,
+        compressed: false,
+        inreloc: 0xFFFF,
+        size: 2,
+        exreloc: 0xFFFF,
+        decompressed_size: 2,
+    };
+    assert_eq!(region.push(entry, b"new file".to_vec()), 3);
+    region.write(&mut rom, &INFO).unwrap();
+
+    let info = region.table_info(&INFO);
+    assert_eq!(info.table_end, INFO.table_end + TableEntry::SIZE);
+    let reread = DataRegion::read(&rom, &info).unwrap();
+    let terminal = *reread.terminal();
+    assert_eq!(reread.entries().len(), 4);
+    assert_eq!(reread.file(0), Some(&b"file"[..]));
+    assert_eq!(reread.file(3), Some(&b"new file"[..]));
+    assert_eq!(terminal.data_end, old.data_end + 8);
+    let start = info.table_end + terminal.next_table as usize;
+    assert_eq!(&rom[start..start + NEXT_TABLE.len()], NEXT_TABLE);
+}
+
+#[test]
+fn identical_files_share_their_data() {
+    let mut rom = test_rom();
+    let len = rom.len();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let first = region.entries()[0];
+    region.replace(2, first, b"file".to_vec()).unwrap();
+    region.write(&mut rom, &INFO).unwrap();
+    let old = *region.terminal();
+
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    assert_eq!(region.share_duplicates(), 4);
+    region.write(&mut rom, &INFO).unwrap();
+
+    let reread = DataRegion::read(&rom, &INFO).unwrap();
+    let terminal = *reread.terminal();
+    assert_eq!(terminal.data_end, old.data_end - 4);
+    assert_eq!(terminal.next_table, old.next_table);
+    assert_eq!(rom.len(), len);
+    assert_eq!(reread.shares(2), Some(0));
+    assert_eq!(reread.entries()[2].offset, reread.entries()[0].offset);
+    assert_eq!(reread.file(2), Some(&b"file"[..]));
+    assert_eq!(reread.file(1), Some(&b"second file data"[..]));
+    assert_eq!(next_table(&rom, &terminal), NEXT_TABLE);
+    assert_eq!(validate(&rom, Some(&INFO)).problems.len(), 1);
+    // neither file can be replaced in place
+    assert_eq!(slot_capacity(&rom, &INFO, 0).unwrap(), 0);
+    assert_eq!(slot_capacity(&rom, &INFO, 2).unwrap(), 0);
+
+    // the shared layout round-trips
+    let shared = rom.clone();
+    reread.clone().write(&mut rom, &INFO).unwrap();
+    assert_eq!(rom, shared);
+
+    // replacing the shared data gives the other file its own copy
+    inject_resized(&mut rom, &INFO, 0, b"new!", 4).unwrap();
+    let region = DataRegion::read(&rom, &INFO).unwrap();
+    assert_eq!(region.shares(2), None);
+    assert_eq!(region.file(0), Some(&b"new!"[..]));
+    assert_eq!(region.file(2), Some(&b"file"[..]));
+    assert_eq!(region.terminal().data_end, old.data_end);
+}
+
+#[test]
+fn rom_offsets_of_shared_files_are_found() {
+    let mut rom = test_rom();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let first = region.entries()[0];
+    region.replace(2, first, b"file".to_vec()).unwrap();
+    region.share_duplicates();
+    region.write(&mut rom, &INFO).unwrap();
+
+    // file 2 goes back to the offset of file 0, after file 1
+    let rom = SsbRom::with_info(Cow::Borrowed(&rom), INFO).unwrap();
+    let at = |offset: usize| rom.file_at_rom_offset(INFO.table_end + offset);
+    assert_eq!(at(0), Some((0, 0)));
+    assert_eq!(at(3), Some((0, 3)));
+    // the end of a file is the start of the next
+    assert_eq!(at(4), Some((1, 0)));
+    assert_eq!(at(19), Some((1, 15)));
+    assert_eq!(at(20), None);
+    assert_eq!(rom.file_at_rom_offset(INFO.table_end - 1), None);
+}
+
+#[test]
+fn files_next_to_external_files_dont_share_data() {
+    let mut rom = test_rom();
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    let first = region.entries()[0];
+    region.replace(2, first, b"file".to_vec()).unwrap();
+    let mut second = region.entries()[1];
+    second.exreloc = 0;
+    region
+        .replace(1, second, b"second file data\0\x02\0\0".to_vec())
+        .unwrap();
+    region.write(&mut rom, &INFO).unwrap();
+
+    let mut region = DataRegion::read(&rom, &INFO).unwrap();
+    assert_eq!(region.share_duplicates(), 0);
+    assert_eq!(region.shares(2), None);
+}
+
+#[test]
+fn slot_capacity_and_compressed_flag() {
+    let mut rom = test_rom();
+    assert_eq!(slot_capacity(&rom, &INFO, 1).unwrap(), 16);
+    // the last file's slot ends at the end of the data
+    assert_eq!(slot_capacity(&rom, &INFO, 2).unwrap(), 8);
+
+    let entry = set_compressed(&mut rom, &INFO, 1, true).unwrap();
+    assert!(entry.compressed);
+    assert_eq!(read_table(&rom, &INFO).unwrap().0[1], entry);
+    assert!(slot_capacity(&rom, &INFO, 3).is_err());
+}
+
+#[test]
+fn set_relocations_grows_the_extern_list() {
+    let mut rom = test_rom();
+    let heads = RelocHeads {
+        internal: Some(4),
+        external: Some(0),
+        externs: vec![1, 2, 3],
+    };
+
+    let entry = set_relocations(&mut rom, &INFO, 1, &heads).unwrap();
+    assert_eq!((entry.inreloc, entry.exreloc), (1, 0));
+    let (entries, terminal) = read_table(&rom, &INFO).unwrap();
+    assert_eq!(entries[1], entry);
+    // the list of external files follows the data, padded to a word
+    let start = INFO.table_end + entry.offset as usize + 16;
+    assert_eq!(&rom[start..start + 8], &[0, 1, 0, 2, 0, 3, 0, 0]);
+    assert_eq!(entries[2].offset, entry.offset + 24);
+    assert_eq!(next_table(&rom, &terminal), NEXT_TABLE);
+}
+
+#[test]
+fn remap_externs_skips_padding() {
+    let mut rom = test_rom();
+    // one external pointer at the start of file 1, into file 2
+    let start = INFO.table_end + read_table(&rom, &INFO).unwrap().0[1].offset as usize;
+    rom[start..start + 4].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x01]);
+    let heads = RelocHeads {
+        internal: None,
+        external: Some(0),
+        externs: vec![2],
+    };
+    set_relocations(&mut rom, &INFO, 1, &heads).unwrap();
+
+    let map = [(2, 0), (0, 1)].into_iter().collect();
+    assert_eq!(remap_externs(&mut rom, &INFO, &map).unwrap(), 1);
+    assert_eq!(&rom[start + 16..start + 20], &[0, 0, 0, 0]);
+}
+
+#[test]
+fn entries_are_checked_before_writing() {
+    let mut rom = test_rom();
+    let old = read_table(&rom, &INFO).unwrap().0[1];
+
+    let disabled = TableEntry {
+        inreloc: 0xFFFF,
+        ..old
+    };
+    check_entry(&rom, &INFO, 1, &disabled).unwrap();
+    write_entry(&mut rom, &INFO, 1, &disabled).unwrap();
+    assert_eq!(read_table(&rom, &INFO).unwrap().0[1], disabled);
+
+    for bad in [
+        TableEntry { size: 5, ..old },
+        TableEntry { offset: 2, ..old },
+        TableEntry {
+            offset: 0x20,
+            ..old
+        },
+        TableEntry { exreloc: 4, ..old },
+    ] {
+        let e = check_entry(&rom, &INFO, 1, &bad).unwrap_err();
+        assert!(matches!(
+            e.downcast_ref(),
+            Some(Error::InconsistentEntry { id: 1, .. })
+        ));
+    }
+    assert!(write_entry(&mut rom, &INFO, 3, &old).is_err());
+}
+
+#[test]
+fn partial_rom_reads_only_requested_files() {
+    let mut original = test_rom();
+    original[..4].copy_from_slice(&0x80371240u32.to_be_bytes());
+    let path = std::env::temp_dir().join(format!("ssbfile-partial-{}.z64", std::process::id()));
+    std::fs::write(&path, &original).unwrap();
+
+    let mut rom = PartialRom::open(&path).unwrap();
+    assert_eq!(rom.header(), &original[..0x40]);
+    rom.read_files(&INFO, &[1]).unwrap();
+    let file = rom.file(1, &INFO).unwrap();
+    assert_eq!(file.raw, b"second file data");
+    assert_eq!(rom.len(), original.len());
+    // the other files aren't read, and can't be gotten
+    let err = rom.file(0, &INFO).err().unwrap();
+    let start = INFO.table_end;
+    assert_eq!(
+        err.root_cause().downcast_ref::<Error>(),
+        Some(&Error::NotRead {
+            start,
+            end: start + 4
+        })
+    );
+
+    // reads that overlap or touch what was read are joined to it
+    rom.read_range(start, start + 4).unwrap();
+    assert_eq!(
+        rom.get(start, start + 20).unwrap(),
+        &original[start..start + 20]
+    );
+    assert!(rom.get(start + 16, start + 24).is_err());
+    assert!(rom.get(0, original.len() + 1).is_err());
+
+    let whole = rom.read_all().unwrap().to_vec();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(whole, original);
+}
@@ -0,0 +1,137 @@
+use ssbfile::{
+    asm::assembly, pointer_offsets_from_csv, relocate, relocations, resolved_relocations,
+    unrelocate, Error, PointerOffsets, RelocInfo, Relocation, TableFile,
+};
+
+/// a file with an internal chain at 0x0 -> 0x8 and an external chain at 0x4 -> 0xC
+const FILE: [u8; 16] = [
+    0x00, 0x02, 0x00, 0x03, // 0x0: next 0x8, points to 0xC
+    0x00, 0x03, 0x00, 0x10, // 0x4: next 0xC, points to 0x40 in file 7
+    0xFF, 0xFF, 0x00, 0x00, // 0x8: end, points to 0x0
+    0xFF, 0xFF, 0x00, 0x01, // 0xC: end, points to 0x4 in file 9
+];
+
+fn entry() -> TableFile<'static> {
+    TableFile {
+        id: 1,
+        offset: 0,
+        compressed: false,
+        decompressed_size: FILE.len(),
+        raw: &FILE,
+        inreloc: Some(RelocInfo::Internal(0)),
+        exreloc: Some(RelocInfo::External(4, vec![7, 9])),
+    }
+}
+
+#[test]
+fn relocations_follow_chains() {
+    let relocs = relocations(&FILE, &entry()).unwrap();
+
+    assert_eq!(
+        relocs,
+        [
+            Relocation {
+                offset: 0x0,
+                target: 0xC,
+                external_file: None
+            },
+            Relocation {
+                offset: 0x8,
+                target: 0x0,
+                external_file: None
+            },
+            Relocation {
+                offset: 0x4,
+                target: 0x40,
+                external_file: Some(7)
+            },
+            Relocation {
+                offset: 0xC,
+                target: 0x4,
+                external_file: Some(9)
+            },
+        ]
+    );
+}
+
+#[test]
+fn unrelocate_round_trips() {
+    let base = 0x8010_0000;
+    let (mut file, _) = relocate(FILE.to_vec(), &entry(), base).unwrap();
+
+    let relocs = resolved_relocations(&file, base, &[0x0, 0x8], &[(0x4, 7), (0xC, 9)]).unwrap();
+    let heads = unrelocate(&mut file, &relocs).unwrap();
+
+    assert_eq!(file, FILE);
+    assert_eq!(heads.internal, Some(0));
+    assert_eq!(heads.external, Some(4));
+    assert_eq!(heads.externs, [7, 9]);
+}
+
+#[test]
+fn unrelocate_links_repeated_pointers_once() {
+    let base = 0x8010_0000;
+    let (mut file, _) = relocate(FILE.to_vec(), &entry(), base).unwrap();
+
+    let mut relocs = resolved_relocations(&file, base, &[0x8, 0x0], &[(0xC, 9), (0x4, 7)]).unwrap();
+    relocs.extend(relocs.clone());
+    let heads = unrelocate(&mut file, &relocs).unwrap();
+
+    assert_eq!(file, FILE);
+    assert_eq!(heads.internal, Some(0));
+    assert_eq!(heads.externs, [7, 9]);
+}
+
+#[test]
+fn unrelocate_rejects_two_pointers_at_one_offset() {
+    let base = 0x8010_0000;
+    let (mut file, _) = relocate(FILE.to_vec(), &entry(), base).unwrap();
+
+    let mut relocs = resolved_relocations(&file, base, &[0x0, 0x8], &[(0x4, 7)]).unwrap();
+    relocs.push(Relocation {
+        offset: 0x8,
+        target: 0x4,
+        external_file: Some(9),
+    });
+    let err = unrelocate(&mut file, &relocs).unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::DuplicateRelocation { offset: 0x8 })
+    );
+}
+
+#[test]
+fn assembly_has_symbolic_pointers() {
+    let (file, relocs) = relocate(FILE.to_vec(), &entry(), 0).unwrap();
+    let asm = assembly(1, &file, &relocs);
+
+    let words = asm
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix(".word "))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [
+            "file0001_off_000C",
+            "file0007_start + 0x40",
+            "file0001_off_0000",
+            "file0009_start + 0x4",
+        ]
+    );
+    assert!(asm.contains("file0001_off_000C:\n"));
+}
+
+#[test]
+fn relocations_from_csv() {
+    let (_, relocs) = relocate(FILE.to_vec(), &entry(), 0x80000000).unwrap();
+    let PointerOffsets { internal, external } = pointer_offsets_from_csv(&relocs.csv()).unwrap();
+    assert_eq!(internal, [0x0, 0x8]);
+    assert_eq!(external, [(0x4, 7), (0xC, 9)]);
+
+    let internal = pointer_offsets_from_csv("internal,0x10\ninternal,20\n")
+        .unwrap()
+        .internal;
+    assert_eq!(internal, [0x10, 20]);
+    assert!(pointer_offsets_from_csv("external,0x10,0x0\n").is_err());
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{reloc, vertex};
+use serde_json::{json, Value};
+use ssbfile::{
+    gltf::{Gltf, Skin},
+    mesh,
+    skeleton::{self, Bone},
+    Relocation,
+};
+use std::f32::consts::FRAC_PI_2;
+
+/// A display list at 0x00 that draws a triangle, and a `DObjDesc` array at 0x70 of two
+/// bones that both draw it: the second a child of the first, moved up and turned around z.
+/// The pointer at 0xF4 points to the array.
+fn two_bones() -> (Vec<u8>, Vec<Relocation>) {
+    let commands: [u64; 3] = [
+        0x0100_3006_0000_0040, // gsSPVertex(0x40, 3, 0)
+        0x0500_0204_0000_0000, // gsSP1Triangle(0, 1, 2, 0)
+        0xDF00_0000_0000_0000, // gsSPEndDisplayList()
+    ];
+    let mut file = common::commands(&commands);
+    file.resize(0x40, 0);
+    for (x, y) in [(0, 0), (1, 0), (0, 1)] {
+        file.extend(vertex(x, y, 0, 0, [0xFF; 4]));
+    }
+
+    let bones: [(u32, [f32; 9]); 2] = [
+        (0, [10.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]),
+        (1, [0.0, 5.0, 0.0, 0.0, 0.0, FRAC_PI_2, 1.0, 1.0, 1.0]),
+    ];
+    for (id, floats) in bones {
+        file.extend_from_slice(&id.to_be_bytes());
+        file.extend_from_slice(&[0; 4]);
+        for f in floats {
+            file.extend_from_slice(&f.to_be_bytes());
+        }
+    }
+    file.extend_from_slice(&18u32.to_be_bytes());
+    file.resize(0xF8, 0);
+
+    (
+        file,
+        vec![
+            reloc(0x04, 0x40),
+            reloc(0x74, 0x00),
+            reloc(0xA0, 0x00),
+            reloc(0xF4, 0x70),
+        ],
+    )
+}
+
+fn rounded(point: [f32; 3]) -> [i32; 3] {
+    point.map(|c| (c * 1000.0).round() as i32)
+}
+
+#[test]
+fn reads_and_poses_bone_hierarchies() {
+    let (file, relocs) = two_bones();
+    assert_eq!(skeleton::find(&file, &relocs), [0x70]);
+
+    let bones = skeleton::skeleton(&file, 0x70, &relocs).unwrap();
+    assert_eq!(bones.len(), 2);
+    assert_eq!(
+        bones[1],
+        Bone {
+            offset: 0x9C,
+            parent: Some(0),
+            display_list: Some(0),
+            translate: [0.0, 5.0, 0.0],
+            rotate: [0.0, 0.0, FRAC_PI_2],
+            scale: [1.0; 3],
+        }
+    );
+    assert_eq!(skeleton::display_lists(&bones), [(0, 0), (1, 0)]);
+    assert_eq!(skeleton::children(&bones), [vec![1], vec![]]);
+    assert_eq!(
+        skeleton::tree(&bones),
+        "bone 0 at 0x0070: display list 0x0000, translate (10, 0, 0), rotate (0, 0, 0), scale (1, 1, 1)\n  \
+         bone 1 at 0x009C: display list 0x0000, translate (0, 5, 0), rotate (0, 0, 90), scale (1, 1, 1)\n"
+    );
+    assert!(skeleton::skeleton(&file, 0x00, &relocs).is_err());
+
+    // the child is turned, so its x axis points up
+    let world = skeleton::world_matrices(&bones);
+    assert_eq!(
+        rounded(skeleton::transform(&world[1], [1.0, 0.0, 0.0])),
+        [10000, 6000, 0]
+    );
+    let identity = skeleton::multiply(&world[1], &skeleton::invert(&world[1]));
+    for (i, c) in identity.iter().enumerate() {
+        let expected = if i % 5 == 0 { 1.0 } else { 0.0 };
+        assert!((c - expected).abs() < 1e-5, "{:?}", identity);
+    }
+}
+
+#[test]
+fn writes_skinned_gltf() {
+    let (file, relocs) = two_bones();
+    let bones = skeleton::skeleton(&file, 0x70, &relocs).unwrap();
+    let mesh = mesh::mesh(&file, &[0, 0], &relocs).unwrap();
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.vertices[3].list, 1);
+
+    let skin = Skin {
+        bones: &bones,
+        joints: &[0, 1],
+    };
+    let gltf = Gltf::new(&mesh, Some(skin), |_| None);
+    let json: Value = serde_json::from_str(&gltf.embedded().unwrap()).unwrap();
+    assert!(json["meshes"][0]["primitives"][0]["attributes"]["JOINTS_0"].is_u64());
+    assert!(json["skins"][0]["inverseBindMatrices"].is_u64());
+    assert_eq!(json["nodes"][0]["name"], "bone_0");
+    assert_eq!(json["nodes"][0]["translation"], json!([10.0, 0.0, 0.0]));
+    assert_eq!(json["nodes"][0]["children"], json!([1]));
+    // the positions are posed: the triangle of the second bone is turned, and 5 above the first
+    assert_eq!(json["accessors"][0]["min"], json!([9.0, 0.0, 0.0]));
+    assert_eq!(json["accessors"][0]["max"], json!([11.0, 6.0, 0.0]));
+    assert!(json["buffers"][0]["uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:application/octet-stream;base64,"));
+
+    let glb = gltf.glb().unwrap();
+    assert_eq!(&glb[..4], b"glTF");
+    assert_eq!(
+        u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+        glb.len()
+    );
+    assert_eq!(&glb[16..20], b"JSON");
+    let length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+    let json: Value = serde_json::from_slice(&glb[20..20 + length]).unwrap();
+    // the buffer is the binary chunk, not a uri
+    assert!(json["buffers"][0]["byteLength"].is_u64());
+    assert!(json["buffers"][0].get("uri").is_none());
+}
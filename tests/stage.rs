@@ -0,0 +1,131 @@
+mod common;
+
+use common::{halves, reloc};
+use ssbfile::{
+    stage::{self, LineKind, MapObject, Rect, Vertex},
+    Relocation,
+};
+
+/// A stage with one group: a ground line from (-100, 0) to (100, 0), with ledges at both
+/// ends, and a left wall down from (100, 0). Its bounds are at 0x18, and its two spawn
+/// points at 0x5C.
+fn stage() -> (Vec<u8>, Vec<Relocation>) {
+    let mut file = halves(&[1, 0, 0, 0x68, 0, 0x78, 0, 0x80, 0, 0x88, 0, 0x5C]);
+    // camera, then blast zones
+    file.extend(halves(&[1000, -500, 1500, -1500, 2000, -1000, 3000, -3000]));
+    file.resize(0x5C, 0);
+    file.extend(halves(&[0, -50, 0, 1, 50, 0]));
+    file.resize(0x68, 0);
+    // vertices, their ids, and the lines
+    let ledge = Vertex::LEDGE as i16;
+    file.extend(halves(&[-100, 0, ledge, 100, 0, ledge, 100, -200, 0]));
+    file.resize(0x78, 0);
+    file.extend(halves(&[0, 1, 1, 2]));
+    file.extend(halves(&[0, 2, 2, 2]));
+    // the lines of the group: ground, ceiling, right wall, left wall
+    file.extend(halves(&[0, 1, 0, 0, 0, 0, 1, 1]));
+
+    let relocs = vec![
+        reloc(0x04, 0x68),
+        reloc(0x08, 0x78),
+        reloc(0x0C, 0x80),
+        reloc(0x10, 0x88),
+        reloc(0x14, 0x5C),
+    ];
+
+    (file, relocs)
+}
+
+#[test]
+fn reads_stage_collision() {
+    let (file, relocs) = stage();
+    let geometry = stage::geometry(&file, 0, &relocs).unwrap();
+
+    assert_eq!(geometry.vertices.len(), 3);
+    assert_eq!(geometry.vertices[1].flags & Vertex::LEDGE, Vertex::LEDGE);
+    assert_eq!(geometry.lines.len(), 2);
+    assert_eq!(geometry.lines[0].kind, LineKind::Ground);
+    assert_eq!(geometry.lines[0].vertices, [0, 1]);
+    assert_eq!(geometry.lines[1].kind, LineKind::LeftWall);
+    assert_eq!(geometry.lines[1].vertices, [1, 2]);
+    assert_eq!(
+        geometry.objects,
+        [
+            MapObject {
+                kind: 0,
+                x: -50,
+                y: 0
+            },
+            MapObject {
+                kind: 1,
+                x: 50,
+                y: 0
+            }
+        ]
+    );
+    assert_eq!(geometry.objects[1].player_spawn(), Some(2));
+
+    let bounds = stage::Bounds::read(&file, 0x18).unwrap();
+    assert_eq!(
+        bounds.blast_zones,
+        Rect {
+            top: 2000,
+            bottom: -1000,
+            right: 3000,
+            left: -3000
+        }
+    );
+    let report = stage::Stage {
+        geometry,
+        bounds: Some(bounds),
+    }
+    .to_string();
+    assert!(report.contains("* group 0 ground: (-100, 0) ledge (100, 0) ledge\n"));
+    assert!(report.contains("* player 1 spawn at (-50, 0)\n"));
+
+    assert!(stage::geometry(&file, 0x18, &relocs).is_err());
+}
+
+#[test]
+fn draws_stages_as_svg() {
+    let (file, relocs) = stage();
+    let stage = stage::Stage {
+        geometry: stage::geometry(&file, 0, &relocs).unwrap(),
+        bounds: Some(stage::Bounds::read(&file, 0x18).unwrap()),
+    };
+    let svg = stage.svg();
+
+    // the blast zones and a margin around them
+    assert!(svg.starts_with(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-3200 -2200 6400 3400\">"
+    ));
+    assert!(svg.contains("<line x1=\"-100\" y1=\"0\" x2=\"100\" y2=\"0\" stroke=\"green\"/>"));
+    assert!(svg.contains("<line x1=\"100\" y1=\"0\" x2=\"100\" y2=\"-200\" stroke=\"blue\"/>"));
+    assert_eq!(svg.matches("fill=\"black\"").count(), 2);
+    assert!(svg.contains(">P2</text>"));
+    assert!(svg.ends_with("</svg>\n"));
+}
+
+#[test]
+fn moves_bonus_stage_objects() {
+    let (mut file, relocs) = stage();
+    file[0x62..0x64].copy_from_slice(&MapObject::TARGET.to_be_bytes());
+    let mut geometry = stage::geometry(&file, 0, &relocs).unwrap();
+    assert_eq!(geometry.objects_offset, 0x5C);
+    assert_eq!(geometry.objects[0].bonus(), None);
+    assert_eq!(geometry.objects[1].bonus(), Some("target"));
+    let report = stage::Stage {
+        geometry: geometry.clone(),
+        bounds: None,
+    }
+    .to_string();
+    assert!(report.contains("* target at (50, 0)\n"));
+
+    geometry.objects[1].y = 1200;
+    geometry.write_objects(&mut file).unwrap();
+    assert_eq!(&file[0x66..0x68], &1200i16.to_be_bytes());
+    assert_eq!(stage::geometry(&file, 0, &relocs).unwrap(), geometry);
+
+    geometry.objects_offset = file.len() - 4;
+    assert!(geometry.write_objects(&mut file).is_err());
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use common::reloc;
+use ssbfile::{
+    text::{self, Message, Translation},
+    Error,
+};
+
+#[test]
+fn finds_strings_that_end_with_nul() {
+    let mut file = vec![0xFF; 4];
+    file.extend_from_slice(b"Mario\0\0\0");
+    // too short, then the Shift-JIS of "マリオ", then a string that doesn't end
+    file.extend_from_slice(b"ab\0\0");
+    file.extend_from_slice(&[0x83, 0x7D, 0x83, 0x8A, 0x83, 0x49, 0, 0]);
+    file.extend_from_slice(b"Say \"hi\"\n\0\0\0");
+    file.extend_from_slice(b"open");
+
+    let messages = text::messages(12, &file, 3);
+    let offsets = messages.iter().map(|m| m.offset).collect::<Vec<_>>();
+    assert_eq!(offsets, [0x04, 0x10, 0x18]);
+    assert_eq!(messages[0].id(), "0012:0004");
+    assert_eq!(messages[1].escaped(), "マリオ");
+    assert_eq!(messages[2].escaped(), "Say \\\"hi\\\"\\n");
+    for m in &messages {
+        assert_eq!(text::unescape(&m.escaped()).unwrap(), m.bytes);
+    }
+    assert!(text::unescape("\\x8").is_err());
+    assert!(text::unescape("\\q").is_err());
+}
+
+#[test]
+fn shift_jis_round_trips_as_utf8() {
+    // "大乱闘スマッシュブラザーズ" in Shift-JIS
+    let title = [
+        0x91, 0xE5, 0x97, 0x90, 0x93, 0xAC, 0x83, 0x58, 0x83, 0x7D, 0x83, 0x62, 0x83, 0x56, 0x83,
+        0x85, 0x83, 0x75, 0x83, 0x89, 0x83, 0x55, 0x81, 0x5B, 0x83, 0x59,
+    ];
+    assert_eq!(text::escape(&title), "大乱闘スマッシュブラザーズ");
+    assert_eq!(text::unescape("大乱闘スマッシュブラザーズ").unwrap(), title);
+
+    // a lead byte without its second byte stays escaped
+    assert_eq!(text::escape(&[b'a', 0x83, b'\n']), "a\\x83\\n");
+    assert_eq!(
+        text::unescape("マリオ\\x83").unwrap(),
+        [0x83, 0x7D, 0x83, 0x8A, 0x83, 0x49, 0x83]
+    );
+    let err = text::unescape("🍄").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::TextChar { c: '🍄' })
+    );
+}
+
+#[test]
+fn writes_po_and_csv() {
+    let messages = [Message {
+        file: 3,
+        offset: 0x20,
+        bytes: b"a \"b\"".to_vec(),
+    }];
+    let po = text::po(&messages);
+    assert!(po.starts_with("msgid \"\"\nmsgstr \"\"\n"));
+    assert!(po.contains(
+        "#: file 3 at 0x20\nmsgctxt \"0003:0020\"\nmsgid \"a \\\"b\\\"\"\nmsgstr \"\"\n"
+    ));
+    assert_eq!(
+        text::csv(&messages),
+        "id,text,translation\n0003:0020,\"a \"\"b\"\"\",\n"
+    );
+}
+
+#[test]
+fn reads_translations_of_po_and_csv() {
+    let messages = [
+        Message {
+            file: 3,
+            offset: 0x20,
+            bytes: b"Start".to_vec(),
+        },
+        Message {
+            file: 4,
+            offset: 0x08,
+            bytes: b"Quit".to_vec(),
+        },
+    ];
+    let po = text::po(&messages).replacen(
+        "msgstr \"\"\n\n#: file 4",
+        "msgstr \"Go \\\"now\\\"\"\n\"!\\x81\"\n\n#: file 4",
+        1,
+    );
+    let expected = Translation {
+        file: 3,
+        offset: 0x20,
+        bytes: b"Go \"now\"!\x81".to_vec(),
+    };
+    assert_eq!(text::read_po(&po).unwrap(), std::slice::from_ref(&expected));
+
+    let csv =
+        text::csv(&messages).replacen("\"Start\",\n", "\"Start\",\"Go \"\"now\"\"!\\x81\"\n", 1);
+    assert_eq!(text::read_csv(&csv).unwrap(), [expected]);
+
+    assert!(text::read_po("msgctxt \"nope\"\nmsgid \"a\"\nmsgstr \"b\"\n").is_err());
+    assert!(text::read_po("msgctxt \"0001:0000\"\nmsgstr \"\u{e9}\"\n").is_err());
+    assert!(text::read_csv("id,text,translation\n0001:0000,\"a,b\n").is_err());
+}
+
+#[test]
+fn reinserts_strings_in_place_or_at_the_end() {
+    // a pointer at 0x00 to "Yes" at 0x04, and "No" at 0x08 that nothing points to
+    let mut file = vec![0; 4];
+    file.extend_from_slice(b"Yes\0No\0\0");
+    let mut relocs = vec![reloc(0, 4)];
+    let translation = |offset, bytes: &[u8]| Translation {
+        file: 1,
+        offset,
+        bytes: bytes.to_vec(),
+    };
+
+    let fits = [translation(4, b"Si"), translation(8, b"Non")];
+    text::reinsert(&mut file, &fits, &mut relocs).unwrap();
+    assert_eq!(&file[4..], b"Si\0\0Non\0");
+
+    // too long for its place, so it moves to the end, and its pointer with it
+    let moves = [translation(4, b"Jawohl")];
+    text::reinsert(&mut file, &moves, &mut relocs).unwrap();
+    assert_eq!(&file[4..8], b"Si\0\0");
+    assert_eq!(&file[12..], b"Jawohl\0\0");
+    assert_eq!(relocs[0].target, 12);
+
+    let too_long = [translation(8, b"Nein!")];
+    assert!(text::reinsert(&mut file, &too_long, &mut relocs).is_err());
+    assert!(text::reinsert(&mut file, &[translation(1, b"x")], &mut relocs).is_err());
+}
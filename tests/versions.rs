@@ -0,0 +1,175 @@
+use ssbfile::{
+    versions::{SSBInfo, VersionDb},
+    Error, SsbRom, TableEntry, TerminalEntry,
+};
+use std::borrow::Cow;
+
+const TABLE_START: usize = 0x1000;
+const FILES: usize = 20;
+
+/// a rom of an unknown version, with a table of files that alternate between
+/// compressed and not
+fn unknown_rom() -> Vec<u8> {
+    let mut rom = vec![0; TABLE_START];
+    rom[..4].copy_from_slice(&0x80371240u32.to_be_bytes());
+    rom[0x10..0x18].copy_from_slice(b"CRC1CRC2");
+    rom[0x3B..0x3F].copy_from_slice(b"NALJ");
+
+    let mut data = Vec::new();
+    for id in 0..FILES {
+        let compressed = id % 2 == 0;
+        let file: &[u8; 8] = if compressed {
+            b"vpk0\0\0\0\x10"
+        } else {
+            b"DATADATA"
+        };
+        let entry = TableEntry {
+            offset: data.len() as u32,
+            compressed,
+            inreloc: 0xFFFF,
+            size: 2,
+            exreloc: 0xFFFF,
+            decompressed_size: if compressed { 4 } else { 2 },
+        };
+        rom.extend_from_slice(&entry.to_bytes());
+        data.extend_from_slice(file);
+    }
+    let terminal = TerminalEntry {
+        data_end: data.len() as u32,
+        next_table: data.len() as u32,
+        unknown: 0,
+    };
+    rom.extend_from_slice(&terminal.to_bytes());
+    rom.extend_from_slice(&data);
+
+    rom
+}
+
+#[test]
+fn detects_the_table_of_an_unknown_version() {
+    let rom = unknown_rom();
+    let info = SSBInfo::detect(&rom).unwrap();
+    assert_eq!(info.version, "NALJ");
+    assert_eq!(info.crc, (0x43524331, 0x43524332));
+    assert_eq!(info.table_start, TABLE_START);
+    assert_eq!(info.table_end, TABLE_START + (FILES + 1) * TableEntry::SIZE);
+    assert_eq!(info.total_entries(), FILES);
+}
+
+#[test]
+fn detects_tables_named_by_any_game_code() {
+    for (code, moved) in [(b"NALJ", 0x40), (b"NALP", 0x1230), (b"NALU", 0x2000)] {
+        let mut rom = unknown_rom();
+        rom[0x3B..0x3F].copy_from_slice(code);
+        // a synthetic table, moved to somewhere else in the rom
+        rom.splice(TABLE_START..TABLE_START, vec![0; moved]);
+
+        let info = SSBInfo::detect(&rom).unwrap();
+        assert_eq!(info.version.as_bytes(), code);
+        assert_eq!(info.table_start, TABLE_START + moved);
+        assert_eq!(info.total_entries(), FILES);
+        let rom = SsbRom::new(&rom).unwrap();
+        assert_eq!(rom.file(1).unwrap().raw, b"DATADATA");
+    }
+}
+
+#[test]
+fn roms_are_found_in_the_version_db() {
+    let rom = unknown_rom();
+    let detected = SsbRom::new(&rom).unwrap();
+    assert_eq!(detected.version().version, "NALJ");
+
+    // a loaded version is used over the detected table
+    let mut db = VersionDb::builtin();
+    let table_end = TABLE_START + (FILES + 1) * TableEntry::SIZE;
+    db.load_toml(&format!(
+        "[[version]]\nname = \"LOADED\"\ncrc = [0x43524331, 0x43524332]\n\
+         table_start = {}\ntable_end = {}\n",
+        TABLE_START, table_end
+    ))
+    .unwrap();
+    let found = SsbRom::with_db(Cow::Borrowed(&rom), &db).unwrap();
+    assert_eq!(found.version().version, "LOADED");
+    assert_eq!(found.file_count(), FILES);
+}
+
+#[test]
+fn detects_a_table_with_shared_data() {
+    let mut rom = unknown_rom();
+    // file 5 shares the data of file 1
+    let at = TABLE_START + 5 * TableEntry::SIZE;
+    rom[at..at + 4].copy_from_slice(&8u32.to_be_bytes());
+    let info = SSBInfo::detect(&rom).unwrap();
+    assert_eq!(info.table_end, TABLE_START + (FILES + 1) * TableEntry::SIZE);
+}
+
+#[test]
+fn no_table_without_compressed_files_in_place() {
+    let mut rom = unknown_rom();
+    // the compressed data no longer lines up with the table
+    let table_end = TABLE_START + (FILES + 1) * TableEntry::SIZE;
+    rom.insert(table_end, 0);
+    assert!(SSBInfo::detect(&rom).is_none());
+    assert!(SSBInfo::detect(&rom[..TABLE_START]).is_none());
+}
+
+/// `unknown_rom`, followed by a secondary table of uncompressed images, where the dummy
+/// entry of the resource table points to
+fn rom_with_secondary_table() -> Vec<u8> {
+    let mut rom = unknown_rom();
+    let images: [&[u8]; 3] = [b"IMG0", b"IMAGE 1.", b"IMG2"];
+    let mut offset = 0;
+    for image in images {
+        let words = (image.len() / 4) as u16;
+        let entry = TableEntry {
+            offset,
+            compressed: false,
+            inreloc: 0xFFFF,
+            size: words,
+            exreloc: 0xFFFF,
+            decompressed_size: words,
+        };
+        rom.extend_from_slice(&entry.to_bytes());
+        offset += image.len() as u32;
+    }
+    let terminal = TerminalEntry {
+        data_end: offset,
+        next_table: 0,
+        unknown: 0,
+    };
+    rom.extend_from_slice(&terminal.to_bytes());
+    images.iter().for_each(|image| rom.extend_from_slice(image));
+
+    rom
+}
+
+#[test]
+fn reads_the_secondary_table() {
+    let rom = rom_with_secondary_table();
+    let info = SSBInfo::detect(&rom).unwrap();
+    let rom = SsbRom::with_info(Cow::Borrowed(&rom), info).unwrap();
+
+    let secondary = rom.secondary_table().unwrap();
+    assert_eq!(
+        secondary.version().table_start,
+        rom.secondary_table_offset()
+    );
+    assert_eq!(secondary.file_count(), 3);
+    assert_eq!(secondary.file(1).unwrap().raw, b"IMAGE 1.");
+    assert_eq!(secondary.terminal().data_end, 16);
+}
+
+#[test]
+fn no_secondary_table_without_entries() {
+    let rom = unknown_rom();
+    let info = SSBInfo::detect(&rom).unwrap();
+    let rom = SsbRom::with_info(Cow::Borrowed(&rom), info).unwrap();
+
+    let err = rom.secondary_table().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::NoSecondaryTable {
+            offset: rom.secondary_table_offset()
+        })
+    );
+}